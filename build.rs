@@ -414,6 +414,8 @@ fn main() {
         (("enet", "TXD3"), quote!(crate::enet::Txd3Pin)),
         (("enet", "RXD2"), quote!(crate::enet::Rxd2Pin)),
         (("enet", "RXD3"), quote!(crate::enet::Rxd3Pin)),
+        // ENET - PTP event output
+        (("enet", "EVTO0"), quote!(crate::enet::PpsPin)),
         // DAO (Digital Audio Output)
         (("dao", "RP"), quote!(crate::dao::RpPin)),
         (("dao", "RN"), quote!(crate::dao::RnPin)),
@@ -0,0 +1,294 @@
+//! PTP slave example for HPM6300EVK
+//!
+//! This example demonstrates:
+//! - Hardware timestamping of PTP event messages with `Config::ptp`
+//! - A two-step end-to-end PTP (IEEE 1588v2 over UDP/IPv4) ordinary clock in slave mode
+//! - A PI servo stepping and frequency-adjusting the ENET system time
+//! - Tracking the PHY link with `phy::link_monitor`
+//!
+//! Usage:
+//! 1. Connect the board to a network with a PTP grandmaster, e.g. `ptp4l -i eth0 -m -4 -E`
+//! 2. Run the example, it reports offset and path delay after each Delay_Resp
+//!
+//! The synchronized time can be output as pulse per second with `PtpClock::enable_pps` on an
+//! ETH0 EVTO0 pin.
+
+#![no_main]
+#![no_std]
+#![feature(type_alias_impl_trait)]
+#![feature(impl_trait_in_assoc_type)]
+#![feature(abi_riscv_interrupt)]
+
+use defmt::{info, warn};
+use embassy_executor::Spawner;
+use embassy_futures::select::{select, Either};
+use embassy_net::udp::{PacketMetadata, UdpSocket};
+use embassy_net::{IpEndpoint, Ipv4Address, StackResources};
+use embassy_time::{Duration, Timer};
+use hal::bind_interrupts;
+use hal::enet::ptp::{MESSAGE_DELAY_REQ, MESSAGE_SYNC};
+use hal::enet::phy::link_monitor;
+use hal::enet::{self, Config as EnetConfig, Ethernet, PacketQueue, Phy, PtpClock, Rtl8201, SmiClockDivider, Timestamp};
+use hal::peripherals::ENET0;
+use static_cell::StaticCell;
+use {defmt_rtt as _, hpm_hal as hal};
+
+const PHY_ADDR: u8 = 0; // RTL8201 at address 0
+
+const MAC_ADDR: [u8; 6] = [0x02, 0x00, 0x11, 0x22, 0x33, 0x55];
+
+/// PTP primary multicast group 224.0.1.129, and its MAC address
+const PTP_GROUP: Ipv4Address = Ipv4Address::new(224, 0, 1, 129);
+const PTP_GROUP_MAC: [u8; 6] = [0x01, 0x00, 0x5E, 0x00, 0x01, 0x81];
+
+const PTP_EVENT_PORT: u16 = 319;
+const PTP_GENERAL_PORT: u16 = 320;
+
+const MESSAGE_FOLLOW_UP: u8 = 0x8;
+const MESSAGE_DELAY_RESP: u8 = 0x9;
+/// Header flagField[0] two-step flag
+const FLAG_TWO_STEP: u8 = 0x02;
+
+/// Offsets above this are corrected by stepping the clock
+const STEP_THRESHOLD_NS: i64 = 100_000;
+/// Servo frequency adjustment limit
+const MAX_PPB: i64 = 500_000;
+
+bind_interrupts!(struct Irqs {
+    ENET0 => enet::InterruptHandler<ENET0>;
+});
+
+#[unsafe(link_section = ".noncacheable")]
+static mut PACKET_QUEUE: PacketQueue<4, 4> = PacketQueue::new();
+
+static RESOURCES: StaticCell<StackResources<4>> = StaticCell::new();
+
+#[embassy_executor::task]
+async fn net_task(mut runner: embassy_net::Runner<'static, Ethernet<'static, ENET0>>) -> ! {
+    runner.run().await
+}
+
+#[embassy_executor::task]
+async fn link_task(mut phy: Rtl8201<ENET0>) -> ! {
+    link_monitor::<ENET0, _>(&mut phy, None).await
+}
+
+/// PI servo on the offset from master, with one sample per sync interval
+struct Servo {
+    integral: i64,
+}
+
+impl Servo {
+    /// Returns the frequency adjustment in ppb
+    fn sample(&mut self, offset_ns: i64) -> i32 {
+        self.integral = (self.integral + offset_ns).clamp(-MAX_PPB * 4, MAX_PPB * 4);
+        let ppb = -(offset_ns * 7 / 10 + self.integral * 3 / 10);
+        ppb.clamp(-MAX_PPB, MAX_PPB) as i32
+    }
+}
+
+/// Sync exchange of the current sequence
+#[derive(Default)]
+struct Exchange {
+    sync_seq: u16,
+    t1: Option<Timestamp>,
+    t2: Option<Timestamp>,
+    delay_req_seq: u16,
+    t3: Option<Timestamp>,
+}
+
+fn header_timestamp(msg: &[u8]) -> Timestamp {
+    Timestamp::from_ptp_bytes(msg[34..44].try_into().unwrap())
+}
+
+/// Delay_Req with our port identity, clockIdentity is the EUI-64 of the MAC address
+fn delay_req(seq: u16) -> [u8; 44] {
+    let mut msg = [0u8; 44];
+    msg[0] = MESSAGE_DELAY_REQ;
+    msg[1] = 2; // PTPv2
+    msg[2..4].copy_from_slice(&44u16.to_be_bytes());
+    msg[20..23].copy_from_slice(&MAC_ADDR[..3]);
+    msg[23..25].copy_from_slice(&[0xFF, 0xFE]);
+    msg[25..28].copy_from_slice(&MAC_ADDR[3..]);
+    msg[28..30].copy_from_slice(&1u16.to_be_bytes());
+    msg[30..32].copy_from_slice(&seq.to_be_bytes());
+    msg[32] = 1; // controlField: Delay_Req
+    msg[33] = 0x7F;
+    msg
+}
+
+fn sequence_id(msg: &[u8]) -> u16 {
+    u16::from_be_bytes([msg[30], msg[31]])
+}
+
+#[embassy_executor::main(entry = "hpm_hal::entry")]
+async fn main(spawner: Spawner) -> ! {
+    let p = hal::init(Default::default());
+
+    info!("HPM6300EVK PTP slave example");
+
+    let enet_config = EnetConfig {
+        mac_addr: MAC_ADDR,
+        // Only the PTP group gets through the multicast hash filter
+        pass_all_multicast: false,
+        multicast: &[PTP_GROUP_MAC],
+        ptp: true,
+        ..Default::default()
+    };
+
+    let eth = Ethernet::new(
+        p.ENET0.into(),
+        Irqs,
+        p.PA22, // ref_clk
+        p.PA19, // crs_dv
+        p.PA18, // rxd0
+        p.PA17, // rxd1
+        p.PA23, // tx_en
+        p.PA20, // txd0
+        p.PA21, // txd1
+        p.PA15, // mdio
+        p.PA16, // mdc
+        unsafe { &mut *core::ptr::addr_of_mut!(PACKET_QUEUE) },
+        enet_config,
+    );
+    let clock = eth.ptp_clock();
+
+    let mut smi = eth.smi();
+    smi.set_clock_divider(SmiClockDivider::Div102);
+    let mut phy = Rtl8201::new(smi, PHY_ADDR);
+    phy.reset().ok();
+    phy.set_refclk_output(false).ok();
+    Timer::after(Duration::from_millis(10)).await;
+    phy.start_autoneg().ok();
+    spawner.must_spawn(link_task(phy));
+
+    let (stack, runner) = embassy_net::new(
+        eth,
+        embassy_net::Config::dhcpv4(Default::default()),
+        RESOURCES.init(StackResources::new()),
+        0x1234_5678_9abc_def0_u64,
+    );
+    spawner.must_spawn(net_task(runner));
+
+    stack.wait_config_up().await;
+    if let Some(config) = stack.config_v4() {
+        info!("DHCP assigned IP: {}", config.address);
+    }
+    if stack.join_multicast_group(PTP_GROUP).is_err() {
+        warn!("Failed to join the PTP multicast group");
+    }
+
+    let mut event_rx_meta = [PacketMetadata::EMPTY; 4];
+    let mut event_rx_buf = [0u8; 256];
+    let mut event_tx_meta = [PacketMetadata::EMPTY; 4];
+    let mut event_tx_buf = [0u8; 256];
+    let mut event = UdpSocket::new(
+        stack,
+        &mut event_rx_meta,
+        &mut event_rx_buf,
+        &mut event_tx_meta,
+        &mut event_tx_buf,
+    );
+    event.bind(PTP_EVENT_PORT).unwrap();
+
+    let mut general_rx_meta = [PacketMetadata::EMPTY; 4];
+    let mut general_rx_buf = [0u8; 512];
+    let mut general_tx_meta = [PacketMetadata::EMPTY; 1];
+    let mut general_tx_buf = [0u8; 64];
+    let mut general = UdpSocket::new(
+        stack,
+        &mut general_rx_meta,
+        &mut general_rx_buf,
+        &mut general_tx_meta,
+        &mut general_tx_buf,
+    );
+    general.bind(PTP_GENERAL_PORT).unwrap();
+
+    let master = IpEndpoint::new(PTP_GROUP.into(), PTP_EVENT_PORT);
+    let mut servo = Servo { integral: 0 };
+    let mut exchange = Exchange::default();
+    let mut event_msg = [0u8; 128];
+    let mut general_msg = [0u8; 128];
+
+    loop {
+        let received = select(event.recv_from(&mut event_msg), general.recv_from(&mut general_msg)).await;
+        match received {
+            Either::First(Ok((n, _))) if n >= 44 && event_msg[0] & 0xF == MESSAGE_SYNC => {
+                let seq = sequence_id(&event_msg);
+                exchange = Exchange {
+                    sync_seq: seq,
+                    t2: clock.rx_timestamp(MESSAGE_SYNC, seq),
+                    delay_req_seq: exchange.delay_req_seq,
+                    ..Default::default()
+                };
+                if event_msg[6] & FLAG_TWO_STEP == 0 {
+                    exchange.t1 = Some(header_timestamp(&event_msg));
+                    send_delay_req(&mut event, master, clock, &mut exchange).await;
+                }
+            }
+            Either::Second(Ok((n, _))) if n >= 44 => {
+                let msg = &general_msg[..n];
+                match msg[0] & 0xF {
+                    MESSAGE_FOLLOW_UP if sequence_id(msg) == exchange.sync_seq => {
+                        exchange.t1 = Some(header_timestamp(msg));
+                        send_delay_req(&mut event, master, clock, &mut exchange).await;
+                    }
+                    MESSAGE_DELAY_RESP if n >= 54 && sequence_id(msg) == exchange.delay_req_seq => {
+                        // Only responses to our own requests
+                        if msg[44..47] != MAC_ADDR[..3] || msg[49..52] != MAC_ADDR[3..] {
+                            continue;
+                        }
+                        let t4 = header_timestamp(msg);
+                        let (Some(t1), Some(t2), Some(t3)) = (exchange.t1, exchange.t2, exchange.t3) else {
+                            warn!("Incomplete exchange, missing timestamps");
+                            continue;
+                        };
+
+                        let master_to_slave = t2.as_nanos() - t1.as_nanos();
+                        let slave_to_master = t4.as_nanos() - t3.as_nanos();
+                        let offset = (master_to_slave - slave_to_master) / 2;
+                        let delay = (master_to_slave + slave_to_master) / 2;
+
+                        if offset.abs() > STEP_THRESHOLD_NS {
+                            if let Err(e) = clock.step(-offset) {
+                                warn!("Clock step failed: {}", e);
+                                continue;
+                            }
+                            servo.integral = 0;
+                            info!("Stepped clock by {} ns", -offset);
+                        } else {
+                            let ppb = servo.sample(offset);
+                            if let Err(e) = clock.adjust_frequency(ppb) {
+                                warn!("Frequency adjustment failed: {}", e);
+                            }
+                            info!("offset {} ns, delay {} ns, adjust {} ppb", offset, delay, ppb);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+async fn send_delay_req(
+    socket: &mut UdpSocket<'_>,
+    master: IpEndpoint,
+    clock: PtpClock<'static, ENET0>,
+    exchange: &mut Exchange,
+) {
+    let seq = exchange.delay_req_seq.wrapping_add(1);
+    exchange.delay_req_seq = seq;
+    if socket.send_to(&delay_req(seq), master).await.is_err() {
+        warn!("Failed to send Delay_Req");
+        return;
+    }
+    exchange.t3 = clock.wait_tx_timestamp(MESSAGE_DELAY_REQ, seq).await;
+}
+
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    defmt::error!("Panic: {}", info);
+    loop {}
+}
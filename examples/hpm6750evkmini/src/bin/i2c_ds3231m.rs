@@ -162,7 +162,7 @@ async fn main(_spawner: embassy_executor::Spawner) -> ! {
         i2c_config.mode = I2cMode::Fast;
         i2c_config.timeout = Duration::from_secs(1);
     }
-    let i2c = I2c::new_blocking(p.I2C3, p.PB14, p.PB13, i2c_config);
+    let i2c = I2c::new_blocking(p.I2C3, p.PB14, p.PB13, i2c_config).unwrap();
 
     let mut ds3231m = DS3231M::new(i2c);
 
@@ -277,7 +277,8 @@ async fn main(_spawner: Spawner) {
         p.PY10, // CLK
         p.PY11, // D0
         pdm_config,
-    );
+    )
+    .unwrap();
 
     info!("--- After PDM::new() ---");
     debug_print_pin_config();
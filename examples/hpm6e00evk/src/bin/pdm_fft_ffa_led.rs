@@ -186,7 +186,7 @@ async fn main(_spawner: Spawner) {
     );
 
     // PDM pins: PB02 (CLK), PB03 (D0)
-    let mut pdm = pdm::Pdm::new(p.PDM, p.I2S0, p.PB02, p.PB03, pdm_config);
+    let mut pdm = pdm::Pdm::new(p.PDM, p.I2S0, p.PB02, p.PB03, pdm_config).unwrap();
     pdm.start();
     Timer::after_millis(50).await;
     pdm.clear_errors();
@@ -0,0 +1,235 @@
+//! USB host, mass storage class (bulk-only transport)
+//!
+//! Plug a USB stick into the USB0 port: the example enumerates it, reads the capacity and
+//! the first sector, and prints the partition table signature.
+//!
+//! The board must supply VBUS to the port, check the USB power jumper of the EVK.
+
+#![no_main]
+#![no_std]
+#![feature(type_alias_impl_trait)]
+#![feature(abi_riscv_interrupt)]
+#![feature(impl_trait_in_assoc_type)]
+
+use defmt::{info, warn};
+use embassy_executor::Spawner;
+use embassy_usb::driver::{Direction, EndpointType};
+use hal::usb::host::{
+    DescriptorIter, Device, EndpointDescriptor, HostError, HostState, InterfaceDescriptor, Pipe, UsbHost,
+    descriptor_type,
+};
+use hpm_hal::{bind_interrupts, peripherals};
+use {defmt_rtt as _, hpm_hal as hal};
+
+bind_interrupts!(struct Irqs {
+    USB0 => hal::usb::InterruptHandler<peripherals::USB0>;
+});
+
+/// USB host state - must be in non-cacheable memory for DMA access
+#[unsafe(link_section = ".noncacheable")]
+static HOST_STATE: HostState = HostState::new();
+
+#[unsafe(link_section = ".noncacheable")]
+static mut CONFIG_BUF: [u8; 256] = [0; 256];
+#[unsafe(link_section = ".noncacheable")]
+static mut CBW_BUF: [u8; 31] = [0; 31];
+#[unsafe(link_section = ".noncacheable")]
+static mut CSW_BUF: [u8; 13] = [0; 13];
+#[unsafe(link_section = ".noncacheable")]
+static mut DATA_BUF: [u8; 512] = [0; 512];
+
+const MSC_CLASS: u8 = 0x08;
+const MSC_SUBCLASS_SCSI: u8 = 0x06;
+const MSC_PROTOCOL_BOT: u8 = 0x50;
+
+const CBW_SIGNATURE: u32 = 0x4342_5355;
+const CSW_SIGNATURE: u32 = 0x5342_5355;
+
+#[derive(Debug, defmt::Format)]
+enum MscError {
+    Usb(HostError),
+    NoInterface,
+    BadCsw,
+    CommandFailed(u8),
+}
+
+impl From<HostError> for MscError {
+    fn from(e: HostError) -> Self {
+        MscError::Usb(e)
+    }
+}
+
+/// Bulk-only transport over a pair of bulk pipes
+struct BulkOnly<'d> {
+    bulk_in: Pipe<'d, peripherals::USB0>,
+    bulk_out: Pipe<'d, peripherals::USB0>,
+    tag: u32,
+}
+
+impl<'d> BulkOnly<'d> {
+    /// Run a SCSI command with an optional IN data stage, returns the data length received
+    async fn command(&mut self, cb: &[u8], data: &mut [u8]) -> Result<usize, MscError> {
+        self.tag = self.tag.wrapping_add(1);
+
+        let cbw = unsafe { &mut *core::ptr::addr_of_mut!(CBW_BUF) };
+        cbw.fill(0);
+        cbw[0..4].copy_from_slice(&CBW_SIGNATURE.to_le_bytes());
+        cbw[4..8].copy_from_slice(&self.tag.to_le_bytes());
+        cbw[8..12].copy_from_slice(&(data.len() as u32).to_le_bytes());
+        cbw[12] = if data.is_empty() { 0x00 } else { 0x80 };
+        cbw[13] = 0; // LUN
+        cbw[14] = cb.len() as u8;
+        cbw[15..15 + cb.len()].copy_from_slice(cb);
+        self.bulk_out.write(cbw).await?;
+
+        let mut len = 0;
+        if !data.is_empty() {
+            len = self.bulk_in.read(data).await?;
+        }
+
+        let csw = unsafe { &mut *core::ptr::addr_of_mut!(CSW_BUF) };
+        let n = self.bulk_in.read(csw).await?;
+        if n != 13 || u32::from_le_bytes([csw[0], csw[1], csw[2], csw[3]]) != CSW_SIGNATURE {
+            return Err(MscError::BadCsw);
+        }
+        if u32::from_le_bytes([csw[4], csw[5], csw[6], csw[7]]) != self.tag {
+            return Err(MscError::BadCsw);
+        }
+        match csw[12] {
+            0 => Ok(len),
+            status => Err(MscError::CommandFailed(status)),
+        }
+    }
+}
+
+/// Find the mass storage interface and its bulk endpoints, returns the configuration value
+fn find_msc(config: &[u8]) -> Option<(u8, EndpointDescriptor, EndpointDescriptor)> {
+    let mut config_value = None;
+    let mut in_msc = false;
+    let mut ep_in = None;
+    let mut ep_out = None;
+
+    for desc in DescriptorIter::new(config) {
+        match desc[1] {
+            descriptor_type::CONFIGURATION => config_value = Some(desc[5]),
+            descriptor_type::INTERFACE => {
+                let intf = InterfaceDescriptor::parse(desc)?;
+                in_msc = intf.interface_class == MSC_CLASS
+                    && intf.interface_sub_class == MSC_SUBCLASS_SCSI
+                    && intf.interface_protocol == MSC_PROTOCOL_BOT;
+            }
+            descriptor_type::ENDPOINT if in_msc => {
+                let ep = EndpointDescriptor::parse(desc)?;
+                if ep.ep_type == EndpointType::Bulk {
+                    match ep.direction() {
+                        Direction::In => ep_in = Some(ep),
+                        Direction::Out => ep_out = Some(ep),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Some((config_value?, ep_in?, ep_out?))
+}
+
+async fn run(device: &mut Device<'_, peripherals::USB0>) -> Result<(), MscError> {
+    let config = unsafe { &mut *core::ptr::addr_of_mut!(CONFIG_BUF) };
+    let len = device.get_configuration_descriptor(0, config).await?;
+    let (config_value, ep_in, ep_out) = find_msc(&config[..len]).ok_or(MscError::NoInterface)?;
+
+    device.set_configuration(config_value).await?;
+
+    let mut msc = BulkOnly {
+        bulk_in: device.open_pipe(&ep_in)?,
+        bulk_out: device.open_pipe(&ep_out)?,
+        tag: 0,
+    };
+    let data = unsafe { &mut *core::ptr::addr_of_mut!(DATA_BUF) };
+
+    // INQUIRY
+    let n = msc.command(&[0x12, 0, 0, 0, 36, 0], &mut data[..36]).await?;
+    if n >= 36 {
+        info!("Vendor: {=[u8]:a}, product: {=[u8]:a}", &data[8..16], &data[16..32]);
+    }
+
+    // TEST UNIT READY, the medium may need some time after power up
+    let mut ready = false;
+    for _ in 0..10 {
+        if msc.command(&[0x00, 0, 0, 0, 0, 0], &mut []).await.is_ok() {
+            ready = true;
+            break;
+        }
+        embassy_time::Timer::after_millis(100).await;
+    }
+    if !ready {
+        warn!("Medium not ready");
+    }
+
+    // READ CAPACITY(10)
+    msc.command(&[0x25, 0, 0, 0, 0, 0, 0, 0, 0, 0], &mut data[..8]).await?;
+    let last_lba = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+    let block_size = u32::from_be_bytes([data[4], data[5], data[6], data[7]]);
+    info!(
+        "Capacity: {} blocks of {} bytes, {} MiB",
+        last_lba + 1,
+        block_size,
+        (last_lba as u64 + 1) * block_size as u64 / 1024 / 1024
+    );
+
+    // READ(10), block 0
+    msc.command(&[0x28, 0, 0, 0, 0, 0, 0, 0, 1, 0], &mut data[..512])
+        .await?;
+    if data[510] == 0x55 && data[511] == 0xAA {
+        for i in 0..4 {
+            let entry = &data[446 + i * 16..446 + (i + 1) * 16];
+            if entry[4] != 0 {
+                let start = u32::from_le_bytes([entry[8], entry[9], entry[10], entry[11]]);
+                let sectors = u32::from_le_bytes([entry[12], entry[13], entry[14], entry[15]]);
+                info!(
+                    "Partition {}: type 0x{:02x}, start {}, {} sectors",
+                    i, entry[4], start, sectors
+                );
+            }
+        }
+    } else {
+        warn!("No MBR signature in block 0");
+    }
+
+    Ok(())
+}
+
+#[embassy_executor::main(entry = "hpm_hal::entry")]
+async fn main(_spawner: Spawner) -> ! {
+    let p = hal::init(Default::default());
+
+    let mut host = UsbHost::new(p.USB0, Irqs, Default::default(), &HOST_STATE);
+
+    loop {
+        info!("Waiting for device");
+        let speed = match host.wait_for_connection().await {
+            Ok(speed) => speed,
+            Err(e) => {
+                warn!("Port reset failed: {:?}", e);
+                host.wait_for_disconnection().await;
+                continue;
+            }
+        };
+        info!("Device connected, {:?} speed", speed);
+
+        match host.enumerate(1).await {
+            Ok(mut device) => {
+                let desc = device.info().descriptor;
+                info!("VID {:04x} PID {:04x}", desc.vendor_id, desc.product_id);
+                if let Err(e) = run(&mut device).await {
+                    warn!("Mass storage error: {:?}", e);
+                }
+            }
+            Err(e) => warn!("Enumeration failed: {:?}", e),
+        }
+
+        host.wait_for_disconnection().await;
+        info!("Device removed");
+    }
+}
@@ -17,13 +17,13 @@
 //! use hpm_hal::dao::{Dao, Config};
 //!
 //! let config = Config::default();
-//! let mut dao = Dao::new(
+//! let mut dao = Dao::new_right_channel(
 //!     p.DAO,
 //!     p.I2S1,
 //!     p.PF04, // DAO_RP
-//!     p.PF03, // DAO_RN (optional for mono output)
+//!     p.PF03, // DAO_RN
 //!     config,
-//! );
+//! ).unwrap();
 //!
 //! dao.start();
 //!
@@ -34,6 +34,12 @@
 //!
 //! dao.stop();
 //! ```
+//!
+//! # Stereo, sample rate and mute
+//!
+//! [`Dao::new_stereo`] drives both channel pin pairs from interleaved samples `[L0, R0, L1, R1, ...]`.
+//! [`Config::oversampling`] plans the audio clock for [`Config::sample_rate`], e.g. 44.1kHz.
+//! [`Dao::set_mute`] ramps the output in software over [`Config::soft_ramp`] frames to avoid pops.
 
 use embassy_hal_internal::{Peri, PeripheralType};
 
@@ -78,10 +84,17 @@ pub struct Config {
     pub enable_remap: bool,
     /// Default output level when disabled
     pub default_output_level: DefaultOutputLevel,
-    /// Enable left channel
+    /// Enable left channel, set by the constructors from the pins given
     pub left_channel: bool,
-    /// Enable right channel
+    /// Enable right channel, set by the constructors from the pins given
     pub right_channel: bool,
+    /// Oversampling ratio of the modulator, MCLK = `sample_rate * oversampling` (e.g. 512)
+    ///
+    /// `Some` plans the I2S1 audio clock for it, see [`crate::sysctl::plan_audio_clock`].
+    /// `None` keeps the current audio clock, 24.576MHz by default.
+    pub oversampling: Option<u32>,
+    /// Length of the mute/unmute soft ramp in stereo frames, 0 to switch immediately
+    pub soft_ramp: u16,
 }
 
 impl Default for Config {
@@ -96,6 +109,8 @@ impl Default for Config {
             default_output_level: DefaultOutputLevel::AllLow,
             left_channel: true,
             right_channel: true,
+            oversampling: None,
+            soft_ramp: 512,
         }
     }
 }
@@ -149,6 +164,256 @@ pub trait I2sInstance: PeripheralType + crate::sysctl::ClockPeripheral + crate::
     fn i2s_regs() -> I2sRegs;
 }
 
+// - MARK: Configuration
+
+/// Set up the audio clock, the I2S TX feeding the DAO and the DAO itself
+///
+/// The DAO is left in reset, `start` releases it. Returns [`Error::InvalidConfig`] if the audio
+/// PLL can't produce the MCLK for the sample rate and oversampling.
+fn configure<T: Instance, I: I2sInstance>(config: &Config) -> Result<(), Error> {
+    // Enable peripheral clocks
+    T::add_resource_group(0);
+    I::add_resource_group(0);
+
+    // Configure I2S1 clock source to AUD1 (24.576MHz for 48kHz sample rate)
+    // DAO uses I2S1, which needs AUD1 clock
+    // For HPM6E00: I2S1 uses AUD1 via mux=AUD0
+    #[cfg(hpm6e)]
+    crate::sysctl::set_i2s_clock_source(1, false); // I2S1 uses AUD1
+
+    #[cfg(hpm67)]
+    crate::sysctl::set_i2s_clock_source(1, crate::sysctl::I2sClkMux::I2S1);
+
+    #[cfg(any(hpm6e, hpm67))]
+    if let Some(oversampling) = config.oversampling {
+        let plan =
+            crate::sysctl::configure_audio_clock_for_rate(1, crate::time::Hertz(config.sample_rate), oversampling)
+                .map_err(|_| Error::InvalidConfig)?;
+        if plan.error_ppm() != 0 {
+            #[cfg(feature = "defmt")]
+            defmt::warn!("DAO: MCLK {} Hz, {} ppm off", plan.mclk.0, plan.error_ppm());
+        }
+    }
+
+    let dao_regs = T::regs();
+    let i2s_regs = I::i2s_regs();
+
+    // Stop and reset DAO
+    dao_regs.cmd().write(|w| {
+        w.set_run(false);
+        w.set_sftrst(true);
+    });
+
+    // Reset I2S completely (like C SDK's i2s_reset_all)
+    // First disable I2S
+    i2s_regs.ctrl().modify(|w| w.set_i2s_en(false));
+
+    // Enable internal clock for software reset (required for reset to work)
+    i2s_regs.cfgr().modify(|w| {
+        w.set_bclk_div(1);
+        w.set_mck_sel_op(false);
+        w.set_bclk_sel_op(false);
+        w.set_fclk_sel_op(false);
+        w.set_bclk_gateoff(false);
+    });
+    i2s_regs.misc_cfgr().modify(|w| w.set_mclk_gateoff(false));
+
+    // Reset all: TX, RX, clock generator, and clear FIFOs
+    i2s_regs.ctrl().modify(|w| {
+        w.set_sftrst_tx(true);
+        w.set_sftrst_rx(true);
+        w.set_sftrst_clkgen(true);
+        w.set_txfifoclr(true);
+        w.set_rxfifoclr(true);
+    });
+    // Clear reset bits
+    i2s_regs.ctrl().modify(|w| {
+        w.set_sftrst_tx(false);
+        w.set_sftrst_rx(false);
+        w.set_sftrst_clkgen(false);
+        w.set_txfifoclr(false);
+        w.set_rxfifoclr(false);
+    });
+
+    // Get I2S MCLK frequency (should be 24.576MHz for 48kHz)
+    #[cfg(hpm6e)]
+    let mclk_freq = crate::sysctl::get_i2s_clock_freq(1).0;
+    #[cfg(hpm67)]
+    let mclk_freq = crate::sysctl::get_audio_clock_freq(1).0;
+    #[cfg(not(any(hpm6e, hpm67)))]
+    let mclk_freq = 24_576_000u32;
+
+    // Calculate BCLK frequency: sample_rate * channel_length_bits * num_channels
+    let channel_bits: u32 = match config.format {
+        Format::Data16Channel16 => 16,
+        _ => 32, // Data16Channel32, Data24Channel32, Data32Channel32
+    };
+    let bclk_freq = config.sample_rate * channel_bits * 2; // 2 channels (stereo)
+
+    // Calculate BCLK divider: MCLK / BCLK_DIV = BCLK
+    // For 48kHz stereo 32-bit: BCLK = 48000 * 32 * 2 = 3,072,000 Hz
+    // BCLK_DIV = 24,576,000 / 3,072,000 = 8
+    let bclk_div = if bclk_freq > 0 {
+        mclk_freq / bclk_freq
+    } else {
+        8 // Default for 48kHz stereo 32-bit
+    };
+    let bclk_div = bclk_div.clamp(1, 511) as u16; // BCLK_DIV is 9 bits
+
+    // Gate BCLK before configuration
+    i2s_regs.cfgr().modify(|w| w.set_bclk_gateoff(true));
+
+    // Configure I2S format with BCLK divider
+    i2s_regs.cfgr().modify(|w| {
+        w.set_datsiz(config.format.data_size());
+        w.set_chsiz(config.format.channel_size());
+        w.set_std(config.standard.to_pac());
+        w.set_tdm_en(false);
+        w.set_ch_max(2); // Stereo
+        w.set_bclk_div(bclk_div);
+        // Use internal clock sources (master mode)
+        w.set_mck_sel_op(false);
+        w.set_fclk_sel_op(false);
+        w.set_bclk_sel_op(false);
+    });
+
+    // Ungate BCLK and MCLK
+    i2s_regs.cfgr().modify(|w| w.set_bclk_gateoff(false));
+    i2s_regs.misc_cfgr().modify(|w| w.set_mclk_gateoff(false));
+
+    // Configure I2S TX FIFO threshold
+    i2s_regs.fifo_thresh().modify(|w| {
+        w.set_tx(4); // TX threshold
+    });
+
+    // Configure slot mask for TX line 0
+    i2s_regs.txdslot(0).write(|w| w.set_en(0x3)); // Channels 0 and 1
+
+    // Enable TX line 0 and TX_DMA_EN
+    // Note: TX_DMA_EN must be set for DAO to read from I2S TX FIFO,
+    // even in blocking mode. This is how DAO gets data from I2S.
+    // Use read-modify-write pattern to ensure bits are properly set
+    let mut ctrl = i2s_regs.ctrl().read();
+    ctrl.set_tx_en(1); // Enable line 0
+    ctrl.set_tx_dma_en(true); // Required for DAO to read from I2S TX FIFO
+    i2s_regs.ctrl().write_value(ctrl);
+
+    // Configure DAO
+    dao_regs.ctrl().write(|w| {
+        w.set_mono(config.mono);
+        w.set_left_en(config.left_channel);
+        w.set_right_en(config.right_channel);
+        w.set_remap(config.enable_remap);
+        w.set_hpf_en(config.enable_hpf);
+        w.set_false_level(config.default_output_level as u8);
+        w.set_false_run(false);
+        w.set_invert(false);
+    });
+
+    // Configure DAO RX format (DAO receives from I2S TX), must match the I2S TX format
+    // Data size: 0=16bit, 1=24bit, 2=32bit
+    let datsiz: u8 = match config.format {
+        Format::Data16Channel16 | Format::Data16Channel32 => 0,
+        Format::Data24Channel32 => 1,
+        Format::Data32Channel32 => 2,
+    };
+
+    // Channel size: false=16bit, true=32bit
+    let chsiz = !matches!(config.format, Format::Data16Channel16);
+
+    // Standard: 0=Philips, 1=MSB, 2=LSB, 3=PCM
+    let std: u8 = match config.standard {
+        Standard::Philips => 0,
+        Standard::MsbJustified => 1,
+        Standard::LsbJustified => 2,
+        Standard::Pcm => 3,
+    };
+
+    // The PAC only generates CH_MAX for RxCfgr, the other fields are written as raw bits.
+    // TDM_EN and FRAME_EDGE (falling edge) stay cleared.
+    dao_regs.rx_cfgr().write(|w| {
+        w.0 = (chsiz as u32) << RX_CFGR_CHSIZ_SHIFT
+            | (datsiz as u32) << RX_CFGR_DATSIZ_SHIFT
+            | (std as u32) << RX_CFGR_STD_SHIFT;
+        w.set_ch_max(2); // Stereo
+    });
+
+    // Configure slot mask - enable channels 0 and 1
+    dao_regs.rxslt().write(|w| w.set_en(0x3));
+
+    Ok(())
+}
+
+/// RX_CFGR field positions, same layout as the I2S CFGR
+const RX_CFGR_CHSIZ_SHIFT: u32 = 0;
+const RX_CFGR_DATSIZ_SHIFT: u32 = 1;
+const RX_CFGR_STD_SHIFT: u32 = 3;
+
+// - MARK: Soft mute
+
+/// Unity gain, Q15
+const GAIN_UNITY: u32 = 1 << 15;
+
+/// Mute gain ramp applied to samples in software, DAO has no volume control
+///
+/// The gain steps once per stereo frame, so that both channels of a frame get the same gain.
+struct SoftMute {
+    gain: u32,
+    target: u32,
+    step: u32,
+    /// Next sample is the right channel of the frame
+    right: bool,
+}
+
+impl SoftMute {
+    fn new(ramp: u16) -> Self {
+        Self {
+            gain: GAIN_UNITY,
+            target: GAIN_UNITY,
+            step: GAIN_UNITY.div_ceil(ramp.max(1) as u32),
+            right: false,
+        }
+    }
+
+    fn set_mute(&mut self, mute: bool) {
+        self.target = if mute { 0 } else { GAIN_UNITY };
+    }
+
+    fn is_muted(&self) -> bool {
+        self.target == 0
+    }
+
+    /// No gain to apply, samples can be passed through
+    fn is_bypass(&self) -> bool {
+        self.gain == GAIN_UNITY && self.target == GAIN_UNITY
+    }
+
+    /// Keep track of the frame position for samples passed through
+    fn skip(&mut self, samples: usize) {
+        self.right ^= samples % 2 == 1;
+    }
+
+    /// Apply the gain to a 32-bit left-justified sample, the ramp advances after the right channel
+    fn apply(&mut self, sample: u32) -> u32 {
+        let out = ((sample as i32 as i64 * self.gain as i64) >> 15) as i32 as u32;
+
+        self.right = !self.right;
+        if self.right {
+            return out;
+        }
+        if self.gain < self.target {
+            self.gain = (self.gain + self.step).min(self.target);
+        } else if self.gain > self.target {
+            self.gain = self.gain.saturating_sub(self.step).max(self.target);
+        }
+        out
+    }
+}
+
+/// Samples are ramped through a stack buffer of this size before going to the DMA ring buffer
+#[cfg(ip_feature_dma_v2)]
+const RAMP_CHUNK: usize = 32;
+
 // - MARK: Driver
 
 /// DAO driver (blocking mode)
@@ -158,6 +423,7 @@ pub trait I2sInstance: PeripheralType + crate::sysctl::ClockPeripheral + crate::
 pub struct Dao<'d, T: Instance, I: I2sInstance> {
     _dao: Peri<'d, T>,
     _i2s: Peri<'d, I>,
+    soft_mute: SoftMute,
     config: Config,
 }
 
@@ -175,183 +441,64 @@ impl<'d, T: Instance, I: I2sInstance> Dao<'d, T, I> {
         i2s: Peri<'d, I>,
         rp: Peri<'d, impl RpPin<T>>,
         rn: Peri<'d, impl RnPin<T>>,
-        config: Config,
-    ) -> Self {
+        mut config: Config,
+    ) -> Result<Self, Error> {
         // Configure pins
         rp.set_as_alt(rp.alt_num());
         rn.set_as_alt(rn.alt_num());
 
+        config.left_channel = false;
+        config.right_channel = true;
         Self::new_inner(dao, i2s, config)
     }
 
-    fn new_inner(dao: Peri<'d, T>, i2s: Peri<'d, I>, config: Config) -> Self {
-        // Enable peripheral clocks
-        T::add_resource_group(0);
-        I::add_resource_group(0);
-
-        // Configure I2S1 clock source to AUD1 (24.576MHz for 48kHz sample rate)
-        // DAO uses I2S1, which needs AUD1 clock
-        // For HPM6E00: I2S1 uses AUD1 via mux=AUD0
-        #[cfg(hpm6e)]
-        crate::sysctl::set_i2s_clock_source(1, false); // I2S1 uses AUD1
-
-        #[cfg(hpm67)]
-        crate::sysctl::set_i2s_clock_source(1, crate::sysctl::I2sClkMux::I2S1);
-
-        let dao_regs = T::regs();
-        let i2s_regs = I::i2s_regs();
-
-        // Stop and reset DAO
-        dao_regs.cmd().write(|w| {
-            w.set_run(false);
-            w.set_sftrst(true);
-        });
-
-        // Reset I2S completely (like C SDK's i2s_reset_all)
-        // First disable I2S
-        i2s_regs.ctrl().modify(|w| w.set_i2s_en(false));
-
-        // Enable internal clock for software reset (required for reset to work)
-        i2s_regs.cfgr().modify(|w| {
-            w.set_bclk_div(1);
-            w.set_mck_sel_op(false);
-            w.set_bclk_sel_op(false);
-            w.set_fclk_sel_op(false);
-            w.set_bclk_gateoff(false);
-        });
-        i2s_regs.misc_cfgr().modify(|w| w.set_mclk_gateoff(false));
-
-        // Reset all: TX, RX, clock generator, and clear FIFOs
-        i2s_regs.ctrl().modify(|w| {
-            w.set_sftrst_tx(true);
-            w.set_sftrst_rx(true);
-            w.set_sftrst_clkgen(true);
-            w.set_txfifoclr(true);
-            w.set_rxfifoclr(true);
-        });
-        // Clear reset bits
-        i2s_regs.ctrl().modify(|w| {
-            w.set_sftrst_tx(false);
-            w.set_sftrst_rx(false);
-            w.set_sftrst_clkgen(false);
-            w.set_txfifoclr(false);
-            w.set_rxfifoclr(false);
-        });
-
-        // Get I2S MCLK frequency (should be 24.576MHz for 48kHz)
-        #[cfg(hpm6e)]
-        let mclk_freq = crate::sysctl::get_i2s_clock_freq(1).0;
-        #[cfg(hpm67)]
-        let mclk_freq = crate::sysctl::get_audio_clock_freq(1).0;
-        #[cfg(not(any(hpm6e, hpm67)))]
-        let mclk_freq = 24_576_000u32;
-
-        // Calculate BCLK frequency: sample_rate * channel_length_bits * num_channels
-        let channel_bits: u32 = match config.format {
-            Format::Data16Channel16 => 16,
-            _ => 32, // Data16Channel32, Data24Channel32, Data32Channel32
-        };
-        let bclk_freq = config.sample_rate * channel_bits * 2; // 2 channels (stereo)
-
-        // Calculate BCLK divider: MCLK / BCLK_DIV = BCLK
-        // For 48kHz stereo 32-bit: BCLK = 48000 * 32 * 2 = 3,072,000 Hz
-        // BCLK_DIV = 24,576,000 / 3,072,000 = 8
-        let bclk_div = if bclk_freq > 0 {
-            mclk_freq / bclk_freq
-        } else {
-            8 // Default for 48kHz stereo 32-bit
-        };
-        let bclk_div = bclk_div.clamp(1, 511) as u16; // BCLK_DIV is 9 bits
-
-        // Gate BCLK before configuration
-        i2s_regs.cfgr().modify(|w| w.set_bclk_gateoff(true));
-
-        // Configure I2S format with BCLK divider
-        i2s_regs.cfgr().modify(|w| {
-            w.set_datsiz(config.format.data_size());
-            w.set_chsiz(config.format.channel_size());
-            w.set_std(config.standard.to_pac());
-            w.set_tdm_en(false);
-            w.set_ch_max(2); // Stereo
-            w.set_bclk_div(bclk_div);
-            // Use internal clock sources (master mode)
-            w.set_mck_sel_op(false);
-            w.set_fclk_sel_op(false);
-            w.set_bclk_sel_op(false);
-        });
-
-        // Ungate BCLK and MCLK
-        i2s_regs.cfgr().modify(|w| w.set_bclk_gateoff(false));
-        i2s_regs.misc_cfgr().modify(|w| w.set_mclk_gateoff(false));
-
-        // Configure I2S TX FIFO threshold
-        i2s_regs.fifo_thresh().modify(|w| {
-            w.set_tx(4); // TX threshold
-        });
-
-        // Configure slot mask for TX line 0
-        i2s_regs.txdslot(0).write(|w| w.set_en(0x3)); // Channels 0 and 1
-
-        // Enable TX line 0 and TX_DMA_EN
-        // Note: TX_DMA_EN must be set for DAO to read from I2S TX FIFO,
-        // even in blocking mode. This is how DAO gets data from I2S.
-        // Use read-modify-write pattern to ensure bits are properly set
-        let mut ctrl = i2s_regs.ctrl().read();
-        ctrl.set_tx_en(1); // Enable line 0
-        ctrl.set_tx_dma_en(true); // Required for DAO to read from I2S TX FIFO
-        i2s_regs.ctrl().write_value(ctrl);
-
-        // Configure DAO
-        dao_regs.ctrl().write(|w| {
-            w.set_mono(config.mono);
-            w.set_left_en(config.left_channel);
-            w.set_right_en(config.right_channel);
-            w.set_remap(config.enable_remap);
-            w.set_hpf_en(config.enable_hpf);
-            w.set_false_level(config.default_output_level as u8);
-            w.set_false_run(false);
-            w.set_invert(false);
-        });
-
-        // Configure DAO RX format (DAO receives from I2S TX)
-        // Data size: 0=16bit, 1=24bit, 2=32bit
-        let datsiz: u8 = match config.format {
-            Format::Data16Channel16 | Format::Data16Channel32 => 0,
-            Format::Data24Channel32 => 1,
-            Format::Data32Channel32 => 2,
-        };
-
-        // Channel size: false=16bit, true=32bit
-        let chsiz = !matches!(config.format, Format::Data16Channel16);
+    /// Create a new DAO driver with left channel only
+    pub fn new_left_channel(
+        dao: Peri<'d, T>,
+        i2s: Peri<'d, I>,
+        lp: Peri<'d, impl LpPin<T>>,
+        ln: Peri<'d, impl LnPin<T>>,
+        mut config: Config,
+    ) -> Result<Self, Error> {
+        lp.set_as_alt(lp.alt_num());
+        ln.set_as_alt(ln.alt_num());
+
+        config.left_channel = true;
+        config.right_channel = false;
+        Self::new_inner(dao, i2s, config)
+    }
 
-        // Standard: 0=Philips, 1=MSB, 2=LSB, 3=PCM
-        let std: u8 = match config.standard {
-            Standard::Philips => 0,
-            Standard::MsbJustified => 1,
-            Standard::LsbJustified => 2,
-            Standard::Pcm => 3,
-        };
+    /// Create a new DAO driver with both channels, e.g. for a stereo speaker pair
+    ///
+    /// Samples are interleaved: [L0, R0, L1, R1, ...]
+    pub fn new_stereo(
+        dao: Peri<'d, T>,
+        i2s: Peri<'d, I>,
+        rp: Peri<'d, impl RpPin<T>>,
+        rn: Peri<'d, impl RnPin<T>>,
+        lp: Peri<'d, impl LpPin<T>>,
+        ln: Peri<'d, impl LnPin<T>>,
+        mut config: Config,
+    ) -> Result<Self, Error> {
+        rp.set_as_alt(rp.alt_num());
+        rn.set_as_alt(rn.alt_num());
+        lp.set_as_alt(lp.alt_num());
+        ln.set_as_alt(ln.alt_num());
 
-        // Configure DAO RX format using PAC API
-        // TODO: fix PAC API - these methods are not generated for RxCfgr
-        dao_regs.rx_cfgr().write(|w| {
-            // w.set_chsiz(chsiz);
-            // w.set_datsiz(datsiz);
-            // w.set_std(std);
-            // w.set_tdm_en(false);
-            w.set_ch_max(2); // Stereo
-            // w.set_frame_edge(false); // Falling edge
-            let _ = (chsiz, datsiz, std);
-        });
+        config.left_channel = true;
+        config.right_channel = true;
+        Self::new_inner(dao, i2s, config)
+    }
 
-        // Configure slot mask - enable channels 0 and 1
-        dao_regs.rxslt().write(|w| w.set_en(0x3));
+    fn new_inner(dao: Peri<'d, T>, i2s: Peri<'d, I>, config: Config) -> Result<Self, Error> {
+        configure::<T, I>(&config)?;
 
-        Self {
+        Ok(Self {
             _dao: dao,
             _i2s: i2s,
+            soft_mute: SoftMute::new(config.soft_ramp),
             config,
-        }
+        })
     }
 
     /// Start DAO playback
@@ -437,6 +584,7 @@ impl<'d, T: Instance, I: I2sInstance> Dao<'d, T, I> {
             core::hint::spin_loop();
         }
 
+        let sample = self.soft_mute.apply(sample);
         i2s_regs.txd(0).write(|w| w.set_d(sample));
     }
 
@@ -457,10 +605,21 @@ impl<'d, T: Instance, I: I2sInstance> Dao<'d, T, I> {
             return Err(Error::Underrun);
         }
 
+        let sample = self.soft_mute.apply(sample);
         I::i2s_regs().txd(0).write(|w| w.set_d(sample));
         Ok(())
     }
 
+    /// Mute or unmute the output, ramping over [`Config::soft_ramp`] frames
+    pub fn set_mute(&mut self, mute: bool) {
+        self.soft_mute.set_mute(mute);
+    }
+
+    /// Check if the output is muted or ramping down to mute
+    pub fn is_muted(&self) -> bool {
+        self.soft_mute.is_muted()
+    }
+
     /// Enable high-pass filter for DC removal
     pub fn enable_hpf(&mut self) {
         T::regs().ctrl().modify(|w| w.set_hpf_en(true));
@@ -596,7 +755,7 @@ impl I2sInstance for crate::peripherals::I2S0 {
 ///     p.PF04, // DAO_RP
 ///     p.PF03, // DAO_RN
 ///     Config::default(),
-/// );
+/// ).unwrap();
 ///
 /// dao.start();
 /// dao.write_exact(&audio_data).await?;
@@ -607,6 +766,7 @@ pub struct DaoDma<'d, T: Instance, I: I2sInstance> {
     _dao: Peri<'d, T>,
     _i2s: Peri<'d, I>,
     ringbuf: WritableRingBuffer<'d, u32>,
+    soft_mute: SoftMute,
     config: Config,
 }
 
@@ -629,159 +789,72 @@ impl<'d, T: Instance, I: I2sInstance> DaoDma<'d, T, I> {
         dma_buf: &'d mut [u32],
         rp: Peri<'d, impl RpPin<T>>,
         rn: Peri<'d, impl RnPin<T>>,
-        config: Config,
-    ) -> Self {
+        mut config: Config,
+    ) -> Result<Self, Error> {
         // Configure pins
         rp.set_as_alt(rp.alt_num());
         rn.set_as_alt(rn.alt_num());
 
+        config.left_channel = false;
+        config.right_channel = true;
         Self::new_inner(dao, i2s, dma_ch, dma_buf, config)
     }
 
-    fn new_inner<DMA: Channel + crate::i2s::TxDma<I>>(
+    /// Create a new DAO DMA driver with left channel only
+    pub fn new_left_channel<DMA: Channel + crate::i2s::TxDma<I>>(
         dao: Peri<'d, T>,
         i2s: Peri<'d, I>,
         dma_ch: Peri<'d, DMA>,
         dma_buf: &'d mut [u32],
-        config: Config,
-    ) -> Self {
-        // Enable peripheral clocks
-        T::add_resource_group(0);
-        I::add_resource_group(0);
+        lp: Peri<'d, impl LpPin<T>>,
+        ln: Peri<'d, impl LnPin<T>>,
+        mut config: Config,
+    ) -> Result<Self, Error> {
+        lp.set_as_alt(lp.alt_num());
+        ln.set_as_alt(ln.alt_num());
+
+        config.left_channel = true;
+        config.right_channel = false;
+        Self::new_inner(dao, i2s, dma_ch, dma_buf, config)
+    }
 
-        // Configure I2S1 clock source to AUD1 (24.576MHz for 48kHz sample rate)
-        #[cfg(hpm6e)]
-        crate::sysctl::set_i2s_clock_source(1, false); // I2S1 uses AUD1
+    /// Create a new DAO DMA driver with both channels, e.g. for a stereo speaker pair
+    ///
+    /// Samples are interleaved: [L0, R0, L1, R1, ...]
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_stereo<DMA: Channel + crate::i2s::TxDma<I>>(
+        dao: Peri<'d, T>,
+        i2s: Peri<'d, I>,
+        dma_ch: Peri<'d, DMA>,
+        dma_buf: &'d mut [u32],
+        rp: Peri<'d, impl RpPin<T>>,
+        rn: Peri<'d, impl RnPin<T>>,
+        lp: Peri<'d, impl LpPin<T>>,
+        ln: Peri<'d, impl LnPin<T>>,
+        mut config: Config,
+    ) -> Result<Self, Error> {
+        rp.set_as_alt(rp.alt_num());
+        rn.set_as_alt(rn.alt_num());
+        lp.set_as_alt(lp.alt_num());
+        ln.set_as_alt(ln.alt_num());
 
-        #[cfg(hpm67)]
-        crate::sysctl::set_i2s_clock_source(1, crate::sysctl::I2sClkMux::I2S1);
+        config.left_channel = true;
+        config.right_channel = true;
+        Self::new_inner(dao, i2s, dma_ch, dma_buf, config)
+    }
+
+    fn new_inner<DMA: Channel + crate::i2s::TxDma<I>>(
+        dao: Peri<'d, T>,
+        i2s: Peri<'d, I>,
+        dma_ch: Peri<'d, DMA>,
+        dma_buf: &'d mut [u32],
+        config: Config,
+    ) -> Result<Self, Error> {
+        configure::<T, I>(&config)?;
 
         let dao_regs = T::regs();
         let i2s_regs = I::i2s_regs();
 
-        // Stop and reset DAO
-        dao_regs.cmd().write(|w| {
-            w.set_run(false);
-            w.set_sftrst(true);
-        });
-
-        // Reset I2S completely
-        i2s_regs.ctrl().modify(|w| w.set_i2s_en(false));
-
-        // Enable internal clock for software reset
-        i2s_regs.cfgr().modify(|w| {
-            w.set_bclk_div(1);
-            w.set_mck_sel_op(false);
-            w.set_bclk_sel_op(false);
-            w.set_fclk_sel_op(false);
-            w.set_bclk_gateoff(false);
-        });
-        i2s_regs.misc_cfgr().modify(|w| w.set_mclk_gateoff(false));
-
-        // Reset TX and clear FIFOs
-        i2s_regs.ctrl().modify(|w| {
-            w.set_sftrst_tx(true);
-            w.set_txfifoclr(true);
-        });
-        i2s_regs.ctrl().modify(|w| {
-            w.set_sftrst_tx(false);
-            w.set_txfifoclr(false);
-        });
-
-        // Get I2S MCLK frequency
-        #[cfg(hpm6e)]
-        let mclk_freq = crate::sysctl::get_i2s_clock_freq(1).0;
-        #[cfg(hpm67)]
-        let mclk_freq = crate::sysctl::get_audio_clock_freq(1).0;
-        #[cfg(not(any(hpm6e, hpm67)))]
-        let mclk_freq = 24_576_000u32;
-
-        // Calculate BCLK frequency
-        let channel_bits: u32 = match config.format {
-            Format::Data16Channel16 => 16,
-            _ => 32,
-        };
-        let bclk_freq = config.sample_rate * channel_bits * 2;
-
-        // Calculate BCLK divider
-        let bclk_div = if bclk_freq > 0 {
-            mclk_freq / bclk_freq
-        } else {
-            8
-        };
-        let bclk_div = bclk_div.clamp(1, 511) as u16;
-
-        // Configure I2S format
-        i2s_regs.cfgr().modify(|w| w.set_bclk_gateoff(true));
-        i2s_regs.cfgr().modify(|w| {
-            w.set_datsiz(config.format.data_size());
-            w.set_chsiz(config.format.channel_size());
-            w.set_std(config.standard.to_pac());
-            w.set_tdm_en(false);
-            w.set_ch_max(2);
-            w.set_bclk_div(bclk_div);
-            w.set_mck_sel_op(false);
-            w.set_fclk_sel_op(false);
-            w.set_bclk_sel_op(false);
-        });
-
-        // Ungate clocks
-        i2s_regs.cfgr().modify(|w| w.set_bclk_gateoff(false));
-        i2s_regs.misc_cfgr().modify(|w| w.set_mclk_gateoff(false));
-
-        // Configure I2S TX FIFO threshold
-        i2s_regs.fifo_thresh().modify(|w| {
-            w.set_tx(4);
-        });
-
-        // Configure slot mask for TX line 0
-        i2s_regs.txdslot(0).write(|w| w.set_en(0x3));
-
-        // Enable TX line 0 and TX DMA
-        // Use read-modify-write pattern to ensure bits are properly set
-        let mut ctrl = i2s_regs.ctrl().read();
-        ctrl.set_tx_en(1);
-        ctrl.set_tx_dma_en(true);
-        i2s_regs.ctrl().write_value(ctrl);
-
-        // Configure DAO
-        dao_regs.ctrl().write(|w| {
-            w.set_mono(config.mono);
-            w.set_left_en(config.left_channel);
-            w.set_right_en(config.right_channel);
-            w.set_remap(config.enable_remap);
-            w.set_hpf_en(config.enable_hpf);
-            w.set_false_level(config.default_output_level as u8);
-            w.set_false_run(false);
-            w.set_invert(false);
-        });
-
-        // Configure DAO RX format
-        let datsiz: u8 = match config.format {
-            Format::Data16Channel16 | Format::Data16Channel32 => 0,
-            Format::Data24Channel32 => 1,
-            Format::Data32Channel32 => 2,
-        };
-        let chsiz = !matches!(config.format, Format::Data16Channel16);
-        let std: u8 = match config.standard {
-            Standard::Philips => 0,
-            Standard::MsbJustified => 1,
-            Standard::LsbJustified => 2,
-            Standard::Pcm => 3,
-        };
-
-        dao_regs.rx_cfgr().write(|w| {
-            w.set_chsiz(chsiz);
-            w.set_datsiz(datsiz);
-            w.set_std(std);
-            w.set_tdm_en(false);
-            w.set_ch_max(2);
-            w.set_frame_edge(false);
-        });
-
-        // Configure slot mask
-        dao_regs.rxslt().write(|w| w.set_en(0x3));
-
         // Get DMA request number
         let request = dma_ch.request();
         #[cfg(feature = "defmt")]
@@ -801,12 +874,13 @@ impl<'d, T: Instance, I: I2sInstance> DaoDma<'d, T, I> {
             w.set_sftrst(false);
         });
 
-        Self {
+        Ok(Self {
             _dao: dao,
             _i2s: i2s,
             ringbuf,
+            soft_mute: SoftMute::new(config.soft_ramp),
             config,
-        }
+        })
     }
 
     /// Start DAO playback with DMA
@@ -895,13 +969,71 @@ impl<'d, T: Instance, I: I2sInstance> DaoDma<'d, T, I> {
     /// Write samples to the ring buffer
     ///
     /// Returns (samples_written, samples_remaining_to_write)
-    pub fn write(&mut self, buf: &[u32]) -> Result<(usize, usize), dma::ringbuffer::Error> {
-        self.ringbuf.write(buf)
+    ///
+    /// Returns [`Error::Underrun`] without writing if the DMA ran out of samples since the last write.
+    /// The ring buffer is resynced then, write again to continue.
+    pub fn write(&mut self, buf: &[u32]) -> Result<(usize, usize), Error> {
+        if self.ringbuf.take_underrun() {
+            return Err(Error::Underrun);
+        }
+        if self.soft_mute.is_bypass() {
+            let (written, remaining) = self.ringbuf.write(buf).map_err(|_| Error::Underrun)?;
+            self.soft_mute.skip(written);
+            return Ok((written, remaining));
+        }
+
+        // Only ramp what fits, so that the ramp stays in step with the samples written
+        let writable = self.ringbuf.len().map_err(|_| Error::Underrun)?.min(buf.len());
+        let mut chunk = [0u32; RAMP_CHUNK];
+        for samples in buf[..writable].chunks(RAMP_CHUNK) {
+            for (out, &sample) in chunk.iter_mut().zip(samples) {
+                *out = self.soft_mute.apply(sample);
+            }
+            self.ringbuf
+                .write(&chunk[..samples.len()])
+                .map_err(|_| Error::Underrun)?;
+        }
+        Ok((writable, buf.len() - writable))
+    }
+
+    /// Write all samples, waiting for space in the ring buffer (async)
+    ///
+    /// Underruns are reported like [`Self::write`].
+    pub async fn write_exact(&mut self, buf: &[u32]) -> Result<usize, Error> {
+        if self.ringbuf.take_underrun() {
+            return Err(Error::Underrun);
+        }
+        if self.soft_mute.is_bypass() {
+            let remaining = self.ringbuf.write_exact(buf).await.map_err(|_| Error::Underrun)?;
+            self.soft_mute.skip(buf.len());
+            return Ok(remaining);
+        }
+
+        let mut remaining = 0;
+        let mut chunk = [0u32; RAMP_CHUNK];
+        for samples in buf.chunks(RAMP_CHUNK) {
+            for (out, &sample) in chunk.iter_mut().zip(samples) {
+                *out = self.soft_mute.apply(sample);
+            }
+            remaining = self
+                .ringbuf
+                .write_exact(&chunk[..samples.len()])
+                .await
+                .map_err(|_| Error::Underrun)?;
+        }
+        Ok(remaining)
+    }
+
+    /// Mute or unmute the output, ramping over [`Config::soft_ramp`] frames
+    ///
+    /// The ramp applies to samples written afterwards, samples already in the ring buffer play as is.
+    pub fn set_mute(&mut self, mute: bool) {
+        self.soft_mute.set_mute(mute);
     }
 
-    /// Write all samples (async)
-    pub async fn write_exact(&mut self, buf: &[u32]) -> Result<usize, dma::ringbuffer::Error> {
-        self.ringbuf.write_exact(buf).await
+    /// Check if the output is muted or ramping down to mute
+    pub fn is_muted(&self) -> bool {
+        self.soft_mute.is_muted()
     }
 
     /// Get available space in the buffer
@@ -176,6 +176,7 @@ pub struct WritableDmaRingBuffer<'a, W: Word> {
     dma_buf: &'a mut [W],
     write_index: DmaIndex,
     read_index: DmaIndex,
+    underrun: bool,
 }
 
 impl<'a, W: Word> WritableDmaRingBuffer<'a, W> {
@@ -185,6 +186,7 @@ impl<'a, W: Word> WritableDmaRingBuffer<'a, W> {
             dma_buf,
             write_index: Default::default(),
             read_index: Default::default(),
+            underrun: false,
         }
     }
 
@@ -194,6 +196,14 @@ impl<'a, W: Word> WritableDmaRingBuffer<'a, W> {
         self.read_index.reset();
         self.read_index.dma_sync(self.cap(), dma);
         self.write_index = self.read_index;
+        self.underrun = false;
+    }
+
+    /// Returns true if the DMA consumed more than was written since the last call
+    ///
+    /// The DMA then replays stale data from the buffer.
+    pub fn take_underrun(&mut self) -> bool {
+        core::mem::take(&mut self.underrun)
     }
 
     /// Sync only read_index without modifying write_index
@@ -224,6 +234,7 @@ impl<'a, W: Word> WritableDmaRingBuffer<'a, W> {
             // For TX, this means the buffer is effectively empty.
             // Resync: set write_index = read_index (buffer empty, all space available)
             self.write_index = self.read_index;
+            self.underrun = true;
             Ok(self.cap())
         } else if diff > self.cap() as isize {
             Err(Error::Overrun)
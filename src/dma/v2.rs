@@ -814,6 +814,15 @@ impl<'a, W: Word> WritableRingBuffer<'a, W> {
         self.ringbuf.len(&mut DmaCtrlImpl(self.channel.reborrow()))
     }
 
+    /// Check whether the DMA ran out of data since the last check
+    ///
+    /// On underrun the ring buffer resyncs to the DMA position, writing can continue.
+    pub fn take_underrun(&mut self) -> bool {
+        // Sync with the DMA position, a running-out DMA is detected here
+        let _ = self.len();
+        self.ringbuf.take_underrun()
+    }
+
     /// Get the buffer capacity
     pub const fn capacity(&self) -> usize {
         self.ringbuf.cap()
@@ -11,12 +11,15 @@
 
 use vcell::VolatileCell;
 
-/// TX buffer size (MTU + headers)
+/// Default TX buffer size (MTU + headers)
 pub const TX_BUFFER_SIZE: usize = 1536;
 
-/// RX buffer size (MTU + headers)
+/// Default RX buffer size (MTU + headers)
 pub const RX_BUFFER_SIZE: usize = 1536;
 
+/// Largest buffer a descriptor can point to (13-bit size, word aligned)
+pub const MAX_BUFFER_SIZE: usize = 8188;
+
 /// Descriptor size in memory (aligned to cacheline for DMA coherency)
 ///
 /// Although the actual descriptor data is 32 bytes (8 x u32), each descriptor
@@ -102,7 +105,7 @@ mod tdes1 {
 
 /// RDES0: RX descriptor word 0 (status in write-back)
 #[allow(dead_code)]
-mod rdes0 {
+pub(crate) mod rdes0 {
     /// Extended Status Available / Rx MAC Address
     pub const ESA: u32 = 1 << 0;
     /// CRC Error
@@ -144,6 +147,14 @@ mod rdes0 {
     pub const OWN: u32 = 1 << 31;
 }
 
+/// RDES4: RX extended status, valid with RDES0 ESA
+pub(crate) mod rdes4 {
+    /// IP Header Error
+    pub const IPHE: u32 = 1 << 3;
+    /// IP Payload Error
+    pub const IPPE: u32 = 1 << 4;
+}
+
 /// RDES1: RX descriptor word 1 (control)
 #[allow(dead_code)]
 mod rdes1 {
@@ -169,7 +180,7 @@ mod rdes1 {
 /// Transmit DMA Descriptor (8-word format for HPM6300)
 ///
 /// HPM6300 uses 8-word (32-byte) descriptors with ATDS=1.
-/// The extra words (tdes4-tdes7) are reserved for PTP timestamp.
+/// The extra words (tdes4-tdes7) hold the PTP transmit timestamp.
 ///
 /// Aligned to 64 bytes (cacheline) to ensure each descriptor occupies its own
 /// cacheline, preventing cache invalidation from affecting adjacent descriptors.
@@ -302,7 +313,7 @@ impl TDes {
     /// Sets: TCH (chain mode), FS (first segment), LS (last segment), OWN
     /// This avoids multiple read-modify-write operations on noncacheable memory.
     #[inline]
-    pub fn prepare_tx(&mut self, buf_addr: u32, len: u16) {
+    pub fn prepare_tx(&mut self, buf_addr: u32, len: u16, timestamp: bool, checksum: bool) {
         // TDES0 flags - simplified configuration:
         // - TCH = bit 20: TX Chain mode
         // - FS = bit 28: First Segment
@@ -312,8 +323,14 @@ impl TDes {
         // - CIC = bits 23:22 = 3: Full checksum offload (IP + TCP/UDP + pseudo-header)
         // NOTE: DC=0 and CRCR=0 so MAC will automatically append CRC to frames
         const CIC_FULL: u32 = 3 << 22;
-        const TX_FLAGS: u32 = tdes0::TCH | tdes0::FS | tdes0::LS | tdes0::IC
-            | CIC_FULL | tdes0::OWN;
+        const TX_FLAGS: u32 = tdes0::TCH | tdes0::FS | tdes0::LS | tdes0::IC | tdes0::OWN;
+        let mut flags = TX_FLAGS;
+        if checksum {
+            flags |= CIC_FULL;
+        }
+        if timestamp {
+            flags |= tdes0::TTSE;
+        }
 
         // TDES1: TBS1 (bits 12:0) only
         // C SDK default: SAIC=0 (disable source address insertion control)
@@ -325,13 +342,22 @@ impl TDes {
 
         // Write control/status to tdes0 (this triggers the DMA)
         // Do this last after all other fields are set
-        self.tdes0.set(TX_FLAGS);
+        self.tdes0.set(flags);
 
         // Memory barrier AFTER setting OWN bit (matching C SDK: fence rw, rw)
         // This ensures DMA sees the complete descriptor before we poll
         core::sync::atomic::fence(core::sync::atomic::Ordering::SeqCst);
     }
 
+    /// Transmit timestamp (seconds, nanoseconds), once the DMA released the descriptor
+    pub fn timestamp(&self) -> Option<(u32, u32)> {
+        if self.tdes0.get() & tdes0::TTSS != 0 {
+            Some((self.tdes7.get(), self.tdes6.get()))
+        } else {
+            None
+        }
+    }
+
     /// Clear TX descriptor after DMA is done (for reuse)
     /// Call this before prepare_tx() on descriptors that have been used.
     #[inline]
@@ -460,6 +486,21 @@ impl RDes {
         ((self.rdes0.get() & rdes0::FL_MASK) >> rdes0::FL_SHIFT) as usize
     }
 
+    /// Receive timestamp (seconds, nanoseconds) of the frame, in the last descriptor
+    pub fn timestamp(&self) -> Option<(u32, u32)> {
+        let rdes0 = self.rdes0.get();
+        if rdes0 & rdes0::TSA == 0 || rdes0 & rdes0::LS == 0 {
+            return None;
+        }
+        let (seconds, nanoseconds) = (self.rdes7.get(), self.rdes6.get());
+        // All ones: the timestamp is corrupted
+        if seconds == u32::MAX && nanoseconds == u32::MAX {
+            None
+        } else {
+            Some((seconds, nanoseconds))
+        }
+    }
+
     /// Prepare RX descriptor for receiving (direct write, no read-modify-write)
     ///
     /// Re-initializes the descriptor for DMA to use.
@@ -511,36 +552,49 @@ impl<const N: usize> Packet<N> {
 /// Aligned to 64 bytes (cacheline) to ensure proper DMA coherency.
 /// Each descriptor is also 64-byte aligned internally.
 ///
+/// # Buffer Size
+/// Buffers default to [`TX_BUFFER_SIZE`]/[`RX_BUFFER_SIZE`], enough for standard and VLAN tagged
+/// frames. Frames that don't fit a single buffer are dropped, so larger MTUs need larger buffers,
+/// multiples of 4 up to [`MAX_BUFFER_SIZE`], e.g. `PacketQueue<4, 4, 9216, 9216>` for 9000 byte jumbo
+/// frames with [`Config::mtu`](super::Config::mtu) 9014.
+///
 /// # Example
 /// ```rust,ignore
 /// #[link_section = ".noncacheable"]
 /// static mut PACKET_QUEUE: PacketQueue<4, 4> = PacketQueue::new();
 /// ```
 #[repr(C, align(64))]
-pub struct PacketQueue<const TX_COUNT: usize, const RX_COUNT: usize> {
+pub struct PacketQueue<
+    const TX_COUNT: usize,
+    const RX_COUNT: usize,
+    const TX_SIZE: usize = TX_BUFFER_SIZE,
+    const RX_SIZE: usize = RX_BUFFER_SIZE,
+> {
     /// TX descriptors
     pub(crate) tx_desc: [TDes; TX_COUNT],
     /// RX descriptors
     pub(crate) rx_desc: [RDes; RX_COUNT],
     /// TX buffers
-    pub(crate) tx_buf: [[u8; TX_BUFFER_SIZE]; TX_COUNT],
+    pub(crate) tx_buf: [[u8; TX_SIZE]; TX_COUNT],
     /// RX buffers
-    pub(crate) rx_buf: [[u8; RX_BUFFER_SIZE]; RX_COUNT],
+    pub(crate) rx_buf: [[u8; RX_SIZE]; RX_COUNT],
 }
 
-impl<const TX_COUNT: usize, const RX_COUNT: usize> PacketQueue<TX_COUNT, RX_COUNT> {
+impl<const TX_COUNT: usize, const RX_COUNT: usize, const TX_SIZE: usize, const RX_SIZE: usize>
+    PacketQueue<TX_COUNT, RX_COUNT, TX_SIZE, RX_SIZE>
+{
     /// Create a new packet queue
     pub const fn new() -> Self {
-        const TX_DESC_INIT: TDes = TDes::new();
-        const RX_DESC_INIT: RDes = RDes::new();
-        const TX_BUF_INIT: [u8; TX_BUFFER_SIZE] = [0u8; TX_BUFFER_SIZE];
-        const RX_BUF_INIT: [u8; RX_BUFFER_SIZE] = [0u8; RX_BUFFER_SIZE];
+        const {
+            assert!(TX_SIZE <= MAX_BUFFER_SIZE && TX_SIZE % 4 == 0, "invalid TX buffer size");
+            assert!(RX_SIZE <= MAX_BUFFER_SIZE && RX_SIZE % 4 == 0, "invalid RX buffer size");
+        }
 
         Self {
-            tx_desc: [TX_DESC_INIT; TX_COUNT],
-            rx_desc: [RX_DESC_INIT; RX_COUNT],
-            tx_buf: [TX_BUF_INIT; TX_COUNT],
-            rx_buf: [RX_BUF_INIT; RX_COUNT],
+            tx_desc: [const { TDes::new() }; TX_COUNT],
+            rx_desc: [const { RDes::new() }; RX_COUNT],
+            tx_buf: [[0u8; TX_SIZE]; TX_COUNT],
+            rx_buf: [[0u8; RX_SIZE]; RX_COUNT],
         }
     }
 }
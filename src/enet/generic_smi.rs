@@ -16,6 +16,8 @@ pub enum SmiError {
     Timeout,
     /// Invalid PHY address
     InvalidAddress,
+    /// Operation not supported by the PHY
+    Unsupported,
 }
 
 /// SMI clock divider
@@ -256,6 +258,7 @@ pub const RTL8201_PHY_ID2_VALUE: u16 = 0xC810;
 /// Generic PHY driver using SMI
 ///
 /// This provides a simple PHY driver that works with most standard PHYs.
+/// See [`phy`](super::phy) for drivers of specific PHYs with link interrupt support.
 pub struct GenericPhy<T: Instance> {
     smi: GenericSmi<T>,
     phy_addr: u8,
@@ -6,9 +6,14 @@
 //! # Features
 //! - RMII and MII interface support
 //! - Full/Half duplex operation
-//! - 10/100 Mbps speed
+//! - 10/100 Mbps speed, 1000 Mbps with RGMII
+//! - IPv4/TCP/UDP/ICMP checksum offload, VLAN and jumbo frames
 //! - SMI (Station Management Interface) for PHY access
-//! - PTP timestamp support (optional)
+//! - PHY drivers and async link monitoring, see [`phy`]
+//! - MMC statistics counters and RX error reporting, see [`Stats`]
+//! - Unicast/broadcast filtering, multicast hash filter and VLAN tagging, see [`Config`]
+//! - IEEE 1588 PTP timestamps, system time and PPS output, see [`ptp`]
+//! - Wake-on-LAN on magic packets and wake-up frames, see [`Ethernet::enter_wol`]
 //!
 //! # Example
 //! ```no_run
@@ -24,15 +29,23 @@
 
 mod descriptors;
 pub mod generic_smi;
+pub mod phy;
+pub mod ptp;
+mod stats;
+mod wol;
 
-pub use descriptors::{PacketQueue, RX_BUFFER_SIZE, TX_BUFFER_SIZE, DESC_SIZE};
+pub use descriptors::{DESC_SIZE, MAX_BUFFER_SIZE, PacketQueue, RX_BUFFER_SIZE, TX_BUFFER_SIZE};
 pub use generic_smi::{GenericPhy, GenericSmi, SmiClockDivider, SmiError};
+pub use phy::{Dp83848, Jl1111, LinkStatus, Phy, Rtl8201, Rtl8211};
+pub use ptp::{PtpClock, Timestamp};
+pub use stats::{Stats, StatsHandle};
+pub use wol::{MAX_WAKEUP_FILTERS, WakeEvent, WakeUpFilter, WolConfig, enter_wol, exit_wol, wait_wake};
 
 use core::marker::PhantomData;
 use core::sync::atomic::{AtomicBool, AtomicU32, Ordering, fence};
 
 use embassy_hal_internal::{Peri, PeripheralType};
-use embassy_net_driver::{Capabilities, HardwareAddress, LinkState};
+use embassy_net_driver::{Capabilities, Checksum, HardwareAddress, LinkState};
 use embassy_sync::waitqueue::AtomicWaker;
 
 use crate::interrupt::typelevel::Interrupt as _;
@@ -53,6 +66,32 @@ pub enum Error {
     TxError,
     /// Receive error
     RxError,
+    /// Received frame with CRC error
+    Crc,
+    /// Received frame with dribble nibble, not a multiple of 8 bits
+    Alignment,
+    /// Receive error signaled by the PHY
+    Phy,
+    /// Received frame cut by the receive watchdog
+    FrameTooLong,
+    /// Late collision while receiving (half duplex)
+    LateCollision,
+    /// Frame lost to RX FIFO overflow
+    Overflow,
+    /// Length field doesn't match the received frame size
+    Length,
+    /// Frame doesn't fit an RX buffer
+    Descriptor,
+    /// IPv4 header or TCP/UDP/ICMP checksum error
+    Checksum,
+    /// Not a multicast MAC address
+    InvalidAddress,
+    /// The multicast list is full
+    FilterFull,
+    /// The DMA did not stop in time
+    Timeout,
+    /// Wake-up frame filter offset or mask out of range
+    InvalidFilter,
 }
 
 // ============================================================================
@@ -68,6 +107,7 @@ pub enum Error {
 // ============================================================================
 
 use andes_riscv::l1c;
+use descriptors::{rdes0, rdes4};
 
 /// Cacheline size for HPM MCUs (Andes D45/D25 cores)
 const CACHELINE_SIZE: u32 = 64;
@@ -131,13 +171,17 @@ pub enum Speed {
     Speed10M,
     /// 100 Mbps
     Speed100M,
+    /// 1000 Mbps, RGMII only
+    Speed1000M,
 }
 
+/// `true` for 100 Mbps and faster
 impl From<Speed> for bool {
     fn from(speed: Speed) -> bool {
         match speed {
             Speed::Speed10M => false,
             Speed::Speed100M => true,
+            Speed::Speed1000M => true,
         }
     }
 }
@@ -161,6 +205,23 @@ impl From<Duplex> for bool {
     }
 }
 
+/// Maximum number of multicast groups, see [`Ethernet::add_multicast`]
+pub const MAX_MULTICAST: usize = 16;
+
+/// VLAN tag filtering and insertion
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct VlanConfig {
+    /// VLAN identifier (12 bits)
+    pub id: u16,
+    /// Priority code point (0-7) of inserted tags
+    pub priority: u8,
+    /// Drop received frames not tagged with `id`
+    pub filter: bool,
+    /// Insert a tag with `id` and `priority` into every transmitted frame
+    pub insert: bool,
+}
+
 /// Ethernet configuration
 #[derive(Clone)]
 pub struct Config {
@@ -170,6 +231,29 @@ pub struct Config {
     pub mode: Mode,
     /// DMA Programmable Burst Length (1, 2, 4, 8, 16, 32)
     pub dma_pbl: u8,
+    /// Receive all frames, regardless of the destination address
+    pub promiscuous: bool,
+    /// Receive broadcast frames, needed for ARP and DHCP
+    pub broadcast: bool,
+    /// Receive all multicast frames, otherwise only the groups in `multicast` pass the hash filter
+    pub pass_all_multicast: bool,
+    /// Multicast groups to receive, e.g. `01:00:5E:00:00:FB` for mDNS, at most [`MAX_MULTICAST`]
+    pub multicast: &'static [[u8; 6]],
+    /// VLAN tag filtering and insertion, tags matching `id` are removed from received frames
+    pub vlan: Option<VlanConfig>,
+    /// Run the PTP system time and timestamp PTP event messages, see [`Ethernet::ptp_clock`]
+    pub ptp: bool,
+    /// Largest frame, without FCS, reported as MTU to the stack
+    ///
+    /// Limited by the [`PacketQueue`] buffer sizes, the RX buffer also holds the FCS. Up to 1518 for
+    /// tagged frames and 1996 for IEEE 802.3as envelope frames (2000 bytes with FCS), both in
+    /// store-and-forward mode. Larger values up to 9014 enable jumbo frames, which the MAC forwards
+    /// at the FIFO threshold instead.
+    pub mtu: usize,
+    /// Hardware IPv4 header and TCP/UDP/ICMP checksums, frames with bad checksums are dropped
+    ///
+    /// Needs store-and-forward, so it only applies up to an MTU of 1996.
+    pub checksum_offload: bool,
 }
 
 impl Default for Config {
@@ -178,16 +262,45 @@ impl Default for Config {
             mac_addr: [0x02, 0x00, 0x00, 0x00, 0x00, 0x01],
             mode: Mode::Rmii,
             dma_pbl: 16,
+            promiscuous: false,
+            broadcast: true,
+            // IPv6 neighbor discovery needs multicast unless the groups are listed
+            pass_all_multicast: true,
+            multicast: &[],
+            vlan: None,
+            ptp: false,
+            mtu: 1514,
+            checksum_offload: true,
+        }
+    }
+}
+
+/// Largest frame without FCS that fits the MAC FIFOs in store-and-forward mode, an 802.3as
+/// envelope frame of 2000 bytes with FCS. Larger MTUs switch to jumbo frames.
+const STORE_AND_FORWARD_MTU: usize = 1996;
+
+/// Bin of the 64-bit multicast hash table: upper 6 bits of the bit-reversed CRC32 of the address
+fn multicast_hash(addr: &[u8; 6]) -> u32 {
+    let mut crc = !0u32;
+    for &b in addr {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
         }
     }
+    (!crc).reverse_bits() >> 26
 }
 
 /// Ethernet driver state
 pub struct State {
     rx_waker: AtomicWaker,
     tx_waker: AtomicWaker,
+    link_waker: AtomicWaker,
     link_up: AtomicBool,
     speed: AtomicU32,
+    ptp: ptp::PtpState,
+    counters: stats::Counters,
+    wol: wol::WolState,
 }
 
 impl State {
@@ -196,8 +309,12 @@ impl State {
         Self {
             rx_waker: AtomicWaker::new(),
             tx_waker: AtomicWaker::new(),
+            link_waker: AtomicWaker::new(),
             link_up: AtomicBool::new(false),
             speed: AtomicU32::new(0),
+            ptp: ptp::PtpState::new(),
+            counters: stats::Counters::new(),
+            wol: wol::WolState::new(),
         }
     }
 }
@@ -207,6 +324,16 @@ struct Info {
     regs: pac::enet::Enet,
 }
 
+/// Read a register missing from the PAC, at `offset` from the ENET base
+fn read_reg(regs: pac::enet::Enet, offset: usize) -> u32 {
+    unsafe { core::ptr::read_volatile((regs.as_ptr() as *const u8).add(offset) as *const u32) }
+}
+
+/// Write a register missing from the PAC, at `offset` from the ENET base
+fn write_reg(regs: pac::enet::Enet, offset: usize, value: u32) {
+    unsafe { core::ptr::write_volatile((regs.as_ptr() as *mut u8).add(offset) as *mut u32, value) }
+}
+
 /// Interrupt handler
 pub struct InterruptHandler<T: Instance> {
     _phantom: PhantomData<T>,
@@ -232,6 +359,11 @@ unsafe fn on_interrupt(regs: pac::enet::Enet, state: &'static State) {
         }
     }
 
+    // PMT wake up, cleared by reading the PMT status
+    if status.0 & wol::GPI != 0 {
+        wol::on_interrupt(regs, &state.wol);
+    }
+
     // Clear ALL status bits by writing 1s (w1c register)
     // This prevents interrupt storms from unhandled status bits
     regs.dma_status().write(|w| w.0 = 0x0001_E7FF);  // All clearable bits
@@ -248,20 +380,37 @@ pub struct Ethernet<'d, T: Instance> {
     tx: TxRing<'d>,
     rx: RxRing<'d>,
     mac_addr: [u8; 6],
+    multicast: [[u8; 6]; MAX_MULTICAST],
+    multicast_len: usize,
+    mtu: usize,
 }
 
 struct TxRing<'d> {
     descriptors: &'d mut [descriptors::TDes],
-    buffers: &'d mut [[u8; TX_BUFFER_SIZE]],
+    /// One buffer of `buf_size` bytes per descriptor
+    buffers: &'d mut [u8],
+    buf_size: usize,
     index: usize,
     regs: pac::enet::Enet,
+    /// Timestamp PTP event messages
+    ptp: Option<&'static ptp::PtpState>,
+    /// Insert IPv4 header and TCP/UDP/ICMP checksums
+    checksum: bool,
+    counters: &'static stats::Counters,
 }
 
 struct RxRing<'d> {
     descriptors: &'d mut [descriptors::RDes],
-    buffers: &'d mut [[u8; RX_BUFFER_SIZE]],
+    /// One buffer of `buf_size` bytes per descriptor
+    buffers: &'d mut [u8],
+    buf_size: usize,
     index: usize,
     regs: pac::enet::Enet,
+    /// Remove VLAN tags with this id from received frames
+    vlan_strip: Option<u16>,
+    /// Record timestamps of PTP event messages
+    ptp: Option<&'static ptp::PtpState>,
+    counters: &'static stats::Counters,
 }
 
 impl<'d, T: Instance> Ethernet<'d, T> {
@@ -269,7 +418,7 @@ impl<'d, T: Instance> Ethernet<'d, T> {
     ///
     /// # Safety
     /// The packet queue must live as long as the driver.
-    pub fn new<const TX_COUNT: usize, const RX_COUNT: usize>(
+    pub fn new<const TX_COUNT: usize, const RX_COUNT: usize, const TX_SIZE: usize, const RX_SIZE: usize>(
         peri: Peri<'d, T>,
         _irq: impl interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>>,
         ref_clk: Peri<'d, impl RefClkPin<T>>,
@@ -281,7 +430,7 @@ impl<'d, T: Instance> Ethernet<'d, T> {
         txd1: Peri<'d, impl Txd1Pin<T>>,
         mdio: Peri<'d, impl MdioPin<T>>,
         mdc: Peri<'d, impl MdcPin<T>>,
-        queue: &'d mut PacketQueue<TX_COUNT, RX_COUNT>,
+        queue: &'d mut PacketQueue<TX_COUNT, RX_COUNT, TX_SIZE, RX_SIZE>,
         config: Config,
     ) -> Self {
         // Configure pins using WRITE (not modify) to clear all other bits
@@ -317,6 +466,9 @@ impl<'d, T: Instance> Ethernet<'d, T> {
 
         // Configure MAC BEFORE starting DMA
         Self::configure_mac(regs, &config);
+        if config.ptp {
+            ptp::init::<T>();
+        }
 
         // Start MAC transmitter and receiver
         regs.maccfg().modify(|w| {
@@ -342,12 +494,7 @@ impl<'d, T: Instance> Ethernet<'d, T> {
         #[cfg(feature = "defmt")]
         defmt::info!("ENET: initialized");
 
-        Self {
-            _peri: peri,
-            tx,
-            rx,
-            mac_addr: config.mac_addr,
-        }
+        Self::from_parts(peri, tx, rx, &config)
     }
 
     /// Create a new Ethernet driver (RGMII mode) for HPM6E00 series
@@ -360,7 +507,7 @@ impl<'d, T: Instance> Ethernet<'d, T> {
     /// The packet queue must live as long as the driver.
     #[cfg(hpm6e)]
     #[allow(clippy::too_many_arguments)]
-    pub fn new_rgmii<const TX_COUNT: usize, const RX_COUNT: usize>(
+    pub fn new_rgmii<const TX_COUNT: usize, const RX_COUNT: usize, const TX_SIZE: usize, const RX_SIZE: usize>(
         peri: Peri<'d, T>,
         _irq: impl interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>>,
         rx_ctl: Peri<'d, impl RgmiiRxCtlPin<T>>,
@@ -377,7 +524,7 @@ impl<'d, T: Instance> Ethernet<'d, T> {
         tx_en: Peri<'d, impl TxEnPin<T>>,
         mdio: Peri<'d, impl MdioPin<T>>,
         mdc: Peri<'d, impl MdcPin<T>>,
-        queue: &'d mut PacketQueue<TX_COUNT, RX_COUNT>,
+        queue: &'d mut PacketQueue<TX_COUNT, RX_COUNT, TX_SIZE, RX_SIZE>,
         config: Config,
         tx_delay: u8,
         rx_delay: u8,
@@ -427,6 +574,9 @@ impl<'d, T: Instance> Ethernet<'d, T> {
         defmt::debug!("RGMII: configure_mac_rgmii");
         // Configure MAC BEFORE starting DMA (use RGMII-specific config)
         Self::configure_mac_rgmii(regs, &config);
+        if config.ptp {
+            ptp::init::<T>();
+        }
 
         #[cfg(feature = "defmt")]
         defmt::debug!("RGMII: enabling MAC TX/RX");
@@ -486,20 +636,15 @@ impl<'d, T: Instance> Ethernet<'d, T> {
         #[cfg(feature = "defmt")]
         defmt::debug!("RGMII: interrupt enabled, init complete");
 
-        // Set default link state to up (100Mbps Full Duplex)
+        // Set default link state to up (1000Mbps Full Duplex)
         let state = T::state();
         state.link_up.store(true, Ordering::Relaxed);
-        state.speed.store(100, Ordering::Relaxed);
+        state.speed.store(1000, Ordering::Relaxed);
 
         #[cfg(feature = "defmt")]
         defmt::info!("ENET: RGMII initialized with tx_delay={}, rx_delay={}", tx_delay, rx_delay);
 
-        Self {
-            _peri: peri,
-            tx,
-            rx,
-            mac_addr: config.mac_addr,
-        }
+        Self::from_parts(peri, tx, rx, &config)
     }
 
     fn reset(regs: pac::enet::Enet) {
@@ -539,12 +684,10 @@ impl<'d, T: Instance> Ethernet<'d, T> {
             w.set_dm(true);   // Full duplex
         });
 
-        // Configure frame filter: promiscuous mode
-        regs.macff().modify(|w| {
-            w.set_ra(true);   // Receive all
-            w.set_pm(true);   // Pass multicast
-            w.set_pr(true);   // Promiscuous mode
-        });
+        // Configure frame filter and VLAN tagging
+        Self::configure_filter(regs, config);
+        Self::configure_frame_size(regs, config);
+        stats::init(regs);
 
         // Configure flow control (disabled)
         regs.flowctrl().write(|w| {
@@ -596,12 +739,10 @@ impl<'d, T: Instance> Ethernet<'d, T> {
             w.set_sarc(3);    // replace_mac0
         });
 
-        // Configure frame filter: promiscuous mode
-        regs.macff().modify(|w| {
-            w.set_ra(true);   // Receive all
-            w.set_pm(true);   // Pass multicast
-            w.set_pr(true);   // Promiscuous mode
-        });
+        // Configure frame filter and VLAN tagging
+        Self::configure_filter(regs, config);
+        Self::configure_frame_size(regs, config);
+        stats::init(regs);
 
         // Configure flow control (disabled)
         regs.flowctrl().write(|w| {
@@ -615,9 +756,87 @@ impl<'d, T: Instance> Ethernet<'d, T> {
         });
     }
 
-    fn init_dma_no_start<const TX_COUNT: usize, const RX_COUNT: usize>(
+    fn from_parts(peri: Peri<'d, T>, mut tx: TxRing<'d>, mut rx: RxRing<'d>, config: &Config) -> Self {
+        rx.vlan_strip = config.vlan.map(|vlan| vlan.id & 0xFFF);
+        tx.checksum = Self::checksum_offload(config);
+        let mtu = config.mtu.min(tx.buf_size).min(rx.buf_size.saturating_sub(4));
+        if config.ptp {
+            tx.ptp = Some(&T::state().ptp);
+            rx.ptp = Some(&T::state().ptp);
+        }
+
+        let mut this = Self {
+            _peri: peri,
+            tx,
+            rx,
+            mac_addr: config.mac_addr,
+            multicast: [[0; 6]; MAX_MULTICAST],
+            multicast_len: 0,
+            mtu,
+        };
+        for addr in config.multicast {
+            // Invalid or excess groups are left out, like with `add_multicast`
+            let _ = this.add_multicast(*addr);
+        }
+        this
+    }
+
+    /// Frame filter: own unicast address in the perfect filter, multicast through the hash table
+    fn configure_filter(regs: pac::enet::Enet, config: &Config) {
+        regs.macff().write(|w| {
+            w.set_pr(config.promiscuous);
+            w.set_dbf(!config.broadcast);
+            w.set_pm(config.pass_all_multicast);
+            w.set_hmc(true);
+            w.set_vtfe(config.vlan.is_some_and(|vlan| vlan.filter));
+        });
+        regs.hash_h().write(|w| w.0 = 0);
+        regs.hash_l().write(|w| w.0 = 0);
+
+        match config.vlan {
+            Some(vlan) => {
+                // 12-bit VLAN id compare
+                regs.vlan_tag().write(|w| {
+                    w.set_vl(vlan.id & 0xFFF);
+                    w.set_etv(true);
+                });
+                // Insert the tag in the MAC, the descriptors don't carry it
+                regs.vlan_tag_inc_rpl().write(|w| {
+                    if vlan.insert {
+                        w.set_vlt(((vlan.priority as u16 & 0x7) << 13) | (vlan.id & 0xFFF));
+                        w.set_vlc(2);
+                    }
+                });
+            }
+            None => {
+                regs.vlan_tag().write(|w| w.0 = 0);
+                regs.vlan_tag_inc_rpl().write(|w| w.0 = 0);
+            }
+        }
+    }
+
+    fn checksum_offload(config: &Config) -> bool {
+        config.checksum_offload && config.mtu <= STORE_AND_FORWARD_MTU
+    }
+
+    /// Checksum offload and long frames
+    fn configure_frame_size(regs: pac::enet::Enet, config: &Config) {
+        let jumbo = config.mtu > STORE_AND_FORWARD_MTU;
+        regs.maccfg().modify(|w| {
+            w.set_ipc(Self::checksum_offload(config));
+            w.set_twokpe(config.mtu > 1518 && !jumbo);
+            w.set_je(jumbo);
+        });
+        // Jumbo frames don't fit the FIFOs, forward them at the threshold instead
+        regs.dma_op_mode().modify(|w| {
+            w.set_rsf(!jumbo);
+            w.set_tsf(!jumbo);
+        });
+    }
+
+    fn init_dma_no_start<const TX_COUNT: usize, const RX_COUNT: usize, const TX_SIZE: usize, const RX_SIZE: usize>(
         regs: pac::enet::Enet,
-        queue: &'d mut PacketQueue<TX_COUNT, RX_COUNT>,
+        queue: &'d mut PacketQueue<TX_COUNT, RX_COUNT, TX_SIZE, RX_SIZE>,
         pbl: u8,
     ) -> (TxRing<'d>, RxRing<'d>) {
         #[cfg(feature = "defmt")]
@@ -697,7 +916,7 @@ impl<'d, T: Instance> Ethernet<'d, T> {
             unsafe {
                 let desc_ptr = rx_desc_base.add(i) as *mut u32;
                 core::ptr::write_volatile(desc_ptr, 0x8000_0000); // OWN
-                core::ptr::write_volatile(desc_ptr.add(1), 0x4000 | (RX_BUFFER_SIZE as u32)); // RCH + size
+                core::ptr::write_volatile(desc_ptr.add(1), 0x4000 | (RX_SIZE as u32)); // RCH + size
                 core::ptr::write_volatile(desc_ptr.add(2), buf_addr);
                 core::ptr::write_volatile(desc_ptr.add(3), next_desc_addr);
             }
@@ -759,15 +978,23 @@ impl<'d, T: Instance> Ethernet<'d, T> {
         (
             TxRing {
                 descriptors: &mut queue.tx_desc,
-                buffers: &mut queue.tx_buf,
+                buffers: queue.tx_buf.as_flattened_mut(),
+                buf_size: TX_SIZE,
                 index: 0,
                 regs,
+                ptp: None,
+                checksum: false,
+                counters: &T::state().counters,
             },
             RxRing {
                 descriptors: &mut queue.rx_desc,
-                buffers: &mut queue.rx_buf,
+                buffers: queue.rx_buf.as_flattened_mut(),
+                buf_size: RX_SIZE,
                 index: 0,
                 regs,
+                vlan_strip: None,
+                ptp: None,
+                counters: &T::state().counters,
             },
         )
     }
@@ -886,27 +1113,59 @@ impl<'d, T: Instance> Ethernet<'d, T> {
     }
 
     /// Set link speed and duplex
+    ///
+    /// Use [`set_link()`] or [`phy::link_monitor`] once the driver is owned by embassy-net.
     pub fn set_link(&mut self, speed: Speed, duplex: Duplex) {
-        let regs = T::info().regs;
-
-        regs.maccfg().modify(|w| {
-            w.set_fes(speed.into());
-            w.set_dm(duplex.into());
-        });
-
-        let state = T::state();
-        let speed_val = match speed {
-            Speed::Speed10M => 10,
-            Speed::Speed100M => 100,
-        };
-        state.speed.store(speed_val, Ordering::Relaxed);
-        state.link_up.store(true, Ordering::Relaxed);
+        set_link::<T>(speed, duplex);
     }
 
     /// Set link down
     pub fn set_link_down(&mut self) {
-        let state = T::state();
-        state.link_up.store(false, Ordering::Relaxed);
+        set_link_down::<T>();
+    }
+
+    /// Receive frames sent to multicast group `addr`, e.g. after joining an IGMP group
+    ///
+    /// Groups share the 64 bins of the hash filter, so some frames of other groups may pass too.
+    pub fn add_multicast(&mut self, addr: [u8; 6]) -> Result<(), Error> {
+        if addr[0] & 0x01 == 0 {
+            return Err(Error::InvalidAddress);
+        }
+        if self.multicast[..self.multicast_len].contains(&addr) {
+            return Ok(());
+        }
+        if self.multicast_len == MAX_MULTICAST {
+            return Err(Error::FilterFull);
+        }
+        self.multicast[self.multicast_len] = addr;
+        self.multicast_len += 1;
+        self.update_hash();
+        Ok(())
+    }
+
+    /// Stop receiving frames of multicast group `addr`
+    pub fn remove_multicast(&mut self, addr: [u8; 6]) {
+        if let Some(i) = self.multicast[..self.multicast_len].iter().position(|a| *a == addr) {
+            self.multicast.copy_within(i + 1..self.multicast_len, i);
+            self.multicast_len -= 1;
+            self.update_hash();
+        }
+    }
+
+    /// Switch promiscuous mode, e.g. for packet capture
+    pub fn set_promiscuous(&mut self, enable: bool) {
+        T::info().regs.macff().modify(|w| w.set_pr(enable));
+    }
+
+    /// Rebuild the hash table from the multicast list
+    fn update_hash(&mut self) {
+        let mut hash = 0u64;
+        for addr in &self.multicast[..self.multicast_len] {
+            hash |= 1 << multicast_hash(addr);
+        }
+        let regs = T::info().regs;
+        regs.hash_h().write(|w| w.0 = (hash >> 32) as u32);
+        regs.hash_l().write(|w| w.0 = hash as u32);
     }
 
     /// Get the MAC address
@@ -918,6 +1177,72 @@ impl<'d, T: Instance> Ethernet<'d, T> {
     pub fn smi(&self) -> GenericSmi<T> {
         GenericSmi::new()
     }
+
+    /// Stop the DMA and put the MAC in power-down mode until a wake up event
+    ///
+    /// The ENET clock stays on in CPU low power modes and its interrupt wakes up the CPU, so the
+    /// application can sleep until a magic packet or wake-up frame arrives. Frames received in
+    /// power-down mode are dropped. Use [`enter_wol()`] once the driver is owned by embassy-net.
+    ///
+    /// Returns [`Error::Timeout`] if the DMA doesn't stop, the MAC keeps running then.
+    pub fn enter_wol(&mut self, config: &WolConfig) -> Result<(), Error> {
+        enter_wol::<T>(config)
+    }
+
+    /// Resume normal operation, returns the event that ended power-down mode
+    pub fn exit_wol(&mut self) -> Option<WakeEvent> {
+        exit_wol::<T>()
+    }
+
+    /// Receive a frame into `buf` without embassy-net, returns its length or `None` if no frame
+    /// is pending
+    ///
+    /// A frame received with errors is dropped and its error returned, e.g. [`Error::Crc`].
+    /// Returns [`Error::BufferTooSmall`] if the frame doesn't fit `buf`, the frame is dropped.
+    pub fn try_receive(&mut self, buf: &mut [u8]) -> Result<Option<usize>, Error> {
+        if !self.rx.available()? {
+            return Ok(None);
+        }
+        let Some(frame) = self.rx.frame() else {
+            return Ok(None);
+        };
+        let len = frame.len();
+        let result = match buf.get_mut(..len) {
+            Some(dst) => {
+                dst.copy_from_slice(frame);
+                Ok(Some(len))
+            }
+            None => Err(Error::BufferTooSmall),
+        };
+        self.rx.release();
+        result
+    }
+
+    /// Link statistics: MAC counters and frames dropped by the driver
+    ///
+    /// Use [`stats_handle`](Self::stats_handle) once the driver is owned by embassy-net.
+    pub fn stats(&self) -> Stats {
+        self.stats_handle().stats()
+    }
+
+    /// Clear the statistics
+    pub fn reset_stats(&mut self) {
+        self.stats_handle().reset()
+    }
+
+    /// Statistics of this instance
+    ///
+    /// The handle stays usable after the driver is moved into embassy-net.
+    pub fn stats_handle(&self) -> StatsHandle<'d, T> {
+        StatsHandle { _phantom: PhantomData }
+    }
+
+    /// PTP hardware clock, requires [`Config::ptp`]
+    ///
+    /// The handle stays usable after the driver is moved into embassy-net.
+    pub fn ptp_clock(&self) -> PtpClock<'d, T> {
+        PtpClock { _phantom: PhantomData }
+    }
 }
 
 /// Wake the RX and TX wakers for an ENET instance to trigger embassy-net polling.
@@ -941,6 +1266,64 @@ pub fn wake<T: Instance>() {
     state.tx_waker.wake();
 }
 
+/// Set link speed and duplex of instance `T`, and report the link up to embassy-net
+pub fn set_link<T: Instance>(speed: Speed, duplex: Duplex) {
+    let regs = T::info().regs;
+
+    // The RGMII transmit clock is derived from PS/FES (125/25/2.5 MHz), retune it with the MAC
+    // stopped so the PHY doesn't see the clock change mid-frame
+    let cfg = regs.maccfg().read();
+    let retune = is_rgmii(regs) && (cfg.te() || cfg.re());
+    if retune {
+        regs.maccfg().modify(|w| {
+            w.set_te(false);
+            w.set_re(false);
+        });
+    }
+
+    // C SDK enet_set_line_speed(): 1000Mbps PS=0 FES=0, 100Mbps PS=1 FES=1, 10Mbps PS=1 FES=0
+    regs.maccfg().modify(|w| {
+        w.set_ps(speed != Speed::Speed1000M);
+        w.set_fes(speed == Speed::Speed100M);
+        w.set_dm(duplex.into());
+    });
+
+    if retune {
+        regs.maccfg().modify(|w| {
+            w.set_te(cfg.te());
+            w.set_re(cfg.re());
+        });
+    }
+
+    let state = T::state();
+    let speed_val = match speed {
+        Speed::Speed10M => 10,
+        Speed::Speed100M => 100,
+        Speed::Speed1000M => 1000,
+    };
+    state.speed.store(speed_val, Ordering::Relaxed);
+    state.link_up.store(true, Ordering::Relaxed);
+    state.link_waker.wake();
+}
+
+/// RGMII interface selected, PHY_INF_SEL: 000=MII, 001=RGMII, 100=RMII
+#[cfg(hpm6e)]
+fn is_rgmii(regs: pac::enet::Enet) -> bool {
+    regs.ctrl2().read().enet0_phy_inf_sel() == 0b001
+}
+
+#[cfg(not(hpm6e))]
+fn is_rgmii(_regs: pac::enet::Enet) -> bool {
+    false
+}
+
+/// Report the link of instance `T` down to embassy-net
+pub fn set_link_down<T: Instance>() {
+    let state = T::state();
+    state.link_up.store(false, Ordering::Relaxed);
+    state.link_waker.wake();
+}
+
 impl<'d> TxRing<'d> {
     fn available(&self) -> bool {
         // CRITICAL: Invalidate D-cache before reading OWN bit
@@ -951,7 +1334,7 @@ impl<'d> TxRing<'d> {
         !self.descriptors[self.index].is_owned()
     }
 
-    fn transmit(&mut self, len: usize) -> Result<(), Error> {
+    fn transmit(&mut self, len: usize, event: Option<(u8, u16)>) -> Result<(), Error> {
         let desc = &mut self.descriptors[self.index];
         let desc_addr = desc as *const _ as u32;
 
@@ -963,8 +1346,11 @@ impl<'d> TxRing<'d> {
         }
 
         // Prepare descriptor for transmission
-        let buf_addr = self.buffers[self.index].as_ptr() as u32;
-        desc.prepare_tx(buf_addr, len as u16);
+        let buf_addr = self.buffers[self.index * self.buf_size..].as_ptr() as u32;
+        desc.prepare_tx(buf_addr, len as u16, event.is_some(), self.checksum);
+        if let (Some(ptp), Some((message_type, sequence_id))) = (self.ptp, event) {
+            ptp.on_tx(message_type, sequence_id, desc_addr);
+        }
 
         // Flush D-cache for TX buffer and descriptor
         flush_dcache(buf_addr, len as u32);
@@ -1008,15 +1394,43 @@ impl<'d> TxRing<'d> {
 
     fn buffer(&mut self) -> Option<&mut [u8]> {
         if self.available() {
-            Some(&mut self.buffers[self.index])
+            let start = self.index * self.buf_size;
+            Some(&mut self.buffers[start..start + self.buf_size])
         } else {
             None
         }
     }
 }
 
+/// Error of a received frame from RDES0 and the extended status RDES4
+fn rx_error(rdes0: u32, rdes4: u32) -> Error {
+    if rdes0 & rdes0::CE != 0 {
+        Error::Crc
+    } else if rdes0 & rdes0::DBE != 0 {
+        Error::Alignment
+    } else if rdes0 & rdes0::RE != 0 {
+        Error::Phy
+    } else if rdes0 & rdes0::RWT != 0 {
+        Error::FrameTooLong
+    } else if rdes0 & rdes0::LC != 0 {
+        Error::LateCollision
+    } else if rdes0 & rdes0::OE != 0 {
+        Error::Overflow
+    } else if rdes0 & rdes0::LE != 0 {
+        Error::Length
+    } else if rdes0 & rdes0::DE != 0 {
+        Error::Descriptor
+    } else if rdes0 & rdes0::ESA != 0 && rdes4 & (rdes4::IPHE | rdes4::IPPE) != 0 {
+        // Extended status: IP header or payload error
+        Error::Checksum
+    } else {
+        Error::RxError
+    }
+}
+
 impl<'d> RxRing<'d> {
-    fn available(&mut self) -> bool {
+    /// Whether a frame is ready, frames with errors are dropped and reported one at a time
+    fn available(&mut self) -> Result<bool, Error> {
         // CRITICAL: Invalidate D-cache before reading OWN bit
         // DMA may have written to descriptor, CPU cache might be stale
         loop {
@@ -1024,20 +1438,33 @@ impl<'d> RxRing<'d> {
             invalidate_dcache(desc_addr, DESC_SIZE as u32);
 
             let rdes0 = unsafe { core::ptr::read_volatile(desc_addr as *const u32) };
-            let owned = (rdes0 & (1 << 31)) != 0;
-            let has_error = (rdes0 & (1 << 15)) != 0;
+            let owned = rdes0 & rdes0::OWN != 0;
+            let has_error = rdes0 & rdes0::ES != 0;
 
             if owned {
-                return false;
+                return Ok(false);
             }
 
             if has_error {
-                // Skip error frames
+                // Drop error frames, the last descriptor of a frame holds the status
+                let rdes4 = unsafe { core::ptr::read_volatile((desc_addr + 16) as *const u32) };
+                let error = rx_error(rdes0, rdes4);
+                self.counters.rx_error(error);
                 self.release();
+                return Err(error);
+            }
+
+            // Frames larger than a buffer span several descriptors, reported once at the first one
+            if rdes0 & rdes0::FS == 0 || rdes0 & rdes0::LS == 0 {
+                self.release();
+                if rdes0 & rdes0::FS != 0 {
+                    self.counters.rx_oversize.fetch_add(1, Ordering::Relaxed);
+                    return Err(Error::Descriptor);
+                }
                 continue;
             }
 
-            return true;
+            return Ok(true);
         }
     }
 
@@ -1048,30 +1475,59 @@ impl<'d> RxRing<'d> {
 
         let rdes0 = unsafe { core::ptr::read_volatile(desc_addr as *const u32) };
 
-        if rdes0 & (1 << 31) != 0 {
+        if rdes0 & rdes0::OWN != 0 {
             return None; // Still owned by DMA
         }
 
-        if rdes0 & (1 << 15) != 0 {
-            self.release(); // Error frame
+        // Error and multi-descriptor frames are dropped by `available`
+        if rdes0 & rdes0::ES != 0 || rdes0 & rdes0::FS == 0 || rdes0 & rdes0::LS == 0 {
+            self.release();
             return None;
         }
 
-        let len = ((rdes0 >> 16) & 0x3FFF) as usize;
+        let len = ((rdes0 & rdes0::FL_MASK) >> rdes0::FL_SHIFT) as usize;
 
         // Read buffer address from descriptor (rdes2) and invalidate D-cache
         let rdes2 = unsafe { core::ptr::read_volatile((desc_addr + 8) as *const u32) };
         invalidate_dcache(rdes2, len as u32);
 
-        Some((&self.buffers[self.index][..len], len))
+        if let Some(ptp) = self.ptp
+            && let Some(timestamp) = desc.timestamp()
+        {
+            ptp.on_rx(&self.buffers[self.index * self.buf_size..][..len], timestamp);
+        }
+
+        Some((&self.buffers[self.index * self.buf_size..][..len], len))
+    }
+
+    /// Current frame with our VLAN tag removed, release the descriptor afterwards
+    fn frame(&mut self) -> Option<&mut [u8]> {
+        let (_, len) = self.receive()?;
+        // CRITICAL: Use volatile copy to ensure we read actual DMA data
+        // Even in noncacheable region, Rust may not guarantee immediate visibility
+        let vlan_strip = self.vlan_strip;
+        let frame = &mut self.buffers[self.index * self.buf_size..];
+        let mut len = len;
+
+        // Remove our 802.1Q tag, the stack only handles untagged frames
+        if let Some(id) = vlan_strip
+            && len >= 18
+            && frame[12..14] == [0x81, 0x00]
+            && u16::from_be_bytes([frame[14], frame[15]]) & 0xFFF == id
+        {
+            frame.copy_within(16..len, 12);
+            len -= 4;
+        }
+
+        Some(&mut frame[..len])
     }
 
     fn release(&mut self) {
         let desc = &mut self.descriptors[self.index];
-        let buf_addr = self.buffers[self.index].as_ptr() as u32;
+        let buf_addr = self.buffers[self.index * self.buf_size..].as_ptr() as u32;
 
         // Re-initialize descriptor for DMA (single write, no read-modify-write)
-        desc.prepare_rx(buf_addr, RX_BUFFER_SIZE as u16);
+        desc.prepare_rx(buf_addr, self.buf_size as u16);
 
         // CRITICAL: Flush D-cache after writing descriptor
         // CPU wrote descriptor, DMA needs to see updated values
@@ -1104,12 +1560,8 @@ impl<'a, 'd> embassy_net_driver::RxToken for RxToken<'a, 'd> {
     where
         F: FnOnce(&mut [u8]) -> R,
     {
-        if let Some((_ptr, len)) = self.rx.receive() {
-            // CRITICAL: Use volatile copy to ensure we read actual DMA data
-            // Even in noncacheable region, Rust may not guarantee immediate visibility
-            let buf = &mut self.rx.buffers[self.rx.index][..len];
-
-            let result = f(buf);
+        if let Some(frame) = self.rx.frame() {
+            let result = f(frame);
             self.rx.release();
             result
         } else {
@@ -1127,10 +1579,14 @@ impl<'a, 'd> embassy_net_driver::TxToken for TxToken<'a, 'd> {
         #[cfg(feature = "defmt")]
         defmt::debug!("TxToken::consume called, len={}", len);
 
+        let timestamp = self.tx.ptp.is_some();
         let buf = self.tx.buffer().expect("TxToken consumed but no buffer available");
         let result = f(&mut buf[..len]);
+        let event = if timestamp { ptp::event_header(&buf[..len]) } else { None };
 
-        self.tx.transmit(len).ok();
+        if self.tx.transmit(len, event).is_err() {
+            self.tx.counters.tx_dropped.fetch_add(1, Ordering::Relaxed);
+        }
         result
     }
 }
@@ -1142,7 +1598,12 @@ impl<'d, T: Instance> embassy_net_driver::Driver for Ethernet<'d, T> {
     fn receive(&mut self, cx: &mut core::task::Context) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
         let state = T::state();
 
-        let rx_avail = self.rx.available();
+        // Frames with errors are counted in the statistics, embassy-net only sees good frames
+        let rx_avail = loop {
+            if let Ok(avail) = self.rx.available() {
+                break avail;
+            }
+        };
         let tx_avail = self.tx.available();
 
         if rx_avail && tx_avail {
@@ -1178,13 +1639,20 @@ impl<'d, T: Instance> embassy_net_driver::Driver for Ethernet<'d, T> {
 
     fn capabilities(&self) -> Capabilities {
         let mut caps = Capabilities::default();
-        caps.max_transmission_unit = 1514;
+        caps.max_transmission_unit = self.mtu;
         caps.max_burst_size = Some(self.tx.descriptors.len());
+        if self.tx.checksum {
+            caps.checksum.ipv4 = Checksum::None;
+            caps.checksum.udp = Checksum::None;
+            caps.checksum.tcp = Checksum::None;
+            caps.checksum.icmpv4 = Checksum::None;
+        }
         caps
     }
 
-    fn link_state(&mut self, _cx: &mut core::task::Context) -> LinkState {
+    fn link_state(&mut self, cx: &mut core::task::Context) -> LinkState {
         let state = T::state();
+        state.link_waker.register(cx.waker());
         if state.link_up.load(Ordering::Relaxed) {
             LinkState::Up
         } else {
@@ -1202,6 +1670,9 @@ impl<'d, T: Instance> embassy_net_driver::Driver for Ethernet<'d, T> {
 // ============================================================================
 
 trait SealedInstance {
+    /// PTP reference clock, `pac::clocks::` index
+    const PTP_CLOCK: usize;
+
     fn info() -> &'static Info;
     fn state() -> &'static State;
 }
@@ -1302,6 +1773,11 @@ pub trait Txd3Pin<T: Instance>: crate::gpio::Pin {
     fn alt_num(&self) -> u8;
 }
 
+/// PTP PPS output pin (EVTO0)
+pub trait PpsPin<T: Instance>: crate::gpio::Pin {
+    fn alt_num(&self) -> u8;
+}
+
 // Pin implementations are auto-generated from hpm-data via build.rs
 // See build.rs signals HashMap for ENET pin mappings
 // Note: RXDV signal generates both CrsDvPin (RMII) and RgmiiRxCtlPin (RGMII)
@@ -1312,6 +1788,8 @@ static ENET0_STATE: State = State::new();
 
 #[cfg(peri_enet0)]
 impl SealedInstance for crate::peripherals::ENET0 {
+    const PTP_CLOCK: usize = pac::clocks::PTP0;
+
     fn info() -> &'static Info {
         static INFO: Info = Info {
             regs: unsafe { pac::enet::Enet::from_ptr(pac::ENET0.as_ptr()) },
@@ -1335,6 +1813,8 @@ static ENET1_STATE: State = State::new();
 
 #[cfg(peri_enet1)]
 impl SealedInstance for crate::peripherals::ENET1 {
+    const PTP_CLOCK: usize = pac::clocks::PTP1;
+
     fn info() -> &'static Info {
         static INFO: Info = Info {
             regs: unsafe { pac::enet::Enet::from_ptr(pac::ENET1.as_ptr()) },
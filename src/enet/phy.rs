@@ -0,0 +1,430 @@
+//! PHY drivers and link monitoring
+//!
+//! [`Phy`] abstracts the PHYs found on HPMicro EVKs, so the application can keep the MAC
+//! in sync with the negotiated link using [`link_monitor`]:
+//!
+//! ```no_run
+//! #[embassy_executor::task]
+//! async fn link_task(mut phy: Rtl8201<ENET0>, mut int: Input<'static>) -> ! {
+//!     enet::phy::link_monitor::<ENET0, _>(&mut phy, Some(&mut int)).await
+//! }
+//! ```
+
+use super::generic_smi::phy_regs::*;
+use super::{Duplex, GenericPhy, GenericSmi, Instance, SmiError, Speed};
+
+/// Negotiated link parameters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct LinkStatus {
+    /// Link speed
+    pub speed: Speed,
+    /// Duplex mode
+    pub duplex: Duplex,
+}
+
+/// Ethernet PHY driver
+pub trait Phy {
+    /// Software reset, returns once the PHY left reset
+    fn reset(&mut self) -> Result<(), SmiError>;
+
+    /// Advertise all supported modes and restart auto-negotiation
+    fn start_autoneg(&mut self) -> Result<(), SmiError>;
+
+    /// Current link, `None` while the link is down or auto-negotiation is running
+    fn link_status(&mut self) -> Result<Option<LinkStatus>, SmiError>;
+
+    /// Signal link changes on the PHY interrupt pin, active low
+    fn enable_link_interrupt(&mut self) -> Result<(), SmiError> {
+        Err(SmiError::Unsupported)
+    }
+
+    /// Acknowledge pending PHY interrupts, releasing the interrupt pin
+    fn clear_interrupt(&mut self) -> Result<(), SmiError> {
+        Ok(())
+    }
+}
+
+/// Page select register of Realtek and compatible PHYs
+const PAGESEL: u8 = 31;
+
+/// 1000BASE-T control register
+const GBCR: u8 = 9;
+/// Advertise 1000BASE-T full duplex
+const GBCR_1000_FD: u16 = 1 << 9;
+/// Advertise 1000BASE-T half duplex
+const GBCR_1000_HD: u16 = 1 << 8;
+
+fn reset<T: Instance>(smi: &mut GenericSmi<T>, addr: u8) -> Result<(), SmiError> {
+    smi.write(addr, BCR, BCR_RESET)?;
+
+    // Reset bit self-clears
+    let mut timeout = 10_000u32;
+    while smi.read(addr, BCR)? & BCR_RESET != 0 {
+        timeout -= 1;
+        if timeout == 0 {
+            return Err(SmiError::Timeout);
+        }
+    }
+    Ok(())
+}
+
+fn start_autoneg<T: Instance>(smi: &mut GenericSmi<T>, addr: u8) -> Result<(), SmiError> {
+    smi.write(
+        addr,
+        ANAR,
+        AN_100TX_FD | AN_100TX_HD | AN_10T_FD | AN_10T_HD | AN_SELECTOR_8023,
+    )?;
+    smi.write(addr, BCR, BCR_AN_ENABLE | BCR_AN_RESTART)
+}
+
+/// Link and auto-negotiation state from BSR
+fn link_negotiated<T: Instance>(smi: &GenericSmi<T>, addr: u8) -> Result<bool, SmiError> {
+    // Link status latches low, the second read returns the current state
+    smi.read(addr, BSR)?;
+    let bsr = smi.read(addr, BSR)?;
+    Ok(bsr & BSR_LINK_STATUS != 0 && bsr & BSR_AN_COMPLETE != 0)
+}
+
+fn link(speed_100: bool, full_duplex: bool) -> LinkStatus {
+    LinkStatus {
+        speed: if speed_100 { Speed::Speed100M } else { Speed::Speed10M },
+        duplex: if full_duplex {
+            Duplex::FullDuplex
+        } else {
+            Duplex::HalfDuplex
+        },
+    }
+}
+
+impl<T: Instance> Phy for GenericPhy<T> {
+    fn reset(&mut self) -> Result<(), SmiError> {
+        GenericPhy::reset(self)
+    }
+
+    fn start_autoneg(&mut self) -> Result<(), SmiError> {
+        GenericPhy::start_autoneg(self)
+    }
+
+    fn link_status(&mut self) -> Result<Option<LinkStatus>, SmiError> {
+        Ok(self
+            .poll_link()?
+            .map(|(speed_100, full_duplex)| link(speed_100, full_duplex)))
+    }
+}
+
+/// RTL8201 registers
+mod rtl8201 {
+    /// RMII mode setting register, page 7
+    pub const RMSR_P7: u8 = 16;
+    /// RMII reference clock input
+    pub const RMSR_CLKDIR: u16 = 1 << 12;
+    /// Interrupt, WOL enable and LED function register, page 7
+    pub const IWELFR_P7: u8 = 19;
+    /// Link change interrupt enable
+    pub const IWELFR_LINK_CHANGE: u16 = 1 << 13;
+    /// Interrupt indicators, page 0, cleared on read
+    pub const IISDR: u8 = 30;
+}
+
+/// Realtek RTL8201F 10/100 PHY, e.g. on HPM6300EVK
+///
+/// The negotiated mode is reflected in BCR speed and duplex bits.
+pub struct Rtl8201<T: Instance> {
+    smi: GenericSmi<T>,
+    addr: u8,
+}
+
+impl<T: Instance> Rtl8201<T> {
+    /// Create the driver for the PHY at `addr`
+    pub fn new(smi: GenericSmi<T>, addr: u8) -> Self {
+        Self { smi, addr }
+    }
+
+    /// Set the RMII reference clock direction, `output` for the PHY to drive the 50MHz clock
+    pub fn set_refclk_output(&mut self, output: bool) -> Result<(), SmiError> {
+        use rtl8201::*;
+
+        self.smi.write(self.addr, PAGESEL, 7)?;
+        let mut rmsr = self.smi.read(self.addr, RMSR_P7)?;
+        if output {
+            rmsr &= !RMSR_CLKDIR;
+        } else {
+            rmsr |= RMSR_CLKDIR;
+        }
+        self.smi.write(self.addr, RMSR_P7, rmsr)?;
+        self.smi.write(self.addr, PAGESEL, 0)
+    }
+}
+
+impl<T: Instance> Phy for Rtl8201<T> {
+    fn reset(&mut self) -> Result<(), SmiError> {
+        reset(&mut self.smi, self.addr)
+    }
+
+    fn start_autoneg(&mut self) -> Result<(), SmiError> {
+        start_autoneg(&mut self.smi, self.addr)
+    }
+
+    fn link_status(&mut self) -> Result<Option<LinkStatus>, SmiError> {
+        if !link_negotiated(&self.smi, self.addr)? {
+            return Ok(None);
+        }
+        let bcr = self.smi.read(self.addr, BCR)?;
+        Ok(Some(link(bcr & BCR_SPEED_100 != 0, bcr & BCR_DUPLEX_FULL != 0)))
+    }
+
+    fn enable_link_interrupt(&mut self) -> Result<(), SmiError> {
+        use rtl8201::*;
+
+        self.smi.write(self.addr, PAGESEL, 7)?;
+        let iwelfr = self.smi.read(self.addr, IWELFR_P7)?;
+        self.smi.write(self.addr, IWELFR_P7, iwelfr | IWELFR_LINK_CHANGE)?;
+        self.smi.write(self.addr, PAGESEL, 0)
+    }
+
+    fn clear_interrupt(&mut self) -> Result<(), SmiError> {
+        self.smi.read(self.addr, rtl8201::IISDR).map(|_| ())
+    }
+}
+
+/// JLSemi JL1111 10/100 PHY
+///
+/// Register compatible with the RTL8201F.
+pub struct Jl1111<T: Instance> {
+    inner: Rtl8201<T>,
+}
+
+impl<T: Instance> Jl1111<T> {
+    /// Create the driver for the PHY at `addr`
+    pub fn new(smi: GenericSmi<T>, addr: u8) -> Self {
+        Self {
+            inner: Rtl8201::new(smi, addr),
+        }
+    }
+
+    /// Set the RMII reference clock direction, `output` for the PHY to drive the 50MHz clock
+    pub fn set_refclk_output(&mut self, output: bool) -> Result<(), SmiError> {
+        self.inner.set_refclk_output(output)
+    }
+}
+
+impl<T: Instance> Phy for Jl1111<T> {
+    fn reset(&mut self) -> Result<(), SmiError> {
+        self.inner.reset()
+    }
+
+    fn start_autoneg(&mut self) -> Result<(), SmiError> {
+        self.inner.start_autoneg()
+    }
+
+    fn link_status(&mut self) -> Result<Option<LinkStatus>, SmiError> {
+        self.inner.link_status()
+    }
+
+    fn enable_link_interrupt(&mut self) -> Result<(), SmiError> {
+        self.inner.enable_link_interrupt()
+    }
+
+    fn clear_interrupt(&mut self) -> Result<(), SmiError> {
+        self.inner.clear_interrupt()
+    }
+}
+
+/// RTL8211 registers
+mod rtl8211 {
+    /// Interrupt enable register
+    pub const INER: u8 = 18;
+    /// Link status change interrupt enable
+    pub const INER_LINK_STATUS: u16 = 1 << 10;
+    /// Interrupt status register, cleared on read
+    pub const INSR: u8 = 19;
+    /// PHY specific status register
+    pub const PHYSR: u8 = 17;
+    /// Resolved speed, bits 15:14
+    pub const PHYSR_SPEED_SHIFT: u16 = 14;
+    /// Resolved full duplex
+    pub const PHYSR_DUPLEX: u16 = 1 << 13;
+    /// Speed and duplex resolved
+    pub const PHYSR_RESOLVED: u16 = 1 << 11;
+    /// Real time link status
+    pub const PHYSR_LINK: u16 = 1 << 10;
+}
+
+/// Realtek RTL8211 gigabit PHY, e.g. on the RGMII port of HPM6750EVK
+pub struct Rtl8211<T: Instance> {
+    smi: GenericSmi<T>,
+    addr: u8,
+}
+
+impl<T: Instance> Rtl8211<T> {
+    /// Create the driver for the PHY at `addr`
+    pub fn new(smi: GenericSmi<T>, addr: u8) -> Self {
+        Self { smi, addr }
+    }
+}
+
+impl<T: Instance> Phy for Rtl8211<T> {
+    fn reset(&mut self) -> Result<(), SmiError> {
+        reset(&mut self.smi, self.addr)
+    }
+
+    fn start_autoneg(&mut self) -> Result<(), SmiError> {
+        let gbcr = self.smi.read(self.addr, GBCR)?;
+        self.smi.write(self.addr, GBCR, gbcr | GBCR_1000_FD | GBCR_1000_HD)?;
+        start_autoneg(&mut self.smi, self.addr)
+    }
+
+    fn link_status(&mut self) -> Result<Option<LinkStatus>, SmiError> {
+        use rtl8211::*;
+
+        let physr = self.smi.read(self.addr, PHYSR)?;
+        if physr & PHYSR_LINK == 0 || physr & PHYSR_RESOLVED == 0 {
+            return Ok(None);
+        }
+        let speed = match (physr >> PHYSR_SPEED_SHIFT) & 0b11 {
+            0b00 => Speed::Speed10M,
+            0b01 => Speed::Speed100M,
+            _ => Speed::Speed1000M,
+        };
+        let duplex = if physr & PHYSR_DUPLEX != 0 {
+            Duplex::FullDuplex
+        } else {
+            Duplex::HalfDuplex
+        };
+        Ok(Some(LinkStatus { speed, duplex }))
+    }
+
+    fn enable_link_interrupt(&mut self) -> Result<(), SmiError> {
+        use rtl8211::*;
+
+        let iner = self.smi.read(self.addr, INER)?;
+        self.smi.write(self.addr, INER, iner | INER_LINK_STATUS)
+    }
+
+    fn clear_interrupt(&mut self) -> Result<(), SmiError> {
+        self.smi.read(self.addr, rtl8211::INSR).map(|_| ())
+    }
+}
+
+/// DP83848 registers
+mod dp83848 {
+    /// PHY status register
+    pub const PHYSTS: u8 = 0x10;
+    /// Link valid
+    pub const PHYSTS_LINK: u16 = 1 << 0;
+    /// 10 Mbps
+    pub const PHYSTS_SPEED_10: u16 = 1 << 1;
+    /// Full duplex
+    pub const PHYSTS_DUPLEX: u16 = 1 << 2;
+    /// Auto-negotiation complete
+    pub const PHYSTS_AN_COMPLETE: u16 = 1 << 4;
+    /// MII interrupt control register
+    pub const MICR: u8 = 0x11;
+    /// Enable interrupts
+    pub const MICR_INTEN: u16 = 1 << 1;
+    /// Drive the PWR_DOWN/INT pin as interrupt output
+    pub const MICR_INT_OE: u16 = 1 << 0;
+    /// MII interrupt status register, cleared on read
+    pub const MISR: u8 = 0x12;
+    /// Link status change interrupt enable
+    pub const MISR_LINK_INT_EN: u16 = 1 << 5;
+}
+
+/// TI DP83848 10/100 PHY, e.g. on the RMII port of HPM6750EVK
+pub struct Dp83848<T: Instance> {
+    smi: GenericSmi<T>,
+    addr: u8,
+}
+
+impl<T: Instance> Dp83848<T> {
+    /// Create the driver for the PHY at `addr`
+    pub fn new(smi: GenericSmi<T>, addr: u8) -> Self {
+        Self { smi, addr }
+    }
+}
+
+impl<T: Instance> Phy for Dp83848<T> {
+    fn reset(&mut self) -> Result<(), SmiError> {
+        reset(&mut self.smi, self.addr)
+    }
+
+    fn start_autoneg(&mut self) -> Result<(), SmiError> {
+        start_autoneg(&mut self.smi, self.addr)
+    }
+
+    fn link_status(&mut self) -> Result<Option<LinkStatus>, SmiError> {
+        use dp83848::*;
+
+        let physts = self.smi.read(self.addr, PHYSTS)?;
+        if physts & PHYSTS_LINK == 0 || physts & PHYSTS_AN_COMPLETE == 0 {
+            return Ok(None);
+        }
+        Ok(Some(link(physts & PHYSTS_SPEED_10 == 0, physts & PHYSTS_DUPLEX != 0)))
+    }
+
+    fn enable_link_interrupt(&mut self) -> Result<(), SmiError> {
+        use dp83848::*;
+
+        self.smi.write(self.addr, MISR, MISR_LINK_INT_EN)?;
+        self.smi.write(self.addr, MICR, MICR_INTEN | MICR_INT_OE)
+    }
+
+    fn clear_interrupt(&mut self) -> Result<(), SmiError> {
+        self.smi.read(self.addr, dp83848::MISR).map(|_| ())
+    }
+}
+
+/// Interval between link polls without interrupt pin, or after an SMI error
+#[cfg(feature = "time")]
+pub const LINK_POLL_INTERVAL: embassy_time::Duration = embassy_time::Duration::from_millis(500);
+
+/// Keep the link configuration of instance `T` in sync with `phy`
+///
+/// Calls [`set_link`](super::set_link) and [`set_link_down`](super::set_link_down) on every
+/// change. With `interrupt`, the PHY interrupt pin configured as input, the monitor sleeps until
+/// the PHY signals a link change. Otherwise, or if the PHY has no link interrupt, it polls every
+/// [`LINK_POLL_INTERVAL`].
+#[cfg(feature = "time")]
+pub async fn link_monitor<T: Instance, P: Phy>(phy: &mut P, mut interrupt: Option<&mut crate::gpio::Input<'_>>) -> ! {
+    if interrupt.is_some() && phy.enable_link_interrupt().is_err() {
+        #[cfg(feature = "defmt")]
+        defmt::warn!("ENET: PHY link interrupt unavailable, polling");
+        interrupt = None;
+    }
+
+    // Force an update on the first poll, the driver starts with the link up
+    let mut last: Option<Option<LinkStatus>> = None;
+    loop {
+        let status = phy.clear_interrupt().and_then(|_| phy.link_status());
+        match status {
+            Ok(status) if Some(status) != last => {
+                match status {
+                    Some(link) => {
+                        #[cfg(feature = "defmt")]
+                        defmt::info!("ENET: link up, {} {}", link.speed, link.duplex);
+                        super::set_link::<T>(link.speed, link.duplex);
+                    }
+                    None => {
+                        #[cfg(feature = "defmt")]
+                        defmt::info!("ENET: link down");
+                        super::set_link_down::<T>();
+                    }
+                }
+                last = Some(status);
+            }
+            Ok(_) => {}
+            Err(_e) => {
+                #[cfg(feature = "defmt")]
+                defmt::warn!("ENET: PHY access failed: {}", _e);
+                embassy_time::Timer::after(LINK_POLL_INTERVAL).await;
+                continue;
+            }
+        }
+
+        match interrupt.as_deref_mut() {
+            Some(pin) => pin.wait_for_low().await,
+            None => embassy_time::Timer::after(LINK_POLL_INTERVAL).await,
+        }
+    }
+}
@@ -0,0 +1,375 @@
+//! IEEE 1588 PTP hardware timestamping and system time
+//!
+//! With [`Config::ptp`](super::Config::ptp) set, the MAC runs its system time from the PTP clock and
+//! timestamps PTP event messages (Sync, Delay_Req, Pdelay_Req, Pdelay_Resp) in the DMA descriptors,
+//! over Ethernet (EtherType 0x88F7) and UDP/IPv4 (port 319). The frames still go through embassy-net
+//! as usual, their timestamps are looked up by message type and sequence id:
+//!
+//! ```ignore
+//! let clock = eth.ptp_clock();
+//! // ... hand `eth` to embassy-net, bind UDP sockets to ports 319 and 320
+//!
+//! let (n, _) = event_socket.recv_from(&mut buf).await?;
+//! let t2 = clock.rx_timestamp(MESSAGE_SYNC, u16::from_be_bytes([buf[30], buf[31]]));
+//!
+//! event_socket.send_to(&delay_req, ep).await?;
+//! let t3 = clock.wait_tx_timestamp(MESSAGE_DELAY_REQ, seq).await;
+//!
+//! clock.step(offset_ns)?;          // large offsets
+//! clock.adjust_frequency(ppb)?;    // servo output
+//! ```
+
+use core::cell::RefCell;
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use critical_section::Mutex;
+use embassy_hal_internal::Peri;
+use embassy_time::Timer;
+
+use super::{DESC_SIZE, Error, Instance, PpsPin, descriptors, invalidate_dcache};
+
+/// PTP messageType of Sync
+pub const MESSAGE_SYNC: u8 = 0x0;
+/// PTP messageType of Delay_Req
+pub const MESSAGE_DELAY_REQ: u8 = 0x1;
+/// PTP messageType of Pdelay_Req
+pub const MESSAGE_PDELAY_REQ: u8 = 0x2;
+/// PTP messageType of Pdelay_Resp
+pub const MESSAGE_PDELAY_RESP: u8 = 0x3;
+
+const NANOS_PER_SEC: u32 = 1_000_000_000;
+
+/// TS_CTRL bits
+const TSENA: u32 = 1 << 0;
+const TSCFUPDT: u32 = 1 << 1;
+const TSINIT: u32 = 1 << 2;
+const TSUPDT: u32 = 1 << 3;
+const TSADDREG: u32 = 1 << 5;
+/// Digital rollover, sub-second register counts nanoseconds
+const TSCTRLSSR: u32 = 1 << 9;
+const TSVER2ENA: u32 = 1 << 10;
+const TSIPENA: u32 = 1 << 11;
+const TSIPV4ENA: u32 = 1 << 13;
+const TSEVNTENA: u32 = 1 << 14;
+/// SNAPTYPSEL = 1 with TSEVNTENA: Sync, Delay_Req, Pdelay_Req, Pdelay_Resp
+const SNAPTYPSEL_EVENT: u32 = 1 << 16;
+/// SYST_NSEC_UPD subtract bit
+const ADDSUB: u32 = 1 << 31;
+
+/// TS_CTRL polls before giving up on an update, the MAC takes a few PTP clock cycles
+const TS_UPDATE_TIMEOUT: u32 = 100_000;
+/// INTR_MASK timestamp interrupt mask
+const TSIM: u32 = 1 << 9;
+
+/// Received event timestamps kept for lookup
+const RX_EVENTS: usize = 4;
+
+/// Time of the MAC system time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Timestamp {
+    /// Seconds
+    pub seconds: u32,
+    /// Nanoseconds, below 1_000_000_000
+    pub nanoseconds: u32,
+}
+
+impl Timestamp {
+    pub const fn new(seconds: u32, nanoseconds: u32) -> Self {
+        Self { seconds, nanoseconds }
+    }
+
+    /// Total nanoseconds, for computing offsets
+    pub const fn as_nanos(&self) -> i64 {
+        self.seconds as i64 * NANOS_PER_SEC as i64 + self.nanoseconds as i64
+    }
+
+    /// From the PTP originTimestamp/receiveTimestamp field, 48-bit seconds (truncated) and 32-bit nanoseconds
+    pub fn from_ptp_bytes(bytes: &[u8; 10]) -> Self {
+        Self {
+            seconds: u32::from_be_bytes([bytes[2], bytes[3], bytes[4], bytes[5]]),
+            nanoseconds: u32::from_be_bytes([bytes[6], bytes[7], bytes[8], bytes[9]]),
+        }
+    }
+
+    /// To the PTP timestamp field format
+    pub fn to_ptp_bytes(&self) -> [u8; 10] {
+        let mut bytes = [0; 10];
+        bytes[2..6].copy_from_slice(&self.seconds.to_be_bytes());
+        bytes[6..10].copy_from_slice(&self.nanoseconds.to_be_bytes());
+        bytes
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Event {
+    message_type: u8,
+    sequence_id: u16,
+    timestamp: Timestamp,
+}
+
+#[derive(Clone, Copy)]
+struct TxPending {
+    message_type: u8,
+    sequence_id: u16,
+    /// Descriptor the frame was sent from
+    desc: u32,
+}
+
+struct Events {
+    rx: [Option<Event>; RX_EVENTS],
+    rx_next: usize,
+    tx: Option<TxPending>,
+}
+
+/// Timestamp bookkeeping, part of the driver [`State`](super::State)
+pub(crate) struct PtpState {
+    events: Mutex<RefCell<Events>>,
+    /// Addend for the nominal frequency
+    addend: AtomicU32,
+}
+
+impl PtpState {
+    pub(crate) const fn new() -> Self {
+        Self {
+            events: Mutex::new(RefCell::new(Events {
+                rx: [None; RX_EVENTS],
+                rx_next: 0,
+                tx: None,
+            })),
+            addend: AtomicU32::new(0),
+        }
+    }
+
+    /// Record the timestamp of a received frame, if it is a PTP event message
+    pub(crate) fn on_rx(&self, frame: &[u8], timestamp: (u32, u32)) {
+        if let Some((message_type, sequence_id)) = event_header(frame) {
+            critical_section::with(|cs| {
+                let mut events = self.events.borrow_ref_mut(cs);
+                let i = events.rx_next;
+                events.rx[i] = Some(Event {
+                    message_type,
+                    sequence_id,
+                    timestamp: Timestamp::new(timestamp.0, timestamp.1),
+                });
+                events.rx_next = (i + 1) % RX_EVENTS;
+            });
+        }
+    }
+
+    /// Remember the descriptor of a transmitted PTP event message
+    pub(crate) fn on_tx(&self, message_type: u8, sequence_id: u16, desc: u32) {
+        critical_section::with(|cs| {
+            self.events.borrow_ref_mut(cs).tx = Some(TxPending {
+                message_type,
+                sequence_id,
+                desc,
+            });
+        });
+    }
+}
+
+/// messageType and sequenceId of a PTP event message, over Ethernet or UDP/IPv4
+pub(crate) fn event_header(frame: &[u8]) -> Option<(u8, u16)> {
+    let mut offset = 12;
+    let mut ethertype = u16::from_be_bytes([*frame.get(offset)?, *frame.get(offset + 1)?]);
+    if ethertype == 0x8100 {
+        offset += 4;
+        ethertype = u16::from_be_bytes([*frame.get(offset)?, *frame.get(offset + 1)?]);
+    }
+    offset += 2;
+
+    let header = match ethertype {
+        0x88F7 => offset,
+        0x0800 => {
+            let ihl = (*frame.get(offset)? & 0xF) as usize * 4;
+            let udp = offset + ihl;
+            // UDP, event port 319
+            if *frame.get(offset + 9)? != 17 || frame.get(udp + 2..udp + 4)? != [0x01, 0x3F] {
+                return None;
+            }
+            udp + 8
+        }
+        _ => return None,
+    };
+
+    let message_type = *frame.get(header)? & 0xF;
+    let sequence_id = u16::from_be_bytes([*frame.get(header + 30)?, *frame.get(header + 31)?]);
+    (message_type <= MESSAGE_PDELAY_RESP).then_some((message_type, sequence_id))
+}
+
+/// Start the system time and event timestamping
+pub(crate) fn init<T: Instance>() {
+    let regs = T::info().regs;
+    let state = &T::state().ptp;
+
+    // The timestamp interrupt is not handled, its status would stay pending
+    regs.intr_mask().modify(|w| w.0 |= TSIM);
+
+    regs.ts_ctrl().write(|w| {
+        w.0 = TSENA | TSCTRLSSR | TSVER2ENA | TSIPENA | TSIPV4ENA | TSEVNTENA | SNAPTYPSEL_EVENT;
+    });
+
+    // Fine update: the accumulator overflows at half the PTP clock with the nominal addend, leaving
+    // room to speed up. The sub-second increment is two clock periods.
+    let freq = crate::sysctl::clocks().get_clock_freq(T::PTP_CLOCK).0 as u64;
+    let ssinc = (2 * NANOS_PER_SEC as u64).div_ceil(freq).clamp(1, 0xFF);
+    let addend = ((NANOS_PER_SEC as u64 / ssinc) << 32) / freq;
+    regs.sub_sec_incr().write(|w| w.0 = ssinc as u32);
+    state.addend.store(addend as u32, Ordering::Relaxed);
+    let _ = write_addend(regs, addend as u32);
+    regs.ts_ctrl().modify(|w| w.0 |= TSCFUPDT);
+
+    regs.syst_sec_upd().write(|w| w.0 = 0);
+    regs.syst_nsec_upd().write(|w| w.0 = 0);
+    regs.ts_ctrl().modify(|w| w.0 |= TSINIT);
+    // A clock stuck here is reported by the next `PtpClock::set`, `step` or `adjust_frequency`
+    let _ = wait_update(regs, TSINIT);
+}
+
+fn write_addend(regs: crate::pac::enet::Enet, addend: u32) -> Result<(), Error> {
+    wait_update(regs, TSADDREG)?;
+    regs.ts_addend().write(|w| w.0 = addend);
+    regs.ts_ctrl().modify(|w| w.0 |= TSADDREG);
+    wait_update(regs, TSADDREG)
+}
+
+/// Wait for the MAC to clear the TS_CTRL update request `bit`
+fn wait_update(regs: crate::pac::enet::Enet, bit: u32) -> Result<(), Error> {
+    let mut timeout = TS_UPDATE_TIMEOUT;
+    while regs.ts_ctrl().read().0 & bit != 0 {
+        timeout -= 1;
+        if timeout == 0 {
+            return Err(Error::Timeout);
+        }
+    }
+    Ok(())
+}
+
+/// PTP hardware clock of an ENET instance, see [`Ethernet::ptp_clock`](super::Ethernet::ptp_clock)
+///
+/// Only valid when the driver was created with [`Config::ptp`](super::Config::ptp) set.
+pub struct PtpClock<'d, T: Instance> {
+    pub(crate) _phantom: PhantomData<&'d T>,
+}
+
+impl<'d, T: Instance> Clone for PtpClock<'d, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'d, T: Instance> Copy for PtpClock<'d, T> {}
+
+impl<'d, T: Instance> PtpClock<'d, T> {
+    /// Current system time
+    pub fn now(&self) -> Timestamp {
+        let regs = T::info().regs;
+        loop {
+            let seconds = regs.syst_sec().read().0;
+            let nanoseconds = regs.syst_nsec().read().0 & 0x7FFF_FFFF;
+            // Retry on a seconds rollover between the reads
+            if regs.syst_sec().read().0 == seconds {
+                return Timestamp::new(seconds, nanoseconds);
+            }
+        }
+    }
+
+    /// Set the system time
+    pub fn set(&self, time: Timestamp) -> Result<(), Error> {
+        let regs = T::info().regs;
+        wait_update(regs, TSINIT)?;
+        regs.syst_sec_upd().write(|w| w.0 = time.seconds);
+        regs.syst_nsec_upd()
+            .write(|w| w.0 = time.nanoseconds.min(NANOS_PER_SEC - 1));
+        regs.ts_ctrl().modify(|w| w.0 |= TSINIT);
+        wait_update(regs, TSINIT)
+    }
+
+    /// Add `offset_ns` to the system time, in hardware without losing time
+    pub fn step(&self, offset_ns: i64) -> Result<(), Error> {
+        let regs = T::info().regs;
+        wait_update(regs, TSUPDT)?;
+        let abs = offset_ns.unsigned_abs();
+        let seconds = (abs / NANOS_PER_SEC as u64) as u32;
+        let nanoseconds = (abs % NANOS_PER_SEC as u64) as u32;
+        regs.syst_sec_upd().write(|w| w.0 = seconds);
+        regs.syst_nsec_upd().write(|w| {
+            // With digital rollover, a subtracted value is programmed as 10^9 - nanoseconds,
+            // a whole number of seconds subtracts zero nanoseconds as 10^9 is out of range
+            w.0 = match (offset_ns < 0, nanoseconds) {
+                (false, ns) => ns,
+                (true, 0) => ADDSUB,
+                (true, ns) => ADDSUB | (NANOS_PER_SEC - ns),
+            }
+        });
+        regs.ts_ctrl().modify(|w| w.0 |= TSUPDT);
+        wait_update(regs, TSUPDT)
+    }
+
+    /// Run the clock `ppb` parts per billion faster (negative: slower) than nominal
+    ///
+    /// Adjustments are limited to about +-90% of the nominal rate, far beyond what a servo needs.
+    pub fn adjust_frequency(&self, ppb: i32) -> Result<(), Error> {
+        let nominal = T::state().ptp.addend.load(Ordering::Relaxed) as i64;
+        let addend = nominal + nominal * ppb as i64 / NANOS_PER_SEC as i64;
+        write_addend(T::info().regs, addend.clamp(1, u32::MAX as i64) as u32)
+    }
+
+    /// Receive timestamp of an event message, as long as it is among the last few received
+    pub fn rx_timestamp(&self, message_type: u8, sequence_id: u16) -> Option<Timestamp> {
+        critical_section::with(|cs| {
+            let events = T::state().ptp.events.borrow_ref(cs);
+            events
+                .rx
+                .iter()
+                .flatten()
+                .find(|e| e.message_type == message_type && e.sequence_id == sequence_id)
+                .map(|e| e.timestamp)
+        })
+    }
+
+    /// Transmit timestamp of the last event message sent, if it matches and has been sent
+    ///
+    /// Read it right after sending, the descriptor is reused once the TX ring wraps around.
+    pub fn tx_timestamp(&self, message_type: u8, sequence_id: u16) -> Option<Timestamp> {
+        critical_section::with(|cs| {
+            let events = T::state().ptp.events.borrow_ref(cs);
+            let pending = events.tx?;
+            if pending.message_type != message_type || pending.sequence_id != sequence_id {
+                return None;
+            }
+            invalidate_dcache(pending.desc, DESC_SIZE as u32);
+            let desc = unsafe { &*(pending.desc as *const descriptors::TDes) };
+            if desc.is_owned() {
+                return None;
+            }
+            desc.timestamp()
+                .map(|(seconds, nanoseconds)| Timestamp::new(seconds, nanoseconds))
+        })
+    }
+
+    /// Wait for the transmit timestamp, gives up after 10ms
+    pub async fn wait_tx_timestamp(&self, message_type: u8, sequence_id: u16) -> Option<Timestamp> {
+        for _ in 0..10 {
+            if let Some(timestamp) = self.tx_timestamp(message_type, sequence_id) {
+                return Some(timestamp);
+            }
+            Timer::after_millis(1).await;
+        }
+        None
+    }
+
+    /// Output a pulse per second on `pin`, in sync with the system time
+    ///
+    /// With `log2_hz` 0, a 1 Hz pulse one PTP clock period wide is output at each second rollover.
+    /// Higher values output a square wave of 2^`log2_hz` Hz, up to 2^14 Hz.
+    pub fn enable_pps(&self, pin: Peri<'d, impl PpsPin<T>>, log2_hz: u8) {
+        pin.ioc_pad().func_ctl().write(|w| w.set_alt_select(pin.alt_num()));
+
+        // With digital rollover, PPSCTRL n > 0 gives 2^(n-1) Hz
+        let ctrl = if log2_hz == 0 { 0 } else { log2_hz.min(14) as u32 + 1 };
+        T::info().regs.pps_ctrl().modify(|w| w.0 = (w.0 & !0xF) | ctrl);
+    }
+}
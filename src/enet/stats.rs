@@ -0,0 +1,176 @@
+//! MAC management (MMC) counters and driver drop counters
+
+use core::cell::Cell;
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use critical_section::Mutex;
+
+use super::{Error, Instance, pac};
+
+/// Link statistics, see [`Ethernet::stats`](super::Ethernet::stats)
+///
+/// MAC counters wrap around at `u32::MAX`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Stats {
+    /// Frames transmitted without error
+    pub tx_frames: u32,
+    /// Bytes of frames transmitted without error
+    pub tx_bytes: u32,
+    /// Frames aborted by TX FIFO underflow
+    pub tx_underflow: u32,
+    /// Frames transmitted after a single collision (half duplex)
+    pub tx_single_collisions: u32,
+    /// Frames transmitted after more than one collision (half duplex)
+    pub tx_multiple_collisions: u32,
+    /// Frames aborted by a late collision
+    pub tx_late_collisions: u32,
+    /// Frames aborted after 16 collisions
+    pub tx_excessive_collisions: u32,
+    /// Frames aborted by loss of carrier
+    pub tx_carrier_errors: u32,
+
+    /// Frames received, good and bad
+    pub rx_frames: u32,
+    /// Bytes of frames received without error
+    pub rx_bytes: u32,
+    /// Good unicast frames received
+    pub rx_unicast: u32,
+    /// Good multicast frames received
+    pub rx_multicast: u32,
+    /// Good broadcast frames received
+    pub rx_broadcast: u32,
+    /// Frames with CRC error
+    pub rx_crc_errors: u32,
+    /// Frames with dribble nibble (alignment error)
+    pub rx_alignment_errors: u32,
+    /// Frames shorter than 64 bytes with error
+    pub rx_runt_errors: u32,
+    /// Frames longer than 1518 bytes with CRC error
+    pub rx_jabber_errors: u32,
+    /// Frames whose length field doesn't match the size
+    pub rx_length_errors: u32,
+    /// Frames lost to RX FIFO overflow
+    pub rx_fifo_overflow: u32,
+    /// Frames cut by the receive watchdog
+    pub rx_watchdog_errors: u32,
+
+    /// Frames lost because no RX descriptor was available
+    pub rx_missed: u32,
+    /// Received frames with errors, dropped by the driver
+    pub rx_dropped: u32,
+    /// Received frames larger than an RX buffer, dropped by the driver
+    pub rx_oversize: u32,
+    /// Frames the driver failed to hand to the DMA
+    pub tx_dropped: u32,
+    /// Error of the last dropped RX frame
+    pub last_rx_error: Option<Error>,
+}
+
+/// Driver counters, part of the driver [`State`](super::State)
+pub(crate) struct Counters {
+    rx_missed: AtomicU32,
+    pub(crate) rx_dropped: AtomicU32,
+    pub(crate) rx_oversize: AtomicU32,
+    pub(crate) tx_dropped: AtomicU32,
+    last_rx_error: Mutex<Cell<Option<Error>>>,
+}
+
+impl Counters {
+    pub(crate) const fn new() -> Self {
+        Self {
+            rx_missed: AtomicU32::new(0),
+            rx_dropped: AtomicU32::new(0),
+            rx_oversize: AtomicU32::new(0),
+            tx_dropped: AtomicU32::new(0),
+            last_rx_error: Mutex::new(Cell::new(None)),
+        }
+    }
+
+    /// Count a received frame dropped for `error`
+    pub(crate) fn rx_error(&self, error: Error) {
+        self.rx_dropped.fetch_add(1, Ordering::Relaxed);
+        critical_section::with(|cs| self.last_rx_error.borrow(cs).set(Some(error)));
+    }
+}
+
+/// Reset the MMC counters and mask their interrupts, which the driver doesn't handle
+pub(crate) fn init(regs: pac::enet::Enet) {
+    regs.mmc_intr_mask_rx().write(|w| w.0 = u32::MAX);
+    regs.mmc_intr_mask_tx().write(|w| w.0 = u32::MAX);
+    regs.mmc_ipc_intr_mask_rx().write(|w| w.0 = u32::MAX);
+    regs.mmc_cntrl().write(|w| w.set_cntrst(true));
+}
+
+/// Statistics of an ENET instance, see [`Ethernet::stats_handle`](super::Ethernet::stats_handle)
+pub struct StatsHandle<'d, T: Instance> {
+    pub(crate) _phantom: PhantomData<&'d T>,
+}
+
+impl<'d, T: Instance> Clone for StatsHandle<'d, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'d, T: Instance> Copy for StatsHandle<'d, T> {}
+
+impl<'d, T: Instance> StatsHandle<'d, T> {
+    /// Read the MAC counters and the frames dropped by the driver
+    pub fn stats(&self) -> Stats {
+        let regs = T::info().regs;
+        let counters = &T::state().counters;
+
+        // Frames missed by the controller, the counter clears on read
+        let missed = regs.dma_miss_ovf_cnt().read().misfroc() as u32;
+        let rx_missed = counters
+            .rx_missed
+            .fetch_add(missed, Ordering::Relaxed)
+            .wrapping_add(missed);
+
+        Stats {
+            tx_frames: regs.txframecount_g().read().0,
+            tx_bytes: regs.txoctetcount_g().read().0,
+            tx_underflow: regs.txunderflowerror().read().0,
+            tx_single_collisions: regs.txsinglecol_g().read().0,
+            tx_multiple_collisions: regs.txmulticol_g().read().0,
+            tx_late_collisions: regs.txlatecol().read().0,
+            tx_excessive_collisions: regs.txexesscol().read().0,
+            tx_carrier_errors: regs.txcarriererror().read().0,
+
+            rx_frames: regs.rxframecount_gb().read().0,
+            rx_bytes: regs.rxoctetcount_g().read().0,
+            rx_unicast: regs.rxunicastframes_g().read().0,
+            rx_multicast: regs.rxmulticastframes_g().read().0,
+            rx_broadcast: regs.rxbroadcastframes_g().read().0,
+            rx_crc_errors: regs.rxcrcerror().read().0,
+            rx_alignment_errors: regs.rxalignmenterror().read().0,
+            rx_runt_errors: regs.rxrunterror().read().0,
+            rx_jabber_errors: regs.rxjabbererror().read().0,
+            rx_length_errors: regs.rxlengtherror().read().0,
+            rx_fifo_overflow: regs.rxfifooverflow().read().0,
+            rx_watchdog_errors: regs.rxwatchdogerror().read().0,
+
+            rx_missed,
+            rx_dropped: counters.rx_dropped.load(Ordering::Relaxed),
+            rx_oversize: counters.rx_oversize.load(Ordering::Relaxed),
+            tx_dropped: counters.tx_dropped.load(Ordering::Relaxed),
+            last_rx_error: critical_section::with(|cs| counters.last_rx_error.borrow(cs).get()),
+        }
+    }
+
+    /// Clear the MAC and driver counters
+    pub fn reset(&self) {
+        let regs = T::info().regs;
+        let counters = &T::state().counters;
+
+        regs.mmc_cntrl().modify(|w| w.set_cntrst(true));
+        regs.dma_miss_ovf_cnt().read();
+        counters.rx_missed.store(0, Ordering::Relaxed);
+        counters.rx_dropped.store(0, Ordering::Relaxed);
+        counters.rx_oversize.store(0, Ordering::Relaxed);
+        counters.tx_dropped.store(0, Ordering::Relaxed);
+        critical_section::with(|cs| counters.last_rx_error.borrow(cs).set(None));
+    }
+}
@@ -0,0 +1,303 @@
+//! Wake-on-LAN with the MAC power management (PMT) block
+//!
+//! In power-down mode the MAC drops all frames and only checks them for a magic packet or a
+//! remote wake-up frame. The match raises the ENET interrupt, which wakes up the CPU when the
+//! ENET clock is kept running in low power mode.
+
+use core::future::poll_fn;
+use core::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use core::task::Poll;
+
+use embassy_sync::waitqueue::AtomicWaker;
+use riscv_pac::InterruptNumber;
+
+use super::{Error, Instance, pac, read_reg, write_reg};
+use crate::interrupt::typelevel::Interrupt as _;
+use crate::sysctl::{self, ResourceMode, SealedClockPeripheral};
+
+/// Remote wake-up frame filter register, 8 words written in sequence
+const RWKFRMFILT: usize = 0x28;
+/// PMT control and status register
+const PMT_CTRL_STATUS: usize = 0x2C;
+
+const PWRDWN: u32 = 1 << 0;
+const MGKPKTEN: u32 = 1 << 1;
+const RWKPKTEN: u32 = 1 << 2;
+const MGKPRCVD: u32 = 1 << 5;
+const RWKPRCVD: u32 = 1 << 6;
+const GLBLUCAST: u32 = 1 << 9;
+const RWKFILTRST: u32 = 1 << 31;
+
+/// INTR_MASK PMT interrupt mask
+const PMTIM: u32 = 1 << 3;
+/// DMA_STATUS PMT interrupt
+pub(crate) const GPI: u32 = 1 << 28;
+
+/// DMA_STATUS polls before giving up on the DMA stopping
+const DMA_STOP_TIMEOUT: u32 = 100_000;
+
+/// Number of remote wake-up frame filters
+pub const MAX_WAKEUP_FILTERS: usize = 4;
+
+/// Wake up event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum WakeEvent {
+    /// Magic packet received
+    MagicPacket,
+    /// Frame matching a wake-up filter, or any unicast frame with `WolConfig::unicast`
+    WakeUpFrame,
+}
+
+/// Remote wake-up frame filter
+///
+/// Matches frames whose selected bytes have the CRC-16 of the pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct WakeUpFilter {
+    mask: u32,
+    offset: u8,
+    crc: u16,
+    multicast: bool,
+}
+
+impl WakeUpFilter {
+    /// Match `pattern` at frame byte `offset`, comparing only bytes `i` with bit `i` set in `mask`
+    ///
+    /// `offset` is counted from the destination MAC address and must be at least 12.
+    /// The mask covers 31 bytes, bit 31 is ignored.
+    ///
+    /// Returns [`Error::InvalidFilter`] if `offset` is below 12 or `mask` selects bytes past the
+    /// end of `pattern`.
+    pub fn new(offset: u8, pattern: &[u8], mask: u32) -> Result<Self, Error> {
+        let mask = mask & 0x7FFF_FFFF;
+        // The MAC addresses are not covered by the filters
+        if offset < 12 || 32 - mask.leading_zeros() as usize > pattern.len() {
+            return Err(Error::InvalidFilter);
+        }
+
+        let mut crc = 0xFFFFu16;
+        for (i, byte) in pattern.iter().enumerate() {
+            if mask & (1 << i) == 0 {
+                continue;
+            }
+            // CRC-16, x^16 + x^15 + x^2 + 1, LSB first like the MAC
+            crc ^= *byte as u16;
+            for _ in 0..8 {
+                crc = if crc & 1 != 0 { (crc >> 1) ^ 0xA001 } else { crc >> 1 };
+            }
+        }
+
+        Ok(Self {
+            mask,
+            offset,
+            crc,
+            multicast: false,
+        })
+    }
+
+    /// Only check multicast frames
+    pub fn multicast_only(mut self) -> Self {
+        self.multicast = true;
+        self
+    }
+}
+
+/// Wake-on-LAN configuration
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WolConfig<'a> {
+    /// Wake up on a magic packet to the station MAC address
+    pub magic_packet: bool,
+    /// Wake up on any unicast frame passing the address filter
+    pub unicast: bool,
+    /// Wake up on frames matching one of these filters, at most [`MAX_WAKEUP_FILTERS`]
+    pub filters: &'a [WakeUpFilter],
+}
+
+/// Wake-on-LAN state, part of the driver [`State`](super::State)
+pub(crate) struct WolState {
+    waker: AtomicWaker,
+    /// `WakeEvent` + 1, 0 without event
+    event: AtomicU8,
+    armed: AtomicBool,
+    /// ENET interrupt enabled before `enter_wol`, disabled for RGMII
+    irq_enabled: AtomicBool,
+    /// CPU woken up by the ENET interrupt
+    wakeup_cpu: AtomicU8,
+    /// ENET interrupt already a wake up source of `wakeup_cpu` before `enter_wol`
+    wakeup_enabled: AtomicBool,
+}
+
+impl WolState {
+    pub(crate) const fn new() -> Self {
+        Self {
+            waker: AtomicWaker::new(),
+            event: AtomicU8::new(0),
+            armed: AtomicBool::new(false),
+            irq_enabled: AtomicBool::new(false),
+            wakeup_cpu: AtomicU8::new(0),
+            wakeup_enabled: AtomicBool::new(false),
+        }
+    }
+
+    fn take_event(&self) -> Option<WakeEvent> {
+        match self.event.swap(0, Ordering::AcqRel) {
+            1 => Some(WakeEvent::MagicPacket),
+            2 => Some(WakeEvent::WakeUpFrame),
+            _ => None,
+        }
+    }
+}
+
+/// PMT interrupt, reading the status clears it
+pub(crate) fn on_interrupt(regs: pac::enet::Enet, state: &WolState) {
+    let pmt = read_reg(regs, PMT_CTRL_STATUS);
+    let event = if pmt & MGKPRCVD != 0 {
+        1
+    } else if pmt & RWKPRCVD != 0 {
+        2
+    } else {
+        return;
+    };
+    state.event.store(event, Ordering::Release);
+    state.waker.wake();
+}
+
+/// Poll DMA_STATUS until `done` accepts it, at most `DMA_STOP_TIMEOUT` times
+fn wait_dma_state(regs: pac::enet::Enet, done: impl Fn(u32) -> bool) -> Result<(), Error> {
+    let mut timeout = DMA_STOP_TIMEOUT;
+    while !done(regs.dma_status().read().0) {
+        timeout -= 1;
+        if timeout == 0 {
+            return Err(Error::Timeout);
+        }
+    }
+    Ok(())
+}
+
+/// Park the DMA and put the MAC of instance `T` in power-down mode, see
+/// [`Ethernet::enter_wol`](super::Ethernet::enter_wol)
+pub fn enter_wol<T: Instance>(config: &WolConfig) -> Result<(), Error> {
+    if config.filters.len() > MAX_WAKEUP_FILTERS {
+        return Err(Error::FilterFull);
+    }
+    let regs = T::info().regs;
+    let state = &T::state().wol;
+
+    // Let the frame in flight go out, then stop the receiver and wait for the RX FIFO to drain.
+    // If the DMA doesn't settle, restart what was stopped and leave the MAC running.
+    regs.dma_op_mode().modify(|w| w.set_st(false));
+    if let Err(e) = wait_dma_state(regs, |status| (status >> 20) & 0x7 == 0) {
+        regs.dma_op_mode().modify(|w| w.set_st(true));
+        return Err(e);
+    }
+    regs.maccfg().modify(|w| {
+        w.set_te(false);
+        w.set_re(false);
+    });
+    if let Err(e) = wait_dma_state(regs, |status| matches!((status >> 17) & 0x7, 0 | 3 | 4)) {
+        regs.maccfg().modify(|w| {
+            w.set_te(true);
+            w.set_re(true);
+        });
+        regs.dma_op_mode().modify(|w| w.set_st(true));
+        return Err(e);
+    }
+    regs.dma_op_mode().modify(|w| w.set_sr(false));
+
+    // Filter registers: byte masks, commands, offsets, then two CRC-16 per word
+    let mut words = [0u32; 8];
+    for (i, filter) in config.filters.iter().enumerate() {
+        words[i] = filter.mask;
+        words[4] |= (1 | (filter.multicast as u32) << 3) << (8 * i);
+        words[5] |= (filter.offset as u32) << (8 * i);
+        words[6 + i / 2] |= (filter.crc as u32) << (16 * (i % 2));
+    }
+    write_reg(regs, PMT_CTRL_STATUS, RWKFILTRST);
+    for word in words {
+        write_reg(regs, RWKFRMFILT, word);
+    }
+
+    let mut pmt = PWRDWN;
+    if config.magic_packet {
+        pmt |= MGKPKTEN;
+    }
+    if config.unicast {
+        pmt |= GLBLUCAST | RWKPKTEN;
+    }
+    if !config.filters.is_empty() {
+        pmt |= RWKPKTEN;
+    }
+
+    state.event.store(0, Ordering::Relaxed);
+    state.irq_enabled.store(T::Interrupt::is_enabled(), Ordering::Relaxed);
+    state.armed.store(true, Ordering::Release);
+
+    regs.intr_mask().modify(|w| w.0 &= !PMTIM);
+    write_reg(regs, PMT_CTRL_STATUS, pmt);
+    regs.maccfg().modify(|w| w.set_re(true));
+
+    // Keep ENET clocked while the CPU sleeps and let its interrupt end the low power mode
+    sysctl::resource_set_mode(T::SYSCTL_RESOURCE, ResourceMode::ForceOn);
+    // The interrupt is taken by the CPU running the driver
+    let cpu = riscv::register::mhartid::read();
+    let irq = T::Interrupt::IRQ.number();
+    let was_enabled = sysctl::is_wakeup_enabled(cpu, irq);
+    state.wakeup_cpu.store(cpu as u8, Ordering::Relaxed);
+    state.wakeup_enabled.store(was_enabled, Ordering::Relaxed);
+    sysctl::enable_wakeup(cpu, irq);
+    T::Interrupt::unpend();
+    unsafe { T::Interrupt::enable() };
+
+    Ok(())
+}
+
+/// Leave power-down mode and restart the DMA of instance `T`
+///
+/// Returns the wake up event, `None` if none was received.
+pub fn exit_wol<T: Instance>() -> Option<WakeEvent> {
+    let regs = T::info().regs;
+    let state = &T::state().wol;
+    if !state.armed.swap(false, Ordering::AcqRel) {
+        return None;
+    }
+
+    // Catch a wake up not seen by the interrupt handler yet, then leave power-down mode if
+    // no frame did
+    on_interrupt(regs, state);
+    write_reg(regs, PMT_CTRL_STATUS, 0);
+    regs.intr_mask().modify(|w| w.0 |= PMTIM);
+
+    sysctl::resource_set_mode(T::SYSCTL_RESOURCE, ResourceMode::Auto);
+    if !state.wakeup_enabled.load(Ordering::Relaxed) {
+        let cpu = state.wakeup_cpu.load(Ordering::Relaxed) as usize;
+        sysctl::disable_wakeup(cpu, T::Interrupt::IRQ.number());
+    }
+    if !state.irq_enabled.load(Ordering::Relaxed) {
+        T::Interrupt::disable();
+    }
+
+    regs.maccfg().modify(|w| w.set_te(true));
+    regs.dma_op_mode().modify(|w| {
+        w.set_st(true);
+        w.set_sr(true);
+    });
+
+    state.take_event()
+}
+
+/// Wait for a wake up event of instance `T`, after [`enter_wol`]
+///
+/// The MAC stays in power-down mode, call [`exit_wol`] to resume operation.
+pub async fn wait_wake<T: Instance>() -> WakeEvent {
+    let state = &T::state().wol;
+    poll_fn(|cx| {
+        state.waker.register(cx.waker());
+        match state.event.load(Ordering::Acquire) {
+            1 => Poll::Ready(WakeEvent::MagicPacket),
+            2 => Poll::Ready(WakeEvent::WakeUpFrame),
+            _ => Poll::Pending,
+        }
+    })
+    .await
+}
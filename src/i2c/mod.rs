@@ -1,4 +1,8 @@
 //! I2C
+//!
+//! - [`I2c`]: controller (master) mode
+//! - [`I2cTarget`]: target (slave) mode
+//! - [`smbus::SmBus`]: SMBus/PMBus protocols on top of [`I2c`]
 
 use core::future::Future;
 use core::marker::PhantomData;
@@ -18,6 +22,10 @@ use crate::mode::{Async, Blocking, Mode};
 use crate::time::Hertz;
 use crate::{interrupt, peripherals};
 
+pub mod smbus;
+mod target;
+pub use target::*;
+
 const HPM_I2C_DRV_DEFAULT_TPM: i32 = 0;
 
 // family specific features
@@ -45,6 +53,9 @@ pub unsafe fn on_interrupt<T: Instance>() {
         w.set_cmpl(false);
         w.set_arblose(false);
         w.set_stop(false);
+        w.set_addrhit(false);
+        w.set_fifofull(false);
+        w.set_fifoempty(false);
     });
 
     T::state().waker.wake();
@@ -75,6 +86,36 @@ pub enum I2cMode {
     Fast,
     /// Fast Plus mode. 1Mb/s
     FastPlus,
+    /// Custom bus frequency, up to 1MHz.
+    ///
+    /// Timing limits of the slowest mode that supports the frequency are used.
+    Custom(Hertz),
+}
+
+/// I2C address, used by both controller and target mode.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Address {
+    /// 7-bit address
+    SevenBit(u8),
+    /// 10-bit address
+    TenBit(u16),
+}
+
+impl Address {
+    /// Raw value of ADDR register, and whether 10-bit addressing is used.
+    fn raw(self) -> (u16, bool) {
+        match self {
+            Address::SevenBit(addr) => (addr as u16 & 0x7f, false),
+            Address::TenBit(addr) => (addr & 0x3ff, true),
+        }
+    }
+}
+
+impl From<u8> for Address {
+    fn from(addr: u8) -> Self {
+        Address::SevenBit(addr)
+    }
 }
 
 /// I2C error.
@@ -101,6 +142,10 @@ pub enum Error {
     NoAddrHit,
     /// Invalid argument
     InvalidArgument,
+    /// Timed out with SCL held low, e.g. SMBus clock low timeout
+    ClockLowTimeout,
+    /// Packet Error Code mismatch
+    Pec,
 }
 
 /// I2C config
@@ -108,6 +153,10 @@ pub enum Error {
 #[derive(Copy, Clone)]
 pub struct Config {
     pub mode: I2cMode,
+    /// SCL/SDA rise time of the bus in ns, depends on pull-up resistors and bus capacitance.
+    pub rise_time: u32,
+    /// SCL/SDA fall time of the bus in ns.
+    pub fall_time: u32,
     /// Timeout.
     #[cfg(feature = "time")]
     pub timeout: embassy_time::Duration,
@@ -117,6 +166,8 @@ impl Default for Config {
     fn default() -> Self {
         Self {
             mode: I2cMode::Standard,
+            rise_time: 0,
+            fall_time: 0,
             #[cfg(feature = "time")]
             timeout: embassy_time::Duration::from_millis(1000),
         }
@@ -132,6 +183,7 @@ pub struct I2c<'d, M: Mode> {
     scl: Option<Peri<'d, AnyPin>>,
     sda: Option<Peri<'d, AnyPin>>,
     dma: Option<ChannelAndRequest<'d>>,
+    config: Config,
     #[cfg(feature = "time")]
     timeout: embassy_time::Duration,
     _phantom: PhantomData<M>,
@@ -139,12 +191,14 @@ pub struct I2c<'d, M: Mode> {
 
 impl<'d> I2c<'d, Blocking> {
     /// Create a new blocking I2C driver.
+    ///
+    /// Fails with [`Error::InvalidArgument`] if the bus timing can't be met, or [`Error::Bus`] if SCL is held low.
     pub fn new_blocking<T: Instance>(
         peri: Peri<'d, T>,
         scl: Peri<'d, impl SclPin<T>>,
         sda: Peri<'d, impl SdaPin<T>>,
         config: Config,
-    ) -> Self {
+    ) -> Result<Self, Error> {
         scl.set_as_ioc_gpio();
         sda.set_as_ioc_gpio();
 
@@ -214,6 +268,8 @@ impl<'d> I2c<'d, Blocking> {
 
 impl<'d> I2c<'d, Async> {
     /// Create a new async I2C driver.
+    ///
+    /// Fails with [`Error::InvalidArgument`] if the bus timing can't be met, or [`Error::Bus`] if SCL is held low.
     pub fn new<T: Instance>(
         peri: Peri<'d, T>,
         scl: Peri<'d, impl SclPin<T>>,
@@ -221,7 +277,7 @@ impl<'d> I2c<'d, Async> {
         _irq: impl interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
         dma: Peri<'d, impl I2cDma<T>>,
         config: Config,
-    ) -> Self {
+    ) -> Result<Self, Error> {
         scl.set_as_ioc_gpio();
         sda.set_as_ioc_gpio();
 
@@ -289,42 +345,12 @@ impl<'d> I2c<'d, Async> {
 
     /// Write.
     pub async fn write(&mut self, address: u8, write: &[u8]) -> Result<(), Error> {
-        if self.info.regs.status().read().busbusy() {
-            let timeout = self.timeout();
-            let fut = self.wait_for_stop();
-            timeout.with(fut).await?;
-        }
-
-        self.do_operation_inner(
-            address,
-            &mut Operation::Write(write),
-            FrameOptions {
-                send_start: true,
-                send_stop: true,
-                send_addr: true,
-            },
-        )
-        .await
+        self.transaction_inner(address.into(), &mut [Operation::Write(write)]).await
     }
 
     /// Read.
     pub async fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), Error> {
-        if self.info.regs.status().read().busbusy() {
-            let timeout = self.timeout();
-            let fut = self.wait_for_stop();
-            timeout.with(fut).await?;
-        }
-
-        self.do_operation_inner(
-            address,
-            &mut Operation::Read(buffer),
-            FrameOptions {
-                send_start: true,
-                send_stop: true,
-                send_addr: true,
-            },
-        )
-        .await
+        self.transaction_inner(address.into(), &mut [Operation::Read(buffer)]).await
     }
 
     /// Write, restart, read.
@@ -335,34 +361,8 @@ impl<'d> I2c<'d, Async> {
             return Err(Error::Overrun);
         }
 
-        if self.info.regs.status().read().busbusy() {
-            let timeout = self.timeout();
-            let fut = self.wait_for_stop();
-            timeout.with(fut).await?;
-        }
-
-        self.do_operation_inner(
-            address,
-            &mut Operation::Write(write),
-            FrameOptions {
-                send_start: true,
-                send_stop: false,
-                send_addr: true,
-            },
-        )
-        .await?;
-        self.do_operation_inner(
-            address,
-            &mut Operation::Read(read),
-            FrameOptions {
-                send_start: true,
-                send_stop: true,
-                send_addr: true,
-            },
-        )
-        .await?;
-
-        Ok(())
+        self.transaction_inner(address.into(), &mut [Operation::Write(write), Operation::Read(read)])
+            .await
     }
 
     /// Transaction with operations.
@@ -371,17 +371,34 @@ impl<'d> I2c<'d, Async> {
     ///
     /// [transaction contract]: embedded_hal::i2c::I2c::transaction
     pub async fn transaction(&mut self, addr: u8, operations: &mut [Operation<'_>]) -> Result<(), Error> {
-        if self.info.regs.status().read().busbusy() {
-            let timeout = self.timeout();
-            let fut = self.wait_for_stop();
-            timeout.with(fut).await?;
-        }
+        self.transaction_inner(addr.into(), operations).await
+    }
 
-        for (op, frame) in operation_frames(operations)? {
-            self.do_operation_inner(addr, op, frame).await?;
-        }
+    /// Transaction with operations, using 10-bit address.
+    pub async fn transaction_10bit(&mut self, addr: u16, operations: &mut [Operation<'_>]) -> Result<(), Error> {
+        self.transaction_inner(Address::TenBit(addr), operations).await
+    }
 
-        Ok(())
+    // The whole transaction is bounded by the configured timeout, the bus is recovered if it gets stuck.
+    async fn transaction_inner(&mut self, addr: Address, operations: &mut [Operation<'_>]) -> Result<(), Error> {
+        let timeout = self.timeout();
+        let res = timeout
+            .with(async {
+                if self.info.regs.status().read().busbusy() {
+                    let timeout = self.timeout();
+                    let fut = self.wait_for_stop();
+                    timeout.with(fut).await.map_err(busy_on_timeout)?;
+                }
+
+                for (op, frame) in operation_frames(operations)? {
+                    self.do_operation_inner(addr, op, frame).await?;
+                }
+
+                Ok(())
+            })
+            .await;
+
+        self.recover_on_stuck(res)
     }
 
     // Wait for STOP condition(BUSBUSY=0)
@@ -407,7 +424,35 @@ impl<'d> I2c<'d, Async> {
         Ok(())
     }
 
-    async fn do_operation_inner(&mut self, addr: u8, op: &mut Operation<'_>, frame: FrameOptions) -> Result<(), Error> {
+    // Operations longer than I2C_SOC_TRANSFER_COUNT_MAX are split into chunks of the same frame.
+    async fn do_operation_inner(
+        &mut self,
+        addr: Address,
+        op: &mut Operation<'_>,
+        frame: FrameOptions,
+    ) -> Result<(), Error> {
+        let len = match op {
+            Operation::Read(read) => read.len(),
+            Operation::Write(write) => write.len(),
+        };
+
+        for (range, frame) in chunk_frames(len, frame) {
+            let mut chunk = match op {
+                Operation::Read(read) => Operation::Read(&mut read[range]),
+                Operation::Write(write) => Operation::Write(&write[range]),
+            };
+            self.do_chunk_inner(addr, &mut chunk, frame).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn do_chunk_inner(
+        &mut self,
+        addr: Address,
+        op: &mut Operation<'_>,
+        frame: FrameOptions,
+    ) -> Result<(), Error> {
         let r = self.info.regs;
 
         let (size, dir) = match op {
@@ -421,7 +466,7 @@ impl<'d> I2c<'d, Async> {
 
         // W1C, clear CMPL bit to avoid blocking the transmission
         r.status().write(|w| w.set_cmpl(true));
-        r.addr().write(|w| w.set_addr(addr as u16));
+        set_address(r, addr);
         r.ctrl().write(|w| {
             w.set_phase_start(frame.send_start);
             w.set_phase_stop(frame.send_stop);
@@ -441,9 +486,13 @@ impl<'d> I2c<'d, Async> {
         });
 
         let ch = self.dma.as_mut().unwrap();
+        // zero-length transfer is an address probe, no data for DMA
         let transfer = match op {
-            Operation::Read(read) => unsafe { ch.read(r.data().as_ptr() as *mut u8, read, Default::default()) },
-            Operation::Write(write) => unsafe { ch.write(write, r.data().as_ptr() as *mut u8, Default::default()) },
+            _ if size == 0 => None,
+            Operation::Read(read) => Some(unsafe { ch.read(r.data().as_ptr() as *mut u8, read, Default::default()) }),
+            Operation::Write(write) => {
+                Some(unsafe { ch.write(write, r.data().as_ptr() as *mut u8, Default::default()) })
+            }
         };
 
         let on_drop = OnDrop::new(|| {
@@ -455,10 +504,12 @@ impl<'d> I2c<'d, Async> {
             regs.setup().modify(|w| w.set_dmaen(false));
         });
 
-        r.setup().modify(|w| w.set_dmaen(true));
+        r.setup().modify(|w| w.set_dmaen(size > 0));
         r.cmd().write(|w| w.set_cmd(vals::Cmd::DATA_TRANSACTION));
 
-        transfer.await;
+        if let Some(transfer) = transfer {
+            transfer.await;
+        }
 
         let s = self.state;
 
@@ -490,7 +541,7 @@ impl<'d, M: Mode> I2c<'d, M> {
         sda: Option<Peri<'d, AnyPin>>,
         dma: Option<ChannelAndRequest<'d>>,
         config: Config,
-    ) -> Self {
+    ) -> Result<Self, Error> {
         unsafe { T::Interrupt::enable() };
 
         let mut this = Self {
@@ -500,12 +551,13 @@ impl<'d, M: Mode> I2c<'d, M> {
             scl,
             sda,
             dma,
+            config,
             #[cfg(feature = "time")]
             timeout: config.timeout,
             _phantom: PhantomData,
         };
-        this.init(config);
-        this
+        this.init(config)?;
+        Ok(this)
     }
 
     fn timeout(&self) -> Timeout {
@@ -516,7 +568,7 @@ impl<'d, M: Mode> I2c<'d, M> {
     }
 
     #[inline]
-    fn reset(&mut self) {
+    fn reset_controller(&mut self) {
         let r = self.info.regs;
 
         r.ctrl().write(|w| w.0 = 0);
@@ -524,13 +576,130 @@ impl<'d, M: Mode> I2c<'d, M> {
         r.setup().modify(|w| w.set_iicen(false));
     }
 
+    /// Recover the bus and reinitialize the controller.
+    ///
+    /// This is done automatically when a transfer fails with [`Error::BusyBusy`], [`Error::Timeout`] or
+    /// [`Error::ClockLowTimeout`].
+    pub fn reset(&mut self) -> Result<(), Error> {
+        let recovered = self.recover_bus();
+        self.init(self.config)?;
+        recovered
+    }
+
+    /// Apply a new config, timing is recalculated.
+    pub fn set_config(&mut self, config: Config) -> Result<(), Error> {
+        self.init(config)?;
+        self.config = config;
+        #[cfg(feature = "time")]
+        {
+            self.timeout = config.timeout;
+        }
+        Ok(())
+    }
+
+    /// Free a stuck bus by clocking SCL manually until the target releases SDA, then issue a STOP.
+    ///
+    /// SCL and SDA are temporarily switched to open-drain GPIOs.
+    pub fn recover_bus(&mut self) -> Result<(), Error> {
+        use embedded_hal::delay::DelayNs;
+        use riscv::delay::McycleDelay;
+
+        use crate::gpio::{Flex, Pin, Pull, SealedPin, Speed};
+
+        // half period of 100kHz
+        const HALF_PERIOD_US: u32 = 5;
+        // a target can hold at most 8 data bits and 1 ACK
+        const MAX_CLOCKS: usize = 9;
+
+        let (Some(scl), Some(sda)) = (self.scl.as_mut(), self.sda.as_mut()) else {
+            return Err(Error::InvalidArgument);
+        };
+
+        let scl_func = scl.ioc_pad().func_ctl().read();
+        let scl_pad = scl.ioc_pad().pad_ctl().read();
+        let sda_func = sda.ioc_pad().func_ctl().read();
+        let sda_pad = sda.ioc_pad().pad_ctl().read();
+
+        let mut delay = McycleDelay::new(crate::sysctl::clocks().cpu0.0);
+
+        let res = {
+            scl.set_as_ioc_gpio();
+            sda.set_as_ioc_gpio();
+
+            let mut scl = Flex::new(scl.reborrow());
+            let mut sda = Flex::new(sda.reborrow());
+            for pin in [&mut scl, &mut sda] {
+                pin.set_high();
+                pin.set_open_drain(true);
+                pin.set_pull(Pull::Up);
+                pin.set_as_output(Speed::default());
+            }
+            delay.delay_us(HALF_PERIOD_US);
+
+            for _ in 0..MAX_CLOCKS {
+                if sda.is_high() {
+                    break;
+                }
+                scl.set_low();
+                delay.delay_us(HALF_PERIOD_US);
+                scl.set_high();
+                delay.delay_us(HALF_PERIOD_US);
+            }
+
+            // STOP: SDA rising while SCL is high
+            scl.set_low();
+            delay.delay_us(HALF_PERIOD_US);
+            sda.set_low();
+            delay.delay_us(HALF_PERIOD_US);
+            scl.set_high();
+            delay.delay_us(HALF_PERIOD_US);
+            sda.set_high();
+            delay.delay_us(HALF_PERIOD_US);
+
+            if scl.is_low() {
+                // SCL is held low by a target, can not be recovered by clocking
+                Err(Error::Bus)
+            } else if sda.is_low() {
+                Err(Error::BusyBusy)
+            } else {
+                Ok(())
+            }
+        };
+
+        // back to I2C function
+        scl.ioc_pad().func_ctl().write_value(scl_func);
+        scl.ioc_pad().pad_ctl().write_value(scl_pad);
+        sda.ioc_pad().func_ctl().write_value(sda_func);
+        sda.ioc_pad().pad_ctl().write_value(sda_pad);
+
+        res
+    }
+
+    // Recover the bus when the controller or a target is stuck.
+    fn recover_on_stuck<R>(&mut self, res: Result<R, Error>) -> Result<R, Error> {
+        if let Err(err @ (Error::BusyBusy | Error::Timeout)) = res {
+            // sample before recovery, SCL still low means someone is stretching the clock
+            let clock_low = !self.info.regs.status().read().linescl();
+
+            #[cfg(feature = "defmt")]
+            defmt::warn!("I2C bus stuck, recovering");
+            let _ = self.reset();
+
+            if err == Error::Timeout && clock_low {
+                return Err(Error::ClockLowTimeout);
+            }
+        }
+        res
+    }
+
     // init master
-    pub(crate) fn init(&mut self, config: Config) {
+    pub(crate) fn init(&mut self, config: Config) -> Result<(), Error> {
         let r = self.info.regs;
 
-        self.reset();
+        let timing = configure_timing(self.kernel_clock.0, config.mode, config.rise_time, config.fall_time)
+            .ok_or(Error::InvalidArgument)?;
 
-        let timing = configure_timing(self.kernel_clock.0, config.mode).unwrap();
+        self.reset_controller();
 
         r.tpm().write(|w| w.set_tpm(HPM_I2C_DRV_DEFAULT_TPM as _));
 
@@ -547,8 +716,8 @@ impl<'d, M: Mode> I2c<'d, M> {
 
         if r.status().read().linescl() == false {
             #[cfg(feature = "defmt")]
-            defmt::info!("CLK is low, panic");
-            loop {}
+            defmt::info!("CLK is low");
+            return Err(Error::Bus);
         }
 
         #[cfg(ip_feature_i2c_support_reset)]
@@ -568,23 +737,34 @@ impl<'d, M: Mode> I2c<'d, M> {
 
             // bus cleared
         }
+
+        Ok(())
     }
 
     /// Blocking write, restart, read.
     pub fn blocking_write_read(&mut self, addr: u8, reg: &[u8], read: &mut [u8]) -> Result<(), Error> {
-        if reg.is_empty()
-            || reg.len() > I2C_SOC_TRANSFER_COUNT_MAX
-            || read.is_empty()
-            || read.len() > I2C_SOC_TRANSFER_COUNT_MAX
-        {
+        let res = self.blocking_write_read_inner(addr, reg, read);
+        self.recover_on_stuck(res)
+    }
+
+    fn blocking_write_read_inner(&mut self, addr: u8, reg: &[u8], read: &mut [u8]) -> Result<(), Error> {
+        if reg.is_empty() || read.is_empty() {
             return Err(Error::InvalidArgument);
         }
 
-        let r = self.info.regs;
         let timeout = self.timeout();
 
+        // Longer than one transfer: split into chunks of the write frame, restart, then the read frame
+        if reg.len() > I2C_SOC_TRANSFER_COUNT_MAX || read.len() > I2C_SOC_TRANSFER_COUNT_MAX {
+            let addr = Address::SevenBit(addr);
+            self.blocking_write_timeout(addr, reg, timeout, false)?;
+            return self.blocking_read_timeout(addr, read, timeout);
+        }
+
+        let r = self.info.regs;
+
         while r.status().read().busbusy() {
-            self.timeout().check()?;
+            timeout.check().map_err(busy_on_timeout)?;
         }
 
         // W1C, clear CMPL bit to avoid blocking the transmission
@@ -601,7 +781,7 @@ impl<'d, M: Mode> I2c<'d, M> {
             w.set_datacnt(reg.len() as _);
         });
 
-        r.addr().modify(|w| w.set_addr(addr as u16));
+        set_address(r, Address::SevenBit(addr));
 
         for b in reg {
             r.data().write(|w| w.set_data(*b));
@@ -660,9 +840,33 @@ impl<'d, M: Mode> I2c<'d, M> {
         Ok(())
     }
 
+    // Operations longer than I2C_SOC_TRANSFER_COUNT_MAX are split into chunks of the same frame.
     fn blocking_do_operation_timeout(
         &mut self,
-        addr: u8,
+        addr: Address,
+        op: &mut Operation<'_>,
+        timeout: Timeout,
+        frame: FrameOptions,
+    ) -> Result<(), Error> {
+        let len = match op {
+            Operation::Read(read) => read.len(),
+            Operation::Write(write) => write.len(),
+        };
+
+        for (range, frame) in chunk_frames(len, frame) {
+            let mut chunk = match op {
+                Operation::Read(read) => Operation::Read(&mut read[range]),
+                Operation::Write(write) => Operation::Write(&write[range]),
+            };
+            self.blocking_do_chunk_timeout(addr, &mut chunk, timeout, frame)?;
+        }
+
+        Ok(())
+    }
+
+    fn blocking_do_chunk_timeout(
+        &mut self,
+        addr: Address,
         op: &mut Operation<'_>,
         timeout: Timeout,
         frame: FrameOptions,
@@ -677,15 +881,18 @@ impl<'d, M: Mode> I2c<'d, M> {
             return Err(Error::InvalidArgument);
         }
 
-        while r.status().read().busbusy() {
-            timeout.check()?;
+        // the bus stays busy between chunks of the same frame
+        if frame.send_start {
+            while r.status().read().busbusy() {
+                timeout.check().map_err(busy_on_timeout)?;
+            }
         }
 
         // W1C, clear CMPL bit to avoid blocking the transmission
         r.status().write(|w| w.set_cmpl(true));
 
         r.cmd().write(|w| w.set_cmd(vals::Cmd::CLEAR_FIFO));
-        r.addr().write(|w| w.set_addr(addr as u16));
+        set_address(r, addr);
         r.ctrl().write(|w| {
             w.set_phase_start(frame.send_start);
             w.set_phase_stop(frame.send_stop);
@@ -702,11 +909,13 @@ impl<'d, M: Mode> I2c<'d, M> {
         r.cmd().write(|w| w.set_cmd(vals::Cmd::DATA_TRANSACTION));
 
         // Before starting to transmit data, judge addrhit to ensure that the slave address exists on the bus.
-        while !r.status().read().addrhit() {
-            timeout.check()?;
-        }
+        if frame.send_addr {
+            while !r.status().read().addrhit() {
+                timeout.check()?;
+            }
 
-        r.status().write(|w| w.set_addrhit(true));
+            r.status().write(|w| w.set_addrhit(true));
+        }
 
         // when size is zero, it's probe slave device, so directly return success
         if size == 0 {
@@ -753,7 +962,7 @@ impl<'d, M: Mode> I2c<'d, M> {
     }
 
     // i2c_master_write
-    fn blocking_read_timeout(&mut self, addr: u8, read: &mut [u8], timeout: Timeout) -> Result<(), Error> {
+    fn blocking_read_timeout(&mut self, addr: Address, read: &mut [u8], timeout: Timeout) -> Result<(), Error> {
         self.blocking_do_operation_timeout(
             addr,
             &mut Operation::Read(read),
@@ -769,7 +978,7 @@ impl<'d, M: Mode> I2c<'d, M> {
     // i2c_master_write
     fn blocking_write_timeout(
         &mut self,
-        addr: u8,
+        addr: Address,
         write: &[u8],
         timeout: Timeout,
         send_stop: bool,
@@ -790,25 +999,38 @@ impl<'d, M: Mode> I2c<'d, M> {
     pub fn blocking_read(&mut self, addr: u8, read: &mut [u8]) -> Result<(), Error> {
         let timeout = self.timeout();
 
-        self.blocking_read_timeout(addr, read, timeout)
+        let res = self.blocking_read_timeout(addr.into(), read, timeout);
+        self.recover_on_stuck(res)
     }
 
     /// Blocking write.
     pub fn blocking_write(&mut self, addr: u8, write: &[u8]) -> Result<(), Error> {
         let timeout = self.timeout();
 
-        self.blocking_write_timeout(addr, write, timeout, true)
+        let res = self.blocking_write_timeout(addr.into(), write, timeout, true);
+        self.recover_on_stuck(res)
     }
 
     /// Blocking transaction with operations.
     pub fn blocking_transaction(&mut self, addr: u8, operations: &mut [Operation<'_>]) -> Result<(), Error> {
-        let timeout = self.timeout();
+        self.blocking_transaction_inner(addr.into(), operations)
+    }
 
-        for (op, frame) in operation_frames(operations)? {
-            self.blocking_do_operation_timeout(addr, op, timeout, frame)?;
-        }
+    /// Blocking transaction with operations, using 10-bit address.
+    pub fn blocking_transaction_10bit(&mut self, addr: u16, operations: &mut [Operation<'_>]) -> Result<(), Error> {
+        self.blocking_transaction_inner(Address::TenBit(addr), operations)
+    }
 
-        Ok(())
+    fn blocking_transaction_inner(&mut self, addr: Address, operations: &mut [Operation<'_>]) -> Result<(), Error> {
+        let timeout = self.timeout();
+
+        let res = operation_frames(operations).and_then(|frames| {
+            for (op, frame) in frames {
+                self.blocking_do_operation_timeout(addr, op, timeout, frame)?;
+            }
+            Ok(())
+        });
+        self.recover_on_stuck(res)
     }
 }
 
@@ -914,6 +1136,8 @@ impl embedded_hal::i2c::Error for Error {
             Self::ZeroLengthTransfer => embedded_hal::i2c::ErrorKind::Other,
             Self::TrasnmitNotCompleted => embedded_hal::i2c::ErrorKind::Other,
             Self::InvalidArgument => embedded_hal::i2c::ErrorKind::Other,
+            Self::ClockLowTimeout => embedded_hal::i2c::ErrorKind::Bus,
+            Self::Pec => embedded_hal::i2c::ErrorKind::Other,
         }
     }
 }
@@ -944,6 +1168,16 @@ impl<'d, M: Mode> embedded_hal::i2c::I2c for I2c<'d, M> {
     }
 }
 
+impl<'d, M: Mode> embedded_hal::i2c::I2c<embedded_hal::i2c::TenBitAddress> for I2c<'d, M> {
+    fn transaction(
+        &mut self,
+        address: u16,
+        operations: &mut [embedded_hal::i2c::Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        self.blocking_transaction_10bit(address, operations)
+    }
+}
+
 impl<'d> embedded_hal_async::i2c::I2c for I2c<'d, Async> {
     async fn read(&mut self, address: u8, read: &mut [u8]) -> Result<(), Self::Error> {
         self.read(address, read).await
@@ -966,6 +1200,16 @@ impl<'d> embedded_hal_async::i2c::I2c for I2c<'d, Async> {
     }
 }
 
+impl<'d> embedded_hal_async::i2c::I2c<embedded_hal::i2c::TenBitAddress> for I2c<'d, Async> {
+    async fn transaction(
+        &mut self,
+        address: u16,
+        operations: &mut [embedded_hal::i2c::Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        self.transaction_10bit(address, operations).await
+    }
+}
+
 // ==========
 // frame options
 
@@ -1033,9 +1277,46 @@ fn operation_frames<'a, 'b: 'a>(
     }))
 }
 
+// Waiting for the bus to become idle timed out, the bus is held by someone else.
+fn busy_on_timeout(err: Error) -> Error {
+    match err {
+        Error::Timeout => Error::BusyBusy,
+        err => err,
+    }
+}
+
+// Split an operation into chunks of at most I2C_SOC_TRANSFER_COUNT_MAX bytes.
+// Only the first chunk generates START and address, only the last one generates STOP.
+fn chunk_frames(len: usize, frame: FrameOptions) -> impl Iterator<Item = (core::ops::Range<usize>, FrameOptions)> {
+    let chunks = len.div_ceil(I2C_SOC_TRANSFER_COUNT_MAX).max(1);
+
+    (0..chunks).map(move |i| {
+        let start = i * I2C_SOC_TRANSFER_COUNT_MAX;
+        let end = usize::min(start + I2C_SOC_TRANSFER_COUNT_MAX, len);
+        let first = i == 0;
+        let last = i == chunks - 1;
+
+        (
+            start..end,
+            FrameOptions {
+                send_start: frame.send_start && first,
+                send_stop: frame.send_stop && last,
+                send_addr: frame.send_addr && first,
+            },
+        )
+    })
+}
+
 // ==========
 // helper functions
 
+#[inline]
+fn set_address(r: crate::pac::i2c::I2c, addr: Address) {
+    let (addr, ten_bit) = addr.raw();
+    r.setup().modify(|w| w.set_addressing(ten_bit));
+    r.addr().write(|w| w.set_addr(addr));
+}
+
 #[inline]
 fn get_data_count(r: crate::pac::i2c::I2c) -> u16 {
     let ctrl = r.ctrl().read();
@@ -1050,16 +1331,32 @@ fn period_in_100ps(freq: u32) -> i32 {
     (10000000000_u64 / (freq as u64)) as i32
 }
 
-fn configure_timing(src_clk_in_hz: u32, i2c_mode: I2cMode) -> Option<Timings> {
+fn configure_timing(
+    src_clk_in_hz: u32,
+    i2c_mode: I2cMode,
+    rise_time_ns: u32,
+    fall_time_ns: u32,
+) -> Option<Timings> {
     let mut timing: Timings = unsafe { core::mem::zeroed() };
-    let setup_time: i32;
+    let mut setup_time: i32;
     let hold_time: i32;
-    let period: i32;
+    let mut period: i32;
     let mut temp1: i32;
     let temp2: i32;
     let temp3: i32;
     let tpclk = period_in_100ps(src_clk_in_hz);
 
+    let (i2c_mode, freq) = match i2c_mode {
+        I2cMode::Custom(Hertz(0)) => return None,
+        I2cMode::Custom(Hertz(f)) if f <= 100_000 => (I2cMode::Standard, f),
+        I2cMode::Custom(Hertz(f)) if f <= 400_000 => (I2cMode::Fast, f),
+        I2cMode::Custom(Hertz(f)) if f <= 1_000_000 => (I2cMode::FastPlus, f),
+        I2cMode::Custom(_) => return None,
+        I2cMode::Standard => (I2cMode::Standard, 100_000),
+        I2cMode::Fast => (I2cMode::Fast, 400_000),
+        I2cMode::FastPlus => (I2cMode::FastPlus, 1_000_000),
+    };
+
     match i2c_mode {
         /*
          *          |Standard mode | Fast mode | Fast mode plus | Uint
@@ -1075,7 +1372,6 @@ fn configure_timing(src_clk_in_hz: u32, i2c_mode: I2cMode) -> Option<Timings> {
             timing.t_sclratio = 2;
             setup_time = 1000;
             hold_time = 3000;
-            period = period_in_100ps(400000); /* baudrate 400KHz */
         }
         I2cMode::FastPlus => {
             timing.t_high = 2600;
@@ -1083,7 +1379,6 @@ fn configure_timing(src_clk_in_hz: u32, i2c_mode: I2cMode) -> Option<Timings> {
             timing.t_sclratio = 2;
             setup_time = 500;
             hold_time = 0;
-            period = period_in_100ps(1000000); /* baudrate 1MHz */
         }
         I2cMode::Standard => {
             timing.t_high = 40000;
@@ -1091,9 +1386,20 @@ fn configure_timing(src_clk_in_hz: u32, i2c_mode: I2cMode) -> Option<Timings> {
             timing.t_sclratio = 1;
             setup_time = 2500;
             hold_time = 3000;
-            period = period_in_100ps(100000); /* baudrate 100KHz */
         }
+        I2cMode::Custom(_) => unreachable!(),
     }
+    period = period_in_100ps(freq);
+
+    /*
+     * SCL high is counted after SCL is seen high, so rise time adds to the period,
+     * fall time is part of SCL low, and SDA rise time reduces the data setup time.
+     */
+    let rise_time = i32::try_from(rise_time_ns).ok()?.checked_mul(10)?;
+    let fall_time = i32::try_from(fall_time_ns).ok()?.checked_mul(10)?;
+    period = period.checked_sub(rise_time).filter(|p| *p > 0)?;
+    timing.t_low = timing.t_low.checked_add(fall_time as u32)?;
+    setup_time = setup_time.checked_add(rise_time)?;
 
     /*
      * Spike Suppression | Standard | Fast mode | Fast mode plus | Uint
@@ -0,0 +1,489 @@
+//! SMBus/PMBus protocols on top of the I2C controller.
+//!
+//! Supports quick command, send/receive byte, read/write byte and word, block read/write,
+//! process call and block process call. PMBus commands are built on the same protocols.
+//!
+//! Packet Error Code (PEC) is a CRC-8 (poly 0x07) over every byte of the message, address bytes
+//! included. It's computed in software, or by a channel of the CRC peripheral, see [`SmBus::new_with_crc`].
+//!
+//! Use [`Config::smbus`] to limit transfers to the SMBus clock low timeout (tTIMEOUT, 35ms).
+//! A transfer that times out while SCL is held low fails with [`Error::ClockLowTimeout`],
+//! the bus is recovered before returning.
+
+use embedded_hal::i2c::Operation;
+
+use super::{Address, Config, Error, FrameOptions, I2c, I2cMode};
+#[cfg(crc)]
+use crate::crc::CrcChannel;
+use crate::mode::{Async, Mode};
+
+/// Max data length of block transfers, raised from 32 to 255 bytes since SMBus 3.0.
+pub const MAX_BLOCK_LEN: usize = 255;
+
+/// SMBus clock low timeout, tTIMEOUT.
+#[cfg(feature = "time")]
+pub const CLOCK_LOW_TIMEOUT: embassy_time::Duration = embassy_time::Duration::from_millis(35);
+
+// command + byte count + data + PEC
+const MAX_MESSAGE_LEN: usize = MAX_BLOCK_LEN + 3;
+
+// START + address, the frame continues
+const FIRST_FRAME: FrameOptions = FrameOptions {
+    send_start: true,
+    send_stop: false,
+    send_addr: true,
+};
+
+// continue the previous frame, then STOP
+const LAST_FRAME: FrameOptions = FrameOptions {
+    send_start: false,
+    send_stop: true,
+    send_addr: false,
+};
+
+impl Config {
+    /// SMBus config, 100kHz, transfers time out after the SMBus clock low timeout.
+    pub fn smbus() -> Self {
+        Self {
+            mode: I2cMode::Standard,
+            #[cfg(feature = "time")]
+            timeout: CLOCK_LOW_TIMEOUT,
+            ..Default::default()
+        }
+    }
+}
+
+// CRC-8, poly x^8 + x^2 + x + 1, init 0
+fn crc8(mut crc: u8, data: &[u8]) -> u8 {
+    for &b in data {
+        crc ^= b;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 { (crc << 1) ^ 0x07 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+struct Message {
+    buf: [u8; MAX_MESSAGE_LEN],
+    len: usize,
+}
+
+impl Message {
+    fn new(parts: &[&[u8]]) -> Result<Self, Error> {
+        let mut msg = Self {
+            buf: [0; MAX_MESSAGE_LEN],
+            len: 0,
+        };
+        for part in parts {
+            msg.push(part)?;
+        }
+        Ok(msg)
+    }
+
+    fn push(&mut self, data: &[u8]) -> Result<(), Error> {
+        let end = self.len + data.len();
+        if end > MAX_MESSAGE_LEN {
+            return Err(Error::InvalidArgument);
+        }
+        self.buf[self.len..end].copy_from_slice(data);
+        self.len = end;
+        Ok(())
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+}
+
+fn block_len(data: &[u8]) -> Result<u8, Error> {
+    if data.len() > MAX_BLOCK_LEN {
+        return Err(Error::InvalidArgument);
+    }
+    Ok(data.len() as u8)
+}
+
+/// SMBus/PMBus host, wraps an [`I2c`] driver.
+///
+/// Addresses are 7-bit. PEC is disabled by default, enable it with [`SmBus::set_pec`].
+pub struct SmBus<'d, M: Mode> {
+    i2c: I2c<'d, M>,
+    #[cfg(crc)]
+    crc: Option<CrcChannel<'d>>,
+    pec: bool,
+}
+
+impl<'d, M: Mode> SmBus<'d, M> {
+    /// Create a new SMBus host, PEC is computed in software.
+    pub fn new(i2c: I2c<'d, M>) -> Self {
+        Self {
+            i2c,
+            #[cfg(crc)]
+            crc: None,
+            pec: false,
+        }
+    }
+
+    /// Create a new SMBus host, PEC is computed by the given CRC channel.
+    ///
+    /// The channel is reconfigured to CRC-8.
+    #[cfg(crc)]
+    pub fn new_with_crc(i2c: I2c<'d, M>, mut crc: CrcChannel<'d>) -> Self {
+        crc.configure(crate::crc::Config::crc8());
+
+        Self {
+            i2c,
+            crc: Some(crc),
+            pec: false,
+        }
+    }
+
+    /// Enable or disable Packet Error Code for the following transfers.
+    pub fn set_pec(&mut self, enabled: bool) {
+        self.pec = enabled;
+    }
+
+    /// Whether Packet Error Code is enabled.
+    pub fn pec(&self) -> bool {
+        self.pec
+    }
+
+    /// Access the underlying I2C driver.
+    pub fn inner(&mut self) -> &mut I2c<'d, M> {
+        &mut self.i2c
+    }
+
+    /// Release the underlying I2C driver.
+    pub fn into_inner(self) -> I2c<'d, M> {
+        self.i2c
+    }
+
+    // PEC of a write of `write` (skipped if empty) followed by a read of `read`
+    fn compute_pec(&mut self, addr: u8, write: &[u8], read: Option<&[u8]>) -> u8 {
+        let addr_w = [addr << 1];
+        let addr_r = [(addr << 1) | 1];
+
+        let mut parts: [&[u8]; 4] = [&[]; 4];
+        if !write.is_empty() {
+            parts[0] = &addr_w;
+            parts[1] = write;
+        }
+        if let Some(read) = read {
+            parts[2] = &addr_r;
+            parts[3] = read;
+        }
+
+        #[cfg(crc)]
+        if let Some(ch) = self.crc.as_mut() {
+            ch.reset();
+            for part in parts {
+                ch.feed_bytes(part);
+            }
+            return ch.read() as u8;
+        }
+
+        parts.iter().fold(0, |crc, part| crc8(crc, part))
+    }
+
+    fn append_pec(&mut self, addr: u8, msg: &mut Message) -> Result<(), Error> {
+        if self.pec {
+            let pec = self.compute_pec(addr, msg.as_slice(), None);
+            msg.push(&[pec])?;
+        }
+        Ok(())
+    }
+
+    // `read` ends with the PEC byte when PEC is enabled
+    fn check_pec(&mut self, addr: u8, write: &[u8], read: &[u8]) -> Result<(), Error> {
+        if self.pec {
+            let (data, pec) = read.split_at(read.len() - 1);
+            if self.compute_pec(addr, write, Some(data)) != pec[0] {
+                return Err(Error::Pec);
+            }
+        }
+        Ok(())
+    }
+
+    // `rx` is byte count + data (+ PEC)
+    fn finish_block_read(&mut self, addr: u8, write: &[u8], rx: &[u8], buf: &mut [u8]) -> Result<usize, Error> {
+        self.check_pec(addr, write, rx)?;
+
+        let count = rx[0] as usize;
+        if count > buf.len() {
+            return Err(Error::Overrun);
+        }
+        buf[..count].copy_from_slice(&rx[1..1 + count]);
+
+        Ok(count)
+    }
+
+    fn blocking_write_message(&mut self, addr: u8, mut msg: Message) -> Result<(), Error> {
+        self.append_pec(addr, &mut msg)?;
+        self.i2c.blocking_write(addr, msg.as_slice())
+    }
+
+    fn blocking_read_message(&mut self, addr: u8, write: &[u8], read: &mut [u8]) -> Result<(), Error> {
+        let mut buf = [0; MAX_MESSAGE_LEN];
+        let rx = &mut buf[..read.len() + self.pec as usize];
+
+        if write.is_empty() {
+            self.i2c.blocking_read(addr, rx)?;
+        } else {
+            self.i2c.blocking_write_read(addr, write, rx)?;
+        }
+
+        self.check_pec(addr, write, rx)?;
+        read.copy_from_slice(&rx[..read.len()]);
+
+        Ok(())
+    }
+
+    fn blocking_read_block_message(&mut self, addr: u8, write: &[u8], buf: &mut [u8]) -> Result<usize, Error> {
+        // byte count + data + PEC
+        let mut rx = [0; MAX_BLOCK_LEN + 2];
+
+        let res = self.blocking_read_block_frames(addr, write, &mut rx);
+        let len = self.i2c.recover_on_stuck(res)?;
+
+        self.finish_block_read(addr, write, &rx[..len], buf)
+    }
+
+    // The byte count is read first to know the length of the rest of the frame.
+    fn blocking_read_block_frames(&mut self, addr: u8, write: &[u8], rx: &mut [u8]) -> Result<usize, Error> {
+        let timeout = self.i2c.timeout();
+        let addr = Address::SevenBit(addr);
+
+        self.i2c
+            .blocking_do_operation_timeout(addr, &mut Operation::Write(write), timeout, FIRST_FRAME)?;
+        self.i2c
+            .blocking_do_operation_timeout(addr, &mut Operation::Read(&mut rx[..1]), timeout, FIRST_FRAME)?;
+
+        let len = 1 + rx[0] as usize + self.pec as usize;
+        self.i2c
+            .blocking_do_operation_timeout(addr, &mut Operation::Read(&mut rx[1..len]), timeout, LAST_FRAME)?;
+
+        Ok(len)
+    }
+
+    /// Blocking quick command, the R/W bit is the only data sent.
+    pub fn blocking_quick_command(&mut self, addr: u8, read: bool) -> Result<(), Error> {
+        if read {
+            self.i2c.blocking_read(addr, &mut [])
+        } else {
+            self.i2c.blocking_write(addr, &[])
+        }
+    }
+
+    /// Blocking send byte, without command code.
+    pub fn blocking_send_byte(&mut self, addr: u8, data: u8) -> Result<(), Error> {
+        self.blocking_write_message(addr, Message::new(&[&[data]])?)
+    }
+
+    /// Blocking receive byte, without command code.
+    pub fn blocking_receive_byte(&mut self, addr: u8) -> Result<u8, Error> {
+        let mut data = [0; 1];
+        self.blocking_read_message(addr, &[], &mut data)?;
+        Ok(data[0])
+    }
+
+    /// Blocking write byte.
+    pub fn blocking_write_byte(&mut self, addr: u8, command: u8, data: u8) -> Result<(), Error> {
+        self.blocking_write_message(addr, Message::new(&[&[command, data]])?)
+    }
+
+    /// Blocking read byte.
+    pub fn blocking_read_byte(&mut self, addr: u8, command: u8) -> Result<u8, Error> {
+        let mut data = [0; 1];
+        self.blocking_read_message(addr, &[command], &mut data)?;
+        Ok(data[0])
+    }
+
+    /// Blocking write word, sent little-endian.
+    pub fn blocking_write_word(&mut self, addr: u8, command: u8, data: u16) -> Result<(), Error> {
+        self.blocking_write_message(addr, Message::new(&[&[command], &data.to_le_bytes()])?)
+    }
+
+    /// Blocking read word, received little-endian.
+    pub fn blocking_read_word(&mut self, addr: u8, command: u8) -> Result<u16, Error> {
+        let mut data = [0; 2];
+        self.blocking_read_message(addr, &[command], &mut data)?;
+        Ok(u16::from_le_bytes(data))
+    }
+
+    /// Blocking process call, write a word and read a word back.
+    pub fn blocking_process_call(&mut self, addr: u8, command: u8, data: u16) -> Result<u16, Error> {
+        let [lo, hi] = data.to_le_bytes();
+        let mut read = [0; 2];
+        self.blocking_read_message(addr, &[command, lo, hi], &mut read)?;
+        Ok(u16::from_le_bytes(read))
+    }
+
+    /// Blocking block write, up to [`MAX_BLOCK_LEN`] bytes.
+    pub fn blocking_block_write(&mut self, addr: u8, command: u8, data: &[u8]) -> Result<(), Error> {
+        let count = block_len(data)?;
+        self.blocking_write_message(addr, Message::new(&[&[command, count], data])?)
+    }
+
+    /// Blocking block read, returns the byte count sent by the target.
+    ///
+    /// Fails with [`Error::Overrun`] if `buf` is shorter than the byte count.
+    pub fn blocking_block_read(&mut self, addr: u8, command: u8, buf: &mut [u8]) -> Result<usize, Error> {
+        self.blocking_read_block_message(addr, &[command], buf)
+    }
+
+    /// Blocking block write-block read process call, returns the byte count of the response.
+    pub fn blocking_block_process_call(
+        &mut self,
+        addr: u8,
+        command: u8,
+        write: &[u8],
+        read: &mut [u8],
+    ) -> Result<usize, Error> {
+        let count = block_len(write)?;
+        let msg = Message::new(&[&[command, count], write])?;
+        self.blocking_read_block_message(addr, msg.as_slice(), read)
+    }
+}
+
+impl<'d> SmBus<'d, Async> {
+    // Bounded by the I2C timeout, the bus is already recovered by the I2C driver when stuck.
+    async fn transaction(&mut self, addr: u8, operations: &mut [Operation<'_>]) -> Result<(), Error> {
+        self.i2c.transaction_inner(addr.into(), operations).await
+    }
+
+    async fn write_message(&mut self, addr: u8, mut msg: Message) -> Result<(), Error> {
+        self.append_pec(addr, &mut msg)?;
+        self.transaction(addr, &mut [Operation::Write(msg.as_slice())]).await
+    }
+
+    async fn read_message(&mut self, addr: u8, write: &[u8], read: &mut [u8]) -> Result<(), Error> {
+        let mut buf = [0; MAX_MESSAGE_LEN];
+        let rx = &mut buf[..read.len() + self.pec as usize];
+
+        if write.is_empty() {
+            self.transaction(addr, &mut [Operation::Read(rx)]).await?;
+        } else {
+            self.transaction(addr, &mut [Operation::Write(write), Operation::Read(rx)])
+                .await?;
+        }
+
+        self.check_pec(addr, write, rx)?;
+        read.copy_from_slice(&rx[..read.len()]);
+
+        Ok(())
+    }
+
+    async fn read_block_message(&mut self, addr: u8, write: &[u8], buf: &mut [u8]) -> Result<usize, Error> {
+        // byte count + data + PEC
+        let mut rx = [0; MAX_BLOCK_LEN + 2];
+
+        let timeout = self.i2c.timeout();
+        let res = timeout.with(self.read_block_frames(addr, write, &mut rx)).await;
+        let len = self.i2c.recover_on_stuck(res)?;
+
+        self.finish_block_read(addr, write, &rx[..len], buf)
+    }
+
+    async fn read_block_frames(&mut self, addr: u8, write: &[u8], rx: &mut [u8]) -> Result<usize, Error> {
+        let addr = Address::SevenBit(addr);
+
+        if self.i2c.info.regs.status().read().busbusy() {
+            self.i2c.wait_for_stop().await?;
+        }
+
+        self.i2c
+            .do_operation_inner(addr, &mut Operation::Write(write), FIRST_FRAME)
+            .await?;
+        self.i2c
+            .do_operation_inner(addr, &mut Operation::Read(&mut rx[..1]), FIRST_FRAME)
+            .await?;
+
+        let len = 1 + rx[0] as usize + self.pec as usize;
+        self.i2c
+            .do_operation_inner(addr, &mut Operation::Read(&mut rx[1..len]), LAST_FRAME)
+            .await?;
+
+        Ok(len)
+    }
+
+    /// Quick command, the R/W bit is the only data sent.
+    pub async fn quick_command(&mut self, addr: u8, read: bool) -> Result<(), Error> {
+        if read {
+            self.transaction(addr, &mut [Operation::Read(&mut [])]).await
+        } else {
+            self.transaction(addr, &mut [Operation::Write(&[])]).await
+        }
+    }
+
+    /// Send byte, without command code.
+    pub async fn send_byte(&mut self, addr: u8, data: u8) -> Result<(), Error> {
+        self.write_message(addr, Message::new(&[&[data]])?).await
+    }
+
+    /// Receive byte, without command code.
+    pub async fn receive_byte(&mut self, addr: u8) -> Result<u8, Error> {
+        let mut data = [0; 1];
+        self.read_message(addr, &[], &mut data).await?;
+        Ok(data[0])
+    }
+
+    /// Write byte.
+    pub async fn write_byte(&mut self, addr: u8, command: u8, data: u8) -> Result<(), Error> {
+        self.write_message(addr, Message::new(&[&[command, data]])?).await
+    }
+
+    /// Read byte.
+    pub async fn read_byte(&mut self, addr: u8, command: u8) -> Result<u8, Error> {
+        let mut data = [0; 1];
+        self.read_message(addr, &[command], &mut data).await?;
+        Ok(data[0])
+    }
+
+    /// Write word, sent little-endian.
+    pub async fn write_word(&mut self, addr: u8, command: u8, data: u16) -> Result<(), Error> {
+        self.write_message(addr, Message::new(&[&[command], &data.to_le_bytes()])?)
+            .await
+    }
+
+    /// Read word, received little-endian.
+    pub async fn read_word(&mut self, addr: u8, command: u8) -> Result<u16, Error> {
+        let mut data = [0; 2];
+        self.read_message(addr, &[command], &mut data).await?;
+        Ok(u16::from_le_bytes(data))
+    }
+
+    /// Process call, write a word and read a word back.
+    pub async fn process_call(&mut self, addr: u8, command: u8, data: u16) -> Result<u16, Error> {
+        let [lo, hi] = data.to_le_bytes();
+        let mut read = [0; 2];
+        self.read_message(addr, &[command, lo, hi], &mut read).await?;
+        Ok(u16::from_le_bytes(read))
+    }
+
+    /// Block write, up to [`MAX_BLOCK_LEN`] bytes.
+    pub async fn block_write(&mut self, addr: u8, command: u8, data: &[u8]) -> Result<(), Error> {
+        let count = block_len(data)?;
+        self.write_message(addr, Message::new(&[&[command, count], data])?)
+            .await
+    }
+
+    /// Block read, returns the byte count sent by the target.
+    ///
+    /// Fails with [`Error::Overrun`] if `buf` is shorter than the byte count.
+    pub async fn block_read(&mut self, addr: u8, command: u8, buf: &mut [u8]) -> Result<usize, Error> {
+        self.read_block_message(addr, &[command], buf).await
+    }
+
+    /// Block write-block read process call, returns the byte count of the response.
+    pub async fn block_process_call(
+        &mut self,
+        addr: u8,
+        command: u8,
+        write: &[u8],
+        read: &mut [u8],
+    ) -> Result<usize, Error> {
+        let count = block_len(write)?;
+        let msg = Message::new(&[&[command, count], write])?;
+        self.read_block_message(addr, msg.as_slice(), read).await
+    }
+}
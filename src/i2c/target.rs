@@ -0,0 +1,547 @@
+//! I2C target (slave) mode.
+//!
+//! The target waits for an address match with [`I2cTarget::listen`], then serves the request from the
+//! controller with [`I2cTarget::respond_to_write`] or [`I2cTarget::respond_to_read`].
+
+use core::marker::PhantomData;
+use core::task::Poll;
+
+use embassy_futures::select::{Either, select};
+use embassy_hal_internal::Peri;
+use embassy_hal_internal::drop::OnDrop;
+use futures_util::future::poll_fn;
+use hpm_metapac::i2c::vals;
+
+use super::{
+    Address, Error, HPM_I2C_DRV_DEFAULT_TPM, I2C_SOC_TRANSFER_COUNT_MAX, I2cDma, I2cMode, Info, Instance,
+    InterruptHandler, SclPin, SdaPin, State, Timeout, configure_timing, get_data_count,
+};
+use crate::dma::ChannelAndRequest;
+use crate::gpio::AnyPin;
+use crate::interrupt;
+use crate::interrupt::typelevel::Interrupt as _;
+use crate::mode::{Async, Blocking, Mode};
+
+/// Byte sent to the controller when it reads past the end of the response buffer.
+const FILL_BYTE: u8 = 0xff;
+
+/// I2C target config
+#[non_exhaustive]
+#[derive(Copy, Clone)]
+pub struct TargetConfig {
+    /// Own address of the target.
+    pub address: Address,
+    /// Report general call (address 0x00) writes from the controller.
+    ///
+    /// When disabled, general call data is received and discarded.
+    pub general_call: bool,
+    /// Timeout of a single request, from address match to STOP.
+    #[cfg(feature = "time")]
+    pub timeout: embassy_time::Duration,
+}
+
+impl Default for TargetConfig {
+    fn default() -> Self {
+        Self {
+            address: Address::SevenBit(0x42),
+            general_call: false,
+            #[cfg(feature = "time")]
+            timeout: embassy_time::Duration::from_millis(1000),
+        }
+    }
+}
+
+/// Request from the controller, returned by [`I2cTarget::listen`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Command {
+    /// Controller reads from the target, respond with [`I2cTarget::respond_to_read`].
+    Read,
+    /// Controller writes to the target, receive with [`I2cTarget::respond_to_write`].
+    Write,
+    /// Controller writes to the general call address, receive with [`I2cTarget::respond_to_write`].
+    GeneralCall,
+}
+
+/// I2C target driver.
+#[allow(unused)]
+pub struct I2cTarget<'d, M: Mode> {
+    info: &'static Info,
+    state: &'static State,
+    scl: Option<Peri<'d, AnyPin>>,
+    sda: Option<Peri<'d, AnyPin>>,
+    dma: Option<ChannelAndRequest<'d>>,
+    general_call: bool,
+    #[cfg(feature = "time")]
+    timeout: embassy_time::Duration,
+    _phantom: PhantomData<M>,
+}
+
+impl<'d> I2cTarget<'d, Blocking> {
+    /// Create a new blocking I2C target driver.
+    pub fn new_blocking<T: Instance>(
+        peri: Peri<'d, T>,
+        scl: Peri<'d, impl SclPin<T>>,
+        sda: Peri<'d, impl SdaPin<T>>,
+        config: TargetConfig,
+    ) -> Self {
+        Self::new_inner(peri, scl, sda, None, config)
+    }
+}
+
+impl<'d> I2cTarget<'d, Async> {
+    /// Create a new async I2C target driver.
+    pub fn new<T: Instance>(
+        peri: Peri<'d, T>,
+        scl: Peri<'d, impl SclPin<T>>,
+        sda: Peri<'d, impl SdaPin<T>>,
+        _irq: impl interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+        dma: Peri<'d, impl I2cDma<T>>,
+        config: TargetConfig,
+    ) -> Self {
+        Self::new_inner(peri, scl, sda, new_dma!(dma), config)
+    }
+
+    /// Wait for the controller to address this target.
+    pub async fn listen(&mut self) -> Result<Command, Error> {
+        let r = self.info.regs;
+
+        loop {
+            r.int_en().modify(|w| w.set_addrhit(true));
+
+            poll_fn(|cx| {
+                self.state.waker.register(cx.waker());
+
+                if r.status().read().addrhit() {
+                    Poll::Ready(())
+                } else {
+                    r.int_en().modify(|w| w.set_addrhit(true));
+                    Poll::Pending
+                }
+            })
+            .await;
+
+            match self.accept_command() {
+                Some(cmd) => return Ok(cmd),
+                None => self.discard().await?,
+            }
+        }
+    }
+
+    // Receive and drop the data of an unwanted general call, until STOP.
+    async fn discard(&mut self) -> Result<(), Error> {
+        let r = self.info.regs;
+        let timeout = self.timeout();
+        let s = self.state;
+
+        let _on_drop = OnDrop::new(|| {
+            r.int_en().modify(|w| {
+                w.set_cmpl(false);
+                w.set_fifofull(false);
+            });
+        });
+
+        let drain = poll_fn(|cx| {
+            s.waker.register(cx.waker());
+
+            let done = r.status().read().cmpl();
+            while !r.status().read().fifoempty() {
+                let _ = r.data().read();
+            }
+            if done {
+                Poll::Ready(())
+            } else {
+                r.int_en().modify(|w| {
+                    w.set_cmpl(true);
+                    w.set_fifofull(true);
+                });
+                Poll::Pending
+            }
+        });
+        timeout.with(async { Ok(drain.await) }).await?;
+
+        self.finish_request();
+        Ok(())
+    }
+
+    /// Receive the data written by the controller, using DMA.
+    ///
+    /// Returns the number of bytes received, once the controller sends STOP or repeated START.
+    /// If the controller writes more than `buf.len()` bytes, the rest is discarded and [`Error::Overrun`]
+    /// is returned.
+    pub async fn respond_to_write(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let r = self.info.regs;
+        let size = buf.len();
+
+        if size == 0 || size > I2C_SOC_TRANSFER_COUNT_MAX {
+            return Err(Error::InvalidArgument);
+        }
+
+        let timeout = self.timeout();
+        let s = self.state;
+
+        set_data_count(r, size);
+
+        let ch = self.dma.as_mut().unwrap();
+        let mut transfer = unsafe { ch.read(r.data().as_ptr() as *mut u8, buf, Default::default()) };
+
+        let _on_drop = OnDrop::new(|| {
+            r.setup().modify(|w| w.set_dmaen(false));
+            r.int_en().modify(|w| {
+                w.set_cmpl(false);
+                w.set_fifofull(false);
+            });
+        });
+
+        r.setup().modify(|w| w.set_dmaen(true));
+
+        let complete = poll_fn(|cx| {
+            s.waker.register(cx.waker());
+
+            if r.status().read().cmpl() {
+                Poll::Ready(())
+            } else {
+                r.int_en().modify(|w| w.set_cmpl(true));
+                Poll::Pending
+            }
+        });
+
+        let buf_full = match timeout.with(async { Ok(select(complete, &mut transfer).await) }).await? {
+            Either::First(()) => false,
+            Either::Second(()) => true,
+        };
+
+        let received = size - transfer.get_remaining_transfers() as usize;
+        drop(transfer);
+        r.setup().modify(|w| w.set_dmaen(false));
+
+        let mut overrun = false;
+        if buf_full {
+            // The last byte lands before STOP, wait for it. Only bytes received past the end of the
+            // buffer are an overrun, drain them so the controller is not stretched forever.
+            let drain = poll_fn(|cx| {
+                s.waker.register(cx.waker());
+
+                let done = r.status().read().cmpl();
+                while !r.status().read().fifoempty() {
+                    let _ = r.data().read();
+                    overrun = true;
+                }
+                if done {
+                    Poll::Ready(())
+                } else {
+                    r.int_en().modify(|w| {
+                        w.set_cmpl(true);
+                        w.set_fifofull(true);
+                    });
+                    Poll::Pending
+                }
+            });
+            timeout.with(async { Ok(drain.await) }).await?;
+        }
+
+        self.finish_request();
+
+        if overrun { Err(Error::Overrun) } else { Ok(received) }
+    }
+
+    /// Send `buf` to the controller, using DMA.
+    ///
+    /// Returns the number of bytes the controller has read, once it sends STOP or repeated START.
+    /// If the controller reads more than `buf.len()` bytes, `0xFF` is sent for the rest.
+    pub async fn respond_to_read(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        let r = self.info.regs;
+        let size = buf.len();
+
+        if size == 0 || size > I2C_SOC_TRANSFER_COUNT_MAX {
+            return Err(Error::InvalidArgument);
+        }
+
+        let timeout = self.timeout();
+        let s = self.state;
+
+        set_data_count(r, size);
+
+        let ch = self.dma.as_mut().unwrap();
+        let mut transfer = unsafe { ch.write(buf, r.data().as_ptr() as *mut u8, Default::default()) };
+
+        let _on_drop = OnDrop::new(|| {
+            r.setup().modify(|w| w.set_dmaen(false));
+            r.int_en().modify(|w| {
+                w.set_cmpl(false);
+                w.set_fifoempty(false);
+            });
+        });
+
+        r.setup().modify(|w| w.set_dmaen(true));
+
+        let complete = poll_fn(|cx| {
+            s.waker.register(cx.waker());
+
+            if r.status().read().cmpl() {
+                Poll::Ready(())
+            } else {
+                r.int_en().modify(|w| w.set_cmpl(true));
+                Poll::Pending
+            }
+        });
+
+        let exhausted = match timeout.with(async { Ok(select(complete, &mut transfer).await) }).await? {
+            Either::First(()) => false,
+            Either::Second(()) => true,
+        };
+        drop(transfer);
+        r.setup().modify(|w| w.set_dmaen(false));
+
+        if exhausted {
+            // The last bytes are still in the FIFO, wait for STOP. Fill bytes are only queued once
+            // the FIFO runs empty, when the controller keeps reading past the end of the buffer.
+            let fill = poll_fn(|cx| {
+                s.waker.register(cx.waker());
+
+                if r.status().read().cmpl() {
+                    return Poll::Ready(());
+                }
+                if r.status().read().fifoempty() {
+                    while !r.status().read().fifofull() {
+                        r.data().write(|w| w.set_data(FILL_BYTE));
+                    }
+                }
+                r.int_en().modify(|w| {
+                    w.set_cmpl(true);
+                    w.set_fifoempty(true);
+                });
+                Poll::Pending
+            });
+            timeout.with(async { Ok(fill.await) }).await?;
+        }
+
+        let sent = size - (get_data_count(r) as usize).min(size);
+        self.finish_request();
+
+        Ok(sent)
+    }
+}
+
+impl<'d, M: Mode> I2cTarget<'d, M> {
+    fn new_inner<T: Instance>(
+        _peri: Peri<'d, T>,
+        scl: Peri<'d, impl SclPin<T>>,
+        sda: Peri<'d, impl SdaPin<T>>,
+        dma: Option<ChannelAndRequest<'d>>,
+        config: TargetConfig,
+    ) -> Self {
+        // ALT, Open Drain, Pull-up
+        scl.ioc_pad().func_ctl().write(|w| {
+            w.set_alt_select(scl.alt_num());
+            w.set_loop_back(true);
+        });
+        scl.ioc_pad().pad_ctl().write(|w| {
+            w.set_od(true);
+            w.set_pe(true);
+            w.set_ps(true);
+        });
+        sda.ioc_pad().func_ctl().write(|w| {
+            w.set_alt_select(sda.alt_num());
+            w.set_loop_back(true);
+        });
+        sda.ioc_pad().pad_ctl().write(|w| {
+            w.set_od(true);
+            w.set_pe(true);
+            w.set_ps(true);
+        });
+
+        T::add_resource_group(0);
+        {
+            use crate::sysctl::*;
+            T::set_clock(ClockConfig::new(ClockMux::CLK_24M, 1));
+        }
+
+        unsafe { T::Interrupt::enable() };
+
+        let mut this = Self {
+            info: T::info(),
+            state: T::state(),
+            scl: Some(scl.into()),
+            sda: Some(sda.into()),
+            dma,
+            general_call: config.general_call,
+            #[cfg(feature = "time")]
+            timeout: config.timeout,
+            _phantom: PhantomData,
+        };
+        this.init(T::frequency().0, config);
+        this
+    }
+
+    // init slave
+    fn init(&mut self, kernel_clock: u32, config: TargetConfig) {
+        let r = self.info.regs;
+
+        r.ctrl().write(|w| w.0 = 0);
+        r.cmd().write(|w| w.set_cmd(vals::Cmd::RESET));
+        r.setup().modify(|w| w.set_iicen(false));
+
+        // Only setup/hold/spike timings are used in target mode, SCL is driven by the controller.
+        let timing = configure_timing(kernel_clock, I2cMode::Standard, 0, 0).unwrap();
+
+        r.tpm().write(|w| w.set_tpm(HPM_I2C_DRV_DEFAULT_TPM as _));
+
+        let (addr, ten_bit) = config.address.raw();
+        r.addr().write(|w| w.set_addr(addr));
+
+        r.setup().write(|w| {
+            w.set_t_sp(timing.t_sp as _);
+            w.set_t_sudat(timing.t_sudat as _);
+            w.set_t_hddat(timing.t_hddat as _);
+            w.set_addressing(ten_bit);
+            w.set_iicen(true);
+            w.set_master(false);
+        });
+    }
+
+    fn timeout(&self) -> Timeout {
+        Timeout {
+            #[cfg(feature = "time")]
+            deadline: embassy_time::Instant::now() + self.timeout,
+        }
+    }
+
+    // Clear address hit and decode the request, `None` for a general call to discard.
+    fn accept_command(&mut self) -> Option<Command> {
+        let r = self.info.regs;
+
+        let status = r.status().read();
+        let dir = r.ctrl().read().dir();
+
+        // W1C, clear CMPL and ADDRHIT bit to avoid blocking the transmission
+        r.status().write(|w| {
+            w.set_cmpl(true);
+            w.set_addrhit(true);
+        });
+
+        if status.gencall() {
+            return self.general_call.then_some(Command::GeneralCall);
+        }
+
+        Some(match dir {
+            vals::Dir::MASTER_READ_SLAVE_WRITE => Command::Read,
+            _ => Command::Write,
+        })
+    }
+
+    // Blocking version of `discard`.
+    fn blocking_discard(&mut self) -> Result<(), Error> {
+        let r = self.info.regs;
+        let timeout = self.timeout();
+
+        loop {
+            while !r.status().read().fifoempty() {
+                let _ = r.data().read();
+            }
+            if r.status().read().cmpl() {
+                break;
+            }
+            timeout.check()?;
+        }
+        self.finish_request();
+        Ok(())
+    }
+
+    fn finish_request(&mut self) {
+        let r = self.info.regs;
+
+        r.cmd().write(|w| w.set_cmd(vals::Cmd::CLEAR_FIFO));
+        // W1C
+        r.status().write(|w| {
+            w.set_cmpl(true);
+            w.set_stop(true);
+        });
+    }
+
+    /// Blocking wait for the controller to address this target.
+    pub fn blocking_listen(&mut self) -> Result<Command, Error> {
+        let r = self.info.regs;
+
+        loop {
+            while !r.status().read().addrhit() {}
+
+            match self.accept_command() {
+                Some(cmd) => return Ok(cmd),
+                None => self.blocking_discard()?,
+            }
+        }
+    }
+
+    /// Blocking receive of the data written by the controller.
+    ///
+    /// Returns the number of bytes received, once the controller sends STOP or repeated START.
+    /// If the controller writes more than `buf.len()` bytes, the rest is discarded and [`Error::Overrun`]
+    /// is returned.
+    pub fn blocking_respond_to_write(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let r = self.info.regs;
+        let timeout = self.timeout();
+
+        let mut received = 0;
+        let mut overrun = false;
+
+        loop {
+            while !r.status().read().fifoempty() {
+                let b = r.data().read().data();
+                if received < buf.len() {
+                    buf[received] = b;
+                    received += 1;
+                } else {
+                    overrun = true;
+                }
+            }
+            if r.status().read().cmpl() && r.status().read().fifoempty() {
+                break;
+            }
+            timeout.check()?;
+        }
+
+        self.finish_request();
+
+        if overrun { Err(Error::Overrun) } else { Ok(received) }
+    }
+
+    /// Blocking send of `buf` to the controller.
+    ///
+    /// Returns the number of bytes the controller has read, once it sends STOP or repeated START.
+    /// If the controller reads more than `buf.len()` bytes, `0xFF` is sent for the rest.
+    pub fn blocking_respond_to_read(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        let r = self.info.regs;
+        let size = buf.len();
+
+        if size > I2C_SOC_TRANSFER_COUNT_MAX {
+            return Err(Error::InvalidArgument);
+        }
+
+        let timeout = self.timeout();
+        set_data_count(r, size);
+
+        let mut bytes = buf.iter();
+        while !r.status().read().cmpl() {
+            if !r.status().read().fifofull() {
+                let b = bytes.next().copied().unwrap_or(FILL_BYTE);
+                r.data().write(|w| w.set_data(b));
+            }
+            timeout.check()?;
+        }
+
+        let sent = size - (get_data_count(r) as usize).min(size);
+        self.finish_request();
+
+        Ok(sent)
+    }
+}
+
+#[inline]
+fn set_data_count(r: crate::pac::i2c::I2c, size: usize) {
+    r.ctrl().modify(|w| {
+        w.set_datacnt(size as _);
+        #[cfg(ip_feature_i2c_transfer_count_max_4096)]
+        w.set_datacnt_high((size >> 8) as _);
+    });
+}
@@ -6,12 +6,39 @@
 //! - Master/Slave mode
 //! - DMA transfer support
 //!
+//! # Drivers
+//!
+//! - [`I2S`]: blocking TX-only, RX-only or full-duplex
+//! - [`I2STxDma`], [`I2SRxDma`], [`I2SFullDuplexDma`]: DMA ring buffer streaming
+//!
+//! In [`Mode::Slave`], BCLK and FCLK are inputs driven by the external master (e.g. an audio codec).
+//!
+//! # TDM
+//!
+//! Up to 16 slots per frame, each data line can enable its own slots, see [`Config::tdm`] and
+//! [`Config::line_slot_mask`]. Samples of the active slots are interleaved in slot order:
+//!
+//! ```ignore
+//! // 8-slot TDM codec, all slots active
+//! let config = Config::tdm(8, 0xff);
+//!
+//! // DMA buffer holds whole frames: [s0, s1, ..., s7, s0, s1, ...]
+//! let mut frame = [0u32; 8];
+//! i2s_rx.read_exact(&mut frame).await?;
+//! ```
+//!
+//! # Clock
+//!
+//! In master mode on HPM6E00/HPM6700, BCLK is divided from the instance's audio clock (MCLK).
+//! Set [`Config::mclk_multiple`] to have the audio clock planned for the sample rate,
+//! [`I2S::sample_rate`] returns the rate actually achieved.
+//!
 //! # DMA Support
 //!
 //! The I2S driver supports DMA for efficient audio streaming:
 //!
 //! ```ignore
-//! use hpm_hal::i2s::{I2STxDma, Config};
+//! use hpm_hal::i2s::{Config, Error, I2STxDma};
 //! use hpm_hal::dma::TransferOptions;
 //!
 //! // Create DMA buffer in noncacheable memory
@@ -19,12 +46,24 @@
 //! static mut DMA_BUF: [u32; 512] = [0u32; 512];
 //!
 //! let dma_buf = unsafe { &mut DMA_BUF };
-//! let i2s_tx = I2STxDma::new(
+//! let mut i2s_tx = I2STxDma::new(
 //!     p.I2S1,
 //!     p.HDMA_CH0,
 //!     dma_buf,
 //!     config,
 //! );
+//!
+//! // Fill the buffer, then await space for each chunk
+//! i2s_tx.write(&chunk)?;
+//! i2s_tx.start();
+//! loop {
+//!     match i2s_tx.write_exact(&chunk).await {
+//!         Ok(_) => {}
+//!         // DMA ran out of samples, the ring buffer is resynced
+//!         Err(Error::Underrun) => defmt::warn!("I2S underrun"),
+//!         Err(e) => return Err(e),
+//!     }
+//! }
 //! ```
 
 use embassy_hal_internal::{Peri, PeripheralType};
@@ -33,7 +72,7 @@ use crate::pac::i2s::vals::{ChannelSize, DataSize, Std};
 use crate::pac::i2s::I2s;
 
 #[cfg(ip_feature_dma_v2)]
-use crate::dma::{self, Channel, TransferOptions, WritableRingBuffer};
+use crate::dma::{self, Channel, ReadableRingBuffer, TransferOptions, WritableRingBuffer};
 
 // - MARK: Config types
 
@@ -135,8 +174,15 @@ pub enum Error {
     Overrun,
     /// TX FIFO underrun
     Underrun,
+    /// Invalid slot or clock configuration, or DMA buffer not holding whole frames
+    InvalidConfig,
+    /// FIFO not serviced in time, e.g. no bit clock from the master in slave mode
+    Timeout,
 }
 
+/// FIFO level polls before a blocking word transfer gives up, well above a frame at 8kHz
+const FIFO_TIMEOUT: u32 = 1_000_000;
+
 /// I2S configuration
 #[derive(Clone, Copy)]
 pub struct Config {
@@ -154,12 +200,19 @@ pub struct Config {
     pub tx_fifo_threshold: u8,
     /// RX FIFO threshold (0-31)
     pub rx_fifo_threshold: u8,
-    /// Enable TDM mode
+    /// Enable TDM mode, required for more than 2 channels per frame
     pub enable_tdm: bool,
     /// Number of channels per frame (2-16, must be even)
     pub channel_num: u8,
     /// Channel slot mask (which slots are active)
     pub channel_slot_mask: u32,
+    /// Per data line slot mask, overrides `channel_slot_mask` for that line
+    pub line_slot_mask: [Option<u16>; 4],
+    /// Master mode: plan and apply the audio clock so that MCLK = `sample_rate * mclk_multiple`,
+    /// e.g. `Some(256)`. `None` keeps the current audio clock. HPM6E00/HPM6700 only.
+    ///
+    /// See [`crate::sysctl::plan_audio_clock`].
+    pub mclk_multiple: Option<u32>,
 }
 
 impl Default for Config {
@@ -175,8 +228,61 @@ impl Default for Config {
             enable_tdm: false,
             channel_num: 2,
             channel_slot_mask: 0x3, // channels 0 and 1
+            line_slot_mask: [None; 4],
+            mclk_multiple: None,
+        }
+    }
+}
+
+impl Config {
+    /// TDM configuration with `slot_count` slots per frame, of which `slot_mask` are active
+    pub fn tdm(slot_count: u8, slot_mask: u16) -> Self {
+        Self {
+            enable_tdm: true,
+            channel_num: slot_count,
+            channel_slot_mask: slot_mask as u32,
+            ..Default::default()
         }
     }
+
+    /// Slot mask of a data line
+    pub fn slot_mask(&self, line: DataLine) -> u16 {
+        self.line_slot_mask[line as usize].unwrap_or(self.channel_slot_mask as u16)
+    }
+
+    /// Number of samples per frame of a data line, i.e. the number of active slots
+    ///
+    /// DMA buffers hold frames of interleaved samples of the active slots, in slot order.
+    pub fn frame_len(&self, line: DataLine) -> usize {
+        self.slot_mask(line).count_ones() as usize
+    }
+
+    /// Number of bits per frame
+    fn frame_bits(&self) -> u32 {
+        let channel_bits = match self.format {
+            Format::Data16Channel16 => 16,
+            _ => 32,
+        };
+        channel_bits * self.channel_num as u32
+    }
+
+    /// Check the slot configuration
+    pub fn validate(&self) -> Result<(), Error> {
+        let slots = self.channel_num;
+        if !(2..=16).contains(&slots) || slots % 2 != 0 || (slots > 2 && !self.enable_tdm) {
+            return Err(Error::InvalidConfig);
+        }
+
+        let valid_mask = (1u32 << slots) - 1;
+        let masks = self.line_slot_mask.iter().flatten().map(|&mask| mask as u32);
+        for mask in masks.chain(core::iter::once(self.channel_slot_mask)) {
+            if mask == 0 || mask & !valid_mask != 0 {
+                return Err(Error::InvalidConfig);
+            }
+        }
+
+        Ok(())
+    }
 }
 
 // - MARK: Pin traits
@@ -216,6 +322,9 @@ dma_trait!(RxDma, Instance);
 // - MARK: Instance trait
 
 pub(crate) trait SealedInstance {
+    /// Instance index, selects the audio clock
+    const INDEX: usize;
+
     fn regs() -> I2s;
 }
 
@@ -228,74 +337,263 @@ pub trait Instance: SealedInstance + PeripheralType + crate::sysctl::ClockPeriph
 
 // - MARK: Driver
 
-/// I2S driver (dry-run / blocking mode for now)
+/// Route the shared clock pins to the I2S.
+///
+/// Their direction is not set here, `configure` makes the I2S drive them in master mode and sample them
+/// in slave mode.
+fn set_clock_pins<T: Instance>(
+    bclk: &Peri<'_, impl BclkPin<T>>,
+    fclk: &Peri<'_, impl FclkPin<T>>,
+    mclk: &Peri<'_, impl MclkPin<T>>,
+) {
+    bclk.set_as_alt(bclk.alt_num());
+    fclk.set_as_alt(fclk.alt_num());
+    mclk.set_as_alt(mclk.alt_num());
+}
+
+/// Reset and configure the I2S peripheral, the TX/RX lines are enabled but I2S is not started.
+/// Returns the actual sample rate
+fn configure<T: Instance>(
+    config: &Config,
+    tx: Option<DataLine>,
+    rx: Option<DataLine>,
+    dma: bool,
+) -> Result<u32, Error> {
+    config.validate()?;
+
+    // Enable peripheral clock
+    T::add_resource_group(0);
+
+    let regs = T::regs();
+
+    // Disable I2S first
+    regs.ctrl().modify(|w| w.set_i2s_en(false));
+
+    // Internal clocks are required for the software reset to work
+    regs.cfgr().modify(|w| {
+        w.set_mck_sel_op(false);
+        w.set_bclk_sel_op(false);
+        w.set_fclk_sel_op(false);
+        w.set_bclk_gateoff(false);
+    });
+    regs.misc_cfgr().modify(|w| w.set_mclk_gateoff(false));
+
+    // Reset TX, RX and clock generator
+    regs.ctrl().modify(|w| {
+        w.set_sftrst_tx(true);
+        w.set_sftrst_rx(true);
+        w.set_sftrst_clkgen(true);
+        w.set_txfifoclr(true);
+        w.set_rxfifoclr(true);
+    });
+    regs.ctrl().modify(|w| {
+        w.set_sftrst_tx(false);
+        w.set_sftrst_rx(false);
+        w.set_sftrst_clkgen(false);
+        w.set_txfifoclr(false);
+        w.set_rxfifoclr(false);
+    });
+
+    // Configure FIFO threshold
+    regs.fifo_thresh().modify(|w| {
+        w.set_tx(config.tx_fifo_threshold);
+        w.set_rx(config.rx_fifo_threshold);
+    });
+
+    // In slave mode, BCLK and FCLK are driven by the external master
+    let slave = config.mode == Mode::Slave;
+
+    // BCLK = MCLK / BCLK_DIV = sample_rate * frame_bits
+    #[cfg(any(hpm6e, hpm67))]
+    let (bclk_div, sample_rate) = if slave {
+        (None, config.sample_rate)
+    } else {
+        let mclk = setup_mclk::<T>(config)?;
+        let bclk = config.sample_rate * config.frame_bits();
+        if bclk == 0 || mclk < bclk {
+            return Err(Error::InvalidConfig);
+        }
+        let bclk_div = (mclk / bclk).min(511); // BCLK_DIV is 9 bits
+        (Some(bclk_div as u16), mclk / bclk_div / config.frame_bits())
+    };
+    #[cfg(not(any(hpm6e, hpm67)))]
+    let sample_rate = config.sample_rate;
+
+    // Configure I2S format
+    regs.cfgr().modify(|w| w.set_bclk_gateoff(true));
+    regs.cfgr().modify(|w| {
+        #[cfg(any(hpm6e, hpm67))]
+        if let Some(bclk_div) = bclk_div {
+            w.set_bclk_div(bclk_div);
+        }
+        w.set_datsiz(config.format.data_size());
+        w.set_chsiz(config.format.channel_size());
+        w.set_std(config.standard.to_pac());
+        w.set_tdm_en(config.enable_tdm);
+        w.set_ch_max(config.channel_num);
+        w.set_bclk_sel_op(slave);
+        w.set_fclk_sel_op(slave);
+    });
+    regs.cfgr().modify(|w| w.set_bclk_gateoff(false));
+
+    // Configure MCLK output
+    regs.misc_cfgr().modify(|w| {
+        w.set_mclkoe(config.master_clock);
+    });
+
+    // Set slot masks of the used lines
+    if let Some(line) = tx {
+        regs.txdslot(line as usize).write(|w| w.set_en(config.slot_mask(line)));
+    }
+    if let Some(line) = rx {
+        regs.rxdslot(line as usize).write(|w| w.set_en(config.slot_mask(line)));
+    }
+
+    // Enable lines and DMA requests
+    regs.ctrl().modify(|w| {
+        w.set_tx_en(tx.map_or(0, |line| 1 << line as u8));
+        w.set_tx_dma_en(dma && tx.is_some());
+        w.set_rx_en(rx.map_or(0, |line| 1 << line as u8));
+        w.set_rx_dma_en(dma && rx.is_some());
+    });
+
+    Ok(sample_rate)
+}
+
+/// Set up the audio clock of the instance if requested, returns the MCLK frequency
+#[cfg(any(hpm6e, hpm67))]
+fn setup_mclk<T: Instance>(config: &Config) -> Result<u32, Error> {
+    use crate::sysctl;
+    use crate::time::Hertz;
+
+    // HPM6700: I2S2 and I2S3 share AUD2
+    #[cfg(hpm67)]
+    let aud_idx = T::INDEX.min(2);
+    // HPM6E00: I2S0 is fed by AUD0 and I2S1 by AUD1, there is no clock node for other instances
+    #[cfg(hpm6e)]
+    let aud_idx = match T::INDEX {
+        idx @ (0 | 1) => idx,
+        _ => return Err(Error::InvalidConfig),
+    };
+
+    if let Some(multiple) = config.mclk_multiple {
+        let plan = sysctl::configure_audio_clock_for_rate(aud_idx, Hertz(config.sample_rate), multiple)
+            .ok_or(Error::InvalidConfig)?;
+        if plan.error_ppm() != 0 {
+            #[cfg(feature = "defmt")]
+            defmt::warn!("I2S: MCLK {} Hz, {} ppm off", plan.mclk.0, plan.error_ppm());
+        }
+
+        #[cfg(hpm6e)]
+        sysctl::set_i2s_clock_source(T::INDEX, aud_idx == 0);
+        #[cfg(hpm67)]
+        sysctl::set_i2s_clock_source(
+            T::INDEX,
+            match aud_idx {
+                0 => sysctl::I2sClkMux::I2S0,
+                1 => sysctl::I2sClkMux::I2S1,
+                _ => sysctl::I2sClkMux::I2S2,
+            },
+        );
+    }
+
+    #[cfg(hpm6e)]
+    let mclk = sysctl::get_i2s_clock_freq(T::INDEX);
+    #[cfg(hpm67)]
+    let mclk = sysctl::get_audio_clock_freq(aud_idx);
+
+    match mclk.0 {
+        0 => Err(Error::InvalidConfig),
+        mclk => Ok(mclk),
+    }
+}
+
+/// DMA buffers must hold whole frames, so that samples stay at the same slot position.
+#[cfg(ip_feature_dma_v2)]
+fn check_dma_buf(config: &Config, line: DataLine, buf: &[u32]) -> Result<(), Error> {
+    if buf.is_empty() || buf.len() % config.frame_len(line) != 0 {
+        return Err(Error::InvalidConfig);
+    }
+    Ok(())
+}
+
+/// I2S driver (blocking mode)
+///
+/// TX and RX share BCLK and FCLK, in full-duplex mode samples are sent and received in the same frame.
 pub struct I2S<'d, T: Instance> {
     _peri: Peri<'d, T>,
     tx_line: Option<DataLine>,
     rx_line: Option<DataLine>,
+    sample_rate: u32,
 }
 
 impl<'d, T: Instance> I2S<'d, T> {
     /// Create a new I2S driver in TX-only mode (blocking, for dry-run testing)
     pub fn new_txonly_blocking(
         peri: Peri<'d, T>,
-        _txd: Peri<'d, impl TxdPin<T>>,
-        _bclk: Peri<'d, impl BclkPin<T>>,
-        _fclk: Peri<'d, impl FclkPin<T>>,
-        _mclk: Peri<'d, impl MclkPin<T>>,
+        txd: Peri<'d, impl TxdPin<T>>,
+        bclk: Peri<'d, impl BclkPin<T>>,
+        fclk: Peri<'d, impl FclkPin<T>>,
+        mclk: Peri<'d, impl MclkPin<T>>,
         config: Config,
-    ) -> Self {
-        // Enable peripheral clock
-        T::add_resource_group(0);
+    ) -> Result<Self, Error> {
+        txd.set_as_alt(txd.alt_num());
+        set_clock_pins(&bclk, &fclk, &mclk);
 
-        let regs = T::regs();
+        Self::new_inner(peri, Some(txd.line()), None, config)
+    }
 
-        // Disable I2S first
-        regs.ctrl().modify(|w| w.set_i2s_en(false));
+    /// Create a new I2S driver in RX-only mode (blocking)
+    pub fn new_rxonly(
+        peri: Peri<'d, T>,
+        rxd: Peri<'d, impl RxdPin<T>>,
+        bclk: Peri<'d, impl BclkPin<T>>,
+        fclk: Peri<'d, impl FclkPin<T>>,
+        mclk: Peri<'d, impl MclkPin<T>>,
+        config: Config,
+    ) -> Result<Self, Error> {
+        rxd.set_as_alt(rxd.alt_num());
+        set_clock_pins(&bclk, &fclk, &mclk);
 
-        // Reset TX
-        regs.ctrl().modify(|w| {
-            w.set_sftrst_tx(true);
-            w.set_txfifoclr(true);
-        });
-        regs.ctrl().modify(|w| {
-            w.set_sftrst_tx(false);
-            w.set_txfifoclr(false);
-        });
-
-        // Configure FIFO threshold
-        regs.fifo_thresh().modify(|w| {
-            w.set_tx(config.tx_fifo_threshold);
-            w.set_rx(config.rx_fifo_threshold);
-        });
-
-        // Configure I2S format
-        regs.cfgr().modify(|w| {
-            w.set_datsiz(config.format.data_size());
-            w.set_chsiz(config.format.channel_size());
-            w.set_std(config.standard.to_pac());
-            w.set_tdm_en(config.enable_tdm);
-            w.set_ch_max(config.channel_num);
-        });
-
-        // Configure MCLK output
-        regs.misc_cfgr().modify(|w| {
-            w.set_mclkoe(config.master_clock);
-        });
-
-        // Set slot mask for TX line 0
-        regs.txdslot(0).write(|w| w.set_en(config.channel_slot_mask as u16));
-
-        // Enable TX line 0
-        regs.ctrl().modify(|w| {
-            w.set_tx_en(1); // Enable line 0
-        });
+        Self::new_inner(peri, None, Some(rxd.line()), config)
+    }
 
-        Self {
+    /// Create a new I2S driver in full-duplex mode (blocking), TX and RX share BCLK/FCLK
+    pub fn new_full_duplex(
+        peri: Peri<'d, T>,
+        txd: Peri<'d, impl TxdPin<T>>,
+        rxd: Peri<'d, impl RxdPin<T>>,
+        bclk: Peri<'d, impl BclkPin<T>>,
+        fclk: Peri<'d, impl FclkPin<T>>,
+        mclk: Peri<'d, impl MclkPin<T>>,
+        config: Config,
+    ) -> Result<Self, Error> {
+        txd.set_as_alt(txd.alt_num());
+        rxd.set_as_alt(rxd.alt_num());
+        set_clock_pins(&bclk, &fclk, &mclk);
+
+        Self::new_inner(peri, Some(txd.line()), Some(rxd.line()), config)
+    }
+
+    fn new_inner(
+        peri: Peri<'d, T>,
+        tx_line: Option<DataLine>,
+        rx_line: Option<DataLine>,
+        config: Config,
+    ) -> Result<Self, Error> {
+        let sample_rate = configure::<T>(&config, tx_line, rx_line, false)?;
+
+        Ok(Self {
             _peri: peri,
-            tx_line: Some(DataLine::Line0),
-            rx_line: None,
-        }
+            tx_line,
+            rx_line,
+            sample_rate,
+        })
+    }
+
+    /// Actual sample rate, may differ from the requested one when MCLK isn't an exact multiple
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
     }
 
     /// Start I2S transfer
@@ -322,13 +620,32 @@ impl<'d, T: Instance> I2S<'d, T> {
         }
     }
 
+    /// Get RX FIFO level for the configured line
+    pub fn rx_fifo_level(&self) -> u8 {
+        let regs = T::regs();
+        let fillings = regs.rfifo_fillings().read();
+        match self.rx_line.unwrap_or(DataLine::Line0) {
+            DataLine::Line0 => fillings.rx0(),
+            DataLine::Line1 => fillings.rx1(),
+            DataLine::Line2 => fillings.rx2(),
+            DataLine::Line3 => fillings.rx3(),
+        }
+    }
+
     /// Send a single data word (blocking)
+    ///
+    /// Returns [`Error::Timeout`] if the FIFO stays full, e.g. without a bit clock in slave mode.
     pub fn send_blocking(&mut self, data: u32) -> Result<(), Error> {
         let regs = T::regs();
         let line = self.tx_line.ok_or(Error::NotATransmitter)?;
 
         // Wait for FIFO space
+        let mut timeout_count = 0u32;
         while self.tx_fifo_level() >= 8 {
+            timeout_count += 1;
+            if timeout_count > FIFO_TIMEOUT {
+                return Err(Error::Timeout);
+            }
             core::hint::spin_loop();
         }
 
@@ -336,6 +653,44 @@ impl<'d, T: Instance> I2S<'d, T> {
         Ok(())
     }
 
+    /// Receive a single data word (blocking)
+    ///
+    /// Returns [`Error::Timeout`] if no word arrives, e.g. without a bit clock in slave mode.
+    pub fn receive_blocking(&mut self) -> Result<u32, Error> {
+        let regs = T::regs();
+        let line = self.rx_line.ok_or(Error::NotAReceiver)?;
+
+        // Wait for FIFO data
+        let mut timeout_count = 0u32;
+        while self.rx_fifo_level() == 0 {
+            timeout_count += 1;
+            if timeout_count > FIFO_TIMEOUT {
+                return Err(Error::Timeout);
+            }
+            core::hint::spin_loop();
+        }
+
+        Ok(regs.rxd(line as usize).read().d())
+    }
+
+    /// Send and receive data words of the same frames (blocking)
+    ///
+    /// `read` and `write` must have the same length.
+    pub fn transfer_blocking(&mut self, read: &mut [u32], write: &[u32]) -> Result<(), Error> {
+        if self.tx_line.is_none() {
+            return Err(Error::NotATransmitter);
+        }
+        if self.rx_line.is_none() {
+            return Err(Error::NotAReceiver);
+        }
+
+        for (r, w) in read.iter_mut().zip(write.iter()) {
+            self.send_blocking(*w)?;
+            *r = self.receive_blocking()?;
+        }
+        Ok(())
+    }
+
     /// Check if I2S is enabled
     pub fn is_enabled(&self) -> bool {
         T::regs().ctrl().read().i2s_en()
@@ -351,8 +706,10 @@ impl<'d, T: Instance> Drop for I2S<'d, T> {
 // - MARK: Instance macro
 
 macro_rules! impl_i2s {
-    ($inst:ident, $irq:ident) => {
+    ($inst:ident, $irq:ident, $index:literal) => {
         impl SealedInstance for crate::peripherals::$inst {
+            const INDEX: usize = $index;
+
             fn regs() -> I2s {
                 crate::pac::$inst
             }
@@ -366,13 +723,13 @@ macro_rules! impl_i2s {
 
 // HPM67xx I2S instances
 #[cfg(peri_i2s0)]
-impl_i2s!(I2S0, I2S0);
+impl_i2s!(I2S0, I2S0, 0);
 #[cfg(peri_i2s1)]
-impl_i2s!(I2S1, I2S1);
+impl_i2s!(I2S1, I2S1, 1);
 #[cfg(peri_i2s2)]
-impl_i2s!(I2S2, I2S2);
+impl_i2s!(I2S2, I2S2, 2);
 #[cfg(peri_i2s3)]
-impl_i2s!(I2S3, I2S3);
+impl_i2s!(I2S3, I2S3, 3);
 
 // - MARK: I2S TX DMA Driver
 
@@ -392,78 +749,47 @@ impl<'d, T: Instance> I2STxDma<'d, T> {
     ///
     /// # Arguments
     /// * `peri` - I2S peripheral instance
+    /// * `txd` - TX data pin, its line is used for transmission
+    /// * `bclk`, `fclk`, `mclk` - Clock pins, inputs in slave mode
     /// * `dma_ch` - DMA channel with TX DMA capability
     /// * `dma_buf` - DMA buffer (must be in noncacheable memory, 4-byte aligned)
     /// * `config` - I2S configuration
+    #[allow(clippy::too_many_arguments)]
     pub fn new<DMA: Channel + TxDma<T>>(
         peri: Peri<'d, T>,
+        txd: Peri<'d, impl TxdPin<T>>,
+        bclk: Peri<'d, impl BclkPin<T>>,
+        fclk: Peri<'d, impl FclkPin<T>>,
+        mclk: Peri<'d, impl MclkPin<T>>,
         dma_ch: Peri<'d, DMA>,
         dma_buf: &'d mut [u32],
         config: Config,
-    ) -> Self {
-        // Enable peripheral clock
-        T::add_resource_group(0);
+    ) -> Result<Self, Error> {
+        txd.set_as_alt(txd.alt_num());
+        set_clock_pins(&bclk, &fclk, &mclk);
 
-        let regs = T::regs();
-
-        // Disable I2S first
-        regs.ctrl().modify(|w| w.set_i2s_en(false));
+        let line = txd.line();
+        check_dma_buf(&config, line, dma_buf)?;
+        configure::<T>(&config, Some(line), None, true)?;
 
-        // Reset TX
-        regs.ctrl().modify(|w| {
-            w.set_sftrst_tx(true);
-            w.set_txfifoclr(true);
-        });
-        regs.ctrl().modify(|w| {
-            w.set_sftrst_tx(false);
-            w.set_txfifoclr(false);
-        });
-
-        // Configure FIFO threshold
-        regs.fifo_thresh().modify(|w| {
-            w.set_tx(config.tx_fifo_threshold);
-            w.set_rx(config.rx_fifo_threshold);
-        });
-
-        // Configure I2S format
-        regs.cfgr().modify(|w| {
-            w.set_datsiz(config.format.data_size());
-            w.set_chsiz(config.format.channel_size());
-            w.set_std(config.standard.to_pac());
-            w.set_tdm_en(config.enable_tdm);
-            w.set_ch_max(config.channel_num);
-        });
-
-        // Configure MCLK output
-        regs.misc_cfgr().modify(|w| {
-            w.set_mclkoe(config.master_clock);
-        });
-
-        // Set slot mask for TX line 0
-        regs.txdslot(0).write(|w| w.set_en(config.channel_slot_mask as u16));
-
-        // Enable TX line 0 and TX DMA
-        regs.ctrl().modify(|w| {
-            w.set_tx_en(1); // Enable line 0
-            w.set_tx_dma_en(true); // Enable DMA request
-        });
+        let regs = T::regs();
 
         // Get DMA request number
         let request = dma_ch.request();
 
         // Get I2S TXD FIFO address
-        let txd_addr = regs.txd(0).as_ptr() as *mut u32;
+        let txd_addr = regs.txd(line as usize).as_ptr() as *mut u32;
 
         // Create DMA ring buffer
         let ringbuf = unsafe {
             WritableRingBuffer::new(dma_ch, request, dma_buf, txd_addr, TransferOptions::default())
         };
 
-        Self {
+        Ok(Self {
             _peri: peri,
             ringbuf,
             config,
-        }
+        })
     }
 
     /// Start I2S TX and DMA
@@ -489,13 +815,24 @@ impl<'d, T: Instance> I2STxDma<'d, T> {
     /// Write samples to the ring buffer
     ///
     /// Returns (samples_written, samples_remaining_to_write)
-    pub fn write(&mut self, buf: &[u32]) -> Result<(usize, usize), dma::ringbuffer::Error> {
-        self.ringbuf.write(buf)
+    ///
+    /// Returns [`Error::Underrun`] without writing if the DMA ran out of samples since the last write.
+    /// The ring buffer is resynced then, write again to continue.
+    pub fn write(&mut self, buf: &[u32]) -> Result<(usize, usize), Error> {
+        if self.ringbuf.take_underrun() {
+            return Err(Error::Underrun);
+        }
+        self.ringbuf.write(buf).map_err(|_| Error::Underrun)
     }
 
-    /// Write all samples (async)
-    pub async fn write_exact(&mut self, buf: &[u32]) -> Result<usize, dma::ringbuffer::Error> {
-        self.ringbuf.write_exact(buf).await
+    /// Write all samples, waiting for space in the ring buffer (async)
+    ///
+    /// Underruns are reported like [`Self::write`].
+    pub async fn write_exact(&mut self, buf: &[u32]) -> Result<usize, Error> {
+        if self.ringbuf.take_underrun() {
+            return Err(Error::Underrun);
+        }
+        self.ringbuf.write_exact(buf).await.map_err(|_| Error::Underrun)
     }
 
     /// Get available space in the buffer
@@ -526,3 +863,284 @@ impl<T: Instance> Drop for I2STxDma<'_, T> {
     }
 }
 
+// - MARK: I2S RX DMA Driver
+
+/// I2S RX with DMA support (I2S peripheral to memory)
+///
+/// This driver uses a ring buffer for continuous audio capture.
+#[cfg(ip_feature_dma_v2)]
+pub struct I2SRxDma<'d, T: Instance> {
+    _peri: Peri<'d, T>,
+    ringbuf: ReadableRingBuffer<'d, u32>,
+    config: Config,
+}
+
+#[cfg(ip_feature_dma_v2)]
+impl<'d, T: Instance> I2SRxDma<'d, T> {
+    /// Create a new I2S RX DMA driver
+    ///
+    /// # Arguments
+    /// * `peri` - I2S peripheral instance
+    /// * `rxd` - RX data pin, its line is used for reception
+    /// * `bclk`, `fclk`, `mclk` - Clock pins, inputs in slave mode
+    /// * `dma_ch` - DMA channel with RX DMA capability
+    /// * `dma_buf` - DMA buffer (must be in noncacheable memory, 4-byte aligned)
+    /// * `config` - I2S configuration
+    #[allow(clippy::too_many_arguments)]
+    pub fn new<DMA: Channel + RxDma<T>>(
+        peri: Peri<'d, T>,
+        rxd: Peri<'d, impl RxdPin<T>>,
+        bclk: Peri<'d, impl BclkPin<T>>,
+        fclk: Peri<'d, impl FclkPin<T>>,
+        mclk: Peri<'d, impl MclkPin<T>>,
+        dma_ch: Peri<'d, DMA>,
+        dma_buf: &'d mut [u32],
+        config: Config,
+    ) -> Result<Self, Error> {
+        rxd.set_as_alt(rxd.alt_num());
+        set_clock_pins(&bclk, &fclk, &mclk);
+
+        let line = rxd.line();
+        check_dma_buf(&config, line, dma_buf)?;
+        configure::<T>(&config, None, Some(line), true)?;
+
+        // Get DMA request number
+        let request = dma_ch.request();
+
+        // Get I2S RXD FIFO address
+        let rxd_addr = T::regs().rxd(line as usize).as_ptr() as *mut u32;
+
+        // Create DMA ring buffer
+        let ringbuf = unsafe {
+            ReadableRingBuffer::new(dma_ch, request, rxd_addr, dma_buf, TransferOptions::default())
+        };
+
+        Ok(Self {
+            _peri: peri,
+            ringbuf,
+            config,
+        })
+    }
+
+    /// Start I2S RX and DMA
+    pub fn start(&mut self) {
+        // Start DMA first
+        self.ringbuf.start();
+
+        // Enable I2S
+        T::regs().ctrl().modify(|w| w.set_i2s_en(true));
+    }
+
+    /// Stop I2S RX and DMA
+    pub fn stop(&mut self) {
+        self.ringbuf.request_pause();
+        T::regs().ctrl().modify(|w| w.set_i2s_en(false));
+    }
+
+    /// Clear the ring buffer (reset read position)
+    ///
+    /// Call this after an overrun error to resync with DMA.
+    pub fn clear(&mut self) {
+        self.ringbuf.clear();
+    }
+
+    /// Read samples from the ring buffer
+    ///
+    /// Returns (samples_read, samples_remaining)
+    ///
+    /// Returns [`Error::Overrun`] if the DMA overwrote unread samples, the ring buffer is resynced then.
+    pub fn read(&mut self, buf: &mut [u32]) -> Result<(usize, usize), Error> {
+        self.ringbuf.read(buf).map_err(|_| Error::Overrun)
+    }
+
+    /// Read exact number of samples, waiting for the DMA (async)
+    ///
+    /// Overruns are reported like [`Self::read`].
+    pub async fn read_exact(&mut self, buf: &mut [u32]) -> Result<usize, Error> {
+        self.ringbuf.read_exact(buf).await.map_err(|_| Error::Overrun)
+    }
+
+    /// Get number of readable samples
+    pub fn len(&mut self) -> Result<usize, dma::ringbuffer::Error> {
+        self.ringbuf.len()
+    }
+
+    /// Get buffer capacity
+    pub const fn capacity(&self) -> usize {
+        self.ringbuf.capacity()
+    }
+
+    /// Check if DMA is running
+    pub fn is_running(&self) -> bool {
+        self.ringbuf.is_running()
+    }
+
+    /// Get current configuration
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+}
+
+#[cfg(ip_feature_dma_v2)]
+impl<T: Instance> Drop for I2SRxDma<'_, T> {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+// - MARK: I2S full-duplex DMA Driver
+
+/// I2S full-duplex with DMA support
+///
+/// TX and RX share BCLK/FCLK and are started together, so a received frame and the frame sent
+/// at the same time stay aligned, e.g. for echo cancellation.
+#[cfg(ip_feature_dma_v2)]
+pub struct I2SFullDuplexDma<'d, T: Instance> {
+    _peri: Peri<'d, T>,
+    tx_ringbuf: WritableRingBuffer<'d, u32>,
+    rx_ringbuf: ReadableRingBuffer<'d, u32>,
+    config: Config,
+}
+
+#[cfg(ip_feature_dma_v2)]
+impl<'d, T: Instance> I2SFullDuplexDma<'d, T> {
+    /// Create a new I2S full-duplex DMA driver
+    ///
+    /// # Arguments
+    /// * `peri` - I2S peripheral instance
+    /// * `txd`, `rxd` - TX and RX data pins
+    /// * `bclk`, `fclk`, `mclk` - Clock pins, inputs in slave mode
+    /// * `tx_dma`, `rx_dma` - DMA channels with TX and RX DMA capability
+    /// * `tx_buf`, `rx_buf` - DMA buffers (must be in noncacheable memory, 4-byte aligned)
+    /// * `config` - I2S configuration
+    #[allow(clippy::too_many_arguments)]
+    pub fn new<TXDMA: Channel + TxDma<T>, RXDMA: Channel + RxDma<T>>(
+        peri: Peri<'d, T>,
+        txd: Peri<'d, impl TxdPin<T>>,
+        rxd: Peri<'d, impl RxdPin<T>>,
+        bclk: Peri<'d, impl BclkPin<T>>,
+        fclk: Peri<'d, impl FclkPin<T>>,
+        mclk: Peri<'d, impl MclkPin<T>>,
+        tx_dma: Peri<'d, TXDMA>,
+        tx_buf: &'d mut [u32],
+        rx_dma: Peri<'d, RXDMA>,
+        rx_buf: &'d mut [u32],
+        config: Config,
+    ) -> Result<Self, Error> {
+        txd.set_as_alt(txd.alt_num());
+        rxd.set_as_alt(rxd.alt_num());
+        set_clock_pins(&bclk, &fclk, &mclk);
+
+        let tx_line = txd.line();
+        let rx_line = rxd.line();
+        check_dma_buf(&config, tx_line, tx_buf)?;
+        check_dma_buf(&config, rx_line, rx_buf)?;
+        configure::<T>(&config, Some(tx_line), Some(rx_line), true)?;
+
+        let regs = T::regs();
+
+        let tx_request = tx_dma.request();
+        let rx_request = rx_dma.request();
+
+        let txd_addr = regs.txd(tx_line as usize).as_ptr() as *mut u32;
+        let rxd_addr = regs.rxd(rx_line as usize).as_ptr() as *mut u32;
+
+        let tx_ringbuf = unsafe {
+            WritableRingBuffer::new(tx_dma, tx_request, tx_buf, txd_addr, TransferOptions::default())
+        };
+        let rx_ringbuf = unsafe {
+            ReadableRingBuffer::new(rx_dma, rx_request, rxd_addr, rx_buf, TransferOptions::default())
+        };
+
+        Ok(Self {
+            _peri: peri,
+            tx_ringbuf,
+            rx_ringbuf,
+            config,
+        })
+    }
+
+    /// Start I2S TX/RX and both DMA channels
+    pub fn start(&mut self) {
+        // Start DMA first
+        self.tx_ringbuf.start();
+        self.rx_ringbuf.start();
+
+        // Enable I2S
+        T::regs().ctrl().modify(|w| w.set_i2s_en(true));
+    }
+
+    /// Stop I2S TX/RX and both DMA channels
+    pub fn stop(&mut self) {
+        self.tx_ringbuf.request_pause();
+        self.rx_ringbuf.request_pause();
+        T::regs().ctrl().modify(|w| w.set_i2s_en(false));
+    }
+
+    /// Clear both ring buffers
+    pub fn clear(&mut self) {
+        self.tx_ringbuf.clear();
+        self.rx_ringbuf.clear();
+    }
+
+    /// Write samples to the TX ring buffer
+    ///
+    /// Returns (samples_written, samples_remaining_to_write)
+    ///
+    /// Returns [`Error::Underrun`] without writing if the DMA ran out of samples since the last write.
+    /// The ring buffer is resynced then, write again to continue.
+    pub fn write(&mut self, buf: &[u32]) -> Result<(usize, usize), Error> {
+        if self.tx_ringbuf.take_underrun() {
+            return Err(Error::Underrun);
+        }
+        self.tx_ringbuf.write(buf).map_err(|_| Error::Underrun)
+    }
+
+    /// Write all samples, waiting for space in the ring buffer (async)
+    ///
+    /// Underruns are reported like [`Self::write`].
+    pub async fn write_exact(&mut self, buf: &[u32]) -> Result<usize, Error> {
+        if self.tx_ringbuf.take_underrun() {
+            return Err(Error::Underrun);
+        }
+        self.tx_ringbuf.write_exact(buf).await.map_err(|_| Error::Underrun)
+    }
+
+    /// Read samples from the RX ring buffer
+    ///
+    /// Returns (samples_read, samples_remaining)
+    ///
+    /// Returns [`Error::Overrun`] if the DMA overwrote unread samples, the ring buffer is resynced then.
+    pub fn read(&mut self, buf: &mut [u32]) -> Result<(usize, usize), Error> {
+        self.rx_ringbuf.read(buf).map_err(|_| Error::Overrun)
+    }
+
+    /// Read exact number of samples, waiting for the DMA (async)
+    ///
+    /// Overruns are reported like [`Self::read`].
+    pub async fn read_exact(&mut self, buf: &mut [u32]) -> Result<usize, Error> {
+        self.rx_ringbuf.read_exact(buf).await.map_err(|_| Error::Overrun)
+    }
+
+    /// Get available space in the TX buffer
+    pub fn tx_len(&mut self) -> Result<usize, dma::ringbuffer::Error> {
+        self.tx_ringbuf.len()
+    }
+
+    /// Get number of readable samples in the RX buffer
+    pub fn rx_len(&mut self) -> Result<usize, dma::ringbuffer::Error> {
+        self.rx_ringbuf.len()
+    }
+
+    /// Get current configuration
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+}
+
+#[cfg(ip_feature_dma_v2)]
+impl<T: Instance> Drop for I2SFullDuplexDma<'_, T> {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -8,6 +8,9 @@
 //!
 //! Note: PDM internally uses I2S0 for data reception. When PDM is active,
 //! I2S0 cannot be used for other purposes.
+//!
+//! Raw samples carry their channel ID, [`demux`] splits them per channel of a [`ChannelMask`],
+//! [`Demux`] additionally applies the per-channel gain/shift and DC removal of [`Config`].
 
 use embassy_hal_internal::{Peri, PeripheralType};
 
@@ -29,6 +32,8 @@ pub enum Error {
     InvalidConfig,
     /// DMA overrun
     Overrun,
+    /// Channel number or channel mask beyond [`CHANNEL_COUNT`]
+    InvalidChannel,
 }
 
 /// CIC Sigma-Delta filter order
@@ -143,8 +148,8 @@ impl Default for ChannelPolarity {
 
 /// Common audio sample rates for PDM
 ///
-/// These rates work with the default 24.576MHz MCLK (PLL3/25).
-/// For 44.1kHz series, a different MCLK would be needed.
+/// The 8/16/32/48kHz family works with the default 24.576MHz MCLK. For the 44.1kHz family
+/// and custom rates, the I2S0 audio clock (AUD0) is planned for the rate, see [`Config::use_audio_pll`].
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum SampleRate {
@@ -154,6 +159,12 @@ pub enum SampleRate {
     Hz16000,
     /// 32000 Hz - High quality voice
     Hz32000,
+    /// 44100 Hz - CD quality
+    Hz44100,
+    /// 48000 Hz - Professional audio
+    Hz48000,
+    /// Custom sample rate in Hz
+    Custom(u32),
 }
 
 impl SampleRate {
@@ -163,48 +174,11 @@ impl SampleRate {
             SampleRate::Hz8000 => 8000,
             SampleRate::Hz16000 => 16000,
             SampleRate::Hz32000 => 32000,
+            SampleRate::Hz44100 => 44100,
+            SampleRate::Hz48000 => 48000,
+            SampleRate::Custom(hz) => hz,
         }
     }
-
-    /// Get the PDM clock half divider (pdm_clk_hfdiv) for this sample rate
-    ///
-    /// Assumes MCLK = 24.576 MHz and CIC decimation ratio = 64
-    ///
-    /// Formula varies by PDM version:
-    /// - PDM LITE (HPM6E00/HPM6P00): sample_rate = MCLK / (2 * (hfdiv + 1)) / cic_ratio
-    /// - Standard PDM (HPM6700/etc): sample_rate = MCLK / (2 * (hfdiv + 1)) / cic_ratio / 3
-    pub(crate) const fn pdm_clk_hfdiv(self, cic_ratio: u8) -> u8 {
-        const MCLK: u32 = 24_576_000;
-
-        // PDM LITE (HPM6E00, HPM6P00) does NOT have DEC_AFTER_CIC factor
-        // Standard PDM (HPM6700, HPM6800, HPM6300) has DEC_AFTER_CIC = 3
-        #[cfg(any(hpm6e, hpm6p))]
-        let k = self.hz() * 2 * cic_ratio as u32;
-
-        #[cfg(not(any(hpm6e, hpm6p)))]
-        let k = self.hz() * 2 * cic_ratio as u32 * 3; // DEC_AFTER_CIC = 3
-
-        ((MCLK / k) - 1) as u8
-    }
-
-    /// Get the I2S BCLK divider for this sample rate
-    ///
-    /// Assumes MCLK = 24.576 MHz, TDM mode with 8 channels, 32-bit per channel
-    /// BCLK = sample_rate * 32 * 8 = sample_rate * 256
-    /// BCLK_DIV = MCLK / BCLK
-    pub(crate) const fn bclk_div(self) -> u16 {
-        const MCLK: u32 = 24_576_000;
-        const BITS_PER_FRAME: u32 = 32 * 8; // 8 channels, 32-bit each
-        let bclk = self.hz() * BITS_PER_FRAME;
-        (MCLK / bclk) as u16
-    }
-
-    /// Check if the sample rate configuration is valid
-    pub(crate) const fn is_valid(self, cic_ratio: u8) -> bool {
-        let hfdiv = self.pdm_clk_hfdiv(cic_ratio);
-        // hfdiv must be 1-15 (0 means bypass, not recommended)
-        hfdiv >= 1 && hfdiv <= 15
-    }
 }
 
 impl Default for SampleRate {
@@ -235,6 +209,17 @@ pub struct Config {
     pub enable_clock_output: bool,
     /// SOF at DAO reference clock falling edge
     pub sof_at_falling_edge: bool,
+    /// Allow reprogramming the audio PLL (PLL2 on HPM6E00) when no existing clock gives the sample rate
+    ///
+    /// This also moves every other clock derived from the PLL, e.g. the DAO clock (AUD1).
+    /// See [`crate::sysctl::plan_audio_clock`].
+    pub use_audio_pll: bool,
+    /// Per channel gain in Q8 (256 = unity), applied by `read_channels` and [`Demux`]
+    pub channel_gain: [u16; CHANNEL_COUNT],
+    /// Per channel shift, positive to the left, applied before the gain
+    pub channel_shift: [i8; CHANNEL_COUNT],
+    /// Remove the DC offset of each channel with a high-pass filter
+    pub dc_removal: bool,
 }
 
 impl Default for Config {
@@ -249,6 +234,10 @@ impl Default for Config {
             capture_delay: 1,
             enable_clock_output: true,
             sof_at_falling_edge: true,
+            use_audio_pll: false,
+            channel_gain: [GAIN_UNITY; CHANNEL_COUNT],
+            channel_shift: [0; CHANNEL_COUNT],
+            dc_removal: false,
         }
     }
 }
@@ -259,6 +248,32 @@ impl Config {
         self.sample_rate = rate;
         self
     }
+
+    /// Set the CIC decimation ratio, filter order and post-scale shift
+    pub const fn with_cic(mut self, decimation_ratio: u8, order: SigmaDeltaOrder, post_scale: u8) -> Self {
+        self.cic_decimation_ratio = decimation_ratio;
+        self.sigma_delta_order = order;
+        self.post_scale = post_scale;
+        self
+    }
+
+    /// Set the software gain (Q8, 256 = unity) and shift of a channel
+    ///
+    /// Returns [`Error::InvalidChannel`] if `channel` is not below [`CHANNEL_COUNT`].
+    pub const fn with_channel_gain(mut self, channel: usize, gain: u16, shift: i8) -> Result<Self, Error> {
+        if channel >= CHANNEL_COUNT {
+            return Err(Error::InvalidChannel);
+        }
+        self.channel_gain[channel] = gain;
+        self.channel_shift[channel] = shift;
+        Ok(self)
+    }
+
+    /// Enable DC offset removal in [`Demux`]
+    pub const fn with_dc_removal(mut self, enable: bool) -> Self {
+        self.dc_removal = enable;
+        self
+    }
 }
 
 // - MARK: Pin traits
@@ -296,6 +311,96 @@ pub trait Instance: SealedInstance + PeripheralType + crate::sysctl::ClockPeriph
 /// PDM LITE (HPM6E00/HPM6P00) does NOT have this factor
 #[cfg(pdm_v67)]
 const DEC_AFTER_CIC: u32 = 3;
+#[cfg(not(pdm_v67))]
+const DEC_AFTER_CIC: u32 = 1;
+
+/// Number of channels, 8 microphones + 2 DAO references
+pub const CHANNEL_COUNT: usize = 10;
+
+/// Unity gain for [`Config::channel_gain`]
+pub const GAIN_UNITY: u16 = 256;
+
+/// Bits per frame of the I2S0 stream carrying PDM data, TDM with 8 slots of 32 bits
+const BITS_PER_FRAME: u32 = 32 * 8;
+
+// - MARK: Clock
+
+/// MCLK cycles per output sample for PDM clock divider `div` (pdm_clk_hfdiv + 1)
+///
+/// Standard PDM: sample_rate = MCLK / (2 * (hfdiv + 1)) / cic_ratio / 3
+/// PDM LITE: sample_rate = MCLK / (2 * (hfdiv + 1)) / cic_ratio
+const fn mclk_multiple(cic_ratio: u8, div: u32) -> u32 {
+    2 * div * cic_ratio as u32 * DEC_AFTER_CIC
+}
+
+/// Route AUD0 to I2S0, which receives the PDM data
+#[cfg(i2s)]
+fn select_i2s0_clock() {
+    // I2sClkMux::I2S0 means AUD0 clock (the naming is confusing in hardware)
+    #[cfg(hpm67)]
+    crate::sysctl::set_i2s_clock_source(0, crate::sysctl::I2sClkMux::I2S0);
+    #[cfg(hpm6e)]
+    crate::sysctl::set_i2s_clock_source(0, true);
+}
+
+/// Current MCLK of I2S0
+#[cfg(i2s)]
+fn i2s0_mclk() -> u32 {
+    #[cfg(hpm6e)]
+    let mclk = crate::sysctl::get_i2s_clock_freq(0).0;
+    #[cfg(hpm67)]
+    let mclk = crate::sysctl::get_audio_clock_freq(0).0;
+    #[cfg(not(any(hpm6e, hpm67)))]
+    let mclk = 24_576_000u32;
+    mclk
+}
+
+/// Select the PDM clock divider for the sample rate, planning the audio clock if needed
+///
+/// The PDM output rate and the I2S0 frame rate (MCLK / BCLK_DIV / 256) must match exactly,
+/// so only dividers giving a MCLK multiple of 256 are usable. AUD0 is only re-planned from the
+/// existing clocks, the audio PLL is left alone unless [`Config::use_audio_pll`] is set.
+///
+/// Returns (pdm_clk_hfdiv, bclk_div), or `None` if the rate is not reachable.
+#[cfg(i2s)]
+fn setup_clocks(config: &Config) -> Option<(u8, u16)> {
+    let rate = config.sample_rate.hz();
+    let cic_ratio = config.cic_decimation_ratio;
+    // hfdiv 1..=15
+    let divs = (2..=16u32).filter(|&div| mclk_multiple(cic_ratio, div) % BITS_PER_FRAME == 0);
+
+    select_i2s0_clock();
+    let mclk = i2s0_mclk();
+    let div = divs
+        .clone()
+        .find(|&div| rate.checked_mul(mclk_multiple(cic_ratio, div)) == Some(mclk));
+
+    #[cfg(any(hpm6e, hpm67))]
+    let div = div.or_else(|| {
+        use crate::sysctl;
+
+        let (div, plan) = divs
+            .filter_map(|div| {
+                let plan = sysctl::plan_audio_clock(crate::time::Hertz(rate), mclk_multiple(cic_ratio, div))?;
+                #[cfg(hpm6e)]
+                if plan.pll.is_some() && !config.use_audio_pll {
+                    return None;
+                }
+                Some((div, plan))
+            })
+            .min_by_key(|(_, plan)| plan.error_ppm().unsigned_abs())?;
+
+        sysctl::apply_audio_clock(0, &plan);
+        if plan.error_ppm() != 0 {
+            #[cfg(feature = "defmt")]
+            defmt::warn!("PDM: MCLK {} Hz, {} ppm off", plan.mclk.0, plan.error_ppm());
+        }
+        Some(div)
+    });
+
+    let div = div?;
+    Some(((div - 1) as u8, (mclk_multiple(cic_ratio, div) / BITS_PER_FRAME) as u16))
+}
 
 // - MARK: Driver
 
@@ -310,6 +415,7 @@ pub struct Pdm<'d, T: Instance> {
     config: Config,
     /// Bitmask of enabled data lines (D0=bit0, D1=bit1, etc.)
     enabled_lines: u8,
+    demux: Demux,
 }
 
 impl<'d, T: Instance> Pdm<'d, T> {
@@ -317,6 +423,9 @@ impl<'d, T: Instance> Pdm<'d, T> {
     ///
     /// This configures I2S0 internally for PDM reception.
     /// I2S0 will be exclusively owned by PDM until dropped.
+    ///
+    /// Returns [`Error::InvalidConfig`] if the sample rate can't be reached with the CIC ratio.
+    /// Returns [`Error::InvalidChannel`] if the channel mask has bits above channel 9.
     #[cfg(i2s)]
     pub fn new(
         peri: Peri<'d, T>,
@@ -324,7 +433,9 @@ impl<'d, T: Instance> Pdm<'d, T> {
         clk: Peri<'d, impl ClkPin<T>>,
         d0: Peri<'d, impl DPin<T>>,
         config: Config,
-    ) -> Self {
+    ) -> Result<Self, Error> {
+        let demux = Demux::new(&config)?;
+
         // Enable peripheral clocks
         T::add_resource_group(0);
         crate::sysctl::clock_add_to_group(crate::pac::resources::I2S0, 0);
@@ -340,10 +451,11 @@ impl<'d, T: Instance> Pdm<'d, T> {
             _i2s0: i2s0,
             config,
             enabled_lines: 0b0001, // D0 only
+            demux,
         };
 
-        this.configure();
-        this
+        this.configure()?;
+        Ok(this)
     }
 
     /// Create a new PDM driver with 2 data lines (4 channels max)
@@ -358,7 +470,9 @@ impl<'d, T: Instance> Pdm<'d, T> {
         d0: Peri<'d, impl DPin<T>>,
         d1: Peri<'d, impl DPin<T>>,
         config: Config,
-    ) -> Self {
+    ) -> Result<Self, Error> {
+        let demux = Demux::new(&config)?;
+
         // Enable peripheral clocks
         T::add_resource_group(0);
         crate::sysctl::clock_add_to_group(crate::pac::resources::I2S0, 0);
@@ -376,10 +490,11 @@ impl<'d, T: Instance> Pdm<'d, T> {
             _i2s0: i2s0,
             config,
             enabled_lines: 0b0011, // D0 + D1
+            demux,
         };
 
-        this.configure();
-        this
+        this.configure()?;
+        Ok(this)
     }
 
     /// Create a new PDM driver with 4 data lines (8 channels max)
@@ -399,7 +514,9 @@ impl<'d, T: Instance> Pdm<'d, T> {
         d2: Peri<'d, impl DPin<T>>,
         d3: Peri<'d, impl DPin<T>>,
         config: Config,
-    ) -> Self {
+    ) -> Result<Self, Error> {
+        let demux = Demux::new(&config)?;
+
         // Enable peripheral clocks
         T::add_resource_group(0);
         crate::sysctl::clock_add_to_group(crate::pac::resources::I2S0, 0);
@@ -421,10 +538,11 @@ impl<'d, T: Instance> Pdm<'d, T> {
             _i2s0: i2s0,
             config,
             enabled_lines: 0b1111, // D0 + D1 + D2 + D3
+            demux,
         };
 
-        this.configure();
-        this
+        this.configure()?;
+        Ok(this)
     }
 
     /// Configure a pin with proper IOC/PIOC/BIOC handling
@@ -457,9 +575,20 @@ impl<'d, T: Instance> Pdm<'d, T> {
         pin.ioc_pad().func_ctl().modify(|w| w.set_alt_select(alt));
     }
 
-    fn configure(&self) {
+    fn configure(&self) -> Result<(), Error> {
         let regs = T::regs();
 
+        #[cfg(i2s)]
+        let (pdm_clk_hfdiv, bclk_div) = setup_clocks(&self.config).ok_or(Error::InvalidConfig)?;
+        #[cfg(not(i2s))]
+        let pdm_clk_hfdiv = {
+            let k = self.config.sample_rate.hz() * mclk_multiple(self.config.cic_decimation_ratio, 1);
+            match 24_576_000u32.checked_div(k) {
+                Some(div @ 2..=16) => (div - 1) as u8,
+                _ => return Err(Error::InvalidConfig),
+            }
+        };
+
         // Stop PDM first
         regs.run().modify(|w| w.set_pdm_en(false));
 
@@ -472,7 +601,6 @@ impl<'d, T: Instance> Pdm<'d, T> {
         // PDM_CLK_HFDIV calculated from sample rate
         // Standard PDM: sample_rate = MCLK / (2 * (div + 1)) / cic_dec_ratio / 3
         // PDM LITE: sample_rate = MCLK / (2 * (div + 1)) / cic_dec_ratio
-        let pdm_clk_hfdiv = self.config.sample_rate.pdm_clk_hfdiv(self.config.cic_decimation_ratio);
         regs.ctrl().write(|w| {
             // Note: HPF requires proper coefficient setup, skip for now
             w.set_sof_fedge(self.config.sof_at_falling_edge);
@@ -503,24 +631,17 @@ impl<'d, T: Instance> Pdm<'d, T> {
 
         // Configure I2S0 for PDM reception
         #[cfg(i2s)]
-        self.configure_i2s0_for_pdm();
+        self.configure_i2s0_for_pdm(bclk_div);
+
+        Ok(())
     }
 
     #[cfg(i2s)]
-    fn configure_i2s0_for_pdm(&self) {
+    fn configure_i2s0_for_pdm(&self, bclk_div: u16) {
         use crate::pac::i2s::vals::{ChannelSize, DataSize, Std};
 
         let i2s = crate::pac::I2S0;
 
-        // Configure I2S0 clock source to AUD0
-        // I2sClkMux::I2S0 means AUD0 clock (the naming is confusing in hardware)
-        #[cfg(hpm67)]
-        crate::sysctl::set_i2s_clock_source(0, crate::sysctl::I2sClkMux::I2S0);
-
-        // HPM6E00: set I2S0 to use AUD0 clock
-        #[cfg(hpm6e)]
-        crate::sysctl::set_i2s_clock_source(0, true);
-
         // Disable I2S first
         i2s.ctrl().modify(|w| w.set_i2s_en(false));
 
@@ -534,11 +655,7 @@ impl<'d, T: Instance> Pdm<'d, T> {
             w.set_rxfifoclr(false);
         });
 
-        // Calculate BCLK divider from sample rate
-        // BCLK = sample_rate * 32bits * 8ch
-        // BCLK_DIV = MCLK / BCLK
-        let bclk_div = self.config.sample_rate.bclk_div();
-
+        // BCLK = sample_rate * 32bits * 8ch, BCLK_DIV from setup_clocks
         // Configure I2S format for PDM: TDM mode, 8 channels, 32-bit, MSB justified
         // Gate BCLK before configuration
         i2s.cfgr().modify(|w| w.set_bclk_gateoff(true));
@@ -587,30 +704,49 @@ impl<'d, T: Instance> Pdm<'d, T> {
     /// Standard PDM: Sample rate = MCLK / (2 * (div + 1)) / cic_dec_ratio / 3
     /// PDM LITE: Sample rate = MCLK / (2 * (div + 1)) / cic_dec_ratio
     ///
-    /// Returns error if the calculated divider is out of range (1-15).
+    /// The I2S0 BCLK divider is updated too, so the I2S0 frame rate keeps matching the PDM output.
+    ///
+    /// Returns error if the calculated divider is out of range (1-15), or if the resulting
+    /// MCLK multiple is not a whole number of I2S0 frames.
     pub fn set_sample_rate(&mut self, mclk_hz: u32, sample_rate: u32) -> Result<(), Error> {
-        let cic_ratio = self.config.cic_decimation_ratio as u32;
+        let cic_ratio = self.config.cic_decimation_ratio;
 
         // Calculate required divider
         // Standard PDM: div = mclk / (sample_rate * 2 * cic_ratio * 3) - 1
         // PDM LITE: div = mclk / (sample_rate * 2 * cic_ratio) - 1
-        #[cfg(pdm_v67)]
-        let k = sample_rate * 2 * cic_ratio * DEC_AFTER_CIC;
-        #[cfg(pdm_v6e)]
-        let k = sample_rate * 2 * cic_ratio;
+        let k = sample_rate
+            .checked_mul(mclk_multiple(cic_ratio, 1))
+            .filter(|&k| k > 0)
+            .ok_or(Error::InvalidConfig)?;
         let div = (mclk_hz + k / 2) / k; // Round to nearest
 
         if div < 2 || div > 16 {
             return Err(Error::InvalidConfig);
         }
 
-        let div = (div - 1) as u8;
+        #[cfg(i2s)]
+        let bclk_div = {
+            let multiple = mclk_multiple(cic_ratio, div);
+            if multiple % BITS_PER_FRAME != 0 {
+                return Err(Error::InvalidConfig);
+            }
+            (multiple / BITS_PER_FRAME) as u16
+        };
 
         let regs = T::regs();
         regs.ctrl().modify(|w| {
-            w.set_pdm_clk_hfdiv(div);
+            w.set_pdm_clk_hfdiv((div - 1) as u8);
         });
 
+        #[cfg(i2s)]
+        {
+            let i2s = crate::pac::I2S0;
+            i2s.cfgr().modify(|w| w.set_bclk_gateoff(true));
+            i2s.cfgr().modify(|w| w.set_bclk_div(bclk_div));
+            i2s.cfgr().modify(|w| w.set_bclk_gateoff(false));
+        }
+
+        self.config.sample_rate = SampleRate::Custom(sample_rate);
         Ok(())
     }
 
@@ -669,6 +805,26 @@ impl<'d, T: Instance> Pdm<'d, T> {
         }
     }
 
+    /// Read `raw.len()` samples (blocking) and demultiplex them into `out`
+    ///
+    /// Applies the channel gain, shift and DC removal of the [`Config`], see [`demux`] for the
+    /// `out` layout. Returns the number of samples written to each output buffer.
+    #[cfg(i2s)]
+    pub fn read_channels(&mut self, raw: &mut [u32], out: &mut [&mut [i32]]) -> [usize; CHANNEL_COUNT] {
+        self.read_blocking(raw);
+        self.demux.process(raw, out)
+    }
+
+    /// Set the gain (Q8, 256 = unity) and shift of a channel
+    ///
+    /// Returns [`Error::InvalidChannel`] if `channel` is not below [`CHANNEL_COUNT`].
+    pub fn set_channel_gain(&mut self, channel: usize, gain: u16, shift: i8) -> Result<(), Error> {
+        self.demux.set_channel_gain(channel, gain, shift)?;
+        self.config.channel_gain[channel] = gain;
+        self.config.channel_shift[channel] = shift;
+        Ok(())
+    }
+
     /// Clear error flags
     pub fn clear_errors(&mut self) {
         let regs = T::regs();
@@ -731,24 +887,129 @@ pub fn extract_channel_id(raw: u32) -> u8 {
 
 /// Demultiplex PDM samples by channel (for stereo: ch0 + ch4)
 pub fn demux_stereo(raw: &[u32], left: &mut [i32], right: &mut [i32]) {
-    let mut idx_left = 0;
-    let mut idx_right = 0;
+    demux(raw, ChannelMask::DUAL_STEREO, &mut [left, right]);
+}
 
-    for &sample in raw {
-        let ch = extract_channel_id(sample);
-        let value = extract_sample(sample);
+/// Demultiplex PDM samples of the channels in `channels`
+///
+/// `out` has one buffer per enabled channel, in ascending channel order, e.g. for
+/// `ChannelMask::QUAD`: ch0, ch1, ch4, ch5. Samples of other channels are dropped.
+///
+/// Returns the number of samples written to each output buffer.
+pub fn demux(raw: &[u32], channels: ChannelMask, out: &mut [&mut [i32]]) -> [usize; CHANNEL_COUNT] {
+    let mut written = [0; CHANNEL_COUNT];
 
-        match ch {
-            0 if idx_left < left.len() => {
-                left[idx_left] = value;
-                idx_left += 1;
+    for &sample in raw {
+        if let Some(idx) = output_index(channels, extract_channel_id(sample)) {
+            if let Some(buf) = out.get_mut(idx) {
+                if written[idx] < buf.len() {
+                    buf[written[idx]] = extract_sample(sample);
+                    written[idx] += 1;
+                }
             }
-            4 if idx_right < right.len() => {
-                right[idx_right] = value;
-                idx_right += 1;
+        }
+    }
+
+    written
+}
+
+/// Index of channel `ch` among the enabled channels, channels from [`CHANNEL_COUNT`] up are dropped
+fn output_index(channels: ChannelMask, ch: u8) -> Option<usize> {
+    if ch as usize >= CHANNEL_COUNT {
+        return None;
+    }
+    let bit = 1u16 << ch;
+    if channels.0 & bit == 0 {
+        return None;
+    }
+    Some((channels.0 & (bit - 1)).count_ones() as usize)
+}
+
+/// Multi-channel demultiplexer with per-channel gain, shift and DC removal
+///
+/// Keeps the DC filter state across calls, use one instance per stream.
+pub struct Demux {
+    channels: ChannelMask,
+    gain: [u16; CHANNEL_COUNT],
+    shift: [i8; CHANNEL_COUNT],
+    dc_removal: bool,
+    /// DC filter state: previous input and output
+    dc_state: [(i32, i32); CHANNEL_COUNT],
+}
+
+impl Demux {
+    /// DC filter pole, y[n] = x[n] - x[n-1] + (1 - 2^-DC_POLE_SHIFT) * y[n-1]
+    const DC_POLE_SHIFT: u32 = 10;
+
+    /// Create a demultiplexer from the channel and processing settings in `config`
+    ///
+    /// Returns [`Error::InvalidChannel`] if the channel mask has bits above channel 9.
+    pub fn new(config: &Config) -> Result<Self, Error> {
+        if config.channels.0 >> CHANNEL_COUNT != 0 {
+            return Err(Error::InvalidChannel);
+        }
+        Ok(Self {
+            channels: config.channels,
+            gain: config.channel_gain,
+            shift: config.channel_shift,
+            dc_removal: config.dc_removal,
+            dc_state: [(0, 0); CHANNEL_COUNT],
+        })
+    }
+
+    /// Set the gain (Q8, 256 = unity) and shift of a channel
+    ///
+    /// Returns [`Error::InvalidChannel`] if `channel` is not below [`CHANNEL_COUNT`].
+    pub fn set_channel_gain(&mut self, channel: usize, gain: u16, shift: i8) -> Result<(), Error> {
+        if channel >= CHANNEL_COUNT {
+            return Err(Error::InvalidChannel);
+        }
+        self.gain[channel] = gain;
+        self.shift[channel] = shift;
+        Ok(())
+    }
+
+    /// Demultiplex and process raw samples, see [`demux`] for the `out` layout
+    ///
+    /// Returns the number of samples written to each output buffer.
+    pub fn process(&mut self, raw: &[u32], out: &mut [&mut [i32]]) -> [usize; CHANNEL_COUNT] {
+        let mut written = [0; CHANNEL_COUNT];
+
+        for &sample in raw {
+            let ch = extract_channel_id(sample);
+            let Some(idx) = output_index(self.channels, ch) else {
+                continue;
+            };
+            let Some(buf) = out.get_mut(idx) else {
+                continue;
+            };
+            if written[idx] < buf.len() {
+                buf[written[idx]] = self.process_sample(ch as usize, extract_sample(sample));
+                written[idx] += 1;
             }
-            _ => {}
         }
+
+        written
+    }
+
+    fn process_sample(&mut self, ch: usize, value: i32) -> i32 {
+        let mut value = value as i64;
+        if self.dc_removal {
+            // 24-bit input, the filter output stays far inside i64 but may exceed i32 on steps
+            let (prev_in, prev_out) = self.dc_state[ch];
+            let (prev_in, prev_out) = (prev_in as i64, prev_out as i64);
+            let out = value - prev_in + prev_out - (prev_out >> Self::DC_POLE_SHIFT);
+            let out = out.clamp(i32::MIN as i64, i32::MAX as i64);
+            self.dc_state[ch] = (value as i32, out as i32);
+            value = out;
+        }
+
+        // Beyond 31 bits any i32 sample saturates or reaches 0 anyway
+        let shift = self.shift[ch];
+        let bits = shift.unsigned_abs().min(31) as u32;
+        let value = if shift >= 0 { value << bits } else { value >> bits };
+        let value = value.saturating_mul(self.gain[ch] as i64) >> 8;
+        value.clamp(i32::MIN as i64, i32::MAX as i64) as i32
     }
 }
 
@@ -766,6 +1027,7 @@ pub struct PdmDma<'d, T: Instance> {
     _i2s0: Peri<'d, crate::peripherals::I2S0>,
     ringbuf: ReadableRingBuffer<'d, u32>,
     config: Config,
+    demux: Demux,
 }
 
 #[cfg(all(i2s, not(ip_feature_dma_v2)))]
@@ -782,6 +1044,9 @@ impl<'d, T: Instance> PdmDma<'d, T> {
     /// - `dma_desc`: DMA linked descriptor (must remain valid, 8-byte aligned)
     /// - `config`: PDM configuration
     ///
+    /// Returns [`Error::InvalidConfig`] if the sample rate can't be reached with the CIC ratio.
+    /// Returns [`Error::InvalidChannel`] if the channel mask has bits above channel 9.
+    ///
     /// # Safety
     /// The DMA buffer and descriptor must remain valid for the lifetime of this driver.
     pub unsafe fn new(
@@ -793,7 +1058,9 @@ impl<'d, T: Instance> PdmDma<'d, T> {
         dma_buf: &'d mut [u32],
         dma_desc: &'d mut LinkedDescriptor,
         config: Config,
-    ) -> Self {
+    ) -> Result<Self, Error> {
+        let demux = Demux::new(&config)?;
+
         // Enable peripheral clocks
         T::add_resource_group(0);
         crate::sysctl::clock_add_to_group(crate::pac::resources::I2S0, 0);
@@ -808,7 +1075,7 @@ impl<'d, T: Instance> PdmDma<'d, T> {
         let pdm_regs = T::regs();
         pdm_regs.run().modify(|w| w.set_pdm_en(false));
 
-        let pdm_clk_hfdiv = config.sample_rate.pdm_clk_hfdiv(config.cic_decimation_ratio);
+        let (pdm_clk_hfdiv, bclk_div) = setup_clocks(&config).ok_or(Error::InvalidConfig)?;
         pdm_regs.ctrl().write(|w| {
             w.set_sof_fedge(config.sof_at_falling_edge);
             w.set_pdm_clk_oe(config.enable_clock_output);
@@ -832,7 +1099,7 @@ impl<'d, T: Instance> PdmDma<'d, T> {
         });
 
         // Configure I2S0 for PDM reception
-        Self::configure_i2s0_for_pdm(&config);
+        Self::configure_i2s0_for_pdm(&config, bclk_div);
 
         // Get I2S0 RX DMA request number from trait (auto-generated)
         let i2s0_rx_request = dma_ch.request();
@@ -850,27 +1117,20 @@ impl<'d, T: Instance> PdmDma<'d, T> {
             dma::TransferOptions::default(),
         );
 
-        Self {
+        Ok(Self {
             _peri: peri,
             _i2s0: i2s0,
             ringbuf,
             config,
-        }
+            demux,
+        })
     }
 
-    fn configure_i2s0_for_pdm(config: &Config) {
+    fn configure_i2s0_for_pdm(config: &Config, bclk_div: u16) {
         use crate::pac::i2s::vals::{ChannelSize, DataSize, Std};
 
         let i2s = crate::pac::I2S0;
 
-        // Configure I2S0 clock source to AUD0
-        #[cfg(hpm67)]
-        crate::sysctl::set_i2s_clock_source(0, crate::sysctl::I2sClkMux::I2S0);
-
-        // HPM6E00: set I2S0 to use AUD0 clock
-        #[cfg(hpm6e)]
-        crate::sysctl::set_i2s_clock_source(0, true);
-
         i2s.ctrl().modify(|w| w.set_i2s_en(false));
 
         i2s.ctrl().modify(|w| {
@@ -882,9 +1142,6 @@ impl<'d, T: Instance> PdmDma<'d, T> {
             w.set_rxfifoclr(false);
         });
 
-        // Calculate BCLK divider from sample rate
-        let bclk_div = config.sample_rate.bclk_div();
-
         i2s.cfgr().modify(|w| w.set_bclk_gateoff(true));
         i2s.cfgr().modify(|w| {
             w.set_bclk_div(bclk_div);
@@ -939,13 +1196,61 @@ impl<'d, T: Instance> PdmDma<'d, T> {
     /// Read samples from ring buffer
     ///
     /// Returns (samples_read, samples_remaining)
-    pub fn read(&mut self, buf: &mut [u32]) -> Result<(usize, usize), dma::ringbuffer::Error> {
-        self.ringbuf.read(buf)
+    ///
+    /// Returns [`Error::Overrun`] if the DMA overwrote unread samples, the ring buffer is resynced then.
+    /// Returns [`Error::FifoOverflow`] if PDM output samples were dropped, the flag is cleared then.
+    pub fn read(&mut self, buf: &mut [u32]) -> Result<(usize, usize), Error> {
+        self.check_fifo_overflow()?;
+        self.ringbuf.read(buf).map_err(|_| Error::Overrun)
     }
 
-    /// Read exact number of samples (async)
-    pub async fn read_exact(&mut self, buf: &mut [u32]) -> Result<usize, dma::ringbuffer::Error> {
-        self.ringbuf.read_exact(buf).await
+    /// Read exact number of samples, waiting for the DMA (async)
+    ///
+    /// Errors are reported like [`Self::read`].
+    pub async fn read_exact(&mut self, buf: &mut [u32]) -> Result<usize, Error> {
+        self.check_fifo_overflow()?;
+        self.ringbuf.read_exact(buf).await.map_err(|_| Error::Overrun)
+    }
+
+    /// Read samples from the ring buffer into `raw` and demultiplex them into `out`
+    ///
+    /// Applies the channel gain, shift and DC removal of the [`Config`], see [`demux`] for the
+    /// `out` layout. Returns the number of samples written to each output buffer.
+    /// Errors are reported like [`Self::read`].
+    pub fn read_channels(&mut self, raw: &mut [u32], out: &mut [&mut [i32]]) -> Result<[usize; CHANNEL_COUNT], Error> {
+        let (n, _) = self.read(raw)?;
+        Ok(self.demux.process(&raw[..n], out))
+    }
+
+    /// Fill `raw`, waiting for the DMA (async), and demultiplex it into `out`
+    ///
+    /// See [`Self::read_channels`].
+    pub async fn read_exact_channels(
+        &mut self,
+        raw: &mut [u32],
+        out: &mut [&mut [i32]],
+    ) -> Result<[usize; CHANNEL_COUNT], Error> {
+        let n = self.read_exact(raw).await?;
+        Ok(self.demux.process(&raw[..n], out))
+    }
+
+    /// Set the gain (Q8, 256 = unity) and shift of a channel
+    ///
+    /// Returns [`Error::InvalidChannel`] if `channel` is not below [`CHANNEL_COUNT`].
+    pub fn set_channel_gain(&mut self, channel: usize, gain: u16, shift: i8) -> Result<(), Error> {
+        self.demux.set_channel_gain(channel, gain, shift)?;
+        self.config.channel_gain[channel] = gain;
+        self.config.channel_shift[channel] = shift;
+        Ok(())
+    }
+
+    fn check_fifo_overflow(&mut self) -> Result<(), Error> {
+        let regs = T::regs();
+        if regs.st().read().ofifo_ovfl_err() {
+            regs.st().write(|w| w.set_ofifo_ovfl_err(true));
+            return Err(Error::FifoOverflow);
+        }
+        Ok(())
     }
 
     /// Get number of readable samples
@@ -1010,8 +1315,8 @@ pub struct PdmDma<'d, T: Instance> {
     _peri: Peri<'d, T>,
     _i2s0: Peri<'d, crate::peripherals::I2S0>,
     ringbuf: ReadableRingBuffer<'d, u32>,
-    #[allow(dead_code)]
     config: Config,
+    demux: Demux,
 }
 
 #[cfg(all(i2s, ip_feature_dma_v2))]
@@ -1027,6 +1332,9 @@ impl<'d, T: Instance> PdmDma<'d, T> {
     /// - `dma_buf`: DMA ring buffer (must remain valid)
     /// - `config`: PDM configuration
     ///
+    /// Returns [`Error::InvalidConfig`] if the sample rate can't be reached with the CIC ratio.
+    /// Returns [`Error::InvalidChannel`] if the channel mask has bits above channel 9.
+    ///
     /// # Safety
     /// The DMA buffer must remain valid for the lifetime of this driver.
     pub unsafe fn new(
@@ -1037,7 +1345,9 @@ impl<'d, T: Instance> PdmDma<'d, T> {
         dma_ch: Peri<'d, impl crate::i2s::RxDma<crate::peripherals::I2S0>>,
         dma_buf: &'d mut [u32],
         config: Config,
-    ) -> Self {
+    ) -> Result<Self, Error> {
+        let demux = Demux::new(&config)?;
+
         // Enable peripheral clocks
         T::add_resource_group(0);
         crate::sysctl::clock_add_to_group(crate::pac::resources::I2S0, 0);
@@ -1052,7 +1362,7 @@ impl<'d, T: Instance> PdmDma<'d, T> {
         let pdm_regs = T::regs();
         pdm_regs.run().modify(|w| w.set_pdm_en(false));
 
-        let pdm_clk_hfdiv = config.sample_rate.pdm_clk_hfdiv(config.cic_decimation_ratio);
+        let (pdm_clk_hfdiv, bclk_div) = setup_clocks(&config).ok_or(Error::InvalidConfig)?;
         pdm_regs.ctrl().write(|w| {
             w.set_sof_fedge(config.sof_at_falling_edge);
             w.set_pdm_clk_oe(config.enable_clock_output);
@@ -1076,7 +1386,7 @@ impl<'d, T: Instance> PdmDma<'d, T> {
         });
 
         // Configure I2S0 for PDM reception
-        Self::configure_i2s0_for_pdm(&config);
+        Self::configure_i2s0_for_pdm(&config, bclk_div);
 
         // Get I2S0 RX DMA request number from trait (auto-generated)
         let i2s0_rx_request = dma_ch.request();
@@ -1093,22 +1403,20 @@ impl<'d, T: Instance> PdmDma<'d, T> {
             TransferOptions::default(),
         );
 
-        Self {
+        Ok(Self {
             _peri: peri,
             _i2s0: i2s0,
             ringbuf,
             config,
-        }
+            demux,
+        })
     }
 
-    fn configure_i2s0_for_pdm(config: &Config) {
+    fn configure_i2s0_for_pdm(config: &Config, bclk_div: u16) {
         use crate::pac::i2s::vals::{ChannelSize, DataSize, Std};
 
         let i2s = crate::pac::I2S0;
 
-        // HPM6E00: set I2S0 to use AUD0 clock
-        crate::sysctl::set_i2s_clock_source(0, true);
-
         i2s.ctrl().modify(|w| w.set_i2s_en(false));
 
         i2s.ctrl().modify(|w| {
@@ -1120,9 +1428,6 @@ impl<'d, T: Instance> PdmDma<'d, T> {
             w.set_rxfifoclr(false);
         });
 
-        // Calculate BCLK divider from sample rate
-        let bclk_div = config.sample_rate.bclk_div();
-
         i2s.cfgr().modify(|w| w.set_bclk_gateoff(true));
         i2s.cfgr().modify(|w| {
             w.set_bclk_div(bclk_div);
@@ -1177,13 +1482,61 @@ impl<'d, T: Instance> PdmDma<'d, T> {
     /// Read samples from ring buffer
     ///
     /// Returns (samples_read, samples_remaining)
-    pub fn read(&mut self, buf: &mut [u32]) -> Result<(usize, usize), dma::ringbuffer::Error> {
-        self.ringbuf.read(buf)
+    ///
+    /// Returns [`Error::Overrun`] if the DMA overwrote unread samples, the ring buffer is resynced then.
+    /// Returns [`Error::FifoOverflow`] if PDM output samples were dropped, the flag is cleared then.
+    pub fn read(&mut self, buf: &mut [u32]) -> Result<(usize, usize), Error> {
+        self.check_fifo_overflow()?;
+        self.ringbuf.read(buf).map_err(|_| Error::Overrun)
+    }
+
+    /// Read exact number of samples, waiting for the DMA (async)
+    ///
+    /// Errors are reported like [`Self::read`].
+    pub async fn read_exact(&mut self, buf: &mut [u32]) -> Result<usize, Error> {
+        self.check_fifo_overflow()?;
+        self.ringbuf.read_exact(buf).await.map_err(|_| Error::Overrun)
+    }
+
+    /// Read samples from the ring buffer into `raw` and demultiplex them into `out`
+    ///
+    /// Applies the channel gain, shift and DC removal of the [`Config`], see [`demux`] for the
+    /// `out` layout. Returns the number of samples written to each output buffer.
+    /// Errors are reported like [`Self::read`].
+    pub fn read_channels(&mut self, raw: &mut [u32], out: &mut [&mut [i32]]) -> Result<[usize; CHANNEL_COUNT], Error> {
+        let (n, _) = self.read(raw)?;
+        Ok(self.demux.process(&raw[..n], out))
+    }
+
+    /// Fill `raw`, waiting for the DMA (async), and demultiplex it into `out`
+    ///
+    /// See [`Self::read_channels`].
+    pub async fn read_exact_channels(
+        &mut self,
+        raw: &mut [u32],
+        out: &mut [&mut [i32]],
+    ) -> Result<[usize; CHANNEL_COUNT], Error> {
+        let n = self.read_exact(raw).await?;
+        Ok(self.demux.process(&raw[..n], out))
+    }
+
+    /// Set the gain (Q8, 256 = unity) and shift of a channel
+    ///
+    /// Returns [`Error::InvalidChannel`] if `channel` is not below [`CHANNEL_COUNT`].
+    pub fn set_channel_gain(&mut self, channel: usize, gain: u16, shift: i8) -> Result<(), Error> {
+        self.demux.set_channel_gain(channel, gain, shift)?;
+        self.config.channel_gain[channel] = gain;
+        self.config.channel_shift[channel] = shift;
+        Ok(())
     }
 
-    /// Read exact number of samples (async)
-    pub async fn read_exact(&mut self, buf: &mut [u32]) -> Result<usize, dma::ringbuffer::Error> {
-        self.ringbuf.read_exact(buf).await
+    fn check_fifo_overflow(&mut self) -> Result<(), Error> {
+        let regs = T::regs();
+        if regs.st().read().ofifo_ovfl_err() {
+            regs.st().write(|w| w.set_ofifo_ovfl_err(true));
+            return Err(Error::FifoOverflow);
+        }
+        Ok(())
     }
 
     /// Get number of readable samples
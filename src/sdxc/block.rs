@@ -0,0 +1,319 @@
+//! Async block device over chained ADMA2 descriptor tables, and card-detect events
+
+use super::{CARD_DETECT_SIGNALS, DataBlock, Error, Sdxc, types};
+use crate::mode::Async;
+
+/// Largest ADMA2 transfer descriptor that is a whole number of blocks (16-bit length field)
+const MAX_DESC_BLOCKS: usize = 127;
+
+/// Fill `tables` with transfer descriptors for `blocks` contiguous blocks at `addr`
+///
+/// The last slot of every table except the final one used links to the next table.
+/// Returns the number of blocks covered, which is less than `blocks` when the tables are too small.
+/// Returns [`Error::InvalidArgument`] for no descriptor slots, or chained tables without room for
+/// a link descriptor.
+fn setup_chain<const N: usize>(tables: &mut [types::Adma2Table<N>], addr: u32, blocks: usize) -> Result<usize, Error> {
+    if tables.is_empty() || N == 0 || (tables.len() > 1 && N < 2) {
+        return Err(Error::InvalidArgument);
+    }
+
+    let mut covered = 0;
+    let count = tables.len();
+    for t in 0..count {
+        let last_table = t + 1 == count;
+        let slots = if last_table { N } else { N - 1 };
+
+        for slot in 0..slots {
+            let n = (blocks - covered).min(MAX_DESC_BLOCKS);
+            let end = covered + n == blocks || (last_table && slot + 1 == slots);
+            tables[t].descriptors[slot] =
+                types::Adma2Descriptor::transfer(addr + (covered * 512) as u32, (n * 512) as u16, end);
+            covered += n;
+            if end {
+                return Ok(covered);
+            }
+        }
+
+        let next = tables[t + 1].as_ptr() as u32;
+        tables[t].descriptors[N - 1] = types::Adma2Descriptor::link(next);
+    }
+    Ok(covered)
+}
+
+/// Card insertion state change reported by [`Sdxc::wait_card_event`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CardEvent {
+    /// A card was inserted, it must be initialized again before use
+    Inserted,
+    /// The card was removed
+    Removed,
+}
+
+impl<'d> Sdxc<'d, Async> {
+    /// Async read of any number of blocks, chaining `tables` for the ADMA2 descriptors
+    ///
+    /// Each descriptor covers up to 127 contiguous blocks, so a single `Adma2Table<2>` already
+    /// reads 254 blocks per command. Longer reads are split into several commands.
+    /// Returns [`Error::InvalidArgument`] if `tables` is empty, or if several tables are chained
+    /// and `N` is below 2.
+    pub async fn read_blocks_chained<const N: usize>(
+        &mut self,
+        block_idx: u32,
+        buffers: &mut [DataBlock],
+        tables: &mut [types::Adma2Table<N>],
+    ) -> Result<(), Error> {
+        let addr = buffers.as_mut_ptr() as u32;
+        self.transfer_chained(block_idx, addr, buffers.len(), tables, false)
+            .await
+    }
+
+    /// Async write of any number of blocks, chaining `tables` for the ADMA2 descriptors
+    ///
+    /// See [`Self::read_blocks_chained`].
+    pub async fn write_blocks_chained<const N: usize>(
+        &mut self,
+        block_idx: u32,
+        buffers: &[DataBlock],
+        tables: &mut [types::Adma2Table<N>],
+    ) -> Result<(), Error> {
+        let addr = buffers.as_ptr() as u32;
+        self.transfer_chained(block_idx, addr, buffers.len(), tables, true)
+            .await
+    }
+
+    async fn transfer_chained<const N: usize>(
+        &mut self,
+        mut block_idx: u32,
+        mut addr: u32,
+        mut blocks: usize,
+        tables: &mut [types::Adma2Table<N>],
+        write: bool,
+    ) -> Result<(), Error> {
+        use core::future::poll_fn;
+        use core::task::Poll;
+
+        let regs = self.info.regs;
+        let state = self.state;
+
+        while blocks > 0 {
+            let count = setup_chain(tables, addr, blocks.min(u16::MAX as usize))?;
+
+            // Address conversion
+            let address = self.block_address(block_idx)?;
+
+            // Configure block transfer
+            regs.blk_attr().write(|w| {
+                w.set_xfer_block_size(512);
+                w.set_block_cnt(count as u16);
+            });
+
+            // Clear all status
+            regs.int_stat().write(|w| w.0 = 0xFFFFFFFF);
+            state.clear_error();
+
+            // Configure ADMA2
+            regs.prot_ctrl().modify(|w| w.set_dma_sel(2)); // ADMA2
+            regs.adma_sys_addr().write(|w| w.0 = tables[0].as_ptr() as u32);
+
+            // Enable interrupts
+            regs.int_stat_en().modify(|w| {
+                w.set_cmd_complete_stat_en(true);
+                w.set_xfer_complete_stat_en(true);
+                w.set_dma_interrupt_stat_en(true);
+                w.set_data_tout_err_stat_en(true);
+                w.set_data_crc_err_stat_en(true);
+                w.set_cmd_tout_err_stat_en(true);
+                w.set_adma_err_stat_en(true);
+            });
+            regs.int_signal_en().modify(|w| {
+                w.set_xfer_complete_signal_en(true);
+                w.set_data_tout_err_signal_en(true);
+                w.set_data_crc_err_signal_en(true);
+                w.set_adma_err_signal_en(true);
+            });
+
+            // CMD16: SET_BLOCKLEN
+            self.set_block_length(512)?;
+
+            // CMD25: WRITE_MULTIPLE_BLOCK / CMD18: READ_MULTIPLE_BLOCK with ADMA2
+            if write {
+                self.cmd_adma2_multi_write(types::common_cmd::write_multiple_blocks(address))?;
+            } else {
+                self.cmd_adma2_multi_read(types::common_cmd::read_multiple_blocks(address))?;
+            }
+
+            // Async wait for transfer complete
+            let result = poll_fn(|cx| {
+                state.waker.register(cx.waker());
+                let status = regs.int_stat().read();
+
+                if status.data_tout_err() {
+                    return Poll::Ready(Err(Error::DataTimeout));
+                }
+                if status.data_crc_err() {
+                    return Poll::Ready(Err(Error::DataCrc));
+                }
+                if status.adma_err() {
+                    return Poll::Ready(Err(Error::AdmaError));
+                }
+                if status.xfer_complete() {
+                    return Poll::Ready(Ok(()));
+                }
+                Poll::Pending
+            })
+            .await;
+
+            // Disable interrupts
+            regs.int_signal_en().modify(|w| w.0 &= CARD_DETECT_SIGNALS);
+
+            // Clear status
+            regs.int_stat().write(|w| w.0 = 0xFFFFFFFF);
+
+            result?;
+            // Wait for card to be ready
+            if write {
+                self.wait_card_ready()?;
+            }
+
+            block_idx += count as u32;
+            addr += (count * 512) as u32;
+            blocks -= count;
+        }
+        Ok(())
+    }
+
+    /// Wait for the card to be inserted or removed
+    ///
+    /// Requires the card-detect pin to be routed to the SDXC peripheral. On removal the
+    /// initialized card is forgotten, on insertion call one of the `init_*` functions again.
+    pub async fn wait_card_event(&mut self) -> CardEvent {
+        use core::future::poll_fn;
+        use core::task::Poll;
+
+        let regs = self.info.regs;
+        let state = self.state;
+        let inserted = regs.pstate().read().card_inserted();
+
+        poll_fn(|cx| {
+            state.card_waker.register(cx.waker());
+
+            // Re-arm card detection, the interrupt handler masks it once raised
+            regs.int_stat().write(|w| w.0 = CARD_DETECT_SIGNALS);
+            regs.int_stat_en().modify(|w| {
+                w.set_card_insertion_stat_en(true);
+                w.set_card_removal_stat_en(true);
+            });
+            regs.int_signal_en().modify(|w| {
+                w.set_card_insertion_signal_en(true);
+                w.set_card_removal_signal_en(true);
+            });
+
+            let pstate = regs.pstate().read();
+            if pstate.card_stable() && pstate.card_inserted() != inserted {
+                return Poll::Ready(());
+            }
+            Poll::Pending
+        })
+        .await;
+
+        regs.int_signal_en().modify(|w| w.0 &= !CARD_DETECT_SIGNALS);
+
+        if inserted {
+            self.card = None;
+            self.emmc = None;
+            self.sdio = None;
+            CardEvent::Removed
+        } else {
+            CardEvent::Inserted
+        }
+    }
+}
+
+/// Async block device over an initialized [`Sdxc`] driver
+///
+/// Transfers of any length go through the chained ADMA2 `tables`, which must stay in
+/// DMA-accessible memory. With the `block-device-driver` feature this implements
+/// `block_device_driver::BlockDevice<512>` for async filesystems such as `embedded-fatfs`.
+///
+/// # Example
+///
+/// ```no_run
+/// #[unsafe(link_section = ".noncacheable")]
+/// static mut TABLES: [Adma2Table<16>; 2] = [Adma2Table::new(), Adma2Table::new()];
+///
+/// sdxc.init_sd_card(Hertz::mhz(50))?;
+/// let mut dev = SdBlockDevice::new(sdxc, unsafe { &mut *core::ptr::addr_of_mut!(TABLES) });
+/// dev.read(0, &mut blocks).await?;
+/// ```
+pub struct SdBlockDevice<'d, 'a, const N: usize> {
+    sdxc: Sdxc<'d, Async>,
+    tables: &'a mut [types::Adma2Table<N>],
+}
+
+impl<'d, 'a, const N: usize> SdBlockDevice<'d, 'a, N> {
+    /// Create a block device from an initialized driver and ADMA2 descriptor tables
+    pub fn new(sdxc: Sdxc<'d, Async>, tables: &'a mut [types::Adma2Table<N>]) -> Self {
+        Self { sdxc, tables }
+    }
+
+    /// Get a mutable reference to the underlying Sdxc driver
+    pub fn inner(&mut self) -> &mut Sdxc<'d, Async> {
+        &mut self.sdxc
+    }
+
+    /// Consume the block device and return the underlying Sdxc driver
+    pub fn into_inner(self) -> Sdxc<'d, Async> {
+        self.sdxc
+    }
+
+    /// Read `blocks.len()` blocks starting at `block_idx`
+    pub async fn read(&mut self, block_idx: u32, blocks: &mut [DataBlock]) -> Result<(), Error> {
+        self.sdxc.read_blocks_chained(block_idx, blocks, self.tables).await
+    }
+
+    /// Write `blocks.len()` blocks starting at `block_idx`
+    pub async fn write(&mut self, block_idx: u32, blocks: &[DataBlock]) -> Result<(), Error> {
+        self.sdxc.write_blocks_chained(block_idx, blocks, self.tables).await
+    }
+
+    /// Number of 512-byte blocks on the card
+    pub fn num_blocks(&self) -> Result<u64, Error> {
+        self.sdxc.block_count()
+    }
+
+    /// Wait for the card to be inserted or removed, see [`Sdxc::wait_card_event`]
+    pub async fn wait_card_event(&mut self) -> CardEvent {
+        self.sdxc.wait_card_event().await
+    }
+}
+
+#[cfg(feature = "block-device-driver")]
+impl<const N: usize> block_device_driver::BlockDevice<512> for SdBlockDevice<'_, '_, N> {
+    type Error = Error;
+    type Align = aligned::A4;
+
+    async fn read(
+        &mut self,
+        block_address: u32,
+        buf: &mut [aligned::Aligned<Self::Align, [u8; 512]>],
+    ) -> Result<(), Self::Error> {
+        // SAFETY: `Aligned<A4, [u8; 512]>` has the size and alignment of `DataBlock`
+        let blocks = unsafe { core::slice::from_raw_parts_mut(buf.as_mut_ptr() as *mut DataBlock, buf.len()) };
+        SdBlockDevice::read(self, block_address, blocks).await
+    }
+
+    async fn write(
+        &mut self,
+        block_address: u32,
+        buf: &[aligned::Aligned<Self::Align, [u8; 512]>],
+    ) -> Result<(), Self::Error> {
+        // SAFETY: `Aligned<A4, [u8; 512]>` has the size and alignment of `DataBlock`
+        let blocks = unsafe { core::slice::from_raw_parts(buf.as_ptr() as *const DataBlock, buf.len()) };
+        SdBlockDevice::write(self, block_address, blocks).await
+    }
+
+    async fn size(&mut self) -> Result<u64, Self::Error> {
+        Ok(self.num_blocks()? * 512)
+    }
+}
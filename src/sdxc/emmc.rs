@@ -0,0 +1,454 @@
+//! eMMC devices: identification, EXT_CSD, bus modes and partitions
+//!
+//! eMMC uses CMD1 instead of ACMD41 for the operating conditions, the host assigns the
+//! relative address, and bus width and timing are set by writing EXT_CSD bytes with CMD6.
+
+use super::types::{self, BusWidth, CID, CSD, CardCapacity, EMMC, Error, OCR};
+use super::{SD_INIT_FREQ, Sdxc, get_dma_buffer};
+use crate::mode::Mode;
+use crate::time::Hertz;
+
+/// CMD1 argument: sector addressing, 2.7-3.6V and 1.70-1.95V
+const OCR_ARG: u32 = 0x40FF_8080;
+/// Relative address given to the device by CMD3
+const EMMC_RCA: u16 = 1;
+
+/// Legacy interface clock limit
+const LEGACY_FREQ: Hertz = Hertz(26_000_000);
+/// High speed SDR and DDR clock limit
+const HS_FREQ: Hertz = Hertz(52_000_000);
+/// HS200 clock limit
+const HS200_FREQ: Hertz = Hertz(200_000_000);
+
+/// EXT_CSD byte offsets
+mod ext_csd {
+    pub const GP_SIZE_MULT: usize = 143;
+    pub const RPMB_SIZE_MULT: usize = 168;
+    pub const PARTITION_CONFIG: usize = 179;
+    pub const BUS_WIDTH: usize = 183;
+    pub const HS_TIMING: usize = 185;
+    pub const EXT_CSD_REV: usize = 192;
+    pub const CARD_TYPE: usize = 196;
+    pub const SEC_COUNT: usize = 212;
+    pub const HC_WP_GRP_SIZE: usize = 221;
+    pub const HC_ERASE_GRP_SIZE: usize = 224;
+    pub const BOOT_SIZE_MULT: usize = 226;
+}
+
+/// CMD13 device status SWITCH_ERROR
+const SWITCH_ERROR: u32 = 1 << 7;
+
+/// Extended CSD register, 512 bytes read with CMD8
+#[derive(Debug, Clone)]
+pub struct ExtCsd(pub [u8; 512]);
+
+impl ExtCsd {
+    /// EXT_CSD structure revision, 8 for eMMC 5.1
+    pub fn revision(&self) -> u8 {
+        self.0[ext_csd::EXT_CSD_REV]
+    }
+
+    /// User data area size in 512-byte sectors, 0 on byte addressed devices up to 2GB
+    pub fn sector_count(&self) -> u32 {
+        let i = ext_csd::SEC_COUNT;
+        u32::from_le_bytes([self.0[i], self.0[i + 1], self.0[i + 2], self.0[i + 3]])
+    }
+
+    /// Raw CARD_TYPE, the supported bus timings
+    pub fn card_type(&self) -> u8 {
+        self.0[ext_csd::CARD_TYPE]
+    }
+
+    /// High speed SDR at 52MHz
+    pub fn supports_hs52(&self) -> bool {
+        self.card_type() & (1 << 1) != 0
+    }
+
+    /// High speed DDR at 52MHz, with 1.8V or 3.3V I/O
+    pub fn supports_ddr52(&self) -> bool {
+        self.card_type() & (1 << 2) != 0
+    }
+
+    /// HS200 at 200MHz, with 1.8V I/O
+    pub fn supports_hs200(&self) -> bool {
+        self.card_type() & (1 << 4) != 0
+    }
+
+    /// PARTITION_CONFIG: boot acknowledge, boot partition enable and partition access
+    pub fn partition_config(&self) -> u8 {
+        self.0[ext_csd::PARTITION_CONFIG]
+    }
+
+    /// Raw BUS_WIDTH, 0/1/2 for 1/4/8-bit SDR, 5/6 for 4/8-bit DDR
+    pub fn bus_width(&self) -> u8 {
+        self.0[ext_csd::BUS_WIDTH]
+    }
+
+    /// Raw HS_TIMING, 0 legacy, 1 high speed, 2 HS200
+    pub fn hs_timing(&self) -> u8 {
+        self.0[ext_csd::HS_TIMING]
+    }
+
+    /// Size of each boot partition in 512-byte blocks
+    pub fn boot_partition_blocks(&self) -> u64 {
+        self.0[ext_csd::BOOT_SIZE_MULT] as u64 * 256
+    }
+
+    /// Size of the RPMB partition in 512-byte blocks
+    pub fn rpmb_blocks(&self) -> u64 {
+        self.0[ext_csd::RPMB_SIZE_MULT] as u64 * 256
+    }
+
+    /// Size of general purpose partition `n` (1 to 4) in 512-byte blocks
+    pub fn gp_partition_blocks(&self, n: u8) -> u64 {
+        if !(1..=4).contains(&n) {
+            return 0;
+        }
+        let i = ext_csd::GP_SIZE_MULT + 3 * (n as usize - 1);
+        let mult = u32::from_le_bytes([self.0[i], self.0[i + 1], self.0[i + 2], 0]) as u64;
+        // Multiples of the high capacity write protect group, in 512KiB units
+        let wp_group = self.0[ext_csd::HC_WP_GRP_SIZE] as u64 * self.0[ext_csd::HC_ERASE_GRP_SIZE] as u64;
+        mult * wp_group * 1024
+    }
+}
+
+/// eMMC hardware partition
+///
+/// The RPMB partition only accepts the authenticated frame protocol of the eMMC
+/// specification, plain block reads and writes fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum EmmcPartition {
+    /// User data area
+    User,
+    /// Boot partition 1
+    Boot1,
+    /// Boot partition 2
+    Boot2,
+    /// Replay protected memory block
+    Rpmb,
+    /// General purpose partition 1 to 4
+    GeneralPurpose(u8),
+}
+
+impl EmmcPartition {
+    /// PARTITION_ACCESS value
+    fn access(self) -> u8 {
+        match self {
+            EmmcPartition::User => 0,
+            EmmcPartition::Boot1 => 1,
+            EmmcPartition::Boot2 => 2,
+            EmmcPartition::Rpmb => 3,
+            EmmcPartition::GeneralPurpose(n) => 3 + n,
+        }
+    }
+}
+
+/// eMMC bus timing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum EmmcTiming {
+    /// Backward compatible timing, up to 26MHz
+    Legacy,
+    /// High speed SDR, up to 52MHz
+    HighSpeed,
+    /// High speed DDR, up to 52MHz on both clock edges
+    Ddr52,
+    /// HS200 SDR with 1.8V I/O and sampling clock tuning, up to 200MHz
+    Hs200,
+}
+
+/// eMMC device information
+#[derive(Clone)]
+pub struct Emmc {
+    /// Byte (up to 2GB) or sector addressing
+    pub capacity: CardCapacity,
+    /// Operation Conditions Register
+    pub ocr: OCR<EMMC>,
+    /// Relative Card Address
+    pub rca: u16,
+    /// Card ID
+    pub cid: CID<EMMC>,
+    /// Card Specific Data
+    pub csd: CSD<EMMC>,
+    /// Extended CSD, kept up to date with the SWITCH commands of the driver
+    pub ext_csd: ExtCsd,
+    /// Bus width in use
+    pub bus_width: BusWidth,
+    /// Bus timing in use
+    pub timing: EmmcTiming,
+    partition: EmmcPartition,
+    /// User area size from the CSD, SEC_COUNT is 0 on byte addressed devices
+    csd_blocks: u64,
+}
+
+/// Device size in 512-byte blocks from the CSD C_SIZE, C_SIZE_MULT and READ_BL_LEN fields
+fn csd_blocks(csd: [u32; 4]) -> u64 {
+    let raw = csd.iter().rev().fold(0u128, |acc, &word| (acc << 32) | word as u128);
+    let field = |lsb: u32, width: u32| ((raw >> lsb) & ((1 << width) - 1)) as u64;
+
+    let c_size = field(62, 12);
+    let c_size_mult = field(47, 3);
+    let read_bl_len = field(80, 4);
+    ((c_size + 1) << (c_size_mult + 2 + read_bl_len)) / 512
+}
+
+impl Emmc {
+    /// Currently selected partition
+    pub fn partition(&self) -> EmmcPartition {
+        self.partition
+    }
+
+    /// Size of `partition` in 512-byte blocks, 0 if not present
+    pub fn partition_blocks(&self, partition: EmmcPartition) -> u64 {
+        match partition {
+            EmmcPartition::User => match self.ext_csd.sector_count() {
+                0 => self.csd_blocks,
+                sectors => sectors as u64,
+            },
+            EmmcPartition::Boot1 | EmmcPartition::Boot2 => self.ext_csd.boot_partition_blocks(),
+            EmmcPartition::Rpmb => self.ext_csd.rpmb_blocks(),
+            EmmcPartition::GeneralPurpose(n) => self.ext_csd.gp_partition_blocks(n),
+        }
+    }
+}
+
+impl<'d, M: Mode> Sdxc<'d, M> {
+    /// Initialize an eMMC device
+    ///
+    /// Uses the widest bus the driver was constructed with and the fastest timing both
+    /// `freq` and the device allow: DDR52 when supported, HS200 only with
+    /// `Config::emmc_vccq_1v8`. The user data area is selected afterwards.
+    pub fn init_emmc(&mut self, freq: Hertz) -> Result<(), Error> {
+        let regs = self.info.regs;
+
+        regs.int_stat().write(|w| w.0 = 0xFFFFFFFF);
+        self.card = None;
+        self.emmc = None;
+        self.sdio = None;
+
+        // Back to the identification mode bus
+        self.set_bus_width(BusWidth::One);
+        regs.ac_host_ctrl().modify(|w| {
+            w.set_uhs_mode_sel(0);
+            w.set_sample_clk_sel(false);
+        });
+        self.set_clock(SD_INIT_FREQ);
+        {
+            use crate::gpio::SealedPin;
+            self._cmd.ioc_pad().pad_ctl().write(|w| {
+                w.set_ds(7);
+                w.set_od(true);
+            });
+        }
+        self.wait_card_active();
+        for _ in 0..500000 {
+            core::hint::spin_loop();
+        }
+
+        // CMD0: GO_IDLE_STATE
+        self.cmd(types::common_cmd::idle(), false)?;
+
+        // CMD1: SEND_OP_COND (repeated until power up is complete)
+        let mut ocr = None;
+        for _ in 0..1000 {
+            match self.cmd(types::emmc_cmd::send_op_cond(OCR_ARG), false) {
+                Ok(_) | Err(Error::Crc) => {}
+                Err(e) => return Err(e),
+            }
+            let response = self.get_response();
+            if response & (1 << 31) != 0 {
+                ocr = Some(response);
+                break;
+            }
+            for _ in 0..10000 {
+                core::hint::spin_loop();
+            }
+        }
+        let ocr = ocr.ok_or(Error::Timeout)?;
+        // OCR access mode, bits 30:29
+        let capacity = if (ocr >> 29) & 0x3 == 0x2 {
+            CardCapacity::HighCapacity
+        } else {
+            CardCapacity::StandardCapacity
+        };
+
+        // CMD2: ALL_SEND_CID
+        self.cmd(types::common_cmd::all_send_cid(), false)?;
+        let cid: CID<EMMC> = self.get_response_r2().into();
+
+        // CMD3: SET_RELATIVE_ADDR
+        self.cmd(types::emmc_cmd::assign_relative_address(EMMC_RCA), false)?;
+
+        // CMD9: SEND_CSD
+        self.cmd(types::common_cmd::send_csd(EMMC_RCA), false)?;
+        let csd_raw = self.get_response_r2();
+        let csd: CSD<EMMC> = csd_raw.into();
+
+        // CMD7: SELECT_CARD
+        self.cmd(types::common_cmd::select_card(EMMC_RCA), false)?;
+
+        // Push-pull CMD for transfer mode
+        self.set_clock(Hertz(freq.0.min(LEGACY_FREQ.0)));
+        {
+            use crate::gpio::SealedPin;
+            self._cmd.ioc_pad().pad_ctl().write(|w| {
+                w.set_ds(7);
+            });
+        }
+        self.wait_card_active();
+
+        self.emmc = Some(Emmc {
+            capacity,
+            ocr: ocr.into(),
+            rca: EMMC_RCA,
+            cid,
+            csd,
+            ext_csd: ExtCsd([0; 512]),
+            bus_width: BusWidth::One,
+            timing: EmmcTiming::Legacy,
+            partition: EmmcPartition::User,
+            csd_blocks: csd_blocks(csd_raw),
+        });
+        let ext_csd = self.read_ext_csd()?;
+
+        let width = if self._d7.is_some() {
+            BusWidth::Eight
+        } else if self._d3.is_some() {
+            BusWidth::Four
+        } else {
+            BusWidth::One
+        };
+        if width != BusWidth::One {
+            self.switch(ext_csd::BUS_WIDTH, width as u8)?;
+            self.set_bus_width(width);
+        }
+
+        let timing =
+            if self.config.emmc_vccq_1v8 && ext_csd.supports_hs200() && width != BusWidth::One && freq.0 > HS_FREQ.0 {
+                self.switch(ext_csd::HS_TIMING, 2)?;
+                regs.ac_host_ctrl().modify(|w| {
+                    w.set_signaling_en(true);
+                    w.set_uhs_mode_sel(3); // SDR104 host timing
+                });
+                self.set_clock(Hertz(freq.0.min(HS200_FREQ.0)));
+                // CMD21: SEND_TUNING_BLOCK
+                let block_size = if width == BusWidth::Eight { 128 } else { 64 };
+                self.execute_tuning(21, block_size)?;
+                EmmcTiming::Hs200
+            } else if ext_csd.supports_hs52() && freq.0 > LEGACY_FREQ.0 {
+                self.switch(ext_csd::HS_TIMING, 1)?;
+                self.set_clock(Hertz(freq.0.min(HS_FREQ.0)));
+                if ext_csd.supports_ddr52() && width != BusWidth::One {
+                    // DDR bus widths are 5 (4-bit) and 6 (8-bit)
+                    self.switch(ext_csd::BUS_WIDTH, width as u8 + 4)?;
+                    regs.ac_host_ctrl().modify(|w| w.set_uhs_mode_sel(4)); // DDR50 host timing
+                    EmmcTiming::Ddr52
+                } else {
+                    EmmcTiming::HighSpeed
+                }
+            } else {
+                EmmcTiming::Legacy
+            };
+
+        if let Some(emmc) = self.emmc.as_mut() {
+            emmc.bus_width = width;
+            emmc.timing = timing;
+        }
+
+        if ext_csd.partition_config() & 0x7 != 0 {
+            self.switch_partition(EmmcPartition::User)?;
+        }
+
+        Ok(())
+    }
+
+    /// Select the partition used by the following block reads and writes
+    pub fn switch_partition(&mut self, partition: EmmcPartition) -> Result<(), Error> {
+        let emmc = self.emmc.as_ref().ok_or(Error::NoCard)?;
+        if partition != EmmcPartition::User && emmc.partition_blocks(partition) == 0 {
+            return Err(Error::NoPartition);
+        }
+
+        // Keep the boot configuration, bits 6:3
+        let config = (emmc.ext_csd.partition_config() & !0x7) | partition.access();
+        self.switch(ext_csd::PARTITION_CONFIG, config)?;
+
+        if let Some(emmc) = self.emmc.as_mut() {
+            emmc.partition = partition;
+        }
+        Ok(())
+    }
+
+    /// CMD6: SWITCH, write byte `index` of EXT_CSD and wait for the device
+    fn switch(&mut self, index: usize, value: u8) -> Result<(), Error> {
+        self.cmd_busy(types::emmc_cmd::modify_ext_csd(
+            types::emmc_cmd::AccessMode::WriteByte,
+            index as u8,
+            value,
+        ))?;
+        self.wait_card_ready()?;
+
+        // CMD13: SEND_STATUS, the device reports a rejected switch here
+        let rca = self.rca()?;
+        self.cmd(types::common_cmd::card_status(rca, false), false)?;
+        if self.get_response() & SWITCH_ERROR != 0 {
+            return Err(Error::SwitchError);
+        }
+
+        if let Some(emmc) = self.emmc.as_mut() {
+            emmc.ext_csd.0[index] = value;
+        }
+        Ok(())
+    }
+
+    /// CMD8: SEND_EXT_CSD into the cached copy
+    fn read_ext_csd(&mut self) -> Result<ExtCsd, Error> {
+        let regs = self.info.regs;
+        let dma_buf = get_dma_buffer();
+
+        regs.blk_attr().write(|w| {
+            w.set_xfer_block_size(512);
+            w.set_block_cnt(1);
+        });
+        regs.int_stat().write(|w| w.0 = 0xFFFFFFFF);
+        regs.prot_ctrl().modify(|w| w.set_dma_sel(0)); // SDMA
+        regs.sdmasa().write(|w| w.0 = dma_buf.as_ptr() as u32);
+
+        self.set_block_length(512)?;
+        self.cmd_dma(types::emmc_cmd::send_ext_csd())?;
+
+        let mut timeout_count = 0u32;
+        loop {
+            let status = regs.int_stat().read();
+            if status.data_tout_err() {
+                return Err(Error::DataTimeout);
+            }
+            if status.data_crc_err() {
+                return Err(Error::DataCrc);
+            }
+            if status.xfer_complete() {
+                regs.int_stat().write(|w| w.set_xfer_complete(true));
+                break;
+            }
+            timeout_count += 1;
+            if timeout_count > 10_000_000 {
+                return Err(Error::SoftwareTimeout);
+            }
+        }
+
+        // EXT_CSD is sent in byte order, no conversion needed
+        let mut ext_csd = ExtCsd([0; 512]);
+        unsafe {
+            andes_riscv::l1c::dc_invalidate(dma_buf.as_ptr() as u32, 512);
+            for i in 0..512 {
+                ext_csd.0[i] = core::ptr::read_volatile(dma_buf.as_ptr().add(i));
+            }
+        }
+
+        if let Some(emmc) = self.emmc.as_mut() {
+            emmc.ext_csd = ext_csd.clone();
+        }
+        Ok(ext_csd)
+    }
+}
@@ -9,12 +9,11 @@
 //! - ✅ Async mode with interrupt-driven transfers
 //! - ✅ FAT32 filesystem via embedded-sdmmc BlockDevice trait
 //! - ✅ High Speed mode (SDR25, 50MHz) at 3.3V
-//!
-//! # TODO (Phase 3)
-//!
-//! - [ ] UHS-I modes (SDR50, SDR104) with 1.8V signaling
-//! - [ ] Auto-tuning for SDR50/SDR104
-//! - [ ] DDR50 mode
+//! - ✅ eMMC with 8-bit bus, DDR52 and HS200 (1.8V VCCQ), boot/RPMB/GP partition switching
+//! - ✅ UHS-I SDR50/SDR104/DDR50 with CMD11 1.8V switching, see [`IoVoltageSwitch`]
+//! - ✅ Sampling clock tuning (CMD19/CMD21) for SDR50/SDR104 and HS200
+//! - ✅ SDIO CMD52/CMD53 I/O, card interrupt and CIS parsing, see [`SdioDevice`]
+//! - ✅ Async block device with chained ADMA2 tables and card-detect events, see [`SdBlockDevice`]
 //!
 //! See `PHASE2_PLAN.md` for implementation details.
 //!
@@ -39,6 +38,20 @@
 //! let mut block = sdxc::DataBlock::new();
 //! sdxc.read_block(0, &mut block)?;
 //! ```
+//!
+//! ## eMMC
+//!
+//! ```no_run
+//! let mut sdxc = sdxc::Sdxc::new_blocking_8bit(
+//!     p.SDXC0,
+//!     clk, cmd, d0, d1, d2, d3, d4, d5, d6, d7,
+//!     Default::default()
+//! );
+//!
+//! sdxc.init_emmc(Hertz::mhz(52))?;
+//! sdxc.switch_partition(sdxc::EmmcPartition::Boot1)?;
+//! sdxc.read_block(0, &mut block)?;
+//! ```
 
 use core::marker::PhantomData;
 use core::sync::atomic::{AtomicU32, Ordering};
@@ -52,8 +65,14 @@ use crate::mode::{Async, Blocking, Mode};
 use crate::time::Hertz;
 use crate::{interrupt, peripherals};
 
+mod block;
+mod emmc;
+mod sdio;
 mod types;
 
+pub use block::{CardEvent, SdBlockDevice};
+pub use emmc::{Emmc, EmmcPartition, EmmcTiming, ExtCsd};
+pub use sdio::{SdioCard, SdioDevice, SdioFunctionInfo};
 pub use types::*;
 
 /// Frequency used for SD Card initialization. Must be no higher than 400 kHz.
@@ -96,9 +115,15 @@ fn configure_sysctl_clock_by_idx(sysctl_clock_idx: usize, target_freq: Hertz) {
     } else if target_freq.0 <= 26_000_000 {
         // 24MHz / 1 = 24MHz (matches C SDK for default speed)
         ClockConfig::new(ClockMux::CLK_24M, 1)
-    } else {
+    } else if target_freq.0 <= 50_000_000 {
         // PLL1CLK1 (400MHz) / 8 = 50MHz (matches C SDK for SDR25)
         ClockConfig::new(ClockMux::PLL1CLK1, 8)
+    } else if target_freq.0 <= 100_000_000 {
+        // PLL1CLK1 (400MHz) / 4 = 100MHz for SDR50
+        ClockConfig::new(ClockMux::PLL1CLK1, 4)
+    } else {
+        // PLL1CLK1 (400MHz) / 2 = 200MHz for SDR104 and HS200
+        ClockConfig::new(ClockMux::PLL1CLK1, 2)
     };
 
     #[cfg(any(sdxc_v63, sdxc_v68))]
@@ -127,6 +152,8 @@ fn get_sysctl_frequency(sysctl_clock_idx: usize) -> Hertz {
 // State and Info structures (following Embassy pattern)
 struct State {
     waker: AtomicWaker,
+    /// Card insertion and removal, see [`Sdxc::wait_card_event`]
+    card_waker: AtomicWaker,
     last_error: AtomicU32,
 }
 
@@ -134,6 +161,7 @@ impl State {
     const fn new() -> Self {
         Self {
             waker: AtomicWaker::new(),
+            card_waker: AtomicWaker::new(),
             last_error: AtomicU32::new(0),
         }
     }
@@ -160,6 +188,8 @@ impl State {
 struct Info {
     regs: crate::pac::sdxc::Sdxc,
     sysctl_clock_idx: usize,
+    /// ADMA2 descriptors of SDIO CMD53 block transfers
+    sdio_adma_table: &'static sdio::SdioAdmaTable,
 }
 
 // Instance trait using peri_trait! macro
@@ -173,9 +203,12 @@ foreach_peripheral!(
         #[allow(private_interfaces)]
         impl SealedInstance for peripherals::$inst {
             fn info() -> &'static Info {
+                #[unsafe(link_section = ".noncacheable")]
+                static SDIO_ADMA_TABLE: sdio::SdioAdmaTable = sdio::SdioAdmaTable::new();
                 static INFO: Info = Info {
                     regs: crate::pac::$inst,
                     sysctl_clock_idx: <peripherals::$inst as crate::sysctl::SealedClockPeripheral>::SYSCTL_CLOCK,
+                    sdio_adma_table: &SDIO_ADMA_TABLE,
                 };
                 &INFO
             }
@@ -203,6 +236,27 @@ pin_trait!(D5Pin, Instance);
 pin_trait!(D6Pin, Instance);
 pin_trait!(D7Pin, Instance);
 
+/// Board switch of the card I/O supply between 3.3V and 1.8V, needed for UHS-I modes
+///
+/// Implemented for a GPIO [`Output`](crate::gpio::Output) driving a regulator select
+/// input, high for 1.8V, and for closures.
+pub trait IoVoltageSwitch {
+    /// Switch the card I/O supply to 1.8V (`true`) or back to 3.3V (`false`)
+    fn set_1v8(&mut self, enable: bool);
+}
+
+impl IoVoltageSwitch for crate::gpio::Output<'_> {
+    fn set_1v8(&mut self, enable: bool) {
+        self.set_level(enable.into());
+    }
+}
+
+impl<F: FnMut(bool)> IoVoltageSwitch for F {
+    fn set_1v8(&mut self, enable: bool) {
+        self(enable)
+    }
+}
+
 /// SDXC interrupt handler
 pub struct InterruptHandler<T: Instance> {
     _phantom: PhantomData<T>,
@@ -221,16 +275,22 @@ impl<T: Instance> interrupt::typelevel::Handler<T::Interrupt> for InterruptHandl
             state.set_error(status.0);
         }
 
-        // Disable interrupt signals to prevent re-triggering
+        // Disable the raised interrupt signals to prevent re-triggering
         // The async function will re-enable if needed after checking status
         // DO NOT clear int_stat here - let the async function read it first
-        regs.int_signal_en().write(|_| {});
+        regs.int_signal_en().modify(|w| w.0 &= !status.0);
 
         // Wake waiting task - it will read int_stat and clear it
         state.wake();
+        if status.0 & CARD_DETECT_SIGNALS != 0 {
+            state.card_waker.wake();
+        }
     }
 }
 
+/// INT_STAT/INT_SIGNAL_EN card insertion and removal, left enabled by the transfers
+const CARD_DETECT_SIGNALS: u32 = (1 << 6) | (1 << 7);
+
 /// Convert types::ResponseLen to hardware RESP_TYPE_SELECT value
 fn get_resp_type_select(resp_len: types::ResponseLen) -> u8 {
     match resp_len {
@@ -242,6 +302,9 @@ fn get_resp_type_select(resp_len: types::ResponseLen) -> u8 {
 
 /// Check if command requires R1b (busy) response
 /// These commands use 48-bit response with busy check (resp_type_select = 3)
+///
+/// CMD6 is R1b only as the eMMC SWITCH, which is sent with [`Sdxc::cmd_busy`]. The SD
+/// SWITCH_FUNC and ACMD6 SET_BUS_WIDTH share the index with an R1 response.
 fn needs_busy_response(cmd_index: u8) -> bool {
     matches!(
         cmd_index,
@@ -273,8 +336,15 @@ pub struct Sdxc<'d, M: Mode> {
     _d7: Option<Peri<'d, AnyPin>>,
 
     card: Option<Card>,
+    emmc: Option<Emmc>,
+    sdio: Option<SdioCard>,
     config: Config,
 
+    io_switch: Option<&'d mut dyn IoVoltageSwitch>,
+    /// Card I/O switched to 1.8V with CMD11
+    signalling_1v8: bool,
+    signalling: Signalling,
+
     _phantom: PhantomData<M>,
 }
 
@@ -396,7 +466,12 @@ impl<'d, M: Mode> Sdxc<'d, M> {
             _d6: d6,
             _d7: d7,
             card: None,
+            emmc: None,
+            sdio: None,
             config,
+            io_switch: None,
+            signalling_1v8: false,
+            signalling: Signalling::SDR12,
             _phantom: PhantomData,
         }
     }
@@ -406,6 +481,56 @@ impl<'d, M: Mode> Sdxc<'d, M> {
         self.card.as_ref()
     }
 
+    /// Current SD card signalling mode
+    pub fn signalling(&self) -> Signalling {
+        self.signalling
+    }
+
+    /// Set the board switch of the card I/O supply, enables UHS-I modes in `init_sd_card`
+    ///
+    /// The card only returns to 3.3V signalling after a power cycle, which the board must do
+    /// before `init_sd_card` is called again.
+    pub fn set_io_voltage_switch(&mut self, switch: &'d mut dyn IoVoltageSwitch) {
+        self.io_switch = Some(switch);
+    }
+
+    /// Get the eMMC device information, if initialized with `init_emmc`
+    pub fn emmc(&self) -> Option<&Emmc> {
+        self.emmc.as_ref()
+    }
+
+    /// Number of 512-byte blocks of the card, or of the selected eMMC partition
+    pub fn block_count(&self) -> Result<u64, Error> {
+        match (&self.card, &self.emmc) {
+            (Some(card), _) => Ok(card.csd.block_count()),
+            (None, Some(emmc)) => Ok(emmc.partition_blocks(emmc.partition())),
+            (None, None) => Err(Error::NoCard),
+        }
+    }
+
+    /// Relative card address of the initialized card
+    fn rca(&self) -> Result<u16, Error> {
+        match (&self.card, &self.emmc, &self.sdio) {
+            (Some(card), _, _) => Ok(card.rca),
+            (None, Some(emmc), _) => Ok(emmc.rca),
+            (None, None, Some(sdio)) => Ok(sdio.rca),
+            (None, None, None) => Err(Error::NoCard),
+        }
+    }
+
+    /// Command address of block `block_idx`: SDSC and small eMMC use byte addresses
+    fn block_address(&self, block_idx: u32) -> Result<u32, Error> {
+        let capacity = match (&self.card, &self.emmc) {
+            (Some(card), _) => card.card_type,
+            (None, Some(emmc)) => emmc.capacity,
+            (None, None) => return Err(Error::NoCard),
+        };
+        Ok(match capacity {
+            types::CardCapacity::StandardCapacity => block_idx * 512,
+            _ => block_idx,
+        })
+    }
+
     /// Check if a card is inserted
     pub fn is_card_inserted(&self) -> bool {
         self.info.regs.pstate().read().card_inserted()
@@ -532,7 +657,7 @@ impl<'d, M: Mode> Sdxc<'d, M> {
     ///
     /// Uses CMD13 (SEND_STATUS) to check card state.
     fn wait_card_ready(&self) -> Result<(), Error> {
-        let rca = self.card.as_ref().ok_or(Error::NoCard)?.rca;
+        let rca = self.rca()?;
 
         for _ in 0..10000 {
             // CMD13: SEND_STATUS
@@ -555,6 +680,16 @@ impl<'d, M: Mode> Sdxc<'d, M> {
 
     /// Send a command and wait for response (blocking)
     fn cmd<R: types::Resp>(&self, cmd: types::Cmd<R>, data: bool) -> Result<(), Error> {
+        let busy = needs_busy_response(cmd.cmd);
+        self.send_cmd(cmd, data, busy)
+    }
+
+    /// Send a command without data and an R1b (busy) response, e.g. the eMMC SWITCH
+    fn cmd_busy<R: types::Resp>(&self, cmd: types::Cmd<R>) -> Result<(), Error> {
+        self.send_cmd(cmd, false, true)
+    }
+
+    fn send_cmd<R: types::Resp>(&self, cmd: types::Cmd<R>, data: bool, busy: bool) -> Result<(), Error> {
         let regs = self.info.regs;
 
         // Clear interrupt status
@@ -574,7 +709,7 @@ impl<'d, M: Mode> Sdxc<'d, M> {
         let resp_len = cmd.response_len();
 
         // Determine response type: use R1b (3) for commands that need busy check
-        let resp_type_sel = if needs_busy_response(cmd.cmd) {
+        let resp_type_sel = if busy {
             3 // R1b: 48-bit with busy check
         } else {
             get_resp_type_select(resp_len)
@@ -585,8 +720,8 @@ impl<'d, M: Mode> Sdxc<'d, M> {
             types::ResponseLen::Zero => (false, false),
             types::ResponseLen::R136 => (true, false),
             types::ResponseLen::R48 => {
-                if cmd.cmd == 41 {
-                    (false, false) // R3 - no CRC
+                if matches!(cmd.cmd, 1 | 5 | 41) {
+                    (false, false) // R3/R4 - no CRC
                 } else {
                     (true, true)
                 }
@@ -642,6 +777,93 @@ impl<'d, M: Mode> Sdxc<'d, M> {
         }
     }
 
+    /// CMD16: SET_BLOCKLEN, skipped in eMMC DDR mode where the block length is fixed to 512
+    fn set_block_length(&self, len: u32) -> Result<(), Error> {
+        if self.emmc.as_ref().is_some_and(|emmc| emmc.timing == EmmcTiming::Ddr52) {
+            return Ok(());
+        }
+        self.cmd(types::common_cmd::set_block_length(len), false)
+    }
+
+    /// Tune the sampling clock with the tuning block command `cmd_index`
+    ///
+    /// CMD19 for SD UHS-I SDR50/SDR104, CMD21 for eMMC HS200. The tuning block is
+    /// 64 bytes on a 4-bit bus and 128 bytes on an 8-bit bus.
+    fn execute_tuning(&self, cmd_index: u8, block_size: u16) -> Result<(), Error> {
+        let regs = self.info.regs;
+
+        regs.ac_host_ctrl().modify(|w| {
+            w.set_sample_clk_sel(false);
+            w.set_exec_tuning(true);
+        });
+        regs.blk_attr().write(|w| {
+            w.set_xfer_block_size(block_size);
+            w.set_block_cnt(1);
+        });
+
+        let result = self.send_tuning_blocks(cmd_index);
+
+        let ctrl = regs.ac_host_ctrl().read();
+        if result.is_err() || ctrl.exec_tuning() || !ctrl.sample_clk_sel() {
+            // Fall back to the fixed sampling clock
+            regs.ac_host_ctrl().modify(|w| {
+                w.set_exec_tuning(false);
+                w.set_sample_clk_sel(false);
+            });
+            regs.int_stat().write(|w| w.0 = 0xFFFFFFFF);
+            return Err(result.err().unwrap_or(Error::TuningFailed));
+        }
+
+        Ok(())
+    }
+
+    /// Tuning block loop of [`Self::execute_tuning`]
+    ///
+    /// The controller shifts the sampling point after each tuning block and clears
+    /// EXEC_TUNING when done, at most 40 blocks.
+    fn send_tuning_blocks(&self, cmd_index: u8) -> Result<(), Error> {
+        let regs = self.info.regs;
+
+        for _ in 0..40 {
+            regs.int_stat().write(|w| w.0 = 0xFFFFFFFF);
+
+            let mut timeout_count = 0u32;
+            while regs.pstate().read().cmd_inhibit() || regs.pstate().read().dat_inhibit() {
+                timeout_count += 1;
+                if timeout_count > 1_000_000 {
+                    return Err(Error::SoftwareTimeout);
+                }
+            }
+
+            regs.cmd_arg().write(|w| w.0 = 0);
+            regs.cmd_xfer().write(|w| {
+                w.set_cmd_index(cmd_index);
+                w.set_resp_type_select(2);
+                w.set_cmd_crc_chk_enable(true);
+                w.set_cmd_idx_chk_enable(true);
+                w.set_data_present_sel(true);
+                w.set_data_xfer_dir(true); // Read
+            });
+
+            // The tuning block is checked by the controller and never leaves the buffer,
+            // BUF_RD_READY is raised for passing and failing blocks alike
+            let mut timeout_count = 0u32;
+            while !regs.int_stat().read().buf_rd_ready() {
+                timeout_count += 1;
+                if timeout_count > 1_000_000 {
+                    return Err(Error::DataTimeout);
+                }
+            }
+            regs.int_stat().write(|w| w.0 = 0xFFFFFFFF);
+
+            if !regs.ac_host_ctrl().read().exec_tuning() {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
     /// Send a data read command with DMA enabled (blocking) - for single block SDMA
     fn cmd_dma<R: types::Resp>(&self, cmd: types::Cmd<R>) -> Result<(), Error> {
         let regs = self.info.regs;
@@ -933,6 +1155,18 @@ impl<'d, M: Mode> Sdxc<'d, M> {
 
         // Clear all interrupt status
         regs.int_stat().write(|w| w.0 = 0xFFFFFFFF);
+        self.card = None;
+        self.emmc = None;
+        self.sdio = None;
+
+        // Back to 3.3V default speed signalling
+        self.set_bus_width(BusWidth::One);
+        self.set_io_1v8(false);
+        self.info.regs.ac_host_ctrl().modify(|w| {
+            w.set_uhs_mode_sel(0);
+            w.set_sample_clk_sel(false);
+        });
+        self.signalling = Signalling::SDR12;
 
         // Set 400kHz for identification
         self.set_clock(SD_INIT_FREQ);
@@ -972,7 +1206,9 @@ impl<'d, M: Mode> Sdxc<'d, M> {
         let mut ocr: types::OCR<types::SD>;
         for i in 0..1000 {
             self.cmd(types::common_cmd::app_cmd(0), false)?;
-            match self.cmd(types::sd_cmd::sd_send_op_cond(true, false, false, 0x1FF), false) {
+            // S18R: request 1.8V signalling when the board can switch the I/O supply
+            let s18r = self.io_switch.is_some();
+            match self.cmd(types::sd_cmd::sd_send_op_cond(true, false, s18r, 0x1FF), false) {
                 Ok(_) | Err(Error::Crc) => {}
                 Err(e) => {
                     #[cfg(feature = "defmt")]
@@ -981,7 +1217,8 @@ impl<'d, M: Mode> Sdxc<'d, M> {
                 }
             }
 
-            ocr = self.get_response().into();
+            let response = self.get_response();
+            ocr = response.into();
             if !ocr.is_busy() {
                 #[cfg(feature = "defmt")]
                 defmt::debug!("init_sd_card: card ready after {} ACMD41 iterations", i + 1);
@@ -992,6 +1229,11 @@ impl<'d, M: Mode> Sdxc<'d, M> {
                     types::CardCapacity::StandardCapacity
                 };
 
+                // S18A: the card accepted 1.8V signalling, switch before identification
+                if s18r && response & (1 << 24) != 0 {
+                    self.voltage_switch()?;
+                }
+
                 // CMD2: ALL_SEND_CID
                 #[cfg(feature = "defmt")]
                 defmt::debug!("init_sd_card: sending CMD2 (ALL_SEND_CID)");
@@ -1053,16 +1295,24 @@ impl<'d, M: Mode> Sdxc<'d, M> {
                     self.set_bus_width(BusWidth::Four);
                 }
 
-                // Try to switch to High Speed mode (SDR25) if supported
-                // This is safe at 3.3V and doesn't require voltage switching
-                if freq.0 >= 50_000_000 {
-                    if let Ok(signalling) = self.switch_signalling_mode(Signalling::SDR25) {
-                        if signalling == Signalling::SDR25 {
-                            // Successfully switched to High Speed
-                            self.set_clock(Hertz(Signalling::SDR25.clock_hz()));
+                // Fastest mode supported by the card whose clock fits in `freq`,
+                // UHS-I modes only after the switch to 1.8V
+                let modes = [
+                    Signalling::SDR104,
+                    Signalling::SDR50,
+                    Signalling::DDR50,
+                    Signalling::SDR25,
+                ];
+                for mode in modes {
+                    if mode.clock_hz() > freq.0 || (mode.requires_1v8() && !self.signalling_1v8) {
+                        continue;
+                    }
+                    if let Ok(signalling) = self.switch_signalling_mode(mode) {
+                        if signalling == mode {
+                            break;
                         }
                     }
-                    // If switch failed, we continue with default speed mode
+                    // If switch failed, try the next slower mode
                 }
 
                 return Ok(());
@@ -1081,19 +1331,17 @@ impl<'d, M: Mode> Sdxc<'d, M> {
 
     /// Switch signalling mode using CMD6 (SWITCH_FUNC)
     ///
-    /// Attempts to switch the card to the specified speed mode.
+    /// Attempts to switch the card to the specified speed mode, then sets the host timing,
+    /// the mode clock and, for SDR50/SDR104, tunes the sampling clock.
     /// Returns the actual mode the card switched to.
     ///
-    /// Note: SDR50, SDR104, and DDR50 require 1.8V signaling which may not be
-    /// supported on all boards. SDR25 (High Speed) works with 3.3V.
+    /// Note: SDR50, SDR104, and DDR50 require 1.8V signaling, only available when
+    /// `init_sd_card` switched the card with an [`IoVoltageSwitch`]. SDR25 (High Speed)
+    /// works with 3.3V.
     pub fn switch_signalling_mode(&mut self, mode: Signalling) -> Result<Signalling, Error> {
-        let card = self.card.as_ref().ok_or(Error::NoCard)?;
-        let rca = card.rca;
+        let rca = self.card.as_ref().ok_or(Error::NoCard)?.rca;
 
-        // For UHS modes, we would need to check 1.8V support
-        if mode.requires_1v8() {
-            // TODO: Check if 1.8V signaling is supported
-            // For now, reject UHS modes that require voltage switching
+        if mode.requires_1v8() && !self.signalling_1v8 {
             return Err(Error::UnsupportedSpeedMode);
         }
 
@@ -1151,9 +1399,85 @@ impl<'d, M: Mode> Sdxc<'d, M> {
             return Err(Error::SignallingSwitchFailed);
         }
 
+        // Host timing follows the card, UHS mode select is only used with 1.8V signalling
+        let regs = self.info.regs;
+        let prev_uhs_mode = regs.ac_host_ctrl().read().uhs_mode_sel();
+        let prev_clock = self.clock;
+        let prev_signalling = self.signalling;
+        regs.ac_host_ctrl().modify(|w| {
+            w.set_sample_clk_sel(false);
+            if self.signalling_1v8 {
+                w.set_uhs_mode_sel(actual_mode.switch_function_value() as u8);
+            }
+        });
+        self.set_clock(Hertz(actual_mode.clock_hz()));
+        self.signalling = actual_mode;
+
+        if actual_mode.requires_tuning() {
+            // CMD19: SEND_TUNING_BLOCK, 64 bytes on the 4-bit bus
+            if let Err(e) = self.execute_tuning(19, 64) {
+                // Back to the previous host timing, a fallback CMD6 must not run at the untuned clock
+                regs.ac_host_ctrl().modify(|w| {
+                    w.set_sample_clk_sel(false);
+                    w.set_uhs_mode_sel(prev_uhs_mode);
+                });
+                self.set_clock(prev_clock);
+                self.signalling = prev_signalling;
+                return Err(e);
+            }
+        }
+
         Ok(actual_mode)
     }
 
+    /// Switch the host and board I/O signalling voltage
+    fn set_io_1v8(&mut self, enable: bool) {
+        self.info.regs.ac_host_ctrl().modify(|w| w.set_signaling_en(enable));
+        if let Some(switch) = self.io_switch.as_mut() {
+            switch.set_1v8(enable);
+        }
+        self.signalling_1v8 = enable;
+    }
+
+    /// CMD11: VOLTAGE_SWITCH sequence to 1.8V signalling
+    ///
+    /// On failure the card must be power cycled before it can be initialized again.
+    fn voltage_switch(&mut self) -> Result<(), Error> {
+        use embedded_hal::delay::DelayNs;
+        use riscv::delay::McycleDelay;
+
+        let regs = self.info.regs;
+        let mut delay = McycleDelay::new(crate::sysctl::clocks().cpu0.0);
+
+        self.cmd(types::common_cmd::cmd::<types::common_cmd::R1>(11, 0), false)?;
+
+        // Stop the clock, the card drives CMD and DAT[3:0] low while switching
+        regs.sys_ctrl().modify(|w| w.set_sd_clk_en(false));
+        while regs.sys_ctrl().read().sd_clk_en() {}
+        // PSTATE DAT[3:0] line levels, bits 23:20
+        if (regs.pstate().read().0 >> 20) & 0xF != 0 {
+            regs.sys_ctrl().modify(|w| w.set_sd_clk_en(true));
+            return Err(Error::SignallingSwitchFailed);
+        }
+
+        self.set_io_1v8(true);
+
+        // At least 5ms for the regulator to settle
+        delay.delay_ms(5);
+
+        regs.sys_ctrl().modify(|w| w.set_sd_clk_en(true));
+        while !regs.sys_ctrl().read().sd_clk_en() {}
+
+        // At least 1ms before the card releases DAT[3:0]
+        delay.delay_ms(1);
+        if (regs.pstate().read().0 >> 20) & 0xF != 0xF {
+            self.set_io_1v8(false);
+            return Err(Error::SignallingSwitchFailed);
+        }
+
+        Ok(())
+    }
+
     /// Send CMD6 (SWITCH_FUNC) command
     ///
     /// This command is used to check and switch card functions like speed mode,
@@ -1318,6 +1642,46 @@ fn configure_d3_pin<T: Instance>(pin: &impl D3Pin<T>) {
     });
 }
 
+fn configure_d4_pin<T: Instance>(pin: &impl D4Pin<T>) {
+    pin.ioc_pad().func_ctl().write(|w| {
+        w.set_alt_select(pin.alt_num());
+        w.set_loop_back(true);
+    });
+    pin.ioc_pad().pad_ctl().write(|w| {
+        w.set_ds(7);
+    });
+}
+
+fn configure_d5_pin<T: Instance>(pin: &impl D5Pin<T>) {
+    pin.ioc_pad().func_ctl().write(|w| {
+        w.set_alt_select(pin.alt_num());
+        w.set_loop_back(true);
+    });
+    pin.ioc_pad().pad_ctl().write(|w| {
+        w.set_ds(7);
+    });
+}
+
+fn configure_d6_pin<T: Instance>(pin: &impl D6Pin<T>) {
+    pin.ioc_pad().func_ctl().write(|w| {
+        w.set_alt_select(pin.alt_num());
+        w.set_loop_back(true);
+    });
+    pin.ioc_pad().pad_ctl().write(|w| {
+        w.set_ds(7);
+    });
+}
+
+fn configure_d7_pin<T: Instance>(pin: &impl D7Pin<T>) {
+    pin.ioc_pad().func_ctl().write(|w| {
+        w.set_alt_select(pin.alt_num());
+        w.set_loop_back(true);
+    });
+    pin.ioc_pad().pad_ctl().write(|w| {
+        w.set_ds(7);
+    });
+}
+
 // ============================================================================
 // Blocking mode implementation
 // ============================================================================
@@ -1376,19 +1740,56 @@ impl<'d> Sdxc<'d, Blocking> {
         )
     }
 
+    /// Create a new blocking SDXC driver with 8-bit bus width, for eMMC
+    pub fn new_blocking_8bit<T: Instance>(
+        peri: Peri<'d, T>,
+        clk: Peri<'d, impl ClkPin<T>>,
+        cmd: Peri<'d, impl CmdPin<T>>,
+        d0: Peri<'d, impl D0Pin<T>>,
+        d1: Peri<'d, impl D1Pin<T>>,
+        d2: Peri<'d, impl D2Pin<T>>,
+        d3: Peri<'d, impl D3Pin<T>>,
+        d4: Peri<'d, impl D4Pin<T>>,
+        d5: Peri<'d, impl D5Pin<T>>,
+        d6: Peri<'d, impl D6Pin<T>>,
+        d7: Peri<'d, impl D7Pin<T>>,
+        config: Config,
+    ) -> Self {
+        configure_clk_pin::<T>(&*clk);
+        configure_cmd_pin::<T>(&*cmd);
+        configure_d0_pin::<T>(&*d0);
+        configure_d1_pin::<T>(&*d1);
+        configure_d2_pin::<T>(&*d2);
+        configure_d3_pin::<T>(&*d3);
+        configure_d4_pin::<T>(&*d4);
+        configure_d5_pin::<T>(&*d5);
+        configure_d6_pin::<T>(&*d6);
+        configure_d7_pin::<T>(&*d7);
+
+        Self::new_inner(
+            peri,
+            clk.into(),
+            cmd.into(),
+            d0.into(),
+            Some(d1.into()),
+            Some(d2.into()),
+            Some(d3.into()),
+            Some(d4.into()),
+            Some(d5.into()),
+            Some(d6.into()),
+            Some(d7.into()),
+            config,
+        )
+    }
+
     /// Read a single 512-byte block
     ///
     /// Note: Uses SDMA mode due to hardware errata E00033 (PIO mode unreliable).
     pub fn read_block(&mut self, block_idx: u32, buffer: &mut DataBlock) -> Result<(), Error> {
-        let card = self.card.as_ref().ok_or(Error::NoCard)?;
         let regs = self.info.regs;
 
         // Address conversion: SDHC/SDXC use block address, SDSC uses byte address
-        let address = match card.card_type {
-            types::CardCapacity::StandardCapacity => block_idx * 512,
-            types::CardCapacity::HighCapacity => block_idx,
-            _ => block_idx,
-        };
+        let address = self.block_address(block_idx)?;
 
         // Use internal DMA buffer in noncacheable AXI_SRAM
         // Stack buffers may be in DLM (not DMA-accessible) or cacheable AXI_SRAM
@@ -1408,7 +1809,7 @@ impl<'d> Sdxc<'d, Blocking> {
         regs.sdmasa().write(|w| w.0 = dma_buf.as_ptr() as u32);
 
         // CMD16: SET_BLOCKLEN
-        self.cmd(types::common_cmd::set_block_length(512), false)?;
+        self.set_block_length(512)?;
 
         // CMD17: READ_SINGLE_BLOCK with DMA enabled
         self.cmd_dma(types::common_cmd::read_single_block(address))?;
@@ -1468,15 +1869,10 @@ impl<'d> Sdxc<'d, Blocking> {
             return self.read_block(block_idx, &mut buffers[0]);
         }
 
-        let card = self.card.as_ref().ok_or(Error::NoCard)?;
         let regs = self.info.regs;
 
         // Address conversion
-        let address = match card.card_type {
-            types::CardCapacity::StandardCapacity => block_idx * 512,
-            types::CardCapacity::HighCapacity => block_idx,
-            _ => block_idx,
-        };
+        let address = self.block_address(block_idx)?;
 
         let block_count = buffers.len() as u16;
 
@@ -1505,7 +1901,7 @@ impl<'d> Sdxc<'d, Blocking> {
         defmt::debug!("PROT_CTRL: 0x{:08X}", regs.prot_ctrl().read().0);
 
         // CMD16: SET_BLOCKLEN
-        self.cmd(types::common_cmd::set_block_length(512), false)?;
+        self.set_block_length(512)?;
 
         #[cfg(feature = "defmt")]
         defmt::debug!("Sending CMD18 (READ_MULTIPLE_BLOCK), addr={}", address);
@@ -1577,15 +1973,10 @@ impl<'d> Sdxc<'d, Blocking> {
     ///
     /// Note: Uses SDMA mode due to hardware errata E00033 (PIO mode unreliable).
     pub fn write_block(&mut self, block_idx: u32, buffer: &DataBlock) -> Result<(), Error> {
-        let card = self.card.as_ref().ok_or(Error::NoCard)?;
         let regs = self.info.regs;
 
         // Address conversion: SDHC/SDXC use block address, SDSC uses byte address
-        let address = match card.card_type {
-            types::CardCapacity::StandardCapacity => block_idx * 512,
-            types::CardCapacity::HighCapacity => block_idx,
-            _ => block_idx,
-        };
+        let address = self.block_address(block_idx)?;
 
         // Copy user data to DMA buffer and flush D-Cache
         let dma_buf = get_dma_buffer();
@@ -1608,7 +1999,7 @@ impl<'d> Sdxc<'d, Blocking> {
         regs.sdmasa().write(|w| w.0 = dma_buf.as_ptr() as u32);
 
         // CMD16: SET_BLOCKLEN
-        self.cmd(types::common_cmd::set_block_length(512), false)?;
+        self.set_block_length(512)?;
 
         // CMD24: WRITE_SINGLE_BLOCK with DMA enabled
         self.cmd_dma_write(types::common_cmd::write_single_block(address))?;
@@ -1662,15 +2053,10 @@ impl<'d> Sdxc<'d, Blocking> {
             return self.write_block(block_idx, &buffers[0]);
         }
 
-        let card = self.card.as_ref().ok_or(Error::NoCard)?;
         let regs = self.info.regs;
 
         // Address conversion
-        let address = match card.card_type {
-            types::CardCapacity::StandardCapacity => block_idx * 512,
-            types::CardCapacity::HighCapacity => block_idx,
-            _ => block_idx,
-        };
+        let address = self.block_address(block_idx)?;
 
         let block_count = buffers.len() as u16;
 
@@ -1691,7 +2077,7 @@ impl<'d> Sdxc<'d, Blocking> {
         regs.adma_sys_addr().write(|w| w.0 = adma_table.as_ptr() as u32);
 
         // CMD16: SET_BLOCKLEN
-        self.cmd(types::common_cmd::set_block_length(512), false)?;
+        self.set_block_length(512)?;
 
         // CMD25: WRITE_MULTIPLE_BLOCK with ADMA2 multi-block mode
         self.cmd_adma2_multi_write(types::common_cmd::write_multiple_blocks(address))?;
@@ -1778,21 +2164,62 @@ impl<'d> Sdxc<'d, Async> {
         )
     }
 
+    /// Create a new async SDXC driver with 8-bit bus width, for eMMC
+    pub fn new_8bit<T: Instance>(
+        peri: Peri<'d, T>,
+        _irq: impl interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+        clk: Peri<'d, impl ClkPin<T>>,
+        cmd: Peri<'d, impl CmdPin<T>>,
+        d0: Peri<'d, impl D0Pin<T>>,
+        d1: Peri<'d, impl D1Pin<T>>,
+        d2: Peri<'d, impl D2Pin<T>>,
+        d3: Peri<'d, impl D3Pin<T>>,
+        d4: Peri<'d, impl D4Pin<T>>,
+        d5: Peri<'d, impl D5Pin<T>>,
+        d6: Peri<'d, impl D6Pin<T>>,
+        d7: Peri<'d, impl D7Pin<T>>,
+        config: Config,
+    ) -> Self {
+        T::Interrupt::unpend();
+        unsafe { T::Interrupt::enable() };
+
+        configure_clk_pin::<T>(&*clk);
+        configure_cmd_pin::<T>(&*cmd);
+        configure_d0_pin::<T>(&*d0);
+        configure_d1_pin::<T>(&*d1);
+        configure_d2_pin::<T>(&*d2);
+        configure_d3_pin::<T>(&*d3);
+        configure_d4_pin::<T>(&*d4);
+        configure_d5_pin::<T>(&*d5);
+        configure_d6_pin::<T>(&*d6);
+        configure_d7_pin::<T>(&*d7);
+
+        Self::new_inner(
+            peri,
+            clk.into(),
+            cmd.into(),
+            d0.into(),
+            Some(d1.into()),
+            Some(d2.into()),
+            Some(d3.into()),
+            Some(d4.into()),
+            Some(d5.into()),
+            Some(d6.into()),
+            Some(d7.into()),
+            config,
+        )
+    }
+
     /// Async read single block using SDMA + interrupt
     pub async fn read_block_async(&mut self, block_idx: u32, buffer: &mut DataBlock) -> Result<(), Error> {
         use core::future::poll_fn;
         use core::task::Poll;
 
-        let card = self.card.as_ref().ok_or(Error::NoCard)?;
         let regs = self.info.regs;
         let state = self.state;
 
         // Address conversion
-        let address = match card.card_type {
-            types::CardCapacity::StandardCapacity => block_idx * 512,
-            types::CardCapacity::HighCapacity => block_idx,
-            _ => block_idx,
-        };
+        let address = self.block_address(block_idx)?;
 
         // Use internal DMA buffer in noncacheable AXI_SRAM
         let dma_buf = get_dma_buffer();
@@ -1812,7 +2239,7 @@ impl<'d> Sdxc<'d, Async> {
         regs.sdmasa().write(|w| w.0 = dma_buf.as_ptr() as u32);
 
         // Enable interrupts (status enable + signal enable)
-        regs.int_stat_en().write(|w| {
+        regs.int_stat_en().modify(|w| {
             w.set_cmd_complete_stat_en(true);
             w.set_xfer_complete_stat_en(true);
             w.set_dma_interrupt_stat_en(true);
@@ -1820,14 +2247,14 @@ impl<'d> Sdxc<'d, Async> {
             w.set_data_crc_err_stat_en(true);
             w.set_cmd_tout_err_stat_en(true);
         });
-        regs.int_signal_en().write(|w| {
+        regs.int_signal_en().modify(|w| {
             w.set_xfer_complete_signal_en(true);
             w.set_data_tout_err_signal_en(true);
             w.set_data_crc_err_signal_en(true);
         });
 
         // CMD16: SET_BLOCKLEN
-        self.cmd(types::common_cmd::set_block_length(512), false)?;
+        self.set_block_length(512)?;
 
         // CMD17: READ_SINGLE_BLOCK with DMA
         self.cmd_dma(types::common_cmd::read_single_block(address))?;
@@ -1851,7 +2278,7 @@ impl<'d> Sdxc<'d, Async> {
         .await;
 
         // Disable interrupts
-        regs.int_signal_en().write(|_| {});
+        regs.int_signal_en().modify(|w| w.0 &= CARD_DETECT_SIGNALS);
 
         // Clear status
         regs.int_stat().write(|w| w.0 = 0xFFFFFFFF);
@@ -1876,16 +2303,11 @@ impl<'d> Sdxc<'d, Async> {
         use core::future::poll_fn;
         use core::task::Poll;
 
-        let card = self.card.as_ref().ok_or(Error::NoCard)?;
         let regs = self.info.regs;
         let state = self.state;
 
         // Address conversion
-        let address = match card.card_type {
-            types::CardCapacity::StandardCapacity => block_idx * 512,
-            types::CardCapacity::HighCapacity => block_idx,
-            _ => block_idx,
-        };
+        let address = self.block_address(block_idx)?;
 
         // Copy user data to noncacheable DMA buffer and flush D-Cache
         let dma_buf = get_dma_buffer();
@@ -1909,7 +2331,7 @@ impl<'d> Sdxc<'d, Async> {
         regs.sdmasa().write(|w| w.0 = dma_buf.as_ptr() as u32);
 
         // Enable interrupts
-        regs.int_stat_en().write(|w| {
+        regs.int_stat_en().modify(|w| {
             w.set_cmd_complete_stat_en(true);
             w.set_xfer_complete_stat_en(true);
             w.set_dma_interrupt_stat_en(true);
@@ -1917,14 +2339,14 @@ impl<'d> Sdxc<'d, Async> {
             w.set_data_crc_err_stat_en(true);
             w.set_cmd_tout_err_stat_en(true);
         });
-        regs.int_signal_en().write(|w| {
+        regs.int_signal_en().modify(|w| {
             w.set_xfer_complete_signal_en(true);
             w.set_data_tout_err_signal_en(true);
             w.set_data_crc_err_signal_en(true);
         });
 
         // CMD16: SET_BLOCKLEN
-        self.cmd(types::common_cmd::set_block_length(512), false)?;
+        self.set_block_length(512)?;
 
         // CMD24: WRITE_SINGLE_BLOCK with DMA
         self.cmd_dma_write(types::common_cmd::write_single_block(address))?;
@@ -1948,7 +2370,7 @@ impl<'d> Sdxc<'d, Async> {
         .await;
 
         // Disable interrupts
-        regs.int_signal_en().write(|_| {});
+        regs.int_signal_en().modify(|w| w.0 &= CARD_DETECT_SIGNALS);
 
         // Clear status
         regs.int_stat().write(|w| w.0 = 0xFFFFFFFF);
@@ -1978,16 +2400,11 @@ impl<'d> Sdxc<'d, Async> {
             return self.read_block_async(block_idx, &mut buffers[0]).await;
         }
 
-        let card = self.card.as_ref().ok_or(Error::NoCard)?;
         let regs = self.info.regs;
         let state = self.state;
 
         // Address conversion
-        let address = match card.card_type {
-            types::CardCapacity::StandardCapacity => block_idx * 512,
-            types::CardCapacity::HighCapacity => block_idx,
-            _ => block_idx,
-        };
+        let address = self.block_address(block_idx)?;
 
         let block_count = buffers.len() as u16;
 
@@ -2009,7 +2426,7 @@ impl<'d> Sdxc<'d, Async> {
         regs.adma_sys_addr().write(|w| w.0 = adma_table.as_ptr() as u32);
 
         // Enable interrupts
-        regs.int_stat_en().write(|w| {
+        regs.int_stat_en().modify(|w| {
             w.set_cmd_complete_stat_en(true);
             w.set_xfer_complete_stat_en(true);
             w.set_dma_interrupt_stat_en(true);
@@ -2018,7 +2435,7 @@ impl<'d> Sdxc<'d, Async> {
             w.set_cmd_tout_err_stat_en(true);
             w.set_adma_err_stat_en(true);
         });
-        regs.int_signal_en().write(|w| {
+        regs.int_signal_en().modify(|w| {
             w.set_xfer_complete_signal_en(true);
             w.set_data_tout_err_signal_en(true);
             w.set_data_crc_err_signal_en(true);
@@ -2026,7 +2443,7 @@ impl<'d> Sdxc<'d, Async> {
         });
 
         // CMD16: SET_BLOCKLEN
-        self.cmd(types::common_cmd::set_block_length(512), false)?;
+        self.set_block_length(512)?;
 
         // CMD18: READ_MULTIPLE_BLOCK with ADMA2
         self.cmd_adma2_multi_read(types::common_cmd::read_multiple_blocks(address))?;
@@ -2053,7 +2470,7 @@ impl<'d> Sdxc<'d, Async> {
         .await;
 
         // Disable interrupts
-        regs.int_signal_en().write(|_| {});
+        regs.int_signal_en().modify(|w| w.0 &= CARD_DETECT_SIGNALS);
 
         // Clear status
         regs.int_stat().write(|w| w.0 = 0xFFFFFFFF);
@@ -2078,16 +2495,11 @@ impl<'d> Sdxc<'d, Async> {
             return self.write_block_async(block_idx, &buffers[0]).await;
         }
 
-        let card = self.card.as_ref().ok_or(Error::NoCard)?;
         let regs = self.info.regs;
         let state = self.state;
 
         // Address conversion
-        let address = match card.card_type {
-            types::CardCapacity::StandardCapacity => block_idx * 512,
-            types::CardCapacity::HighCapacity => block_idx,
-            _ => block_idx,
-        };
+        let address = self.block_address(block_idx)?;
 
         let block_count = buffers.len() as u16;
 
@@ -2109,7 +2521,7 @@ impl<'d> Sdxc<'d, Async> {
         regs.adma_sys_addr().write(|w| w.0 = adma_table.as_ptr() as u32);
 
         // Enable interrupts
-        regs.int_stat_en().write(|w| {
+        regs.int_stat_en().modify(|w| {
             w.set_cmd_complete_stat_en(true);
             w.set_xfer_complete_stat_en(true);
             w.set_dma_interrupt_stat_en(true);
@@ -2118,7 +2530,7 @@ impl<'d> Sdxc<'d, Async> {
             w.set_cmd_tout_err_stat_en(true);
             w.set_adma_err_stat_en(true);
         });
-        regs.int_signal_en().write(|w| {
+        regs.int_signal_en().modify(|w| {
             w.set_xfer_complete_signal_en(true);
             w.set_data_tout_err_signal_en(true);
             w.set_data_crc_err_signal_en(true);
@@ -2126,7 +2538,7 @@ impl<'d> Sdxc<'d, Async> {
         });
 
         // CMD16: SET_BLOCKLEN
-        self.cmd(types::common_cmd::set_block_length(512), false)?;
+        self.set_block_length(512)?;
 
         // CMD25: WRITE_MULTIPLE_BLOCK with ADMA2
         self.cmd_adma2_multi_write(types::common_cmd::write_multiple_blocks(address))?;
@@ -2153,7 +2565,7 @@ impl<'d> Sdxc<'d, Async> {
         .await;
 
         // Disable interrupts
-        regs.int_signal_en().write(|_| {});
+        regs.int_signal_en().modify(|w| w.0 &= CARD_DETECT_SIGNALS);
 
         // Clear status
         regs.int_stat().write(|w| w.0 = 0xFFFFFFFF);
@@ -2275,10 +2687,7 @@ mod sdmmc_impl {
 
         fn num_blocks(&self) -> Result<embedded_sdmmc::BlockCount, Self::Error> {
             let driver = self.inner.borrow();
-            let card = driver.card().ok_or(Error::NoCard)?;
-            // Calculate block count from CSD
-            // For SDHC/SDXC, CSD v2.0 uses C_SIZE directly
-            let block_count = card.csd.block_count();
+            let block_count = driver.block_count()?;
             Ok(embedded_sdmmc::BlockCount(block_count as u32))
         }
     }
@@ -0,0 +1,780 @@
+//! SDIO cards: CMD52/CMD53 I/O, function setup, card interrupt and CIS
+//!
+//! [`SdioDevice`] is the async interface for SDIO Wi-Fi and Bluetooth chips. Register
+//! accesses with CMD52 are short and blocking, CMD53 data transfers wait for the
+//! transfer complete interrupt.
+
+use core::cell::UnsafeCell;
+use core::future::poll_fn;
+use core::task::Poll;
+
+use super::types::{self, Adma2Descriptor, Adma2Table, BusWidth, Error, Signalling};
+use super::{CARD_DETECT_SIGNALS, SD_INIT_FREQ, Sdxc, get_dma_buffer};
+use crate::mode::{Async, Mode};
+use crate::time::Hertz;
+
+/// Card Common Control Registers, function 0
+mod cccr {
+    pub const REVISION: u32 = 0x00;
+    pub const SD_REVISION: u32 = 0x01;
+    pub const IO_ENABLE: u32 = 0x02;
+    pub const IO_READY: u32 = 0x03;
+    pub const INT_ENABLE: u32 = 0x04;
+    pub const INT_PENDING: u32 = 0x05;
+    pub const IO_ABORT: u32 = 0x06;
+    pub const BUS_INTERFACE: u32 = 0x07;
+    pub const CAPABILITY: u32 = 0x08;
+    pub const CIS_POINTER: u32 = 0x09;
+    pub const FN0_BLOCK_SIZE: u32 = 0x10;
+    pub const BUS_SPEED: u32 = 0x13;
+    pub const UHS_SUPPORT: u32 = 0x14;
+
+    /// IO_ABORT: reset all functions
+    pub const RES: u8 = 1 << 3;
+    /// INT_ENABLE: interrupt enable master
+    pub const IENM: u8 = 1 << 0;
+    /// BUS_INTERFACE: 4-bit bus, disable the card detect pull-up on DAT3
+    pub const BUS_WIDTH_4: u8 = 0x2;
+    pub const CD_DISABLE: u8 = 1 << 7;
+    /// CAPABILITY: multi-block CMD53, low speed card, 4-bit low speed card
+    pub const SMB: u8 = 1 << 1;
+    pub const LSC: u8 = 1 << 6;
+    pub const LS_4BIT: u8 = 1 << 7;
+    /// BUS_SPEED: high speed support, bus speed select field
+    pub const SHS: u8 = 1 << 0;
+    pub const BSS_MASK: u8 = 0x7 << 1;
+    /// UHS_SUPPORT: SDR50 and SDR104
+    pub const SSDR50: u8 = 1 << 0;
+    pub const SSDR104: u8 = 1 << 1;
+}
+
+/// Function Basic Registers of function `n`, at `0x100 * n`
+mod fbr {
+    pub const INTERFACE_CODE: u32 = 0x00;
+    pub const CIS_POINTER: u32 = 0x09;
+    pub const BLOCK_SIZE: u32 = 0x10;
+}
+
+/// CIS tuple codes
+mod cis {
+    pub const NULL: u8 = 0x00;
+    pub const MANFID: u8 = 0x20;
+    pub const FUNCE: u8 = 0x22;
+    pub const END: u8 = 0xFF;
+}
+
+/// R5 response flags with an error: COM_CRC_ERROR, ILLEGAL_COMMAND, ERROR,
+/// FUNCTION_NUMBER and OUT_OF_RANGE
+const R5_ERROR_FLAGS: u8 = 0xCB;
+
+/// Largest CMD53 byte mode transfer
+const MAX_BYTE_COUNT: usize = 512;
+/// Largest CMD53 block count
+const MAX_BLOCK_COUNT: usize = 511;
+/// ADMA2 descriptor length, a multiple of 4 below the 16-bit limit
+const ADMA2_CHUNK: usize = 0xF000;
+
+/// Descriptors for CMD53 block transfers of one instance, 511 blocks of 2048 bytes at most
+///
+/// Placed in noncacheable memory by the instance `Info`.
+pub(crate) struct SdioAdmaTable(UnsafeCell<Adma2Table<18>>);
+
+// Only used through `&mut Sdxc` of the owning instance
+unsafe impl Sync for SdioAdmaTable {}
+
+impl SdioAdmaTable {
+    pub(crate) const fn new() -> Self {
+        Self(UnsafeCell::new(Adma2Table::new()))
+    }
+}
+
+/// SDIO card information
+#[derive(Debug, Clone, Copy)]
+pub struct SdioCard {
+    /// Relative Card Address
+    pub rca: u16,
+    /// I/O OCR from the CMD5 response
+    pub ocr: u32,
+    /// Number of I/O functions, 1 to 7
+    pub functions: u8,
+    /// Combo card with a memory part, not used by this driver
+    pub memory_present: bool,
+    /// CCCR format and SDIO specification revision
+    pub cccr_revision: u8,
+    /// SD physical layer revision
+    pub sd_revision: u8,
+    /// CCCR card capability
+    pub capability: u8,
+}
+
+impl SdioCard {
+    /// CMD53 multi-block transfers are supported
+    pub fn supports_multi_block(&self) -> bool {
+        self.capability & cccr::SMB != 0
+    }
+}
+
+/// SDIO function information from the FBR and CIS
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SdioFunctionInfo {
+    /// Manufacturer code (MANFID), e.g. 0x02D0 for Broadcom/Cypress
+    pub manufacturer: u16,
+    /// Manufacturer information, the card ID (MANFID)
+    pub card_id: u16,
+    /// Largest block size of the function (FUNCE)
+    pub max_block_size: u16,
+    /// Standard SDIO function interface code, 0 for function 0
+    pub interface_code: u8,
+}
+
+/// CMD52 IO_RW_DIRECT argument
+fn cmd52_arg(write: bool, function: u8, address: u32, raw: bool, value: u8) -> u32 {
+    (write as u32) << 31 | (function as u32 & 0x7) << 28 | (raw as u32) << 27 | (address & 0x1FFFF) << 9 | value as u32
+}
+
+/// CMD53 IO_RW_EXTENDED argument, `count` bytes or blocks with 0 meaning 512 bytes
+fn cmd53_arg(write: bool, function: u8, address: u32, block_mode: bool, incrementing: bool, count: u16) -> u32 {
+    (write as u32) << 31
+        | (function as u32 & 0x7) << 28
+        | (block_mode as u32) << 27
+        | (incrementing as u32) << 26
+        | (address & 0x1FFFF) << 9
+        | (count as u32 & 0x1FF)
+}
+
+/// Check the R5 response flags, bits 15:8, and return the data byte
+fn r5_data(response: u32) -> Result<u8, Error> {
+    let flags = (response >> 8) as u8;
+    if flags & R5_ERROR_FLAGS != 0 {
+        return Err(Error::IoResponse(flags));
+    }
+    Ok(response as u8)
+}
+
+/// I/O functions are numbered 1-7, function 0 is the CCCR
+fn check_io_function(function: u8) -> Result<(), Error> {
+    if (1..=7).contains(&function) {
+        Ok(())
+    } else {
+        Err(Error::InvalidArgument)
+    }
+}
+
+impl<'d, M: Mode> Sdxc<'d, M> {
+    /// Initialize an SDIO card
+    ///
+    /// Selects the 4-bit bus when available and the fastest bus speed that fits in `freq`:
+    /// SDR104 and SDR50 after a switch to 1.8V with an [`IoVoltageSwitch`](super::IoVoltageSwitch),
+    /// otherwise high speed.
+    pub fn init_sdio(&mut self, freq: Hertz) -> Result<(), Error> {
+        let regs = self.info.regs;
+
+        regs.int_stat().write(|w| w.0 = 0xFFFFFFFF);
+        self.card = None;
+        self.emmc = None;
+        self.sdio = None;
+
+        self.set_bus_width(BusWidth::One);
+        self.set_io_1v8(false);
+        regs.ac_host_ctrl().modify(|w| {
+            w.set_uhs_mode_sel(0);
+            w.set_sample_clk_sel(false);
+        });
+        self.signalling = Signalling::SDR12;
+        // Only enabled while waiting, DAT1 stays low until the function clears its source
+        regs.int_stat_en().modify(|w| w.set_card_interrupt_stat_en(false));
+
+        self.set_clock(SD_INIT_FREQ);
+        {
+            use crate::gpio::SealedPin;
+            self._cmd.ioc_pad().pad_ctl().write(|w| {
+                w.set_ds(7);
+                w.set_od(true);
+            });
+        }
+        self.wait_card_active();
+        for _ in 0..500000 {
+            core::hint::spin_loop();
+        }
+
+        // Reset the I/O part of an already initialized card, no response from a fresh one
+        let _ = self.io_rw_direct(cmd52_arg(true, 0, cccr::IO_ABORT, false, cccr::RES));
+
+        // CMD0: GO_IDLE_STATE
+        self.cmd(types::common_cmd::idle(), false)?;
+
+        // CMD5: IO_SEND_OP_COND, query the I/O OCR
+        self.cmd(types::common_cmd::cmd::<types::common_cmd::R3>(5, 0), false)?;
+        let response = self.get_response();
+        let functions = ((response >> 28) & 0x7) as u8;
+        let memory_present = response & (1 << 27) != 0;
+
+        // CMD5 with the 2.7-3.6V window until power up is complete, S18R with a 1.8V switch
+        let s18r = self.io_switch.is_some();
+        let arg = (response & 0x00FF_8000) | (s18r as u32) << 24;
+        let mut ocr = None;
+        for _ in 0..1000 {
+            match self.cmd(types::common_cmd::cmd::<types::common_cmd::R3>(5, arg), false) {
+                Ok(_) | Err(Error::Crc) => {}
+                Err(e) => return Err(e),
+            }
+            let response = self.get_response();
+            if response & (1 << 31) != 0 {
+                ocr = Some(response);
+                break;
+            }
+            for _ in 0..10000 {
+                core::hint::spin_loop();
+            }
+        }
+        let ocr = ocr.ok_or(Error::Timeout)?;
+
+        if s18r && ocr & (1 << 24) != 0 {
+            self.voltage_switch()?;
+        }
+
+        // CMD3: SEND_RELATIVE_ADDR
+        self.cmd(types::sd_cmd::send_relative_address(), false)?;
+        let rca = types::RCA::<types::SD>::from(self.get_response()).address();
+
+        // CMD7: SELECT_CARD
+        self.cmd(types::common_cmd::select_card(rca), false)?;
+
+        self.set_clock(Hertz(freq.0.min(Signalling::SDR12.clock_hz())));
+        {
+            use crate::gpio::SealedPin;
+            self._cmd.ioc_pad().pad_ctl().write(|w| {
+                w.set_ds(7);
+            });
+        }
+        self.wait_card_active();
+
+        let cccr_revision = self.io_read_byte(0, cccr::REVISION)?;
+        let sd_revision = self.io_read_byte(0, cccr::SD_REVISION)?;
+        let capability = self.io_read_byte(0, cccr::CAPABILITY)?;
+        self.sdio = Some(SdioCard {
+            rca,
+            ocr,
+            functions,
+            memory_present,
+            cccr_revision,
+            sd_revision,
+            capability,
+        });
+
+        // Low speed cards only have a 4-bit bus with 4BLS
+        let four_bit = capability & cccr::LSC == 0 || capability & cccr::LS_4BIT != 0;
+        if self._d3.is_some() && four_bit {
+            let bus = self.io_read_byte(0, cccr::BUS_INTERFACE)?;
+            self.io_write_byte(
+                0,
+                cccr::BUS_INTERFACE,
+                (bus & !0x3) | cccr::BUS_WIDTH_4 | cccr::CD_DISABLE,
+            )?;
+            self.set_bus_width(BusWidth::Four);
+        }
+
+        // Fastest bus speed supported by the card whose clock fits in `freq`
+        let bus_speed = self.io_read_byte(0, cccr::BUS_SPEED)?;
+        let uhs = if self.signalling_1v8 {
+            self.io_read_byte(0, cccr::UHS_SUPPORT)?
+        } else {
+            0
+        };
+        let modes = [
+            (Signalling::SDR104, uhs & cccr::SSDR104 != 0),
+            (Signalling::SDR50, uhs & cccr::SSDR50 != 0),
+            (Signalling::SDR25, bus_speed & cccr::SHS != 0),
+        ];
+        for (mode, supported) in modes {
+            if !supported || mode.clock_hz() > freq.0 {
+                continue;
+            }
+            // BSS uses the CMD6 access mode values
+            let bss = (mode.switch_function_value() as u8) << 1;
+            self.io_write_byte(0, cccr::BUS_SPEED, (bus_speed & !cccr::BSS_MASK) | bss)?;
+            if self.io_read_byte(0, cccr::BUS_SPEED)? & cccr::BSS_MASK != bss {
+                continue;
+            }
+
+            let prev_clock = self.clock;
+            regs.ac_host_ctrl().modify(|w| {
+                if self.signalling_1v8 {
+                    w.set_uhs_mode_sel(mode.switch_function_value() as u8);
+                }
+            });
+            self.set_clock(Hertz(mode.clock_hz()));
+            if mode.requires_tuning() && self.execute_tuning(19, 64).is_err() {
+                // Back to the SDR12 timing the card is still reachable with, then try the next slower mode
+                regs.ac_host_ctrl().modify(|w| {
+                    w.set_sample_clk_sel(false);
+                    w.set_uhs_mode_sel(0);
+                });
+                self.set_clock(prev_clock);
+                self.io_write_byte(0, cccr::BUS_SPEED, bus_speed & !cccr::BSS_MASK)?;
+                continue;
+            }
+            self.signalling = mode;
+            break;
+        }
+
+        Ok(())
+    }
+
+    /// Get the SDIO card information, if initialized with `init_sdio`
+    pub fn sdio(&self) -> Option<&SdioCard> {
+        self.sdio.as_ref()
+    }
+
+    /// CMD52: IO_RW_DIRECT
+    fn io_rw_direct(&self, arg: u32) -> Result<u8, Error> {
+        self.cmd(types::common_cmd::cmd::<types::common_cmd::R1>(52, arg), false)?;
+        r5_data(self.get_response())
+    }
+
+    /// CMD52 read of register `address` of `function`
+    fn io_read_byte(&self, function: u8, address: u32) -> Result<u8, Error> {
+        self.io_rw_direct(cmd52_arg(false, function, address, false, 0))
+    }
+
+    /// CMD52 write of register `address` of `function`
+    fn io_write_byte(&self, function: u8, address: u32, value: u8) -> Result<(), Error> {
+        self.io_rw_direct(cmd52_arg(true, function, address, false, value))
+            .map(|_| ())
+    }
+
+    /// CMD53: IO_RW_EXTENDED, issue the command of a DMA data transfer
+    fn cmd_io_extended(&self, arg: u32, write: bool, multi_block: bool) -> Result<(), Error> {
+        let regs = self.info.regs;
+
+        while regs.pstate().read().cmd_inhibit() {}
+        while regs.pstate().read().dat_inhibit() {}
+
+        regs.cmd_arg().write(|w| w.0 = arg);
+        regs.cmd_xfer().write(|w| {
+            w.set_cmd_index(53);
+            w.set_resp_type_select(2);
+            w.set_cmd_crc_chk_enable(true);
+            w.set_cmd_idx_chk_enable(true);
+            w.set_data_present_sel(true);
+            w.set_data_xfer_dir(!write);
+            w.set_dma_enable(true);
+            w.set_multi_blk_sel(multi_block);
+            w.set_block_count_enable(multi_block);
+        });
+
+        let mut timeout_count = 0u32;
+        loop {
+            let status = regs.int_stat().read();
+
+            if status.cmd_complete() {
+                regs.int_stat().write(|w| w.set_cmd_complete(true));
+                return r5_data(self.get_response()).map(|_| ());
+            }
+
+            if status.cmd_tout_err() {
+                return Err(Error::Timeout);
+            }
+            if status.cmd_crc_err() {
+                return Err(Error::Crc);
+            }
+            if status.cmd_end_bit_err() {
+                return Err(Error::CmdEndBit);
+            }
+            if status.cmd_idx_err() {
+                return Err(Error::CmdIndex);
+            }
+
+            timeout_count += 1;
+            if timeout_count > 10_000_000 {
+                return Err(Error::SoftwareTimeout);
+            }
+        }
+    }
+}
+
+/// Async SDIO device on an initialized [`Sdxc`], the bus of an SDIO Wi-Fi driver
+pub struct SdioDevice<'d> {
+    sdxc: Sdxc<'d, Async>,
+    /// Block size set for each function, 0 for byte mode only
+    block_size: [u16; 8],
+}
+
+impl<'d> SdioDevice<'d> {
+    /// Initialize the SDIO card on `sdxc`, see [`Sdxc::init_sdio`]
+    pub fn new(mut sdxc: Sdxc<'d, Async>, freq: Hertz) -> Result<Self, Error> {
+        sdxc.init_sdio(freq)?;
+        Ok(Self {
+            sdxc,
+            block_size: [0; 8],
+        })
+    }
+
+    /// Card information
+    pub fn card(&self) -> &SdioCard {
+        // Set by `init_sdio` in `new`
+        self.sdxc.sdio.as_ref().unwrap()
+    }
+
+    /// Get the SDXC driver
+    pub fn inner(&mut self) -> &mut Sdxc<'d, Async> {
+        &mut self.sdxc
+    }
+
+    /// Release the SDXC driver
+    pub fn into_inner(self) -> Sdxc<'d, Async> {
+        self.sdxc
+    }
+
+    /// Read register `address` of `function` with CMD52
+    pub fn read_byte(&mut self, function: u8, address: u32) -> Result<u8, Error> {
+        self.sdxc.io_read_byte(function, address)
+    }
+
+    /// Write register `address` of `function` with CMD52
+    pub fn write_byte(&mut self, function: u8, address: u32, value: u8) -> Result<(), Error> {
+        self.sdxc.io_write_byte(function, address, value)
+    }
+
+    /// Write register `address` of `function` and read it back in the same CMD52 (RAW)
+    pub fn write_read_byte(&mut self, function: u8, address: u32, value: u8) -> Result<u8, Error> {
+        self.sdxc.io_rw_direct(cmd52_arg(true, function, address, true, value))
+    }
+
+    /// Enable I/O `function` (1-7) and wait until it is ready
+    pub fn enable_function(&mut self, function: u8) -> Result<(), Error> {
+        check_io_function(function)?;
+        let mask = 1 << function;
+        let enabled = self.read_byte(0, cccr::IO_ENABLE)?;
+        self.write_byte(0, cccr::IO_ENABLE, enabled | mask)?;
+
+        for _ in 0..10000 {
+            if self.read_byte(0, cccr::IO_READY)? & mask != 0 {
+                return Ok(());
+            }
+            for _ in 0..1000 {
+                core::hint::spin_loop();
+            }
+        }
+        Err(Error::SoftwareTimeout)
+    }
+
+    /// Disable I/O `function`
+    pub fn disable_function(&mut self, function: u8) -> Result<(), Error> {
+        check_io_function(function)?;
+        let enabled = self.read_byte(0, cccr::IO_ENABLE)?;
+        self.write_byte(0, cccr::IO_ENABLE, enabled & !(1 << function))
+    }
+
+    /// Set the CMD53 block size of `function`, 0 to use byte mode transfers only
+    ///
+    /// Must not exceed the `max_block_size` from [`function_info`](Self::function_info)
+    /// nor 2048 bytes, [`Error::InvalidArgument`] is returned above 2048 or for a function above 7.
+    pub fn set_block_size(&mut self, function: u8, size: u16) -> Result<(), Error> {
+        if size > 2048 || function > 7 {
+            return Err(Error::InvalidArgument);
+        }
+        let base = if function == 0 {
+            cccr::FN0_BLOCK_SIZE
+        } else {
+            0x100 * function as u32 + fbr::BLOCK_SIZE
+        };
+        let [low, high] = size.to_le_bytes();
+        self.write_byte(0, base, low)?;
+        self.write_byte(0, base + 1, high)?;
+        self.block_size[function as usize & 0x7] = size;
+        Ok(())
+    }
+
+    /// Enable or disable the card interrupt of `function` (1-7)
+    pub fn enable_interrupt(&mut self, function: u8, enable: bool) -> Result<(), Error> {
+        check_io_function(function)?;
+        let mut int_enable = self.read_byte(0, cccr::INT_ENABLE)?;
+        if enable {
+            int_enable |= (1 << function) | cccr::IENM;
+        } else {
+            int_enable &= !(1 << function);
+            if int_enable & !cccr::IENM == 0 {
+                int_enable = 0;
+            }
+        }
+        self.write_byte(0, cccr::INT_ENABLE, int_enable)
+    }
+
+    /// Wait for the card interrupt on DAT1, returns the pending functions as a bit mask
+    ///
+    /// The interrupt is level triggered: the function's interrupt source must be cleared
+    /// before waiting again.
+    pub async fn wait_interrupt(&mut self) -> Result<u8, Error> {
+        let regs = self.sdxc.info.regs;
+        let state = self.sdxc.state;
+
+        poll_fn(|cx| {
+            state.waker.register(cx.waker());
+            if regs.int_stat().read().card_interrupt() {
+                return Poll::Ready(());
+            }
+            // The interrupt handler masks the raised signals, enable again on each poll
+            regs.int_stat_en().modify(|w| w.set_card_interrupt_stat_en(true));
+            regs.int_signal_en().modify(|w| w.set_card_interrupt_signal_en(true));
+            Poll::Pending
+        })
+        .await;
+
+        regs.int_signal_en().modify(|w| w.set_card_interrupt_signal_en(false));
+        regs.int_stat_en().modify(|w| w.set_card_interrupt_stat_en(false));
+
+        self.read_byte(0, cccr::INT_PENDING)
+    }
+
+    /// Read `buf.len()` bytes from `address` of `function` with CMD53
+    ///
+    /// With `incrementing` the address advances with the data, otherwise all bytes come from
+    /// the same register, e.g. a FIFO. Transfers of at least one block use block mode and
+    /// ADMA2 straight into `buf`, which must then be 4-byte aligned in DMA-accessible,
+    /// noncacheable memory. Shorter transfers go through the driver DMA buffer.
+    pub async fn read(&mut self, function: u8, address: u32, buf: &mut [u8], incrementing: bool) -> Result<(), Error> {
+        let mut address = address;
+        let mut offset = 0;
+        while offset < buf.len() {
+            let n = self
+                .transfer(
+                    function,
+                    address,
+                    buf[offset..].as_mut_ptr(),
+                    buf.len() - offset,
+                    incrementing,
+                    false,
+                )
+                .await?;
+            offset += n;
+            if incrementing {
+                address += n as u32;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write `buf` to `address` of `function` with CMD53, see [`read`](Self::read)
+    pub async fn write(&mut self, function: u8, address: u32, buf: &[u8], incrementing: bool) -> Result<(), Error> {
+        let mut address = address;
+        let mut offset = 0;
+        while offset < buf.len() {
+            // The buffer is only read for writes
+            let ptr = buf[offset..].as_ptr() as *mut u8;
+            let n = self
+                .transfer(function, address, ptr, buf.len() - offset, incrementing, true)
+                .await?;
+            offset += n;
+            if incrementing {
+                address += n as u32;
+            }
+        }
+        Ok(())
+    }
+
+    /// One CMD53 transfer of at most `len` bytes at `ptr`, returns the bytes transferred
+    async fn transfer(
+        &mut self,
+        function: u8,
+        address: u32,
+        ptr: *mut u8,
+        len: usize,
+        incrementing: bool,
+        write: bool,
+    ) -> Result<usize, Error> {
+        let regs = self.sdxc.info.regs;
+        let block_size = self.block_size[function as usize & 0x7] as usize;
+        let block_mode = block_size != 0 && len >= block_size && self.card().supports_multi_block();
+
+        regs.int_stat().write(|w| w.0 = 0xFFFFFFFF);
+
+        let n = if block_mode {
+            if ptr as usize % 4 != 0 {
+                return Err(Error::UnalignedBuffer);
+            }
+            let blocks = (len / block_size).min(MAX_BLOCK_COUNT);
+            let n = blocks * block_size;
+
+            // Safety: `&mut self` makes this the only user of the instance's table
+            let table = unsafe { &mut *self.sdxc.info.sdio_adma_table.0.get() };
+            let chunks = n.div_ceil(ADMA2_CHUNK);
+            for i in 0..chunks {
+                let start = i * ADMA2_CHUNK;
+                let size = (n - start).min(ADMA2_CHUNK);
+                table.descriptors[i] =
+                    Adma2Descriptor::transfer(ptr as u32 + start as u32, size as u16, i == chunks - 1);
+            }
+
+            regs.blk_attr().write(|w| {
+                w.set_xfer_block_size(block_size as u16);
+                w.set_block_cnt(blocks as u16);
+            });
+            regs.prot_ctrl().modify(|w| w.set_dma_sel(2)); // ADMA2
+            regs.adma_sys_addr().write(|w| w.0 = table.as_ptr() as u32);
+
+            let arg = cmd53_arg(write, function, address, true, incrementing, blocks as u16);
+            self.sdxc.cmd_io_extended(arg, write, blocks > 1)?;
+            self.wait_transfer().await?;
+            n
+        } else {
+            let limit = if block_size != 0 { block_size } else { MAX_BYTE_COUNT };
+            let n = len.min(limit).min(MAX_BYTE_COUNT);
+            let dma_buf = get_dma_buffer();
+
+            if write {
+                unsafe {
+                    core::ptr::copy_nonoverlapping(ptr, dma_buf.as_mut_ptr(), n);
+                    andes_riscv::l1c::dc_writeback(dma_buf.as_ptr() as u32, 512);
+                }
+            }
+
+            regs.blk_attr().write(|w| {
+                w.set_xfer_block_size(n as u16);
+                w.set_block_cnt(1);
+            });
+            regs.prot_ctrl().modify(|w| w.set_dma_sel(0)); // SDMA
+            regs.sdmasa().write(|w| w.0 = dma_buf.as_ptr() as u32);
+
+            // A byte count of 0 means 512
+            let arg = cmd53_arg(write, function, address, false, incrementing, (n % 512) as u16);
+            self.sdxc.cmd_io_extended(arg, write, false)?;
+            self.wait_transfer().await?;
+
+            if !write {
+                unsafe {
+                    andes_riscv::l1c::dc_invalidate(dma_buf.as_ptr() as u32, 512);
+                    let src = dma_buf.as_ptr();
+                    for i in 0..n {
+                        ptr.add(i).write(core::ptr::read_volatile(src.add(i)));
+                    }
+                }
+            }
+            n
+        };
+
+        Ok(n)
+    }
+
+    /// Wait for the transfer complete interrupt
+    async fn wait_transfer(&mut self) -> Result<(), Error> {
+        let regs = self.sdxc.info.regs;
+        let state = self.sdxc.state;
+
+        regs.int_stat_en().modify(|w| {
+            w.set_xfer_complete_stat_en(true);
+            w.set_data_tout_err_stat_en(true);
+            w.set_data_crc_err_stat_en(true);
+            w.set_adma_err_stat_en(true);
+        });
+        regs.int_signal_en().modify(|w| {
+            w.set_xfer_complete_signal_en(true);
+            w.set_data_tout_err_signal_en(true);
+            w.set_data_crc_err_signal_en(true);
+            w.set_adma_err_signal_en(true);
+        });
+
+        let result = poll_fn(|cx| {
+            state.waker.register(cx.waker());
+            let status = regs.int_stat().read();
+
+            if status.data_tout_err() {
+                return Poll::Ready(Err(Error::DataTimeout));
+            }
+            if status.data_crc_err() {
+                return Poll::Ready(Err(Error::DataCrc));
+            }
+            if status.adma_err() {
+                return Poll::Ready(Err(Error::AdmaError));
+            }
+            if status.xfer_complete() {
+                return Poll::Ready(Ok(()));
+            }
+            Poll::Pending
+        })
+        .await;
+
+        regs.int_signal_en().modify(|w| w.0 &= CARD_DETECT_SIGNALS);
+        regs.int_stat().write(|w| w.0 = 0xFFFFFFFF);
+
+        result
+    }
+
+    /// Find the first CIS tuple `code` of `function` and copy its body into `buf`
+    ///
+    /// Returns the full body length, which may exceed `buf`, or `None` if the tuple is absent.
+    /// `function` 0 is the common CIS.
+    pub fn find_cis_tuple(&mut self, function: u8, code: u8, buf: &mut [u8]) -> Result<Option<usize>, Error> {
+        let base = if function == 0 {
+            cccr::CIS_POINTER
+        } else {
+            check_io_function(function)?;
+            0x100 * function as u32 + fbr::CIS_POINTER
+        };
+        let mut ptr = 0u32;
+        for i in 0..3 {
+            ptr |= (self.read_byte(0, base + i)? as u32) << (8 * i);
+        }
+
+        // Bounded walk, a broken chain must not hang the caller
+        for _ in 0..256 {
+            let tuple = self.read_byte(0, ptr)?;
+            match tuple {
+                cis::END => return Ok(None),
+                cis::NULL => {
+                    ptr += 1;
+                    continue;
+                }
+                _ => {}
+            }
+            let link = self.read_byte(0, ptr + 1)?;
+            if link == cis::END {
+                return Ok(None);
+            }
+            if tuple == code {
+                let len = (link as usize).min(buf.len());
+                for (i, byte) in buf[..len].iter_mut().enumerate() {
+                    *byte = self.read_byte(0, ptr + 2 + i as u32)?;
+                }
+                return Ok(Some(link as usize));
+            }
+            ptr += 2 + link as u32;
+        }
+        Ok(None)
+    }
+
+    /// Read the identification and block size limit of `function` from its CIS, 0 for the common CIS
+    pub fn function_info(&mut self, function: u8) -> Result<SdioFunctionInfo, Error> {
+        if function != 0 {
+            check_io_function(function)?;
+        }
+        let mut info = SdioFunctionInfo::default();
+        let mut body = [0u8; 14];
+
+        // MANFID is in the common CIS, some cards repeat it per function
+        let manfid = match self.find_cis_tuple(function, cis::MANFID, &mut body)? {
+            Some(len) => Some(len),
+            None if function != 0 => self.find_cis_tuple(0, cis::MANFID, &mut body)?,
+            None => None,
+        };
+        if manfid.is_some_and(|len| len >= 4) {
+            info.manufacturer = u16::from_le_bytes([body[0], body[1]]);
+            info.card_id = u16::from_le_bytes([body[2], body[3]]);
+        }
+
+        // FUNCE: type 0 for function 0 with FN0_BLK_SIZE, type 1 with MAX_BLK_SIZE at 12
+        if let Some(len) = self.find_cis_tuple(function, cis::FUNCE, &mut body)? {
+            if function == 0 && len >= 3 {
+                info.max_block_size = u16::from_le_bytes([body[1], body[2]]);
+            } else if len >= 14 {
+                info.max_block_size = u16::from_le_bytes([body[12], body[13]]);
+            }
+        }
+
+        if function != 0 {
+            info.interface_code = self.read_byte(0, 0x100 * function as u32 + fbr::INTERFACE_CODE)? & 0xF;
+        }
+        Ok(info)
+    }
+}
@@ -3,7 +3,8 @@
 // Re-export sdio-host types
 pub use sdio_host::common_cmd::{self, Cmd, Resp, ResponseLen};
 pub use sdio_host::sd::{CardCapacity, CardStatus, CurrentState, CIC, CID, CSD, OCR, RCA, SCR, SD, SDStatus};
-pub use sdio_host::sd_cmd;
+pub use sdio_host::emmc::EMMC;
+pub use sdio_host::{emmc_cmd, sd_cmd};
 
 /// SD Card information
 #[derive(Debug, Clone, Default)]
@@ -78,12 +79,15 @@ impl core::ops::IndexMut<usize> for DataBlock {
 pub struct Config {
     /// Data timeout in clock cycles
     pub data_timeout: u32,
+    /// eMMC I/O (VCCQ) is supplied with 1.8V, allows HS200 in `init_emmc`
+    pub emmc_vccq_1v8: bool,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             data_timeout: 5_000_000,
+            emmc_vccq_1v8: false,
         }
     }
 }
@@ -129,6 +133,18 @@ pub enum Error {
     SignallingSwitchFailed,
     /// Unsupported speed mode
     UnsupportedSpeedMode,
+    /// Sampling clock tuning failed
+    TuningFailed,
+    /// Card rejected a SWITCH command (eMMC SWITCH_ERROR)
+    SwitchError,
+    /// eMMC partition not present on the device
+    NoPartition,
+    /// SDIO R5 response flags with an error bit set
+    IoResponse(u8),
+    /// Buffer is not 4-byte aligned for ADMA2
+    UnalignedBuffer,
+    /// Argument out of range, e.g. an SDIO function number or block size
+    InvalidArgument,
 }
 
 // ============================================================================
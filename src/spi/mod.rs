@@ -16,12 +16,15 @@ use embassy_sync::waitqueue::AtomicWaker;
 pub use embedded_hal::spi::{MODE_0, MODE_1, MODE_2, MODE_3, Mode};
 
 use self::consts::*;
+pub use self::shared_bus::*;
 use crate::dma::{self, ChannelAndRequest, word};
 use crate::gpio::AnyPin;
 use crate::mode::{Async, Blocking, Mode as PeriMode};
 pub use crate::pac::spi::vals::{AddrLen, AddrPhaseFormat, DataPhaseFormat, TransMode};
 use crate::time::Hertz;
 
+mod shared_bus;
+
 #[cfg(any(hpm53, hpm68, hpm6e))]
 mod consts {
     pub const TRANSFER_COUNT_MAX: usize = 0xFFFFFFFF;
@@ -133,6 +136,7 @@ pub enum BitOrder {
 }
 
 /// Config struct of SPI
+#[derive(Clone, Copy)]
 pub struct Config {
     /// Whether to use LSB.
     pub bit_order: BitOrder,
@@ -757,6 +761,12 @@ impl<'d, M: PeriMode> Spi<'d, M> {
         }
     }
 
+    /// Reconfigure frequency, mode, bit order and timings.
+    pub fn set_config(&mut self, config: &Config) -> Result<(), Error> {
+        self.blocking_flush();
+        self.enable_and_configure(config)
+    }
+
     fn enable_and_configure(&mut self, config: &Config) -> Result<(), Error> {
         let r = self.info.regs;
 
@@ -805,6 +815,7 @@ impl<'d, M: PeriMode> Spi<'d, M> {
             w.set_cpha(cpha);
             w.set_cpol(cpol);
         });
+        self.current_word_size = <u8 as SealedWord>::CONFIG;
 
         Ok(())
     }
@@ -0,0 +1,298 @@
+//! Shared SPI bus, multiple devices with their own chip-select and config on one SPI peripheral.
+//!
+//! Each device switches the bus to its own [`Config`] (frequency, mode, bit order and timings)
+//! at the start of every transaction.
+//!
+//! Chip-select can be either a GPIO [`Output`] or, on chips with `SPI_CS_SELECT`
+//! (HPM53, HPM68, HPM6E), one of the hardware CS lines of the SPI controller.
+//!
+//! NOTE: Hardware CS is only asserted during a single controller transfer, so devices using it only
+//! accept transactions made of a single operation.
+
+use core::cell::RefCell;
+
+#[cfg(ip_feature_spi_cs_select)]
+use embassy_hal_internal::Peri;
+use embassy_sync::blocking_mutex::raw::RawMutex;
+use embassy_sync::mutex::Mutex;
+use embedded_hal::delay::DelayNs;
+use embedded_hal::spi::Operation;
+use riscv::delay::McycleDelay;
+
+use super::{Config, Error, Spi};
+#[cfg(ip_feature_spi_cs_select)]
+use super::{CsIndexPin, CsPin, Instance};
+#[cfg(ip_feature_spi_cs_select)]
+use crate::gpio::AnyPin;
+use crate::gpio::Output;
+use crate::mode::{Async, Mode as PeriMode};
+
+enum ChipSelect<'a> {
+    Gpio(Output<'a>),
+    #[cfg(ip_feature_spi_cs_select)]
+    Hardware {
+        _pin: Peri<'a, AnyPin>,
+        index: u8,
+    },
+}
+
+impl<'a> ChipSelect<'a> {
+    #[cfg(ip_feature_spi_cs_select)]
+    fn new_hardware<T: Instance>(cs: Peri<'a, impl CsPin<T> + CsIndexPin<T>>) -> Self {
+        cs.set_as_alt(cs.alt_num());
+        let index = cs.cs_index();
+
+        Self::Hardware {
+            _pin: cs.into(),
+            index,
+        }
+    }
+}
+
+#[cfg(ip_feature_spi_cs_select)]
+fn check_instance<T: Instance>(regs: crate::pac::spi::Spi) {
+    assert!(
+        regs.as_ptr() == T::info().regs.as_ptr(),
+        "CS pin does not belong to this SPI"
+    );
+}
+
+// - MARK: Blocking
+
+/// Blocking shared SPI bus, backed by a `RefCell`.
+pub struct SharedBus<'d, M: PeriMode> {
+    bus: RefCell<Spi<'d, M>>,
+    #[cfg(ip_feature_spi_cs_select)]
+    regs: crate::pac::spi::Spi,
+}
+
+impl<'d, M: PeriMode> SharedBus<'d, M> {
+    /// Create a new shared bus from an SPI driver.
+    pub fn new(spi: Spi<'d, M>) -> Self {
+        Self {
+            #[cfg(ip_feature_spi_cs_select)]
+            regs: spi.info.regs,
+            bus: RefCell::new(spi),
+        }
+    }
+
+    /// Release the SPI driver.
+    pub fn into_inner(self) -> Spi<'d, M> {
+        self.bus.into_inner()
+    }
+}
+
+/// A device on a [`SharedBus`].
+pub struct SharedBusDevice<'a, 'd, M: PeriMode> {
+    bus: &'a SharedBus<'d, M>,
+    cs: ChipSelect<'a>,
+    config: Config,
+}
+
+impl<'a, 'd, M: PeriMode> SharedBusDevice<'a, 'd, M> {
+    /// Create a new device, using a GPIO as chip-select.
+    pub fn new(bus: &'a SharedBus<'d, M>, mut cs: Output<'a>, config: Config) -> Self {
+        cs.set_high();
+
+        Self {
+            bus,
+            cs: ChipSelect::Gpio(cs),
+            config,
+        }
+    }
+
+    /// Create a new device, using a hardware CS line of the SPI controller.
+    #[cfg(ip_feature_spi_cs_select)]
+    pub fn new_hw_cs<T: Instance>(
+        bus: &'a SharedBus<'d, M>,
+        cs: Peri<'a, impl CsPin<T> + CsIndexPin<T>>,
+        config: Config,
+    ) -> Self {
+        check_instance::<T>(bus.regs);
+
+        Self {
+            bus,
+            cs: ChipSelect::new_hardware(cs),
+            config,
+        }
+    }
+
+    /// Change the config used by this device for the following transactions.
+    pub fn set_config(&mut self, config: Config) {
+        self.config = config;
+    }
+}
+
+fn blocking_operation<M: PeriMode>(bus: &mut Spi<'_, M>, op: &mut Operation<'_, u8>) -> Result<(), Error> {
+    use embedded_hal::spi::SpiBus;
+
+    match op {
+        Operation::Read(buf) => SpiBus::read(bus, buf),
+        Operation::Write(buf) => SpiBus::write(bus, buf),
+        Operation::Transfer(read, write) => SpiBus::transfer(bus, read, write),
+        Operation::TransferInPlace(buf) => SpiBus::transfer_in_place(bus, buf),
+        Operation::DelayNs(ns) => {
+            bus.blocking_flush();
+            McycleDelay::new(crate::sysctl::clocks().cpu0.0).delay_ns(*ns);
+            Ok(())
+        }
+    }
+}
+
+impl<'a, 'd, M: PeriMode> embedded_hal::spi::ErrorType for SharedBusDevice<'a, 'd, M> {
+    type Error = Error;
+}
+
+impl<'a, 'd, M: PeriMode> embedded_hal::spi::SpiDevice for SharedBusDevice<'a, 'd, M> {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Self::Error> {
+        let mut bus = self.bus.bus.borrow_mut();
+        bus.set_config(&self.config)?;
+
+        match &mut self.cs {
+            ChipSelect::Gpio(cs) => {
+                // release the hardware CS left selected by a device using it
+                #[cfg(ip_feature_spi_cs_select)]
+                bus.info.regs.ctrl().modify(|w| w.set_cs_en(0));
+                cs.set_low();
+                let res = operations.iter_mut().try_for_each(|op| blocking_operation(&mut bus, op));
+                // must wait tx finished before releasing CS
+                bus.blocking_flush();
+                cs.set_high();
+                res
+            }
+            #[cfg(ip_feature_spi_cs_select)]
+            ChipSelect::Hardware { index, .. } => {
+                bus.info.regs.ctrl().modify(|w| w.set_cs_en(*index));
+                match operations {
+                    [] => Ok(()),
+                    [op] => blocking_operation(&mut bus, op),
+                    _ => Err(Error::InvalidArgument),
+                }
+            }
+        }
+    }
+}
+
+// - MARK: Async
+
+/// Async shared SPI bus, backed by an async `Mutex`.
+pub struct AsyncSharedBus<'d, R: RawMutex> {
+    bus: Mutex<R, Spi<'d, Async>>,
+    #[cfg(ip_feature_spi_cs_select)]
+    regs: crate::pac::spi::Spi,
+}
+
+impl<'d, R: RawMutex> AsyncSharedBus<'d, R> {
+    /// Create a new shared bus from an async SPI driver.
+    pub fn new(spi: Spi<'d, Async>) -> Self {
+        Self {
+            #[cfg(ip_feature_spi_cs_select)]
+            regs: spi.info.regs,
+            bus: Mutex::new(spi),
+        }
+    }
+
+    /// Release the SPI driver.
+    pub fn into_inner(self) -> Spi<'d, Async> {
+        self.bus.into_inner()
+    }
+}
+
+/// A device on an [`AsyncSharedBus`].
+pub struct AsyncSharedBusDevice<'a, 'd, R: RawMutex> {
+    bus: &'a AsyncSharedBus<'d, R>,
+    cs: ChipSelect<'a>,
+    config: Config,
+}
+
+impl<'a, 'd, R: RawMutex> AsyncSharedBusDevice<'a, 'd, R> {
+    /// Create a new device, using a GPIO as chip-select.
+    pub fn new(bus: &'a AsyncSharedBus<'d, R>, mut cs: Output<'a>, config: Config) -> Self {
+        cs.set_high();
+
+        Self {
+            bus,
+            cs: ChipSelect::Gpio(cs),
+            config,
+        }
+    }
+
+    /// Create a new device, using a hardware CS line of the SPI controller.
+    #[cfg(ip_feature_spi_cs_select)]
+    pub fn new_hw_cs<T: Instance>(
+        bus: &'a AsyncSharedBus<'d, R>,
+        cs: Peri<'a, impl CsPin<T> + CsIndexPin<T>>,
+        config: Config,
+    ) -> Self {
+        check_instance::<T>(bus.regs);
+
+        Self {
+            bus,
+            cs: ChipSelect::new_hardware(cs),
+            config,
+        }
+    }
+
+    /// Change the config used by this device for the following transactions.
+    pub fn set_config(&mut self, config: Config) {
+        self.config = config;
+    }
+}
+
+async fn async_operation(bus: &mut Spi<'_, Async>, op: &mut Operation<'_, u8>) -> Result<(), Error> {
+    use embedded_hal_async::spi::SpiBus;
+
+    match op {
+        Operation::Read(buf) => SpiBus::read(bus, buf).await,
+        Operation::Write(buf) => SpiBus::write(bus, buf).await,
+        Operation::Transfer(read, write) => SpiBus::transfer(bus, read, write).await,
+        Operation::TransferInPlace(buf) => SpiBus::transfer_in_place(bus, buf).await,
+        Operation::DelayNs(ns) => {
+            bus.blocking_flush();
+            #[cfg(feature = "time")]
+            embassy_time::Timer::after_nanos(*ns as u64).await;
+            #[cfg(not(feature = "time"))]
+            McycleDelay::new(crate::sysctl::clocks().cpu0.0).delay_ns(*ns);
+            Ok(())
+        }
+    }
+}
+
+impl<'a, 'd, R: RawMutex> embedded_hal_async::spi::ErrorType for AsyncSharedBusDevice<'a, 'd, R> {
+    type Error = Error;
+}
+
+impl<'a, 'd, R: RawMutex> embedded_hal_async::spi::SpiDevice for AsyncSharedBusDevice<'a, 'd, R> {
+    async fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Self::Error> {
+        let mut bus = self.bus.bus.lock().await;
+        bus.set_config(&self.config)?;
+
+        match &mut self.cs {
+            ChipSelect::Gpio(cs) => {
+                // release the hardware CS left selected by a device using it
+                #[cfg(ip_feature_spi_cs_select)]
+                bus.info.regs.ctrl().modify(|w| w.set_cs_en(0));
+                cs.set_low();
+                let mut res = Ok(());
+                for op in operations {
+                    res = async_operation(&mut bus, op).await;
+                    if res.is_err() {
+                        break;
+                    }
+                }
+                bus.blocking_flush();
+                cs.set_high();
+                res
+            }
+            #[cfg(ip_feature_spi_cs_select)]
+            ChipSelect::Hardware { index, .. } => {
+                bus.info.regs.ctrl().modify(|w| w.set_cs_en(*index));
+                match operations {
+                    [] => Ok(()),
+                    [op] => async_operation(&mut bus, op).await,
+                    _ => Err(Error::InvalidArgument),
+                }
+            }
+        }
+    }
+}
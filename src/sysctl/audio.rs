@@ -0,0 +1,152 @@
+//! Audio clock (AUD0/AUD1/...) planning
+//!
+//! 44.1kHz and 48kHz sample rate families can't both be derived exactly from one clock source.
+//! [`plan_audio_clock`] searches the clock sources (and on HPM6E00, the audio PLL settings)
+//! for the AUD clock closest to `sample_rate * mclk_multiple`, [`apply_audio_clock`] applies it.
+//!
+//! ```ignore
+//! let plan = sysctl::plan_audio_clock(Hertz(44_100), 256).unwrap();
+//! sysctl::apply_audio_clock(0, &plan);
+//! defmt::info!("MCLK {}Hz, error {}ppm", plan.mclk.0, plan.error_ppm());
+//! ```
+
+use super::sysctl_impl::AUDIO_CLOCK_SOURCES;
+#[cfg(hpm6e)]
+use super::sysctl_impl::{AUDIO_PLL_SOURCE, configure_audio_pll};
+use super::{ClockConfig, clocks, configure_audio_clock};
+#[cfg(hpm6e)]
+use super::{PllPostDiv, pllctlv2};
+use crate::time::Hertz;
+
+/// AUD clock divider range is 1 to 256
+const AUD_DIV_MAX: u32 = 256;
+
+#[cfg(hpm6e)]
+const POST_DIVS: [PllPostDiv; 11] = [
+    PllPostDiv::Div1p0,
+    PllPostDiv::Div1p2,
+    PllPostDiv::Div1p4,
+    PllPostDiv::Div1p6,
+    PllPostDiv::Div1p8,
+    PllPostDiv::Div2p0,
+    PllPostDiv::Div2p2,
+    PllPostDiv::Div2p4,
+    PllPostDiv::Div2p6,
+    PllPostDiv::Div2p8,
+    PllPostDiv::Div3p0,
+];
+
+/// Audio clock settings computed by [`plan_audio_clock`]
+#[derive(Clone, Copy)]
+pub struct AudioClockPlan {
+    /// Audio PLL VCO frequency and post divider, `None` if the PLLs are used as is
+    #[cfg(hpm6e)]
+    pub pll: Option<(Hertz, PllPostDiv)>,
+    /// AUD clock source and divider
+    pub aud: ClockConfig,
+    /// Requested MCLK frequency
+    pub target: Hertz,
+    /// Actual MCLK frequency
+    pub mclk: Hertz,
+}
+
+impl AudioClockPlan {
+    /// Deviation of the actual MCLK (and so the sample rate) from the requested one, in ppm
+    pub fn error_ppm(&self) -> i32 {
+        let diff = self.mclk.0 as i64 - self.target.0 as i64;
+        (diff * 1_000_000 / self.target.0 as i64) as i32
+    }
+
+    /// Actual sample rate for the `mclk_multiple` used in planning
+    pub fn sample_rate(&self, mclk_multiple: u32) -> u32 {
+        self.mclk.0 / mclk_multiple
+    }
+}
+
+/// Find the AUD clock settings closest to `sample_rate * mclk_multiple`, e.g. 256 * 48kHz
+///
+/// Existing clock sources are preferred. On HPM6E00 the audio PLL (PLL2) is only reprogrammed
+/// when none of them gives the exact frequency.
+///
+/// Returns `None` if the requested MCLK is 0 or overflows.
+pub fn plan_audio_clock(sample_rate: Hertz, mclk_multiple: u32) -> Option<AudioClockPlan> {
+    let target = sample_rate.0.checked_mul(mclk_multiple).filter(|&f| f > 0)?;
+
+    let mut best: Option<AudioClockPlan> = None;
+    let mut consider = |plan: AudioClockPlan| {
+        if best.is_none_or(|best| plan.error_ppm().abs() < best.error_ppm().abs()) {
+            best = Some(plan);
+        }
+    };
+
+    for &src in AUDIO_CLOCK_SOURCES {
+        let freq = clocks().of(src).0;
+        if freq == 0 {
+            continue;
+        }
+        let div = ((freq + target / 2) / target).clamp(1, AUD_DIV_MAX);
+
+        consider(AudioClockPlan {
+            #[cfg(hpm6e)]
+            pll: None,
+            aud: ClockConfig::new(src, div as u16),
+            target: Hertz(target),
+            mclk: Hertz(freq / div),
+        });
+    }
+
+    // VCO = MCLK * AUD div * post div, the VCO resolution is far below 1Hz
+    #[cfg(hpm6e)]
+    if best.is_none_or(|best| best.mclk.0 != target) {
+        'search: for div in 1..=AUD_DIV_MAX {
+            for postdiv in POST_DIVS {
+                let vco = target as u64 * div as u64 * postdiv.value_x10() as u64 / 10;
+                if vco < pllctlv2::FREQ_MIN as u64 {
+                    continue;
+                }
+                if vco > pllctlv2::FREQ_MAX as u64 {
+                    break;
+                }
+                let vco = vco as u32;
+                let mclk = (vco as u64 * 10 / postdiv.value_x10() as u64) as u32 / div;
+
+                consider(AudioClockPlan {
+                    pll: Some((Hertz(vco), postdiv)),
+                    aud: ClockConfig::new(AUDIO_PLL_SOURCE, div as u16),
+                    target: Hertz(target),
+                    mclk: Hertz(mclk),
+                });
+                if mclk == target {
+                    break 'search;
+                }
+            }
+        }
+    }
+
+    best
+}
+
+/// Apply a plan from [`plan_audio_clock`] to audio clock `aud_idx`
+///
+/// NOTE: On HPM6E00, reprogramming the audio PLL also changes other clocks derived from PLL2.
+pub fn apply_audio_clock(aud_idx: usize, plan: &AudioClockPlan) {
+    #[cfg(hpm6e)]
+    if let Some((vco, postdiv)) = plan.pll {
+        configure_audio_pll(vco, postdiv).expect("audio PLL frequency out of range");
+    }
+
+    configure_audio_clock(aud_idx, &plan.aud);
+}
+
+/// Plan and apply audio clock `aud_idx` for `sample_rate * mclk_multiple`
+///
+/// The returned plan reports the actual MCLK and its error.
+pub fn configure_audio_clock_for_rate(
+    aud_idx: usize,
+    sample_rate: Hertz,
+    mclk_multiple: u32,
+) -> Option<AudioClockPlan> {
+    let plan = plan_audio_clock(sample_rate, mclk_multiple)?;
+    apply_audio_clock(aud_idx, &plan);
+    Some(plan)
+}
@@ -24,8 +24,13 @@ mod sysctl_impl;
 
 mod pll;
 
+#[cfg(any(hpm6e, hpm67))]
+mod audio;
+
 use core::ptr::addr_of;
 
+#[cfg(any(hpm6e, hpm67))]
+pub use audio::*;
 pub use pll::*;
 pub use sysctl_impl::*;
 
@@ -82,6 +87,50 @@ pub fn clock_remove_from_group(resource: usize, group: usize) {
     while SYSCTL.resource(resource).read().loc_busy() {}
 }
 
+/// Clock state of a resource while its CPU is in low power mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ResourceMode {
+    /// Follow the low power mode of the resource groups
+    Auto = 0,
+    /// Keep clocked, e.g. for a peripheral that wakes up the CPU
+    ForceOn = 1,
+    /// Always gated
+    ForceOff = 2,
+}
+
+/// Set the low power clock mode of a resource
+pub fn resource_set_mode(resource: usize, mode: ResourceMode) {
+    const RESOURCE_START: usize = 256;
+    if resource < RESOURCE_START || resource == usize::MAX {
+        return;
+    }
+
+    SYSCTL.resource(resource).modify(|w| w.set_mode(mode as u8));
+    while SYSCTL.resource(resource).read().loc_busy() {}
+}
+
+/// Allow interrupt `irq` to wake up `cpu` from WAIT or STOP low power mode
+pub fn enable_wakeup(cpu: usize, irq: usize) {
+    SYSCTL
+        .cpu(cpu)
+        .wakeup_enable(irq / 32)
+        .modify(|w| w.set_enable(w.enable() | 1 << (irq % 32)));
+}
+
+/// Stop interrupt `irq` from waking up `cpu`
+pub fn disable_wakeup(cpu: usize, irq: usize) {
+    SYSCTL
+        .cpu(cpu)
+        .wakeup_enable(irq / 32)
+        .modify(|w| w.set_enable(w.enable() & !(1 << (irq % 32))));
+}
+
+/// Whether interrupt `irq` wakes up `cpu`
+pub fn is_wakeup_enabled(cpu: usize, irq: usize) -> bool {
+    SYSCTL.cpu(cpu).wakeup_enable(irq / 32).read().enable() & 1 << (irq % 32) != 0
+}
+
 pub(crate) trait SealedClockPeripheral {
     const SYSCTL_CLOCK: usize = usize::MAX;
     const SYSCTL_RESOURCE: usize = usize::MAX;
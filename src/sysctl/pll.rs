@@ -113,7 +113,7 @@ pub enum PllClkIndex {
 ///
 /// This module provides low-level PLL configuration functions shared across
 /// chip families that use the PLLCTLV2 peripheral.
-#[cfg(any(hpm63, hpm62))]
+#[cfg(any(hpm63, hpm62, hpm6e))]
 pub mod pllctlv2_hal {
     use super::*;
     use crate::pac::PLLCTL;
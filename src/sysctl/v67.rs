@@ -158,6 +158,20 @@ pub fn set_i2s_clock_source(i2s_idx: usize, src: I2sClkMux) {
     while SYSCTL.i2sclk(i2s_idx).read().loc_busy() {}
 }
 
+/// Clock sources an audio clock can be derived from
+///
+/// PLLs are not reconfigured for audio, sample rates must be reachable by dividing one of them.
+pub(crate) const AUDIO_CLOCK_SOURCES: &[ClockMux] = &[
+    ClockMux::PLL3CLK0,
+    ClockMux::PLL4CLK0,
+    ClockMux::PLL0CLK0,
+    ClockMux::PLL1CLK0,
+    ClockMux::PLL1CLK1,
+    ClockMux::PLL2CLK0,
+    ClockMux::PLL2CLK1,
+    ClockMux::CLK_24M,
+];
+
 /// Configure audio clock (AUD0/AUD1/AUD2)
 ///
 /// # Arguments
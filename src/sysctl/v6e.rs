@@ -2,7 +2,7 @@
 
 use core::ops;
 
-use super::clock_add_to_group;
+use super::{PllClkIndex, PllIndex, PllPostDiv, clock_add_to_group, pllctlv2_hal};
 use crate::pac;
 pub use crate::pac::sysctl::vals::ClockMux;
 use crate::pac::{PLLCTL, SYSCTL};
@@ -472,6 +472,48 @@ pub fn get_audio_clock_freq(aud_idx: usize) -> Hertz {
     }
 }
 
+/// Clock sources an audio clock can be derived from, without reconfiguring PLLs
+pub(crate) const AUDIO_CLOCK_SOURCES: &[ClockMux] = &[
+    ClockMux::PLL2CLK0,
+    ClockMux::PLL2CLK1,
+    ClockMux::PLL0CLK0,
+    ClockMux::PLL0CLK1,
+    ClockMux::PLL1CLK0,
+    ClockMux::PLL1CLK1,
+    ClockMux::PLL1CLK2,
+    ClockMux::CLK_24M,
+];
+
+/// PLL2 is dedicated to audio, its CLK0 output can be reconfigured for exact MCLK frequencies
+pub(crate) const AUDIO_PLL_SOURCE: ClockMux = ClockMux::PLL2CLK0;
+
+/// Reconfigure the audio PLL (PLL2 CLK0)
+///
+/// NOTE: PLL2 CLK1 shares the VCO, its frequency changes too.
+pub(crate) fn configure_audio_pll(vco_freq: Hertz, postdiv: PllPostDiv) -> Option<()> {
+    pllctlv2_hal::configure_pll_freq(PllIndex::Pll2, vco_freq.0)?;
+    pllctlv2_hal::configure_postdiv(PllIndex::Pll2, PllClkIndex::Clk0, postdiv);
+
+    unsafe {
+        CLOCKS.pll2clk0 = read_pll_postdiv_freq(2, 0);
+        CLOCKS.pll2clk1 = read_pll_postdiv_freq(2, 1);
+
+        // AUD clocks derived from PLL2 follow the new frequency
+        for aud_idx in 0..2 {
+            let r = SYSCTL.clock(pac::clocks::AUD0 + aud_idx).read();
+            let freq = CLOCKS.get_freq(&ClockConfig {
+                src: r.mux(),
+                raw_div: r.div(),
+            });
+            match aud_idx {
+                0 => CLOCKS.aud0 = freq,
+                _ => CLOCKS.aud1 = freq,
+            }
+        }
+    }
+    Some(())
+}
+
 /// I2S clock source selection
 pub use crate::pac::sysctl::vals::I2sClkMux;
 
@@ -8,7 +8,11 @@ use embedded_hal::delay::DelayNs;
 use hpm_metapac::usb::regs::*;
 use riscv::delay::McycleDelay;
 
-use super::{ENDPOINT_COUNT, EP_IN_WAKERS, EP_OUT_WAKERS, EndpointState, Instance, init_qhd, local_to_sys_address};
+use super::otg::{Role, power_handler};
+use super::{
+    ENDPOINT_COUNT, EP_IN_WAKERS, EP_OUT_WAKERS, EndpointState, Instance, enter_low_power, exit_low_power, init_qhd,
+    local_to_sys_address, phy_deinit, phy_init, wait_phy_clock,
+};
 use crate::usb::{BUS_WAKER, Config, EpConfig, IRQ_RESET, IRQ_SUSPEND, IRQ_VBUS_CHANGE, reset_dcd_data};
 
 /// USB bus
@@ -18,6 +22,8 @@ pub struct Bus<'d, T: Instance> {
     pub(crate) endpoints_in: [EndpointInfo; ENDPOINT_COUNT],
     pub(crate) delay: McycleDelay,
     pub(crate) inited: bool,
+    /// Suspend was reported, waiting for the port to resume
+    pub(crate) suspended: bool,
     pub(crate) config: Config,
     pub(crate) ep_state: &'d EndpointState,
 }
@@ -136,12 +142,38 @@ impl<T: Instance> embassy_usb_driver::Bus for Bus<'_, T> {
                 return Poll::Ready(Event::Reset);
             }
 
+            // Registers are not accessible while the clock is gated, wait for the wake-up interrupt
+            if self.suspended && T::state().low_power.load(Ordering::Acquire) {
+                return Poll::Pending;
+            }
+            // The interrupt handler restored the controller clock, the PHY clock follows
+            wait_phy_clock::<T>();
+
+            // RESUME event, the port left suspend state
+            if self.suspended && !r.portsc1().read().susp() {
+                self.suspended = false;
+                if let Some(handler) = power_handler::<T>() {
+                    handler.resume(Role::Device);
+                }
+                return Poll::Ready(Event::Resume);
+            }
+
             // SUSPEND event
             if IRQ_SUSPEND.load(Ordering::Acquire) {
                 IRQ_SUSPEND.store(false, Ordering::Relaxed);
                 if r.portsc1().read().susp() {
                     // Note: Host may delay more than 3 ms before and/or after bus reset before doing enumeration.
                     let _device_adr = r.deviceaddr().read().usbadr();
+
+                    // Resume is signalled by a port change
+                    self.suspended = true;
+                    r.usbintr().modify(|w| w.set_pce(true));
+                    if self.config.low_power_suspend {
+                        enter_low_power::<T>();
+                    }
+                    if let Some(handler) = power_handler::<T>() {
+                        handler.suspend(Role::Device);
+                    }
                 }
                 return Poll::Ready(Event::Suspend);
             }
@@ -201,6 +233,12 @@ impl<T: Instance> embassy_usb_driver::Bus for Bus<'_, T> {
     async fn remote_wakeup(&mut self) -> Result<(), Unsupported> {
         let r = T::info().regs;
 
+        if let Some(handler) = power_handler::<T>() {
+            handler.resume(Role::Device);
+        }
+        exit_low_power::<T>();
+        wait_phy_clock::<T>();
+
         // Set Force Port Resume bit
         r.portsc1().modify(|w| w.set_fpr(true));
 
@@ -211,69 +249,17 @@ impl<T: Instance> embassy_usb_driver::Bus for Bus<'_, T> {
     }
 }
 
-impl<T: Instance> Bus<'_, T> {
-    /// Initialize USB phy
-    fn phy_init(&mut self) {
-        let r = T::info().regs;
-
-        // Enable dp/dm pulldown
-        // In hpm_sdk, this operation is done by `ptr->PHY_CTRL0 &= ~0x001000E0u`.
-        // But there's corresponding bits in register, so we write the register directly here.
-        r.phy_ctrl0().modify(|w| w.0 = w.0 & (!(0x001000E0)));
-
-        r.otg_ctrl0().modify(|w| {
-            w.set_otg_utmi_reset_sw(true);
-            w.set_otg_utmi_suspendm_sw(false);
-        });
-
-        r.phy_ctrl1().modify(|w| {
-            w.set_utmi_cfg_rst_n(false);
-        });
-
-        // Wait for reset status
-        while !r.otg_ctrl0().read().otg_utmi_reset_sw() {}
-
-        // Set suspend
-        r.otg_ctrl0().modify(|w| {
-            w.set_otg_utmi_suspendm_sw(true);
-        });
-
-        // Delay at least 1us
-        self.delay.delay_us(5);
-
-        r.otg_ctrl0().modify(|w| {
-            // Disable dm/dp wakeup
-            w.set_otg_wkdpdmchg_en(false);
-            // Clear reset sw
-            w.set_otg_utmi_reset_sw(false);
-        });
-
-        // OTG utmi clock detection
-        r.phy_status().modify(|w| w.set_utmi_clk_valid(true));
-        while !r.phy_status().read().utmi_clk_valid() {}
-
-        // Reset and set suspend
-        r.phy_ctrl1().modify(|w| {
-            w.set_utmi_cfg_rst_n(true);
-            w.set_utmi_otg_suspendm(true);
-        });
-    }
-
-    // Deinitialize USB phy
-    fn phy_deinit(&mut self) {
-        let r = T::info().regs;
-
-        r.otg_ctrl0().modify(|w| {
-            w.set_otg_utmi_suspendm_sw(true);
-            w.set_otg_utmi_reset_sw(false);
-        });
-
-        r.phy_ctrl1().modify(|w| {
-            w.set_utmi_cfg_rst_n(false);
-            w.set_utmi_otg_suspendm(false);
-        });
+/// Stop the controller and release the endpoint state, e.g. when switching roles with `UsbOtg`
+impl<T: Instance> Drop for Bus<'_, T> {
+    fn drop(&mut self) {
+        exit_low_power::<T>();
+        wait_phy_clock::<T>();
+        self.device_deinit();
+        self.ep_state.release();
     }
+}
 
+impl<T: Instance> Bus<'_, T> {
     /// Get port speed: 00: full speed, 01: low speed, 10: high speed, 11: undefined
     #[allow(unused)]
     pub(crate) fn get_port_speed(&mut self) -> u8 {
@@ -319,7 +305,7 @@ impl<T: Instance> Bus<'_, T> {
     /// Initialize USB device controller driver
     fn device_init(&mut self) {
         // Initialize phy first
-        self.phy_init();
+        phy_init(T::info().regs, &mut self.delay);
 
         let r = T::info().regs;
 
@@ -380,7 +366,7 @@ impl<T: Instance> Bus<'_, T> {
         while r.usbcmd().read().rst() {}
 
         // Disable phy
-        self.phy_deinit();
+        phy_deinit(T::info().regs);
 
         // Reset endpoint list address register, status register and interrupt enable register
         r.endptlistaddr().write_value(Endptlistaddr(0));
@@ -453,8 +439,8 @@ impl<T: Instance> Bus<'_, T> {
             }
         }
 
-        // Disable endpoint
-        r.endptctrl(ep_addr.index() as usize).write(|w| {
+        // Disable endpoint, keeping the other direction configured
+        r.endptctrl(ep_addr.index() as usize).modify(|w| {
             if ep_addr.is_in() {
                 w.set_txt(0);
                 w.set_txe(false);
@@ -467,7 +453,7 @@ impl<T: Instance> Bus<'_, T> {
         });
 
         // Set transfer type back to ANY type other than control
-        r.endptctrl(ep_addr.index() as usize).write(|w| {
+        r.endptctrl(ep_addr.index() as usize).modify(|w| {
             if ep_addr.is_in() {
                 w.set_txt(EndpointType::Bulk as u8);
             } else {
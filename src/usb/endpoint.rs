@@ -2,18 +2,18 @@ use core::future::poll_fn;
 use core::marker::PhantomData;
 use core::task::Poll;
 
-use embassy_usb_driver::{EndpointAddress, EndpointIn, EndpointInfo, EndpointOut};
+use embassy_usb_driver::{EndpointAddress, EndpointIn, EndpointInfo, EndpointOut, EndpointType};
 
 use embassy_sync::waitqueue::AtomicWaker;
 
-use super::{QTD_COUNT_EACH_QHD, get_active_ep_state, local_to_sys_address};
+use super::{QTD_COUNT_EACH_QHD, exit_low_power, get_active_ep_state, local_to_sys_address, wait_phy_clock};
 use crate::usb::{EP_IN_WAKERS, EP_OUT_WAKERS, Instance};
 
 /// USB transfer error types
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum TransferError {
-    /// Transfer size exceeds maximum (>128KB, needs >8 QTDs)
+    /// Transfer size exceeds maximum (>128KB, needs >8 QTDs, or more than one (micro)frame for isochronous)
     BufferTooLarge,
     /// Buffer alignment requirement not met (>4K data needs 4K alignment)
     BufferAlignment,
@@ -75,10 +75,16 @@ impl<'d, T: Instance, D> Endpoint<'d, T, D> {
         });
     }
 
+    /// Packet size and packets per (micro)frame, from bits 10:0 and 12:11 of `max_packet_size`
+    fn packet_size_and_mult(&self) -> (usize, usize) {
+        let mps = self.info.max_packet_size;
+        ((mps & 0x7FF) as usize, ((mps >> 11) & 0x3) as usize + 1)
+    }
+
     /// Schedule the transfer
     ///
     /// # Errors
-    /// - `TransferError::BufferTooLarge` - Data exceeds 128KB (needs >8 QTDs)
+    /// - `TransferError::BufferTooLarge` - Data exceeds 128KB (needs >8 QTDs), or one (micro)frame for isochronous
     /// - `TransferError::BufferAlignment` - Data >4K but not 4K-aligned
     pub(crate) fn transfer(&mut self, data: &[u8]) -> Result<(), TransferError> {
         let r = T::info().regs;
@@ -98,6 +104,13 @@ impl<'d, T: Instance, D> Endpoint<'d, T, D> {
             return Err(TransferError::BufferTooLarge);
         }
 
+        // Isochronous transfers are a single qtd, carrying the packets of one (micro)frame
+        let iso = self.info.ep_type == EndpointType::Isochronous;
+        let (mps, mult) = self.packet_size_and_mult();
+        if iso && data.len() > mps * mult {
+            return Err(TransferError::BufferTooLarge);
+        }
+
         // Check alignment for >4K transfers (buffer[1-4] must be 4K aligned)
         if data.len() > 0x1000 && (data.as_ptr() as usize) % 0x1000 != 0 {
             return Err(TransferError::BufferAlignment);
@@ -139,6 +152,19 @@ impl<'d, T: Instance, D> Endpoint<'d, T, D> {
                 };
             }
 
+            // Number of packets to send in the (micro)frame, overrides the mult of the qhd
+            if iso && self.info.addr.is_in() {
+                let packets = data.len().div_ceil(mps.max(1)).clamp(1, mult);
+                unsafe {
+                    let ep_state = get_active_ep_state();
+                    ep_state
+                        .qtd_list()
+                        .qtd(qtd_idx)
+                        .qtd_token()
+                        .modify(|w| w.set_multo(packets as u8));
+                };
+            }
+
             data_offset += transfer_bytes;
 
             // Set qtd linked list
@@ -227,10 +253,25 @@ impl<'d, T: Instance> EndpointOut for Endpoint<'d, T, Out> {
         let r = T::info().regs;
         let ep_num = self.info.addr.index();
 
+        // The controller clock is gated while suspended in low power
+        exit_low_power::<T>();
+        if !wait_phy_clock::<T>() {
+            return Err(embassy_usb_driver::EndpointError::Disabled);
+        }
+
         if !Out::is_enabled(r, ep_num) {
             return Err(embassy_usb_driver::EndpointError::Disabled);
         }
 
+        // Isochronous reads receive one (micro)frame, larger buffers are fine
+        let buf = if self.info.ep_type == EndpointType::Isochronous {
+            let (mps, mult) = self.packet_size_and_mult();
+            let len = buf.len().min(mps * mult);
+            &mut buf[..len]
+        } else {
+            buf
+        };
+
         // Start read and wait
         self.transfer(buf).map_err(|_| embassy_usb_driver::EndpointError::BufferOverflow)?;
         poll_fn(|cx| {
@@ -262,6 +303,12 @@ impl<'d, T: Instance> EndpointIn for Endpoint<'d, T, In> {
         let r = T::info().regs;
         let ep_num = self.info.addr.index();
 
+        // The controller clock is gated while suspended in low power
+        exit_low_power::<T>();
+        if !wait_phy_clock::<T>() {
+            return Err(embassy_usb_driver::EndpointError::Disabled);
+        }
+
         if !In::is_enabled(r, ep_num) {
             return Err(embassy_usb_driver::EndpointError::Disabled);
         }
@@ -281,8 +328,8 @@ impl<'d, T: Instance> EndpointIn for Endpoint<'d, T, In> {
         })
         .await;
 
-        // Send zlt packet(if needed)
-        if buf.len() == self.info.max_packet_size as usize {
+        // Send zlt packet(if needed), isochronous transfers are framed by SOF instead
+        if self.info.ep_type != EndpointType::Isochronous && buf.len() == self.packet_size_and_mult().0 {
             let _ = self.transfer(&[]);
             poll_fn(|cx| {
                 In::waker(ep_num).register(cx.waker());
@@ -0,0 +1,307 @@
+//! Standard USB requests and descriptors used by the host driver
+
+use embassy_usb_driver::{Direction, EndpointAddress, EndpointType};
+
+/// Descriptor types
+pub mod descriptor_type {
+    pub const DEVICE: u8 = 1;
+    pub const CONFIGURATION: u8 = 2;
+    pub const STRING: u8 = 3;
+    pub const INTERFACE: u8 = 4;
+    pub const ENDPOINT: u8 = 5;
+    pub const HID: u8 = 0x21;
+    pub const HID_REPORT: u8 = 0x22;
+}
+
+/// Standard request codes
+pub mod request {
+    pub const GET_STATUS: u8 = 0;
+    pub const CLEAR_FEATURE: u8 = 1;
+    pub const SET_FEATURE: u8 = 3;
+    pub const SET_ADDRESS: u8 = 5;
+    pub const GET_DESCRIPTOR: u8 = 6;
+    pub const SET_DESCRIPTOR: u8 = 7;
+    pub const GET_CONFIGURATION: u8 = 8;
+    pub const SET_CONFIGURATION: u8 = 9;
+    pub const GET_INTERFACE: u8 = 10;
+    pub const SET_INTERFACE: u8 = 11;
+}
+
+/// `bmRequestType` direction bit, device to host
+pub const REQUEST_TYPE_IN: u8 = 0x80;
+/// `bmRequestType` type field, class request
+pub const REQUEST_TYPE_CLASS: u8 = 0x20;
+/// `bmRequestType` type field, vendor request
+pub const REQUEST_TYPE_VENDOR: u8 = 0x40;
+/// `bmRequestType` recipient field, interface
+pub const REQUEST_TYPE_INTERFACE: u8 = 0x01;
+/// `bmRequestType` recipient field, endpoint
+pub const REQUEST_TYPE_ENDPOINT: u8 = 0x02;
+
+/// Feature selector of `CLEAR_FEATURE` for endpoints
+pub const FEATURE_ENDPOINT_HALT: u16 = 0;
+/// Feature selector of `SET_FEATURE`/`CLEAR_FEATURE` for devices
+pub const FEATURE_DEVICE_REMOTE_WAKEUP: u16 = 1;
+
+/// Setup packet of a control transfer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SetupPacket {
+    pub request_type: u8,
+    pub request: u8,
+    pub value: u16,
+    pub index: u16,
+    pub length: u16,
+}
+
+impl SetupPacket {
+    /// `GET_DESCRIPTOR` of descriptor `desc_type`/`index`, `lang_id` is only used by string descriptors
+    pub const fn get_descriptor(desc_type: u8, index: u8, lang_id: u16, length: u16) -> Self {
+        Self {
+            request_type: REQUEST_TYPE_IN,
+            request: request::GET_DESCRIPTOR,
+            value: ((desc_type as u16) << 8) | index as u16,
+            index: lang_id,
+            length,
+        }
+    }
+
+    pub const fn set_address(address: u8) -> Self {
+        Self {
+            request_type: 0,
+            request: request::SET_ADDRESS,
+            value: address as u16,
+            index: 0,
+            length: 0,
+        }
+    }
+
+    pub const fn set_configuration(value: u8) -> Self {
+        Self {
+            request_type: 0,
+            request: request::SET_CONFIGURATION,
+            value: value as u16,
+            index: 0,
+            length: 0,
+        }
+    }
+
+    pub const fn set_interface(interface: u8, alternate_setting: u8) -> Self {
+        Self {
+            request_type: REQUEST_TYPE_INTERFACE,
+            request: request::SET_INTERFACE,
+            value: alternate_setting as u16,
+            index: interface as u16,
+            length: 0,
+        }
+    }
+
+    /// `CLEAR_FEATURE(ENDPOINT_HALT)`, clears a STALL condition
+    pub fn clear_halt(ep_addr: EndpointAddress) -> Self {
+        Self {
+            request_type: REQUEST_TYPE_ENDPOINT,
+            request: request::CLEAR_FEATURE,
+            value: FEATURE_ENDPOINT_HALT,
+            index: u8::from(ep_addr) as u16,
+            length: 0,
+        }
+    }
+
+    /// `SET_FEATURE`/`CLEAR_FEATURE(DEVICE_REMOTE_WAKEUP)`
+    pub const fn remote_wakeup(enable: bool) -> Self {
+        Self {
+            request_type: 0,
+            request: if enable {
+                request::SET_FEATURE
+            } else {
+                request::CLEAR_FEATURE
+            },
+            value: FEATURE_DEVICE_REMOTE_WAKEUP,
+            index: 0,
+            length: 0,
+        }
+    }
+
+    /// Direction of the data stage
+    pub const fn is_in(&self) -> bool {
+        self.request_type & REQUEST_TYPE_IN != 0
+    }
+
+    pub fn to_bytes(&self) -> [u8; 8] {
+        let mut buf = [0; 8];
+        buf[0] = self.request_type;
+        buf[1] = self.request;
+        buf[2..4].copy_from_slice(&self.value.to_le_bytes());
+        buf[4..6].copy_from_slice(&self.index.to_le_bytes());
+        buf[6..8].copy_from_slice(&self.length.to_le_bytes());
+        buf
+    }
+}
+
+/// Device descriptor
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DeviceDescriptor {
+    pub usb_version: u16,
+    pub device_class: u8,
+    pub device_sub_class: u8,
+    pub device_protocol: u8,
+    pub max_packet_size0: u8,
+    pub vendor_id: u16,
+    pub product_id: u16,
+    pub device_version: u16,
+    pub manufacturer_index: u8,
+    pub product_index: u8,
+    pub serial_number_index: u8,
+    pub num_configurations: u8,
+}
+
+impl DeviceDescriptor {
+    pub const SIZE: usize = 18;
+
+    pub fn parse(buf: &[u8]) -> Option<Self> {
+        if buf.len() < Self::SIZE || buf[1] != descriptor_type::DEVICE {
+            return None;
+        }
+        Some(Self {
+            usb_version: u16::from_le_bytes([buf[2], buf[3]]),
+            device_class: buf[4],
+            device_sub_class: buf[5],
+            device_protocol: buf[6],
+            max_packet_size0: buf[7],
+            vendor_id: u16::from_le_bytes([buf[8], buf[9]]),
+            product_id: u16::from_le_bytes([buf[10], buf[11]]),
+            device_version: u16::from_le_bytes([buf[12], buf[13]]),
+            manufacturer_index: buf[14],
+            product_index: buf[15],
+            serial_number_index: buf[16],
+            num_configurations: buf[17],
+        })
+    }
+}
+
+/// Configuration descriptor header
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ConfigurationDescriptor {
+    /// Length of the configuration including all interface and endpoint descriptors
+    pub total_length: u16,
+    pub num_interfaces: u8,
+    pub configuration_value: u8,
+    pub attributes: u8,
+    /// Maximum power in 2mA units
+    pub max_power: u8,
+}
+
+impl ConfigurationDescriptor {
+    pub const SIZE: usize = 9;
+
+    pub fn parse(buf: &[u8]) -> Option<Self> {
+        if buf.len() < Self::SIZE || buf[1] != descriptor_type::CONFIGURATION {
+            return None;
+        }
+        Some(Self {
+            total_length: u16::from_le_bytes([buf[2], buf[3]]),
+            num_interfaces: buf[4],
+            configuration_value: buf[5],
+            attributes: buf[7],
+            max_power: buf[8],
+        })
+    }
+}
+
+/// Interface descriptor
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct InterfaceDescriptor {
+    pub interface_number: u8,
+    pub alternate_setting: u8,
+    pub num_endpoints: u8,
+    pub interface_class: u8,
+    pub interface_sub_class: u8,
+    pub interface_protocol: u8,
+}
+
+impl InterfaceDescriptor {
+    pub const SIZE: usize = 9;
+
+    pub fn parse(buf: &[u8]) -> Option<Self> {
+        if buf.len() < Self::SIZE || buf[1] != descriptor_type::INTERFACE {
+            return None;
+        }
+        Some(Self {
+            interface_number: buf[2],
+            alternate_setting: buf[3],
+            num_endpoints: buf[4],
+            interface_class: buf[5],
+            interface_sub_class: buf[6],
+            interface_protocol: buf[7],
+        })
+    }
+}
+
+/// Endpoint descriptor
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct EndpointDescriptor {
+    pub address: EndpointAddress,
+    pub ep_type: EndpointType,
+    /// Maximum packet size, bits 12:11 are the additional transactions per micro-frame
+    pub max_packet_size: u16,
+    /// Raw `bInterval`, in frames for full/low-speed and as `2^(bInterval-1)` micro-frames for high-speed
+    pub interval: u8,
+}
+
+impl EndpointDescriptor {
+    pub const SIZE: usize = 7;
+
+    pub fn parse(buf: &[u8]) -> Option<Self> {
+        if buf.len() < Self::SIZE || buf[1] != descriptor_type::ENDPOINT {
+            return None;
+        }
+        let ep_type = match buf[3] & 0x03 {
+            0 => EndpointType::Control,
+            1 => EndpointType::Isochronous,
+            2 => EndpointType::Bulk,
+            _ => EndpointType::Interrupt,
+        };
+        Some(Self {
+            address: EndpointAddress::from(buf[2]),
+            ep_type,
+            max_packet_size: u16::from_le_bytes([buf[4], buf[5]]),
+            interval: buf[6],
+        })
+    }
+
+    pub fn direction(&self) -> Direction {
+        self.address.direction()
+    }
+}
+
+/// Iterator over the descriptors in a configuration descriptor blob
+///
+/// Yields each descriptor as raw bytes, starting with `bLength` and `bDescriptorType`.
+pub struct DescriptorIter<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> DescriptorIter<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self { buf }
+    }
+}
+
+impl<'a> Iterator for DescriptorIter<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let len = *self.buf.first()? as usize;
+        if len < 2 || len > self.buf.len() {
+            self.buf = &[];
+            return None;
+        }
+        let (desc, rest) = self.buf.split_at(len);
+        self.buf = rest;
+        Some(desc)
+    }
+}
@@ -0,0 +1,334 @@
+//! EHCI data structures for host mode
+//!
+//! In host mode the controller walks EHCI queue heads (QH) and queue element transfer
+//! descriptors (qTD) instead of the device mode dQH/dTD. Both live in the same QHD/QTD
+//! list storage as the device driver: a host QH fits in one 64-byte QHD slot, a qTD has
+//! the same 32-byte size as a dTD.
+//!
+//! The bytes after the 48-byte QH are not touched by the controller, they hold the setup
+//! packet of control pipes and the polling period of interrupt pipes.
+
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use vcell::VolatileCell;
+
+use super::super::state::{QhdListData, QtdListData};
+use super::super::types::{QhdList, QtdList};
+use super::super::{ENDPOINT_COUNT, QHD_ITEM_SIZE, QTD_COUNT_EACH_QHD, QTD_ITEM_SIZE, local_to_sys_address};
+
+/// Number of QH slots, slot 0 is the head of the asynchronous schedule
+pub(crate) const QH_COUNT: usize = ENDPOINT_COUNT * 2;
+
+/// Periodic frame list size, the controller default after reset
+pub(crate) const FRAME_LIST_SIZE: usize = 1024;
+
+/// Horizontal link pointer and qTD pointer bits
+#[allow(dead_code)]
+pub(crate) mod link {
+    /// Terminate, the pointer is invalid
+    pub const T: u32 = 1 << 0;
+    /// Type (bits 2:1) = QH
+    pub const TYPE_QH: u32 = 0b01 << 1;
+    /// 32-byte aligned address
+    pub const ADDR_MASK: u32 = !0x1F;
+}
+
+/// qTD token bits
+#[allow(dead_code)]
+pub(crate) mod token {
+    /// Ping state / split transaction error
+    pub const PING: u32 = 1 << 0;
+    /// Split transaction state
+    pub const SPLIT: u32 = 1 << 1;
+    /// Missed micro-frame
+    pub const MISSED_UFRAME: u32 = 1 << 2;
+    /// Transaction error (timeout, CRC, bad PID)
+    pub const XACT_ERR: u32 = 1 << 3;
+    /// Babble detected
+    pub const BABBLE: u32 = 1 << 4;
+    /// Data buffer error, overrun(IN) or underrun(OUT)
+    pub const BUFFER_ERR: u32 = 1 << 5;
+    /// Halted, set on STALL or on any error after the retries
+    pub const HALTED: u32 = 1 << 6;
+    /// Active, owned by the controller
+    pub const ACTIVE: u32 = 1 << 7;
+    /// PID code (bits 9:8)
+    pub const PID_SHIFT: u32 = 8;
+    pub const PID_OUT: u32 = 0;
+    pub const PID_IN: u32 = 1;
+    pub const PID_SETUP: u32 = 2;
+    /// Error counter (bits 11:10)
+    pub const CERR_SHIFT: u32 = 10;
+    /// Interrupt on complete
+    pub const IOC: u32 = 1 << 15;
+    /// Total bytes to transfer (bits 30:16), remaining bytes after completion
+    pub const TOTAL_BYTES_SHIFT: u32 = 16;
+    pub const TOTAL_BYTES_MASK: u32 = 0x7FFF;
+    /// Data toggle
+    pub const TOGGLE: u32 = 1 << 31;
+}
+
+/// QH endpoint characteristics bits
+#[allow(dead_code)]
+pub(crate) mod ep_char {
+    /// Device address (bits 6:0)
+    pub const DEV_ADDR_MASK: u32 = 0x7F;
+    /// Endpoint number (bits 11:8)
+    pub const EP_NUM_SHIFT: u32 = 8;
+    /// Endpoint speed (bits 13:12), 0: full, 1: low, 2: high
+    pub const EPS_SHIFT: u32 = 12;
+    /// Data toggle control, take the toggle from qTD instead of QH
+    pub const DTC: u32 = 1 << 14;
+    /// Head of reclamation list
+    pub const H: u32 = 1 << 15;
+    /// Maximum packet length (bits 26:16)
+    pub const MPS_SHIFT: u32 = 16;
+    /// Control endpoint flag, for full/low-speed control endpoints
+    pub const C: u32 = 1 << 27;
+    /// NAK count reload (bits 31:28)
+    pub const RL_SHIFT: u32 = 28;
+}
+
+/// QH endpoint capabilities bits
+#[allow(dead_code)]
+pub(crate) mod ep_cap {
+    /// Interrupt schedule mask (bits 7:0)
+    pub const SMASK_SHIFT: u32 = 0;
+    /// Split completion mask (bits 15:8)
+    pub const CMASK_SHIFT: u32 = 8;
+    /// Hub address (bits 22:16)
+    pub const HUB_ADDR_SHIFT: u32 = 16;
+    /// Hub port (bits 29:23)
+    pub const HUB_PORT_SHIFT: u32 = 23;
+    /// High-bandwidth pipe multiplier (bits 31:30)
+    pub const MULT_SHIFT: u32 = 30;
+}
+
+/// Queue element transfer descriptor (qTD)
+#[repr(C, align(32))]
+pub(crate) struct HostQtd {
+    pub next: VolatileCell<u32>,
+    pub alt_next: VolatileCell<u32>,
+    pub token: VolatileCell<u32>,
+    pub buffer: [VolatileCell<u32>; 5],
+}
+
+/// Queue head (QH) with the transfer overlay, plus software fields
+#[repr(C, align(32))]
+pub(crate) struct HostQh {
+    pub link: VolatileCell<u32>,
+    pub ep_char: VolatileCell<u32>,
+    pub ep_cap: VolatileCell<u32>,
+    pub cur_qtd: VolatileCell<u32>,
+    // Transfer overlay, same layout as `HostQtd`
+    pub next_qtd: VolatileCell<u32>,
+    pub alt_next_qtd: VolatileCell<u32>,
+    pub token: VolatileCell<u32>,
+    pub buffer: [VolatileCell<u32>; 5],
+    // Software fields, not used by the controller
+    /// Setup packet of a control pipe
+    pub setup: [VolatileCell<u32>; 2],
+    /// Polling period in frames of an interrupt pipe, 0 for the asynchronous schedule
+    pub period: VolatileCell<u32>,
+    _reserved: VolatileCell<u32>,
+}
+
+static_assertions::const_assert_eq!(core::mem::size_of::<HostQh>(), QHD_ITEM_SIZE);
+static_assertions::const_assert_eq!(core::mem::size_of::<HostQtd>(), QTD_ITEM_SIZE);
+
+impl HostQtd {
+    /// Fill in a qTD for `len` bytes at `addr`, the next pointer is left terminated
+    ///
+    /// A qTD covers 5 pages, any transfer up to 16KB fits regardless of the start offset.
+    pub(crate) fn init(&self, pid: u32, toggle: bool, addr: u32, len: usize, ioc: bool) {
+        self.next.set(link::T);
+        self.alt_next.set(link::T);
+
+        let addr = local_to_sys_address(addr);
+        self.buffer[0].set(addr);
+        for (i, buf) in self.buffer.iter().enumerate().skip(1) {
+            buf.set((addr & !0xFFF).wrapping_add(0x1000 * i as u32));
+        }
+
+        let mut tok = token::ACTIVE
+            | (pid << token::PID_SHIFT)
+            | (3 << token::CERR_SHIFT)
+            | ((len as u32 & token::TOTAL_BYTES_MASK) << token::TOTAL_BYTES_SHIFT);
+        if toggle {
+            tok |= token::TOGGLE;
+        }
+        if ioc {
+            tok |= token::IOC;
+        }
+        self.token.set(tok);
+    }
+
+    /// Bytes not transferred yet
+    pub(crate) fn remaining(&self) -> usize {
+        ((self.token.get() >> token::TOTAL_BYTES_SHIFT) & token::TOTAL_BYTES_MASK) as usize
+    }
+
+    /// System bus address of this qTD
+    pub(crate) fn addr(&self) -> u32 {
+        local_to_sys_address(self as *const _ as u32)
+    }
+}
+
+impl HostQh {
+    pub(crate) fn reset(&self) {
+        self.link.set(link::T);
+        self.ep_char.set(0);
+        self.ep_cap.set(0);
+        self.cur_qtd.set(0);
+        self.next_qtd.set(link::T);
+        self.alt_next_qtd.set(link::T);
+        self.token.set(0);
+        for b in &self.buffer {
+            b.set(0);
+        }
+        self.setup[0].set(0);
+        self.setup[1].set(0);
+        self.period.set(0);
+    }
+
+    /// Queue a qTD chain, keeping the data toggle of the overlay
+    pub(crate) fn start(&self, first: &HostQtd) {
+        self.next_qtd.set(first.addr());
+        self.alt_next_qtd.set(link::T);
+        // Clearing Active/Halted makes the controller fetch the next qTD
+        self.token.set(self.token.get() & token::TOGGLE);
+    }
+
+    /// System bus address of this QH, as a horizontal link pointer
+    pub(crate) fn link_addr(&self) -> u32 {
+        local_to_sys_address(self as *const _ as u32) | link::TYPE_QH
+    }
+}
+
+/// Periodic frame list, must be 4K aligned
+#[repr(C, align(4096))]
+pub(crate) struct FrameListData(UnsafeCell<[u32; FRAME_LIST_SIZE]>);
+
+/// Scratch buffer for requests issued by the driver itself, e.g. during enumeration
+#[repr(C, align(32))]
+pub(crate) struct ScratchData(UnsafeCell<[u8; 64]>);
+
+/// USB host state that must be placed in non-cacheable memory.
+///
+/// This struct holds the periodic frame list and the QH/qTD pools used by the controller
+/// in host mode. Users must allocate this statically with `#[link_section = ".noncacheable"]`.
+///
+/// # Example
+///
+/// ```ignore
+/// #[link_section = ".noncacheable"]
+/// static HOST_STATE: HostState = HostState::new();
+///
+/// let host = UsbHost::new(p.USB0, Irqs, Default::default(), &HOST_STATE);
+/// ```
+#[repr(C)]
+pub struct HostState {
+    frame_list: FrameListData,
+    qhd_list: QhdListData,
+    qtd_list: QtdListData,
+    scratch: ScratchData,
+    /// Bit 31: state is in use
+    alloc_mask: AtomicU32,
+    /// Allocated QH slots
+    qh_mask: AtomicU32,
+}
+
+// Safety: HostState uses UnsafeCell internally, the controller and the pipes owning a QH slot
+// are the only users of each slot. Slot allocation uses atomic operations.
+unsafe impl Sync for HostState {}
+
+impl HostState {
+    /// Create a new host state.
+    ///
+    /// This is a const fn so it can be used in static initialization.
+    pub const fn new() -> Self {
+        Self {
+            frame_list: FrameListData(UnsafeCell::new([link::T; FRAME_LIST_SIZE])),
+            qhd_list: QhdListData::new(),
+            qtd_list: QtdListData::new(),
+            scratch: ScratchData(UnsafeCell::new([0; 64])),
+            alloc_mask: AtomicU32::new(0),
+            qh_mask: AtomicU32::new(1),
+        }
+    }
+
+    /// Try to acquire exclusive access to this state.
+    pub(crate) fn try_acquire(&self) -> bool {
+        const STATE_IN_USE: u32 = 1 << 31;
+        let prev = self.alloc_mask.fetch_or(STATE_IN_USE, Ordering::SeqCst);
+        (prev & STATE_IN_USE) == 0
+    }
+
+    /// Release the state for reuse after the host is dropped
+    pub(crate) fn release(&self) {
+        const STATE_IN_USE: u32 = 1 << 31;
+        self.alloc_mask.fetch_and(!STATE_IN_USE, Ordering::SeqCst);
+    }
+
+    /// Allocate a QH slot, slot 0 is never returned
+    pub(crate) fn alloc_qh(&self) -> Option<usize> {
+        let mut mask = self.qh_mask.load(Ordering::Relaxed);
+        loop {
+            let idx = (!mask).trailing_zeros() as usize;
+            if idx >= QH_COUNT {
+                return None;
+            }
+            match self
+                .qh_mask
+                .compare_exchange_weak(mask, mask | (1 << idx), Ordering::AcqRel, Ordering::Relaxed)
+            {
+                Ok(_) => return Some(idx),
+                Err(m) => mask = m,
+            }
+        }
+    }
+
+    pub(crate) fn free_qh(&self, idx: usize) {
+        self.qh_mask.fetch_and(!(1 << idx), Ordering::AcqRel);
+    }
+
+    pub(crate) fn is_allocated(&self, idx: usize) -> bool {
+        self.qh_mask.load(Ordering::Acquire) & (1 << idx) != 0
+    }
+
+    /// Get the QH in slot `idx`, slot 0 is the asynchronous schedule head
+    pub(crate) fn qh(&self, idx: usize) -> &HostQh {
+        let qhd = unsafe { QhdList::from_ptr(self.qhd_list.as_ptr()) }.qhd(idx);
+        unsafe { &*(qhd.as_ptr() as *const HostQh) }
+    }
+
+    /// Get qTD `n` (0..QTD_COUNT_EACH_QHD) of QH slot `idx`
+    pub(crate) fn qtd(&self, idx: usize, n: usize) -> &HostQtd {
+        debug_assert!(n < QTD_COUNT_EACH_QHD);
+        let qtd = unsafe { QtdList::from_ptr(self.qtd_list.as_ptr()) }.qtd(idx * QTD_COUNT_EACH_QHD + n);
+        unsafe { &*(qtd.as_ptr() as *const HostQtd) }
+    }
+
+    /// Set periodic frame list entry `n`
+    pub(crate) fn set_frame(&self, n: usize, val: u32) {
+        unsafe { core::ptr::write_volatile((self.frame_list.0.get() as *mut u32).add(n), val) }
+    }
+
+    pub(crate) fn frame_list_addr(&self) -> u32 {
+        local_to_sys_address(self.frame_list.0.get() as u32)
+    }
+
+    /// # Safety
+    /// Only one request may use the scratch buffer at a time.
+    #[allow(clippy::mut_from_ref)]
+    pub(crate) unsafe fn scratch(&self) -> &mut [u8; 64] {
+        &mut *self.scratch.0.get()
+    }
+}
+
+impl Default for HostState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,616 @@
+//! USB host mode
+//!
+//! The USB controller is a dual-role ChipIdea core, in host mode it works as an EHCI host
+//! controller with an integrated transaction translator, so full/low-speed devices can be
+//! attached to the root port directly. Hubs are not supported.
+//!
+//! [`UsbHost`] handles port power, connection and reset, [`UsbHost::enumerate`] assigns an
+//! address and returns a [`Device`], whose endpoints are then opened as [`Pipe`]s for bulk and
+//! interrupt transfers.
+//!
+//! Transfer buffers are accessed by the USB DMA directly, they must not be cached: place them
+//! in `.noncacheable` or local memory (DLM).
+//!
+//! # Example
+//!
+//! ```ignore
+//! #[link_section = ".noncacheable"]
+//! static HOST_STATE: HostState = HostState::new();
+//!
+//! let mut host = UsbHost::new(p.USB0, Irqs, Default::default(), &HOST_STATE);
+//! loop {
+//!     let speed = host.wait_for_connection().await?;
+//!     let mut device = host.enumerate(1).await?;
+//!     let len = device.get_configuration_descriptor(0, &mut CONFIG_BUF).await?;
+//!     for desc in DescriptorIter::new(&CONFIG_BUF[..len]) {
+//!         // find interfaces and endpoints
+//!     }
+//!     device.set_configuration(1).await?;
+//!     let mut pipe = device.open_pipe(&endpoint)?;
+//!     let n = pipe.read(&mut DATA_BUF).await?;
+//!     host.wait_for_disconnection().await;
+//! }
+//! ```
+
+use core::future::poll_fn;
+use core::marker::PhantomData;
+use core::task::Poll;
+
+use embassy_hal_internal::Peri;
+use embassy_time::{Duration, Timer};
+use embedded_hal::delay::DelayNs;
+use hpm_metapac::usb::regs::{Portsc1, Usbintr, Usbsts};
+use riscv::delay::McycleDelay;
+
+use super::otg::{Role, power_handler};
+use super::{Config, Instance, InterruptHandler, phy_deinit, phy_init};
+#[cfg(feature = "usb-pin-reuse-hpm5300")]
+use super::{DmPin, DpPin};
+use crate::interrupt::typelevel::Interrupt as _;
+use crate::sysctl;
+
+mod descriptor;
+mod ehci;
+mod pipe;
+
+pub use descriptor::*;
+pub use ehci::HostState;
+pub(crate) use ehci::QH_COUNT;
+use ehci::{FRAME_LIST_SIZE, ep_char, link, token};
+pub use pipe::{ControlPipe, Pipe};
+
+/// USBMODE.CM value for host mode
+pub(crate) const USBMODE_CM_HOST: u8 = 0b11;
+
+/// Timeout of control transfers, USB 2.0 9.2.6.4
+const CONTROL_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Time for the controller to halt after clearing USBCMD.RS, 16 microframes per EHCI
+const HALT_TIMEOUT_US: u32 = 2000;
+
+/// USB host error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum HostError {
+    /// No device is connected, or it was removed during the transfer
+    Disconnected,
+    /// The port was not enabled after reset
+    ResetFailed,
+    /// The endpoint returned STALL
+    Stall,
+    /// No response, CRC or bit stuffing error, after 3 retries
+    Transaction,
+    /// The device sent more data than expected
+    Babble,
+    /// Data buffer overrun or underrun, the DMA could not keep up
+    DataBuffer,
+    /// Control transfer timeout
+    Timeout,
+    /// Control transfer data stage larger than 16KB
+    BufferTooLarge,
+    /// All QH slots are in use
+    NoResources,
+    /// Isochronous or control endpoint, or wrong direction for the transfer
+    InvalidEndpoint,
+    /// The device returned an invalid descriptor
+    InvalidDescriptor,
+    /// Invalid USB address or a buffer too small for the request
+    InvalidArgument,
+}
+
+impl HostError {
+    fn from_token(tok: u32) -> Self {
+        if tok & token::BABBLE != 0 {
+            HostError::Babble
+        } else if tok & token::BUFFER_ERR != 0 {
+            HostError::DataBuffer
+        } else if tok & token::XACT_ERR != 0 {
+            HostError::Transaction
+        } else {
+            HostError::Stall
+        }
+    }
+}
+
+/// Speed of the connected device
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+pub enum Speed {
+    Full = 0,
+    Low = 1,
+    High = 2,
+}
+
+impl Speed {
+    fn from_pspd(pspd: u8) -> Self {
+        match pspd {
+            1 => Speed::Low,
+            2 => Speed::High,
+            _ => Speed::Full,
+        }
+    }
+}
+
+/// Modify PORTSC1 without clearing the write-1-to-clear change bits
+fn modify_portsc1(r: crate::pac::usb::Usb, f: impl FnOnce(&mut Portsc1)) {
+    r.portsc1().modify(|w| {
+        w.set_csc(false);
+        w.set_pec(false);
+        w.set_occ(false);
+        f(w);
+    });
+}
+
+/// USB host driver
+pub struct UsbHost<'d, T: Instance> {
+    _phantom: PhantomData<&'d mut T>,
+    state: &'d HostState,
+}
+
+impl<'d, T: Instance> UsbHost<'d, T> {
+    /// Create a new USB host, the controller is started and the port is powered.
+    ///
+    /// # Arguments
+    /// * `_peri` - USB peripheral
+    /// * `_irq` - Interrupt binding
+    /// * `dm` - D- pin (only when `usb-pin-reuse-hpm5300` feature is enabled)
+    /// * `dp` - D+ pin (only when `usb-pin-reuse-hpm5300` feature is enabled)
+    /// * `config` - USB configuration, `force_full_speed` limits the port to full-speed
+    /// * `state` - Host state, must be placed in non-cacheable memory with `#[link_section = ".noncacheable"]`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `state` is already in use by another host instance.
+    pub fn new(
+        _peri: Peri<'d, T>,
+        _irq: impl crate::interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+        #[cfg(feature = "usb-pin-reuse-hpm5300")] dm: Peri<'d, impl DmPin<T>>,
+        #[cfg(feature = "usb-pin-reuse-hpm5300")] dp: Peri<'d, impl DpPin<T>>,
+        config: Config,
+        state: &'d HostState,
+    ) -> Self {
+        #[cfg(feature = "usb-pin-reuse-hpm5300")]
+        {
+            // Set to analog
+            dp.set_as_analog();
+            dm.set_as_analog();
+        }
+
+        Self::new_inner(config, state)
+    }
+
+    /// Pins are already set up, see [`UsbOtg::host`](super::UsbOtg::host)
+    pub(crate) fn new_inner(config: Config, state: &'d HostState) -> Self {
+        // Runtime singleton check
+        assert!(state.try_acquire(), "HostState is already in use by another USB host");
+
+        T::add_resource_group(0);
+
+        let r = T::info().regs;
+
+        // Set power control polarity, aka vbus high level enable
+        r.otg_ctrl0().modify(|w| w.set_otg_power_mask(true));
+
+        // Host keeps the dp/dm pulldowns enabled
+        let mut delay = McycleDelay::new(sysctl::clocks().cpu0.0);
+        phy_init(r, &mut delay);
+
+        // Reset controller
+        r.usbcmd().modify(|w| w.set_rst(true));
+        while r.usbcmd().read().rst() {}
+
+        // Set mode to host IMMEDIATELY after reset
+        r.usbmode().modify(|w| w.set_cm(USBMODE_CM_HOST));
+        r.usbmode().modify(|w| w.set_es(false));
+
+        modify_portsc1(r, |w| {
+            // Parallel interface signal
+            w.set_sts(false);
+            // Parallel transceiver width
+            w.set_ptw(false);
+            w.set_pfsc(config.force_full_speed);
+        });
+
+        // Do not use interrupt threshold
+        r.usbcmd().modify(|w| w.set_itc(0));
+
+        // Asynchronous schedule head: an empty, halted QH pointing to itself
+        let head = state.qh(0);
+        head.reset();
+        head.ep_char.set(ep_char::H);
+        head.token.set(token::HALTED);
+        head.link.set(head.link_addr());
+
+        for frame in 0..FRAME_LIST_SIZE {
+            state.set_frame(frame, link::T);
+        }
+
+        // In host mode DEVICEADDR is PERIODICLISTBASE and ENDPTLISTADDR is ASYNCLISTADDR
+        r.deviceaddr().write(|w| w.0 = state.frame_list_addr());
+        r.endptlistaddr().write(|w| w.0 = head.link_addr() & link::ADDR_MASK);
+
+        // Clear status, enable transfer, error and port change interrupts
+        r.usbsts().write_value(r.usbsts().read());
+        r.usbintr().write(|w| {
+            w.set_ue(true);
+            w.set_uee(true);
+            w.set_pce(true);
+        });
+
+        r.usbcmd().modify(|w| {
+            w.set_ase(true);
+            w.set_pse(true);
+            w.set_rs(true);
+        });
+
+        unsafe { T::Interrupt::enable() };
+
+        let mut host = Self {
+            _phantom: PhantomData,
+            state,
+        };
+        host.set_port_power(true);
+        host
+    }
+
+    /// Switch port power (VBUS) on or off
+    ///
+    /// The PP bit drives the USB power control output; boards with a GPIO controlled VBUS
+    /// switch have to enable it separately.
+    pub fn set_port_power(&mut self, on: bool) {
+        modify_portsc1(T::info().regs, |w| w.set_pp(on));
+    }
+
+    /// Whether a device is connected to the port
+    pub fn is_connected(&self) -> bool {
+        T::info().regs.portsc1().read().ccs()
+    }
+
+    /// Speed of the connected device, valid after [`reset_port`](Self::reset_port)
+    pub fn port_speed(&self) -> Speed {
+        Speed::from_pspd(T::info().regs.portsc1().read().pspd())
+    }
+
+    /// Wait for a device to be connected, then debounce and reset the port
+    pub async fn wait_for_connection(&mut self) -> Result<Speed, HostError> {
+        loop {
+            poll_fn(|cx| {
+                T::state().port_waker.register(cx.waker());
+                if self.is_connected() {
+                    Poll::Ready(())
+                } else {
+                    Poll::Pending
+                }
+            })
+            .await;
+
+            // Connect debounce, USB 2.0 7.1.7.3
+            Timer::after_millis(100).await;
+            if self.is_connected() {
+                return self.reset_port().await;
+            }
+        }
+    }
+
+    /// Wait for the device to be removed
+    pub async fn wait_for_disconnection(&mut self) {
+        poll_fn(|cx| {
+            T::state().port_waker.register(cx.waker());
+            if self.is_connected() {
+                Poll::Pending
+            } else {
+                Poll::Ready(())
+            }
+        })
+        .await
+    }
+
+    /// Reset the port and return the speed of the device
+    pub async fn reset_port(&mut self) -> Result<Speed, HostError> {
+        let r = T::info().regs;
+        if !self.is_connected() {
+            return Err(HostError::Disconnected);
+        }
+
+        // Root port reset, USB 2.0 7.1.7.5
+        modify_portsc1(r, |w| w.set_pr(true));
+        Timer::after_millis(50).await;
+        modify_portsc1(r, |w| w.set_pr(false));
+
+        // The controller completes the reset (and high-speed chirp) and enables the port
+        for _ in 0..10 {
+            if !r.portsc1().read().pr() {
+                break;
+            }
+            Timer::after_millis(1).await;
+        }
+
+        // Reset recovery
+        Timer::after_millis(10).await;
+
+        let portsc = r.portsc1().read();
+        if !portsc.ccs() {
+            return Err(HostError::Disconnected);
+        }
+        if !portsc.pe() {
+            return Err(HostError::ResetFailed);
+        }
+        Ok(Speed::from_pspd(portsc.pspd()))
+    }
+
+    /// Suspend the port, the device enters low power mode after 3ms of bus idle
+    ///
+    /// Pending transfers do not complete while suspended.
+    pub async fn suspend_port(&mut self) -> Result<(), HostError> {
+        let r = T::info().regs;
+        if !self.is_connected() {
+            return Err(HostError::Disconnected);
+        }
+
+        modify_portsc1(r, |w| w.set_susp(true));
+        // The device suspends after 3ms of idle, USB 2.0 7.1.7.6
+        Timer::after_millis(5).await;
+
+        if let Some(handler) = power_handler::<T>() {
+            handler.suspend(Role::Host);
+        }
+        Ok(())
+    }
+
+    /// Resume a suspended port, USB 2.0 7.1.7.7
+    pub async fn resume_port(&mut self) -> Result<(), HostError> {
+        let r = T::info().regs;
+        if let Some(handler) = power_handler::<T>() {
+            handler.resume(Role::Host);
+        }
+
+        modify_portsc1(r, |w| w.set_fpr(true));
+        self.finish_resume().await
+    }
+
+    /// Wait for the suspended device to signal remote wakeup, then complete the resume
+    ///
+    /// Enable remote wakeup on the device with [`SetupPacket::remote_wakeup`] before suspending.
+    pub async fn wait_remote_wakeup(&mut self) -> Result<(), HostError> {
+        let r = T::info().regs;
+
+        // The controller sets FPR when it detects resume signaling on a suspended port
+        poll_fn(|cx| {
+            T::state().port_waker.register(cx.waker());
+            let portsc = r.portsc1().read();
+            if !portsc.ccs() {
+                Poll::Ready(Err(HostError::Disconnected))
+            } else if portsc.fpr() {
+                Poll::Ready(Ok(()))
+            } else {
+                Poll::Pending
+            }
+        })
+        .await?;
+
+        if let Some(handler) = power_handler::<T>() {
+            handler.resume(Role::Host);
+        }
+        self.finish_resume().await
+    }
+
+    /// Drive resume signaling for 20ms, then end it and wait for the port to leave suspend
+    async fn finish_resume(&mut self) -> Result<(), HostError> {
+        let r = T::info().regs;
+        Timer::after_millis(20).await;
+        modify_portsc1(r, |w| w.set_fpr(false));
+
+        for _ in 0..10 {
+            if !r.portsc1().read().susp() {
+                break;
+            }
+            Timer::after_millis(1).await;
+        }
+
+        // Resume recovery
+        Timer::after_millis(10).await;
+
+        if !self.is_connected() {
+            return Err(HostError::Disconnected);
+        }
+        Ok(())
+    }
+
+    /// Enumerate the device on the port: read the max packet size of endpoint 0, assign
+    /// `address` (1..=127) and read the device descriptor
+    ///
+    /// The port must have been reset, see [`wait_for_connection`](Self::wait_for_connection).
+    pub async fn enumerate(&mut self, address: u8) -> Result<Device<'d, T>, HostError> {
+        if address == 0 || address >= 128 {
+            return Err(HostError::InvalidArgument);
+        }
+        if !self.is_connected() {
+            return Err(HostError::Disconnected);
+        }
+
+        let speed = self.port_speed();
+        let mps0 = if speed == Speed::High { 64 } else { 8 };
+        let mut control = ControlPipe::new(self.state, 0, speed, mps0)?;
+
+        // Safety: `&mut self` makes this the only user of the scratch buffer
+        let buf = unsafe { self.state.scratch() };
+
+        // The first 8 bytes contain bMaxPacketSize0
+        let setup = SetupPacket::get_descriptor(descriptor_type::DEVICE, 0, 0, 8);
+        let n = control.control_in(&setup, &mut buf[..8]).await?;
+        if n < 8 {
+            return Err(HostError::InvalidDescriptor);
+        }
+        let mps0 = buf[7] as u16;
+        if !matches!(mps0, 8 | 16 | 32 | 64) {
+            return Err(HostError::InvalidDescriptor);
+        }
+
+        control.control_out(&SetupPacket::set_address(address), &[]).await?;
+        // SetAddress recovery, USB 2.0 9.2.6.3
+        Timer::after_millis(2).await;
+        control.set_address(address, mps0).await;
+
+        let setup = SetupPacket::get_descriptor(descriptor_type::DEVICE, 0, 0, DeviceDescriptor::SIZE as u16);
+        let n = control.control_in(&setup, &mut buf[..DeviceDescriptor::SIZE]).await?;
+        let descriptor = DeviceDescriptor::parse(&buf[..n]).ok_or(HostError::InvalidDescriptor)?;
+
+        Ok(Device {
+            info: DeviceInfo {
+                address,
+                speed,
+                descriptor,
+            },
+            control,
+            state: self.state,
+        })
+    }
+}
+
+/// Stop the controller, power off the port and release the host state, e.g. when switching
+/// roles with `UsbOtg`
+impl<'d, T: Instance> Drop for UsbHost<'d, T> {
+    fn drop(&mut self) {
+        let r = T::info().regs;
+        self.set_port_power(false);
+
+        r.usbcmd().modify(|w| {
+            w.set_ase(false);
+            w.set_pse(false);
+            w.set_rs(false);
+        });
+        let mut delay = McycleDelay::new(sysctl::clocks().cpu0.0);
+        for _ in 0..HALT_TIMEOUT_US {
+            if r.usbsts().read().hch() {
+                break;
+            }
+            delay.delay_us(1);
+        }
+
+        r.usbintr().write_value(Usbintr(0));
+        phy_deinit(r);
+
+        // Pending transfers see the disconnect
+        for w in &T::state().pipe_wakers {
+            w.wake();
+        }
+        T::state().port_waker.wake();
+
+        self.state.release();
+    }
+}
+
+/// Address, speed and device descriptor of an enumerated device
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DeviceInfo {
+    pub address: u8,
+    pub speed: Speed,
+    pub descriptor: DeviceDescriptor,
+}
+
+/// Enumerated device
+pub struct Device<'d, T: Instance> {
+    info: DeviceInfo,
+    control: ControlPipe<'d, T>,
+    state: &'d HostState,
+}
+
+impl<'d, T: Instance> Device<'d, T> {
+    pub fn info(&self) -> &DeviceInfo {
+        &self.info
+    }
+
+    /// Control pipe for class and vendor requests
+    pub fn control(&mut self) -> &mut ControlPipe<'d, T> {
+        &mut self.control
+    }
+
+    /// Read descriptor `desc_type`/`index` into `buf`, returns the length read
+    pub async fn get_descriptor(
+        &mut self,
+        desc_type: u8,
+        index: u8,
+        lang_id: u16,
+        buf: &mut [u8],
+    ) -> Result<usize, HostError> {
+        let len = buf.len().min(u16::MAX as usize) as u16;
+        let setup = SetupPacket::get_descriptor(desc_type, index, lang_id, len);
+        self.control.control_in(&setup, buf).await
+    }
+
+    /// Read the full configuration descriptor `index`, including interface and endpoint
+    /// descriptors, truncated to `buf`
+    ///
+    /// Iterate the result with [`DescriptorIter`]. `buf` must hold at least the 9-byte header,
+    /// otherwise [`HostError::InvalidArgument`] is returned.
+    pub async fn get_configuration_descriptor(&mut self, index: u8, buf: &mut [u8]) -> Result<usize, HostError> {
+        if buf.len() < ConfigurationDescriptor::SIZE {
+            return Err(HostError::InvalidArgument);
+        }
+
+        let n = self
+            .get_descriptor(
+                descriptor_type::CONFIGURATION,
+                index,
+                0,
+                &mut buf[..ConfigurationDescriptor::SIZE],
+            )
+            .await?;
+        let config = ConfigurationDescriptor::parse(&buf[..n]).ok_or(HostError::InvalidDescriptor)?;
+
+        let len = buf.len().min(config.total_length as usize);
+        self.get_descriptor(descriptor_type::CONFIGURATION, index, 0, &mut buf[..len])
+            .await
+    }
+
+    /// `SET_CONFIGURATION`, endpoints can be opened afterwards
+    pub async fn set_configuration(&mut self, value: u8) -> Result<(), HostError> {
+        self.control
+            .control_out(&SetupPacket::set_configuration(value), &[])
+            .await
+    }
+
+    /// Clear a STALL condition of `pipe` and reset its data toggle
+    pub async fn clear_halt(&mut self, pipe: &mut Pipe<'d, T>) -> Result<(), HostError> {
+        let setup = SetupPacket::clear_halt(pipe.endpoint().address);
+        self.control.control_out(&setup, &[]).await?;
+        pipe.reset_toggle().await;
+        Ok(())
+    }
+
+    /// Open a bulk or interrupt endpoint of the device
+    pub fn open_pipe(&self, endpoint: &EndpointDescriptor) -> Result<Pipe<'d, T>, HostError> {
+        Pipe::new(self.state, self.info.address, self.info.speed, endpoint)
+    }
+}
+
+/// Host mode part of the USB interrupt handler
+pub(crate) fn on_interrupt<T: Instance>(r: crate::pac::usb::Usb) {
+    let status = Usbsts(r.usbsts().read().0 & r.usbintr().read().0);
+    if status.0 == 0 {
+        return;
+    }
+    // Only clear the handled bits, async advance is left set for the unlinking pipe to see
+    let mut clear = status;
+    clear.set_aai(false);
+    r.usbsts().write_value(clear);
+
+    if status.aai() {
+        r.usbintr().modify(|w| w.set_aae(false));
+    }
+
+    if status.pci() {
+        // Acknowledge the connect, enable and over-current change bits
+        r.portsc1().modify(|_| {});
+        T::state().port_waker.wake();
+    }
+
+    // Pending transfers complete, fail or see the disconnect, or a QH unlink finished
+    if status.ui() || status.uei() || status.pci() || status.aai() {
+        for w in &T::state().pipe_wakers {
+            w.wake();
+        }
+    }
+}
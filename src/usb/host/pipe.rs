@@ -0,0 +1,529 @@
+//! Host pipes: control, bulk and interrupt transfers
+//!
+//! Each pipe owns one QH slot and the qTDs of that slot. Control and bulk QHs are linked into
+//! the asynchronous schedule, interrupt QHs into the periodic frame list.
+
+use core::future::poll_fn;
+use core::marker::PhantomData;
+use core::task::Poll;
+
+use embassy_hal_internal::drop::OnDrop;
+use embassy_time::{Duration, Timer};
+use embassy_usb_driver::{Direction, EndpointType};
+use embedded_hal::delay::DelayNs;
+use riscv::delay::McycleDelay;
+
+use super::descriptor::{EndpointDescriptor, SetupPacket};
+use super::ehci::{FRAME_LIST_SIZE, HostQh, HostQtd, HostState, QH_COUNT, ep_cap, ep_char, link, token};
+use super::{CONTROL_TIMEOUT, HostError, Speed};
+use crate::sysctl;
+use crate::usb::{Instance, QTD_COUNT_EACH_QHD};
+
+/// Largest transfer queued as one qTD, fits in the 5 qTD pages for any buffer alignment
+const MAX_QTD_BYTES: usize = 0x4000;
+
+/// Time for the controller to let go of an unlinked QH, the periodic list is walked once per frame
+const UNLINK_TIMEOUT: Duration = Duration::from_millis(2);
+
+/// Link a QH into the asynchronous schedule, right after the head
+fn async_link(state: &HostState, idx: usize) {
+    critical_section::with(|_| {
+        let head = state.qh(0);
+        let qh = state.qh(idx);
+        qh.link.set(head.link.get());
+        head.link.set(qh.link_addr());
+    });
+}
+
+/// Remove a QH from the asynchronous schedule list
+///
+/// Returns `true` if the Interrupt on Async Advance doorbell was rung, the controller may
+/// still have the QH cached until USBSTS.AAI is set.
+fn async_remove<T: Instance>(state: &HostState, idx: usize) -> bool {
+    let target = state.qh(idx).link_addr();
+    critical_section::with(|_| {
+        for i in (0..QH_COUNT).filter(|&i| i != idx) {
+            let qh = state.qh(i);
+            if qh.link.get() == target {
+                qh.link.set(state.qh(idx).link.get());
+                break;
+            }
+        }
+    });
+
+    let r = T::info().regs;
+    let cmd = r.usbcmd().read();
+    if cmd.rs() && cmd.ase() {
+        r.usbcmd().modify(|w| w.set_iaa(true));
+        true
+    } else {
+        false
+    }
+}
+
+/// Unlink a QH from the asynchronous schedule, returns when the controller no longer uses it
+///
+/// Blocking version for drop paths, polls the doorbell for at most [`UNLINK_TIMEOUT`].
+fn async_unlink<T: Instance>(state: &HostState, idx: usize) {
+    if async_remove::<T>(state, idx) {
+        let r = T::info().regs;
+        let mut delay = McycleDelay::new(sysctl::clocks().cpu0.0);
+        for _ in 0..UNLINK_TIMEOUT.as_micros() {
+            if r.usbsts().read().aai() {
+                break;
+            }
+            delay.delay_us(1);
+        }
+        r.usbsts().write(|w| w.set_aai(true));
+    }
+}
+
+/// Unlink a QH from the asynchronous schedule, waiting for the async advance interrupt
+async fn async_unlink_wait<T: Instance>(state: &HostState, idx: usize) {
+    if async_remove::<T>(state, idx) {
+        let r = T::info().regs;
+        let _ = embassy_time::with_timeout(
+            UNLINK_TIMEOUT,
+            poll_fn(|cx| {
+                T::state().pipe_wakers[idx].register(cx.waker());
+                if r.usbsts().read().aai() {
+                    return Poll::Ready(());
+                }
+                // The interrupt handler disables it again and leaves AAI set for us
+                r.usbintr().modify(|w| w.set_aae(true));
+                Poll::Pending
+            }),
+        )
+        .await;
+        r.usbintr().modify(|w| w.set_aae(false));
+        r.usbsts().write(|w| w.set_aai(true));
+    }
+}
+
+/// Rebuild the periodic frame list from the allocated interrupt QHs
+///
+/// Periods are powers of two, QHs are chained from the longest to the shortest period, so every
+/// QH reached from a frame entry is also due in that frame.
+fn periodic_rebuild(state: &HostState, exclude: Option<usize>) {
+    critical_section::with(|_| {
+        let mut order = [0u8; QH_COUNT];
+        let mut n = 0;
+        for i in 1..QH_COUNT {
+            if Some(i) != exclude && state.is_allocated(i) && state.qh(i).period.get() != 0 {
+                order[n] = i as u8;
+                n += 1;
+            }
+        }
+        let order = &mut order[..n];
+        order.sort_unstable_by_key(|&i| core::cmp::Reverse(state.qh(i as usize).period.get()));
+
+        for pair in order.windows(2) {
+            let next = state.qh(pair[1] as usize).link_addr();
+            state.qh(pair[0] as usize).link.set(next);
+        }
+        if let Some(&last) = order.last() {
+            state.qh(last as usize).link.set(link::T);
+        }
+
+        for frame in 0..FRAME_LIST_SIZE {
+            let entry = order
+                .iter()
+                .find(|&&i| frame % state.qh(i as usize).period.get() as usize == 0)
+                .map(|&i| state.qh(i as usize).link_addr())
+                .unwrap_or(link::T);
+            state.set_frame(frame, entry);
+        }
+    });
+}
+
+/// Polling period in frames, S-mask and C-mask of an interrupt endpoint
+fn interrupt_schedule(speed: Speed, interval: u8) -> (u32, u8, u8) {
+    match speed {
+        Speed::High => {
+            let uframes = 1u32 << (interval.clamp(1, 16) - 1);
+            match uframes {
+                1 => (1, 0xFF, 0),
+                2 => (1, 0x55, 0),
+                4 => (1, 0x11, 0),
+                _ => ((uframes / 8).min(FRAME_LIST_SIZE as u32), 0x01, 0),
+            }
+        }
+        // Split transactions through the integrated transaction translator
+        Speed::Full | Speed::Low => {
+            let ms = interval.max(1) as u32;
+            (1 << (31 - ms.leading_zeros()), 0x01, 0x1C)
+        }
+    }
+}
+
+/// QH slot owned by a pipe
+struct PipeInner<'d, T: Instance> {
+    _phantom: PhantomData<&'d mut T>,
+    state: &'d HostState,
+    idx: usize,
+    periodic: bool,
+}
+
+impl<'d, T: Instance> PipeInner<'d, T> {
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        state: &'d HostState,
+        address: u8,
+        ep_num: u8,
+        speed: Speed,
+        max_packet_size: u16,
+        control: bool,
+        schedule: Option<(u32, u8, u8)>,
+    ) -> Result<Self, HostError> {
+        let idx = state.alloc_qh().ok_or(HostError::NoResources)?;
+        let qh = state.qh(idx);
+        qh.reset();
+
+        let mut ch = (address as u32 & ep_char::DEV_ADDR_MASK)
+            | ((ep_num as u32) << ep_char::EP_NUM_SHIFT)
+            | ((speed as u32) << ep_char::EPS_SHIFT)
+            | (((max_packet_size & 0x7FF) as u32) << ep_char::MPS_SHIFT);
+        if control {
+            ch |= ep_char::DTC;
+            if speed != Speed::High {
+                ch |= ep_char::C;
+            }
+        }
+        if speed == Speed::High && schedule.is_none() {
+            ch |= 15 << ep_char::RL_SHIFT;
+        }
+        qh.ep_char.set(ch);
+
+        let mult = ((max_packet_size >> 11) & 0x3) as u32 + 1;
+        let (period, smask, cmask) = schedule.unwrap_or((0, 0, 0));
+        qh.ep_cap.set(
+            ((smask as u32) << ep_cap::SMASK_SHIFT)
+                | ((cmask as u32) << ep_cap::CMASK_SHIFT)
+                | (mult << ep_cap::MULT_SHIFT),
+        );
+        qh.period.set(period);
+
+        let pipe = Self {
+            _phantom: PhantomData,
+            state,
+            idx,
+            periodic: schedule.is_some(),
+        };
+        pipe.link();
+        Ok(pipe)
+    }
+
+    fn qh(&self) -> &HostQh {
+        self.state.qh(self.idx)
+    }
+
+    fn qtd(&self, n: usize) -> &HostQtd {
+        self.state.qtd(self.idx, n)
+    }
+
+    fn link(&self) {
+        if self.periodic {
+            periodic_rebuild(self.state, None);
+        } else {
+            async_link(self.state, self.idx);
+        }
+    }
+
+    /// Blocking unlink, only for drop paths where awaiting is not possible
+    fn unlink(&self) {
+        if self.periodic {
+            periodic_rebuild(self.state, Some(self.idx));
+            // The controller may be walking the old list until the end of the frame
+            McycleDelay::new(sysctl::clocks().cpu0.0).delay_us(UNLINK_TIMEOUT.as_micros() as u32);
+        } else {
+            async_unlink::<T>(self.state, self.idx);
+        }
+    }
+
+    async fn unlink_async(&self) {
+        if self.periodic {
+            periodic_rebuild(self.state, Some(self.idx));
+            // The controller may be walking the old list until the end of the frame
+            Timer::after(UNLINK_TIMEOUT).await;
+        } else {
+            async_unlink_wait::<T>(self.state, self.idx).await;
+        }
+    }
+
+    /// Change the static QH fields, the QH must be unlinked meanwhile
+    async fn update(&self, f: impl FnOnce(&HostQh)) {
+        self.unlink_async().await;
+        f(self.qh());
+        self.link();
+    }
+
+    /// Remove all queued qTDs, keeping the data toggle
+    async fn abort(&self) {
+        self.unlink_async().await;
+        self.clear_queue();
+        self.link();
+    }
+
+    /// Blocking [`Self::abort`] for a dropped transfer future
+    fn abort_blocking(&self) {
+        self.unlink();
+        self.clear_queue();
+        self.link();
+    }
+
+    fn clear_queue(&self) {
+        let qh = self.qh();
+        qh.next_qtd.set(link::T);
+        qh.alt_next_qtd.set(link::T);
+        qh.token.set(qh.token.get() & token::TOGGLE);
+        for n in 0..QTD_COUNT_EACH_QHD {
+            self.qtd(n).token.set(0);
+        }
+    }
+
+    /// Run qTDs `0..count`, which are already filled in
+    async fn run(&self, count: usize) -> Result<(), HostError> {
+        let r = T::info().regs;
+        if !r.portsc1().read().ccs() {
+            return Err(HostError::Disconnected);
+        }
+
+        for n in 1..count {
+            self.qtd(n - 1).next.set(self.qtd(n).addr());
+        }
+
+        // Dropping the future must not leave the controller writing to the buffer
+        let on_drop = OnDrop::new(|| self.abort_blocking());
+        self.qh().start(self.qtd(0));
+
+        let res = poll_fn(|cx| {
+            T::state().pipe_wakers[self.idx].register(cx.waker());
+
+            for n in 0..count {
+                let tok = self.qtd(n).token.get();
+                if tok & token::HALTED != 0 {
+                    return Poll::Ready(Err(HostError::from_token(tok)));
+                }
+                if tok & token::ACTIVE != 0 {
+                    if !r.portsc1().read().ccs() {
+                        return Poll::Ready(Err(HostError::Disconnected));
+                    }
+                    return Poll::Pending;
+                }
+            }
+            Poll::Ready(Ok(()))
+        })
+        .await;
+
+        on_drop.defuse();
+        if res.is_err() {
+            self.abort().await;
+        }
+        res
+    }
+}
+
+impl<'d, T: Instance> Drop for PipeInner<'d, T> {
+    fn drop(&mut self) {
+        self.unlink();
+        self.qh().reset();
+        for n in 0..QTD_COUNT_EACH_QHD {
+            self.qtd(n).token.set(0);
+        }
+        self.state.free_qh(self.idx);
+    }
+}
+
+/// Control pipe of a device, endpoint 0
+pub struct ControlPipe<'d, T: Instance> {
+    pipe: PipeInner<'d, T>,
+    address: u8,
+    max_packet_size: u16,
+}
+
+impl<'d, T: Instance> ControlPipe<'d, T> {
+    pub(crate) fn new(
+        state: &'d HostState,
+        address: u8,
+        speed: Speed,
+        max_packet_size: u16,
+    ) -> Result<Self, HostError> {
+        Ok(Self {
+            pipe: PipeInner::new(state, address, 0, speed, max_packet_size, true, None)?,
+            address,
+            max_packet_size,
+        })
+    }
+
+    /// Device address
+    pub fn address(&self) -> u8 {
+        self.address
+    }
+
+    /// Maximum packet size of endpoint 0
+    pub fn max_packet_size(&self) -> u16 {
+        self.max_packet_size
+    }
+
+    /// Switch to the new address after `SET_ADDRESS`
+    pub(crate) async fn set_address(&mut self, address: u8, max_packet_size: u16) {
+        self.pipe
+            .update(|qh| {
+                let mask = ep_char::DEV_ADDR_MASK | (0x7FF << ep_char::MPS_SHIFT);
+                let ch = (qh.ep_char.get() & !mask)
+                    | (address as u32 & ep_char::DEV_ADDR_MASK)
+                    | (((max_packet_size & 0x7FF) as u32) << ep_char::MPS_SHIFT);
+                qh.ep_char.set(ch);
+            })
+            .await;
+        self.address = address;
+        self.max_packet_size = max_packet_size;
+    }
+
+    /// Control transfer with an IN data stage, returns the number of bytes received
+    ///
+    /// `buf` must be accessible by the USB DMA, see [`HostState`].
+    pub async fn control_in(&mut self, setup: &SetupPacket, buf: &mut [u8]) -> Result<usize, HostError> {
+        let len = buf.len().min(setup.length as usize);
+        self.control(setup, buf.as_mut_ptr() as u32, len, true).await
+    }
+
+    /// Control transfer with an optional OUT data stage
+    ///
+    /// `data` must be accessible by the USB DMA, see [`HostState`].
+    pub async fn control_out(&mut self, setup: &SetupPacket, data: &[u8]) -> Result<(), HostError> {
+        let len = data.len().min(setup.length as usize);
+        self.control(setup, data.as_ptr() as u32, len, false).await.map(|_| ())
+    }
+
+    async fn control(&mut self, setup: &SetupPacket, addr: u32, len: usize, is_in: bool) -> Result<usize, HostError> {
+        if len > MAX_QTD_BYTES {
+            return Err(HostError::BufferTooLarge);
+        }
+
+        // The setup packet is kept in the QH slot, so it is in non-cacheable memory
+        let qh = self.pipe.qh();
+        let bytes = setup.to_bytes();
+        qh.setup[0].set(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]));
+        qh.setup[1].set(u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]));
+        self.pipe
+            .qtd(0)
+            .init(token::PID_SETUP, false, qh.setup.as_ptr() as u32, 8, false);
+
+        let mut count = 1;
+        if len > 0 {
+            let pid = if is_in { token::PID_IN } else { token::PID_OUT };
+            self.pipe.qtd(1).init(pid, true, addr, len, false);
+            count += 1;
+        }
+
+        // Status stage in the opposite direction of the data stage, always DATA1
+        let pid = if is_in && len > 0 {
+            token::PID_OUT
+        } else {
+            token::PID_IN
+        };
+        self.pipe.qtd(count).init(pid, true, 0, 0, true);
+        count += 1;
+
+        embassy_time::with_timeout(CONTROL_TIMEOUT, self.pipe.run(count))
+            .await
+            .map_err(|_| HostError::Timeout)??;
+
+        Ok(if len > 0 { len - self.pipe.qtd(1).remaining() } else { 0 })
+    }
+}
+
+/// Bulk or interrupt pipe
+pub struct Pipe<'d, T: Instance> {
+    pipe: PipeInner<'d, T>,
+    endpoint: EndpointDescriptor,
+}
+
+impl<'d, T: Instance> Pipe<'d, T> {
+    pub(crate) fn new(
+        state: &'d HostState,
+        address: u8,
+        speed: Speed,
+        endpoint: &EndpointDescriptor,
+    ) -> Result<Self, HostError> {
+        let schedule = match endpoint.ep_type {
+            EndpointType::Bulk => None,
+            EndpointType::Interrupt => Some(interrupt_schedule(speed, endpoint.interval)),
+            EndpointType::Control | EndpointType::Isochronous => return Err(HostError::InvalidEndpoint),
+        };
+        let pipe = PipeInner::new(
+            state,
+            address,
+            endpoint.address.index() as u8,
+            speed,
+            endpoint.max_packet_size,
+            false,
+            schedule,
+        )?;
+
+        Ok(Self {
+            pipe,
+            endpoint: *endpoint,
+        })
+    }
+
+    /// Endpoint of this pipe
+    pub fn endpoint(&self) -> &EndpointDescriptor {
+        &self.endpoint
+    }
+
+    /// Read from an IN endpoint, returns the number of bytes received
+    ///
+    /// Completes when `buf` is full or on a short packet. For interrupt endpoints this waits
+    /// until the device has data. `buf` must be accessible by the USB DMA, see [`HostState`].
+    pub async fn read(&mut self, buf: &mut [u8]) -> Result<usize, HostError> {
+        if self.endpoint.direction() != Direction::In {
+            return Err(HostError::InvalidEndpoint);
+        }
+
+        let mut total = 0;
+        loop {
+            let chunk = (buf.len() - total).min(MAX_QTD_BYTES);
+            let addr = buf[total..].as_mut_ptr() as u32;
+            self.pipe.qtd(0).init(token::PID_IN, false, addr, chunk, true);
+            self.pipe.run(1).await?;
+
+            let received = chunk - self.pipe.qtd(0).remaining();
+            total += received;
+            if received < chunk || total == buf.len() {
+                return Ok(total);
+            }
+        }
+    }
+
+    /// Write to an OUT endpoint
+    ///
+    /// An empty `data` sends a zero-length packet. `data` must be accessible by the USB DMA,
+    /// see [`HostState`].
+    pub async fn write(&mut self, data: &[u8]) -> Result<(), HostError> {
+        if self.endpoint.direction() != Direction::Out {
+            return Err(HostError::InvalidEndpoint);
+        }
+
+        let mut total = 0;
+        loop {
+            let chunk = (data.len() - total).min(MAX_QTD_BYTES);
+            let addr = data[total..].as_ptr() as u32;
+            self.pipe.qtd(0).init(token::PID_OUT, false, addr, chunk, true);
+            self.pipe.run(1).await?;
+
+            total += chunk;
+            if total == data.len() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Reset the data toggle to DATA0, required after the device cleared a halt
+    pub async fn reset_toggle(&mut self) {
+        self.pipe
+            .update(|qh| qh.token.set(qh.token.get() & !token::TOGGLE))
+            .await;
+    }
+}
@@ -0,0 +1,187 @@
+//! Isochronous endpoint helpers: SOF frame number and explicit feedback
+//!
+//! Isochronous endpoints are allocated through embassy-usb like any other endpoint. For high-speed
+//! high-bandwidth endpoints, pass the additional transactions per micro-frame in bits 12:11 of
+//! `max_packet_size`, e.g. `1024 | (2 << 11)` for 3x1024 bytes per micro-frame. Each `read`/`write`
+//! on an isochronous endpoint is one (micro)frame of data.
+//!
+//! An asynchronous audio OUT endpoint (e.g. USB Audio Class 2 speaker feeding `i2s::I2STxDma`) pairs
+//! with an isochronous IN feedback endpoint, reporting the rate at which the device consumes samples.
+//! [`Feedback`] encodes that rate, [`Sof`] provides the frame timing to measure it:
+//!
+//! ```ignore
+//! let sof = driver.sof();
+//! // ... build the embassy-usb device, with an ISO OUT stream endpoint and an ISO IN feedback endpoint
+//!
+//! let mut feedback = Feedback::new(FeedbackFormat::HighSpeed, 48_000);
+//! loop {
+//!     sof.wait().await;
+//!     // Ring buffer free space of the I2S TX DMA, keep it half full
+//!     let free = i2s_tx.len().unwrap_or(0);
+//!     feedback.update_fill((i2s_tx.capacity() - free) as i32, i2s_tx.capacity() as i32 / 2);
+//!     feedback_ep.write(&feedback.to_bytes()[..feedback.packet_size()]).await.ok();
+//! }
+//! ```
+
+use core::future::poll_fn;
+use core::marker::PhantomData;
+use core::task::Poll;
+
+use super::{Instance, SOF_WAKER};
+
+/// Start-of-frame tracking, see [`UsbDriver::sof`](super::UsbDriver::sof)
+///
+/// Only one task should wait for SOF at a time.
+pub struct Sof<'d, T: Instance> {
+    pub(crate) _phantom: PhantomData<&'d T>,
+}
+
+impl<'d, T: Instance> Clone for Sof<'d, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'d, T: Instance> Copy for Sof<'d, T> {}
+
+impl<'d, T: Instance> Sof<'d, T> {
+    /// Raw FRINDEX: frame number in bits 13:3, micro-frame in bits 2:0
+    pub fn frame_index(&self) -> u16 {
+        (T::info().regs.frindex().read().0 & 0x3FFF) as u16
+    }
+
+    /// Frame number of the last SOF, 11 bits, increments every 1ms
+    pub fn frame_number(&self) -> u16 {
+        self.frame_index() >> 3
+    }
+
+    /// Micro-frame of the last SOF, always 0 for full-speed
+    pub fn microframe(&self) -> u8 {
+        (self.frame_index() & 0x7) as u8
+    }
+
+    /// Wait for the next SOF (micro-frame for high-speed), returns the new frame number
+    ///
+    /// The SOF interrupt is only enabled while waiting.
+    pub async fn wait(&self) -> u16 {
+        let r = T::info().regs;
+        let start = self.frame_index();
+        poll_fn(|cx| {
+            SOF_WAKER.register(cx.waker());
+            if self.frame_index() != start {
+                Poll::Ready(self.frame_number())
+            } else {
+                // Disabled again by the interrupt handler
+                r.usbintr().modify(|w| w.set_sre(true));
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
+
+/// Encoding of the feedback value
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FeedbackFormat {
+    /// Samples per 1ms frame, 10.14 fixed point in 3 bytes
+    FullSpeed,
+    /// Samples per 125us micro-frame, 16.16 fixed point in 4 bytes
+    HighSpeed,
+}
+
+/// Explicit feedback value of an asynchronous isochronous OUT endpoint
+///
+/// The value is kept within +-1/8 of the nominal rate, as hosts drop the stream on larger deviations.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Feedback {
+    format: FeedbackFormat,
+    nominal: u32,
+    value: u32,
+}
+
+impl Feedback {
+    /// Fill level error, in samples, that corrects the rate by one sample per second
+    pub const FILL_GAIN: i64 = 1;
+
+    pub fn new(format: FeedbackFormat, sample_rate: u32) -> Self {
+        let nominal = Self::encode(format, sample_rate as u64, 1000);
+        Self {
+            format,
+            nominal,
+            value: nominal,
+        }
+    }
+
+    /// Encode `samples` per `frames` (1ms frames) in the fixed point format
+    fn encode(format: FeedbackFormat, samples: u64, frames: u64) -> u32 {
+        match format {
+            FeedbackFormat::FullSpeed => ((samples << 14) / frames) as u32,
+            FeedbackFormat::HighSpeed => ((samples << 16) / (frames * 8)) as u32,
+        }
+    }
+
+    fn clamp(&self, value: i64) -> u32 {
+        let nominal = self.nominal as i64;
+        value.clamp(nominal - nominal / 8, nominal + nominal / 8) as u32
+    }
+
+    pub fn format(&self) -> FeedbackFormat {
+        self.format
+    }
+
+    /// Feedback value for the nominal sample rate
+    pub fn nominal(&self) -> u32 {
+        self.nominal
+    }
+
+    /// Current feedback value
+    pub fn value(&self) -> u32 {
+        self.value
+    }
+
+    /// Go back to the nominal rate, e.g. when the stream restarts
+    pub fn reset(&mut self) {
+        self.value = self.nominal;
+    }
+
+    /// Set the rate from `samples` consumed during `frames` 1ms frames
+    ///
+    /// Measure over a power of two frames, e.g. counting I2S samples between SOFs 64 frames apart,
+    /// see [`Sof::frame_number`].
+    pub fn update(&mut self, samples: u32, frames: u32) {
+        if frames == 0 {
+            return;
+        }
+        let value = Self::encode(self.format, samples as u64, frames as u64);
+        self.value = self.clamp(value as i64);
+    }
+
+    /// Set the rate from the fill level of the device buffer, in samples
+    ///
+    /// The host is asked for more samples while the buffer is below `target` and for fewer above it,
+    /// by one sample per second for each [`Self::FILL_GAIN`] samples of error.
+    pub fn update_fill(&mut self, level: i32, target: i32) {
+        let error = (target - level) as i64 / Self::FILL_GAIN;
+        // One sample per second in the fixed point format
+        let step = match self.format {
+            FeedbackFormat::FullSpeed => error * (1 << 14) / 1000,
+            FeedbackFormat::HighSpeed => error * (1 << 16) / 8000,
+        };
+        self.value = self.clamp(self.nominal as i64 + step);
+    }
+
+    /// Number of bytes of the feedback packet
+    pub fn packet_size(&self) -> usize {
+        match self.format {
+            FeedbackFormat::FullSpeed => 3,
+            FeedbackFormat::HighSpeed => 4,
+        }
+    }
+
+    /// Little endian feedback packet, only the first [`Self::packet_size`] bytes are sent
+    pub fn to_bytes(&self) -> [u8; 4] {
+        self.value.to_le_bytes()
+    }
+}
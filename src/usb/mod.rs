@@ -1,8 +1,10 @@
+use core::cell::Cell;
 use core::marker::PhantomData;
-use core::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicU8, Ordering};
 
 use bus::Bus;
 use control_pipe::ControlPipe;
+use critical_section::Mutex;
 use embassy_hal_internal::Peri;
 use embassy_sync::waitqueue::AtomicWaker;
 use embassy_usb_driver::{Direction, Driver, EndpointAddress, EndpointAllocError, EndpointInfo, EndpointType};
@@ -32,12 +34,26 @@ pub struct Config {
     /// When `true`, the USB controller will be forced to Full-Speed mode
     /// by setting PORTSC1.PFSC bit.
     pub force_full_speed: bool,
+
+    /// Put the PHY into low power while the bus is suspended (device mode).
+    ///
+    /// On suspend, PORTSC1.PHCD stops the PHY clocks and the controller bus clock is gated,
+    /// the wake-up interrupt on line state or VBUS changes restores both. Required to meet the
+    /// 2.5mA suspend current limit.
+    ///
+    /// L1 (LPM) sleep is not supported: the controller is the ChipIdea variant built without
+    /// Link Power Management. PHCD and PSPD sit in PORTSC1, there is no DEVLC register and no
+    /// register to handshake or read back LPM tokens, so the host never gets an ACK to an L1
+    /// request. Only L2 suspend is supported and LPM must not be advertised in the BOS
+    /// descriptor (USB 2.0 extension bmAttributes bit 1 cleared).
+    pub low_power_suspend: bool,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             force_full_speed: false,
+            low_power_suspend: false,
         }
     }
 }
@@ -93,15 +109,148 @@ pub(crate) fn local_to_sys_address(addr: u32) -> u32 {
     addr
 }
 
+/// Initialize USB phy, shared by device and host mode
+pub(crate) fn phy_init(r: crate::pac::usb::Usb, delay: &mut McycleDelay) {
+    // Enable dp/dm pulldown
+    // In hpm_sdk, this operation is done by `ptr->PHY_CTRL0 &= ~0x001000E0u`.
+    // But there's corresponding bits in register, so we write the register directly here.
+    r.phy_ctrl0().modify(|w| w.0 = w.0 & (!(0x001000E0)));
+
+    r.otg_ctrl0().modify(|w| {
+        w.set_otg_utmi_reset_sw(true);
+        w.set_otg_utmi_suspendm_sw(false);
+    });
+
+    r.phy_ctrl1().modify(|w| {
+        w.set_utmi_cfg_rst_n(false);
+    });
+
+    // Wait for reset status
+    while !r.otg_ctrl0().read().otg_utmi_reset_sw() {}
+
+    // Set suspend
+    r.otg_ctrl0().modify(|w| {
+        w.set_otg_utmi_suspendm_sw(true);
+    });
+
+    // Delay at least 1us
+    delay.delay_us(5);
+
+    r.otg_ctrl0().modify(|w| {
+        // Disable dm/dp wakeup
+        w.set_otg_wkdpdmchg_en(false);
+        // Clear reset sw
+        w.set_otg_utmi_reset_sw(false);
+    });
+
+    // OTG utmi clock detection
+    r.phy_status().modify(|w| w.set_utmi_clk_valid(true));
+    while !r.phy_status().read().utmi_clk_valid() {}
+
+    // Reset and set suspend
+    r.phy_ctrl1().modify(|w| {
+        w.set_utmi_cfg_rst_n(true);
+        w.set_utmi_otg_suspendm(true);
+    });
+}
+
+/// Deinitialize USB phy
+pub(crate) fn phy_deinit(r: crate::pac::usb::Usb) {
+    r.otg_ctrl0().modify(|w| {
+        w.set_otg_utmi_suspendm_sw(true);
+        w.set_otg_utmi_reset_sw(false);
+    });
+
+    r.phy_ctrl1().modify(|w| {
+        w.set_utmi_cfg_rst_n(false);
+        w.set_utmi_otg_suspendm(false);
+    });
+}
+
+/// Enter PHY low power on bus suspend: stop the PHY clocks, gate the controller clock and
+/// arm the wake-up interrupt
+pub(crate) fn enter_low_power<T: Instance>() {
+    let r = T::info().regs;
+
+    // Wake up on resume/reset signaling and on VBUS changes
+    r.otg_ctrl0().modify(|w| {
+        w.set_otg_wkdpdmchg_en(true);
+        w.set_otg_vbus_wakeup_en(true);
+        w.set_otg_wakeup_int_enable(true);
+    });
+
+    r.portsc1().modify(|w| w.set_phcd(true));
+
+    T::state().low_power.store(true, Ordering::Release);
+    crate::sysctl::clock_remove_from_group(T::SYSCTL_RESOURCE, 0);
+}
+
+/// Leave PHY low power, restoring the controller clock first
+///
+/// Returns `false` if the PHY was not in low power. Runs in a critical section, so the
+/// interrupt handler never sees the flag cleared before the clock is back. The PHY clock
+/// restarts in the background, see [`wait_phy_clock`].
+pub(crate) fn exit_low_power<T: Instance>() -> bool {
+    critical_section::with(|_| {
+        if !T::state().low_power.swap(false, Ordering::AcqRel) {
+            return false;
+        }
+        T::add_resource_group(0);
+
+        let r = T::info().regs;
+        r.otg_ctrl0().modify(|w| {
+            w.set_otg_wakeup_int_enable(false);
+            w.set_otg_wkdpdmchg_en(false);
+            w.set_otg_vbus_wakeup_en(false);
+        });
+
+        r.portsc1().modify(|w| w.set_phcd(false));
+        T::state().phy_waking.store(true, Ordering::Release);
+        true
+    })
+}
+
+/// Time for the PHY clock to restart after clearing PHCD, in us
+const PHY_CLOCK_TIMEOUT_US: u32 = 1000;
+
+/// Wait until the PHY clock is valid again after [`exit_low_power`], in task context
+///
+/// Returns `false` if the clock did not come back in time.
+pub(crate) fn wait_phy_clock<T: Instance>() -> bool {
+    let state = T::state();
+    if !state.phy_waking.load(Ordering::Acquire) {
+        return true;
+    }
+
+    let r = T::info().regs;
+    let mut delay = McycleDelay::new(sysctl::clocks().cpu0.0);
+    for _ in 0..PHY_CLOCK_TIMEOUT_US {
+        if r.phy_status().read().utmi_clk_valid() {
+            state.phy_waking.store(false, Ordering::Release);
+            return true;
+        }
+        delay.delay_us(1);
+    }
+
+    #[cfg(feature = "defmt")]
+    defmt::warn!("USB: PHY clock not valid after leaving low power");
+    false
+}
+
 mod bus;
 mod control_pipe;
 mod endpoint;
+pub mod host;
+mod iso;
+mod otg;
 mod state;
 #[cfg(any(hpm53, hpm68, hpm6e))]
 mod types_v53;
 #[cfg(any(hpm67, hpm63, hpm62))]
 mod types_v62;
 
+pub use iso::{Feedback, FeedbackFormat, Sof};
+pub use otg::{PowerHandler, Role, RoleMonitor, UsbOtg};
 pub use state::EndpointState;
 
 static IRQ_RESET: AtomicBool = AtomicBool::new(false);
@@ -129,6 +278,7 @@ const AW_NEW: AtomicWaker = AtomicWaker::new();
 static EP_IN_WAKERS: [AtomicWaker; ENDPOINT_COUNT] = [AW_NEW; ENDPOINT_COUNT];
 static EP_OUT_WAKERS: [AtomicWaker; ENDPOINT_COUNT] = [AW_NEW; ENDPOINT_COUNT];
 static BUS_WAKER: AtomicWaker = AtomicWaker::new();
+static SOF_WAKER: AtomicWaker = AtomicWaker::new();
 
 #[cfg(usb_v67)]
 const ENDPOINT_COUNT: usize = 8;
@@ -337,6 +487,18 @@ impl<'d, T: Instance> UsbDriver<'d, T> {
         config: Config,
         ep_state: &'d EndpointState,
     ) -> Self {
+        #[cfg(feature = "usb-pin-reuse-hpm5300")]
+        {
+            // Set to analog
+            dp.set_as_analog();
+            dm.set_as_analog();
+        }
+
+        Self::new_inner(config, ep_state)
+    }
+
+    /// Pins are already set up, see [`UsbOtg::device`]
+    pub(crate) fn new_inner(config: Config, ep_state: &'d EndpointState) -> Self {
         // Runtime singleton check
         assert!(
             ep_state.try_acquire(),
@@ -355,15 +517,6 @@ impl<'d, T: Instance> UsbDriver<'d, T> {
         // Disable dp/dm pulldown
         r.phy_ctrl0().modify(|w| w.0 |= 0x001000E0);
 
-        #[cfg(feature = "usb-pin-reuse-hpm5300")]
-        {
-            // Set to analog
-            dp.set_as_analog();
-            dm.set_as_analog();
-        }
-
-        let r = T::info().regs;
-
         // Set power control polarity, aka vbus high level enable
         r.otg_ctrl0().modify(|w| w.set_otg_power_mask(true));
 
@@ -392,6 +545,13 @@ impl<'d, T: Instance> UsbDriver<'d, T> {
         }
     }
 
+    /// SOF frame number, for isochronous streaming
+    ///
+    /// Call before the driver is handed to embassy-usb, the handle stays valid while the driver runs.
+    pub fn sof(&self) -> Sof<'d, T> {
+        Sof { _phantom: PhantomData }
+    }
+
     /// Find the free endpoint
     pub(crate) fn find_free_endpoint(&mut self, ep_type: EndpointType, dir: Direction) -> Option<usize> {
         let endpoint_list = match dir {
@@ -554,6 +714,7 @@ impl<'a, T: Instance> Driver<'a> for UsbDriver<'a, T> {
             endpoints_out,
             delay: McycleDelay::new(sysctl::clocks().cpu0.0),
             inited: false,
+            suspended: false,
             config: self.config,
             ep_state: self.ep_state,
         };
@@ -575,13 +736,31 @@ pub(super) struct Info {
 }
 
 struct State {
-    _waker: AtomicWaker,
+    /// ID pin and VBUS changes
+    otg_waker: AtomicWaker,
+    /// Last role reported by `RoleMonitor`
+    role: AtomicU8,
+    /// PHY suspended and controller clock gated, see `enter_low_power`
+    low_power: AtomicBool,
+    /// PHCD cleared and the PHY clock not seen valid yet, see `wait_phy_clock`
+    phy_waking: AtomicBool,
+    power_handler: Mutex<Cell<Option<&'static dyn PowerHandler>>>,
+    /// Host pipes, one per QH slot, see `host::UsbHost`
+    pipe_wakers: [AtomicWaker; host::QH_COUNT],
+    /// Host port connect and resume changes
+    port_waker: AtomicWaker,
 }
 
 impl State {
     const fn new() -> Self {
         Self {
-            _waker: AtomicWaker::new(),
+            otg_waker: AtomicWaker::new(),
+            role: AtomicU8::new(0),
+            low_power: AtomicBool::new(false),
+            phy_waking: AtomicBool::new(false),
+            power_handler: Mutex::new(Cell::new(None)),
+            pipe_wakers: [AW_NEW; host::QH_COUNT],
+            port_waker: AtomicWaker::new(),
         }
     }
 }
@@ -626,6 +805,31 @@ impl<T: Instance> crate::interrupt::typelevel::Handler<T::Interrupt> for Interru
 pub unsafe fn on_interrupt<T: Instance>() {
     let r = T::info().regs;
 
+    // Wake-up from PHY low power, registers are only accessible after the clock is restored.
+    // The PHY clock is waited for by the bus task, not here.
+    if exit_low_power::<T>() {
+        BUS_WAKER.wake();
+    }
+
+    // ID pin and VBUS changes for `UsbOtg`, in any mode
+    otg::on_interrupt::<T>(r);
+
+    // Controller is running as host, see `host::UsbHost`
+    if r.usbmode().read().cm() == host::USBMODE_CM_HOST {
+        host::on_interrupt::<T>(r);
+        return;
+    }
+
+    // VBUS change detection (OTGSC register, independent of USBSTS)
+    let otgsc = r.otgsc().read();
+    if otgsc.asvis() && otgsc.asvie() {
+        // Clear A Session Valid Interrupt Status (write 1 to clear)
+        otg::clear_otgsc_status(r, otg::OTGSC_ASVIS);
+        // Signal VBUS change
+        IRQ_VBUS_CHANGE.store(true, Ordering::Relaxed);
+        BUS_WAKER.wake();
+    }
+
     // Get triggered interrupts
     let status = r.usbsts().read();
     let enabled_interrupts = r.usbintr().read();
@@ -662,6 +866,12 @@ pub unsafe fn on_interrupt<T: Instance>() {
         BUS_WAKER.wake();
     }
 
+    // Start of frame, only enabled while `Sof::wait` is pending
+    if status.sri() {
+        r.usbintr().modify(|w| w.set_sre(false));
+        SOF_WAKER.wake();
+    }
+
     // Port change event
     if status.pci() {
         if r.portsc1().read().ccs() {
@@ -700,16 +910,6 @@ pub unsafe fn on_interrupt<T: Instance>() {
         // Re-enable USB transfer interrupt
         r.usbintr().modify(|w| w.set_ue(true));
     }
-
-    // VBUS change detection (OTGSC register, independent of USBSTS)
-    let otgsc = r.otgsc().read();
-    if otgsc.asvis() {
-        // Clear A Session Valid Interrupt Status (write 1 to clear)
-        r.otgsc().modify(|w| w.set_asvis(true));
-        // Signal VBUS change
-        IRQ_VBUS_CHANGE.store(true, Ordering::Relaxed);
-        BUS_WAKER.wake();
-    }
 }
 
 pin_trait!(DmPin, Instance);
@@ -0,0 +1,264 @@
+//! USB OTG dual-role support
+//!
+//! [`UsbOtg`] owns the USB peripheral and watches the ID pin and VBUS. The role follows the
+//! cable: a grounded ID pin (A-plug, OTG adapter) makes the port a host, otherwise it is a device
+//! as long as VBUS is supplied by a host. [`UsbOtg::device`] and [`UsbOtg::host`] create the driver
+//! for the current role, drop it to switch roles.
+//!
+//! # Example
+//!
+//! ```ignore
+//! let mut otg = UsbOtg::new(p.USB0, Irqs, Default::default());
+//! let monitor = otg.monitor();
+//! loop {
+//!     match otg.role() {
+//!         Role::Device => {
+//!             let driver = otg.device(&EP_STATE);
+//!             let mut usb = build_device(driver);
+//!             select(usb.run(), monitor.wait_role_change()).await;
+//!         }
+//!         Role::Host => {
+//!             let mut host = otg.host(&HOST_STATE);
+//!             select(run_host(&mut host), monitor.wait_role_change()).await;
+//!         }
+//!         Role::Idle => {
+//!             monitor.wait_role_change().await;
+//!         }
+//!     }
+//! }
+//! ```
+
+use core::future::poll_fn;
+use core::marker::PhantomData;
+use core::sync::atomic::Ordering;
+use core::task::Poll;
+
+use embassy_hal_internal::Peri;
+use embassy_time::Timer;
+
+use super::host::{HostState, UsbHost};
+use super::{Config, EndpointState, Instance, InterruptHandler, UsbDriver};
+#[cfg(feature = "usb-pin-reuse-hpm5300")]
+use super::{DmPin, DpPin};
+use crate::interrupt::typelevel::Interrupt as _;
+
+/// OTGSC interrupt status bits, write 1 to clear
+const OTGSC_IS_MASK: u32 = 0x7F << 16;
+/// OTGSC ID pin change interrupt status
+const OTGSC_IDIS: u32 = 1 << 16;
+/// OTGSC A session valid change interrupt status
+pub(crate) const OTGSC_ASVIS: u32 = 1 << 18;
+/// OTGSC B session valid change interrupt status
+const OTGSC_BSVIS: u32 = 1 << 19;
+
+/// Debounce of ID pin and VBUS changes
+const ROLE_DEBOUNCE_MS: u64 = 50;
+
+/// Role of the port
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+pub enum Role {
+    /// ID pin floating and no VBUS, nothing attached
+    Idle = 0,
+    /// ID pin floating and VBUS valid, attached to a host
+    Device = 1,
+    /// ID pin grounded, a device is attached through an A-plug
+    Host = 2,
+}
+
+impl Role {
+    fn from_u8(v: u8) -> Self {
+        match v {
+            1 => Role::Device,
+            2 => Role::Host,
+            _ => Role::Idle,
+        }
+    }
+}
+
+/// Board power control on bus suspend and resume
+///
+/// Called in both roles: from the device bus on suspend/resume events, and from
+/// [`UsbHost::suspend_port`]/[`UsbHost::resume_port`] in host mode. Runs in task context.
+pub trait PowerHandler: Sync {
+    /// The bus was suspended, board power can be reduced
+    fn suspend(&self, role: Role);
+    /// The bus is about to resume, restore board power
+    fn resume(&self, role: Role);
+}
+
+/// Registered power handler of instance `T`
+pub(crate) fn power_handler<T: Instance>() -> Option<&'static dyn PowerHandler> {
+    critical_section::with(|cs| T::state().power_handler.borrow(cs).get())
+}
+
+/// Clear OTGSC interrupt status `bits`, leaving the other pending ones
+pub(crate) fn clear_otgsc_status(r: crate::pac::usb::Usb, bits: u32) {
+    r.otgsc().modify(|w| w.0 = (w.0 & !OTGSC_IS_MASK) | bits);
+}
+
+/// Current role from the ID pin and B session valid
+fn current_role(r: crate::pac::usb::Usb) -> Role {
+    let otgsc = r.otgsc().read();
+    if !otgsc.id() {
+        Role::Host
+    } else if otgsc.bsv() {
+        Role::Device
+    } else {
+        Role::Idle
+    }
+}
+
+/// OTG part of the USB interrupt handler, runs in any mode
+pub(crate) fn on_interrupt<T: Instance>(r: crate::pac::usb::Usb) {
+    let otgsc = r.otgsc().read().0;
+    // Enable bits are 8 bits above the status bits
+    let pending = otgsc & (otgsc >> 8) & (OTGSC_IDIS | OTGSC_BSVIS);
+    if pending != 0 {
+        clear_otgsc_status(r, pending);
+        T::state().otg_waker.wake();
+    }
+}
+
+/// USB OTG dual-role port
+pub struct UsbOtg<'d, T: Instance> {
+    _peri: Peri<'d, T>,
+    config: Config,
+}
+
+impl<'d, T: Instance> UsbOtg<'d, T> {
+    /// Create a dual-role port, enables ID pin and VBUS detection
+    ///
+    /// # Arguments
+    /// * `peri` - USB peripheral
+    /// * `_irq` - Interrupt binding
+    /// * `dm` - D- pin (only when `usb-pin-reuse-hpm5300` feature is enabled)
+    /// * `dp` - D+ pin (only when `usb-pin-reuse-hpm5300` feature is enabled)
+    /// * `config` - USB configuration, used for both roles
+    pub fn new(
+        peri: Peri<'d, T>,
+        _irq: impl crate::interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+        #[cfg(feature = "usb-pin-reuse-hpm5300")] dm: Peri<'d, impl DmPin<T>>,
+        #[cfg(feature = "usb-pin-reuse-hpm5300")] dp: Peri<'d, impl DpPin<T>>,
+        config: Config,
+    ) -> Self {
+        T::add_resource_group(0);
+
+        #[cfg(feature = "usb-pin-reuse-hpm5300")]
+        {
+            // Set to analog
+            dp.set_as_analog();
+            dm.set_as_analog();
+        }
+
+        let r = T::info().regs;
+
+        // ID pull-up, a floating ID pin reads as B-device
+        r.otgsc().modify(|w| {
+            w.0 &= !OTGSC_IS_MASK;
+            w.set_idpu(true);
+        });
+        clear_otgsc_status(r, OTGSC_IDIS | OTGSC_BSVIS);
+        r.otgsc().modify(|w| {
+            w.0 &= !OTGSC_IS_MASK;
+            w.set_idie(true);
+            w.set_bsvie(true);
+        });
+
+        T::state().role.store(current_role(r) as u8, Ordering::Relaxed);
+
+        unsafe { T::Interrupt::enable() };
+
+        Self { _peri: peri, config }
+    }
+
+    /// Current role, from the ID pin and VBUS
+    pub fn role(&self) -> Role {
+        self.monitor().role()
+    }
+
+    /// Whether VBUS is supplied by a host
+    pub fn vbus_valid(&self) -> bool {
+        T::info().regs.otgsc().read().bsv()
+    }
+
+    /// Handle to wait for role changes while a driver borrows the port
+    pub fn monitor(&self) -> RoleMonitor<'d, T> {
+        RoleMonitor { _phantom: PhantomData }
+    }
+
+    /// Wait until the role changes, returns the new role
+    pub async fn wait_role_change(&self) -> Role {
+        self.monitor().wait_role_change().await
+    }
+
+    /// Register a power handler for bus suspend and resume, in both roles
+    pub fn set_power_handler(&mut self, handler: &'static dyn PowerHandler) {
+        critical_section::with(|cs| T::state().power_handler.borrow(cs).set(Some(handler)));
+    }
+
+    /// Create the device driver, to be passed to embassy-usb
+    ///
+    /// The controller is reset when the bus is enabled. Drop the `UsbDevice` to leave device mode,
+    /// `ep_state` can then be reused for the next session.
+    pub fn device<'a>(&'a mut self, ep_state: &'a EndpointState) -> UsbDriver<'a, T> {
+        UsbDriver::new_inner(self.config, ep_state)
+    }
+
+    /// Create the host, the controller is started in host mode and the port is powered
+    ///
+    /// Drop the host to leave host mode, `state` can then be reused for the next session.
+    pub fn host<'a>(&'a mut self, state: &'a HostState) -> UsbHost<'a, T> {
+        UsbHost::new_inner(self.config, state)
+    }
+}
+
+/// Role change monitor, see [`UsbOtg::monitor`]
+///
+/// Only one task should wait for role changes at a time.
+pub struct RoleMonitor<'d, T: Instance> {
+    _phantom: PhantomData<&'d T>,
+}
+
+impl<'d, T: Instance> Clone for RoleMonitor<'d, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'d, T: Instance> Copy for RoleMonitor<'d, T> {}
+
+impl<'d, T: Instance> RoleMonitor<'d, T> {
+    /// Current role, from the ID pin and VBUS
+    pub fn role(&self) -> Role {
+        let role = current_role(T::info().regs);
+        T::state().role.store(role as u8, Ordering::Relaxed);
+        role
+    }
+
+    /// Wait until the role differs from the last one returned, then debounce
+    pub async fn wait_role_change(&self) -> Role {
+        let r = T::info().regs;
+        let state = T::state();
+        loop {
+            let last = Role::from_u8(state.role.load(Ordering::Relaxed));
+            poll_fn(|cx| {
+                state.otg_waker.register(cx.waker());
+                if current_role(r) != last {
+                    Poll::Ready(())
+                } else {
+                    Poll::Pending
+                }
+            })
+            .await;
+
+            Timer::after_millis(ROLE_DEBOUNCE_MS).await;
+            let role = current_role(r);
+            if role != last {
+                state.role.store(role as u8, Ordering::Relaxed);
+                return role;
+            }
+        }
+    }
+}
@@ -28,9 +28,13 @@ const QTD_LIST_SIZE: usize = QTD_ITEM_SIZE * ENDPOINT_COUNT * 2 * QTD_COUNT_EACH
 pub struct QhdListData(UnsafeCell<[u8; QHD_LIST_SIZE]>);
 
 impl QhdListData {
-    const fn new() -> Self {
+    pub(crate) const fn new() -> Self {
         Self(UnsafeCell::new([0; QHD_LIST_SIZE]))
     }
+
+    pub(crate) fn as_ptr(&self) -> *mut () {
+        self.0.get() as *mut ()
+    }
 }
 
 /// QTD list data with 32-byte alignment requirement.
@@ -38,9 +42,13 @@ impl QhdListData {
 pub struct QtdListData(UnsafeCell<[u8; QTD_LIST_SIZE]>);
 
 impl QtdListData {
-    const fn new() -> Self {
+    pub(crate) const fn new() -> Self {
         Self(UnsafeCell::new([0; QTD_LIST_SIZE]))
     }
+
+    pub(crate) fn as_ptr(&self) -> *mut () {
+        self.0.get() as *mut ()
+    }
 }
 
 /// USB endpoint state that must be placed in non-cacheable memory.
@@ -97,8 +105,7 @@ impl EndpointState {
         (prev & STATE_IN_USE) == 0
     }
 
-    /// Release the state (for potential future reuse after driver is dropped).
-    #[allow(dead_code)]
+    /// Release the state for reuse after the bus is dropped.
     pub(crate) fn release(&self) {
         const STATE_IN_USE: u32 = 1 << 31;
         self.alloc_mask.fetch_and(!STATE_IN_USE, Ordering::SeqCst);